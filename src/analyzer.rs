@@ -1,23 +1,172 @@
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{self, Sender};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
-use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::DecoderOptions;
-use symphonia::core::formats::{FormatOptions, FormatReader};
-use symphonia::core::io::{MediaSourceStream, ReadOnlySource};
-use symphonia::core::meta::MetadataOptions;
+use symphonia::core::audio::{AudioBufferRef, Channels, SampleBuffer};
+use symphonia::core::codecs::{
+    CodecParameters, CodecType, Decoder, DecoderOptions, CODEC_TYPE_AAC, CODEC_TYPE_AC4,
+    CODEC_TYPE_ALAC, CODEC_TYPE_ATRAC3, CODEC_TYPE_ATRAC3PLUS, CODEC_TYPE_DCA, CODEC_TYPE_EAC3,
+    CODEC_TYPE_MONKEYS_AUDIO, CODEC_TYPE_MP1, CODEC_TYPE_MP2, CODEC_TYPE_MP3, CODEC_TYPE_MUSEPACK,
+    CODEC_TYPE_OPUS, CODEC_TYPE_SPEEX, CODEC_TYPE_TTA, CODEC_TYPE_VORBIS, CODEC_TYPE_WAVPACK,
+    CODEC_TYPE_WMA,
+};
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::{MediaSource, MediaSourceStream, ReadOnlySource, SeekBuffered};
+use symphonia::core::meta::{Metadata, MetadataOptions, StandardTagKey, Tag};
 use symphonia::core::probe::Hint;
-
-use crate::models::{AlbumResult, AnalysisEvent, TrackResult};
+use symphonia::core::units::{Time, TimeBase};
+
+use crate::chapters::{self, ChapterMark};
+use crate::error::DrError;
+use crate::fingerprint;
+use crate::loudness::{self, KWeighting, LoudnessMeter};
+use crate::models::{
+    crest_factor_db, plr, round_to_dr, AlbumAggregate, AlbumResult, AnalysisEvent, ChannelDr,
+    Confidence, DrScale, SampleKind, TrackResult, DURATION_TOLERANCE_SECS, SCHEMA_VERSION,
+};
+use crate::truepeak::TruePeakMeter;
 
 const AUDIO_EXTENSIONS: &[&str] = &[
-    "flac", "mp3", "wav", "ogg", "m4a", "opus", "wv", "aif", "aiff",
+    "flac", "mp3", "wav", "ogg", "m4a", "opus", "wv", "aif", "aiff", "aifc",
 ];
 
+/// Optional analysis settings. `AnalysisOptions::default()` is the standard
+/// TT DR measurement with no extra statistics.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisOptions {
+    /// Compute the inter-channel correlation coefficient for 2-channel files
+    pub stereo_stats: bool,
+    /// Extensions to include when scanning directories (default: all supported formats)
+    pub extensions: Option<Vec<String>>,
+    /// Treat a directory with no audio of its own as one album across its
+    /// subdirectories (e.g. `CD1/`, `CD2/`), ordered by disc then track
+    pub multi_disc: bool,
+    /// Where track titles come from
+    pub title_source: TitleSource,
+    /// Also compute the experimental perceptual DR over a K-weighted signal
+    pub k_weighted: bool,
+    /// Keep the momentary and short-term loudness time series
+    pub loudness_series: bool,
+    /// Keep each block's RMS (the loudest channel's) as a level envelope
+    pub envelope: bool,
+    /// Analyze only this part of each file
+    pub range: Option<TimeRange>,
+    /// Stop after this many seconds of each track (after any `range`) for a
+    /// quick rough figure; cut tracks are marked `preview`
+    pub preview_secs: Option<f64>,
+    /// Leave out this many seconds at the start and end of each track (after
+    /// any `range`), e.g. crossfades on a live album. Non-standard DR.
+    pub trim_edges_secs: Option<f64>,
+    /// Leave leading and trailing silent blocks (below `SILENCE_THRESHOLD_DB`
+    /// in every channel) out of the DR, e.g. padding at the end of a rip.
+    /// The duration still covers them. Non-standard DR.
+    pub trim_silence: bool,
+    /// How album DR is combined from the track DRs
+    pub album_agg: AlbumAggregate,
+    /// Leave silent tracks (`TrackResult::is_silent`) out of the album DR,
+    /// still listing them
+    pub skip_silent_in_average: bool,
+    /// The scale the album's `dr_rating` is on. It doesn't change the
+    /// measurement, so cached reports are re-rated rather than re-analyzed.
+    pub dr_scale: DrScale,
+    /// Estimate each track's noise floor from its quietest blocks
+    pub noise_floor: bool,
+    /// Also report each channel's DR, labeled by channel position
+    pub per_channel: bool,
+    /// Compute the DR by the older method that combines the channels of each
+    /// block before the top-20% selection (see `legacy_block_dr`). Non-standard DR.
+    pub legacy_dr: bool,
+    /// Scale samples by each file's REPLAYGAIN_TRACK_GAIN tag before analysis,
+    /// measuring levels as played back. Files without the tag are measured as is.
+    pub apply_existing_gain: bool,
+    /// Read the decoded samples as this many interleaved channels, whatever
+    /// the header says, to rescue a file that declares the wrong count. Must
+    /// be a multiple or divisor of the decoded channel count; can't be
+    /// combined with `range` or `preview_secs`.
+    pub force_channels: Option<usize>,
+    /// Measure at this sample rate instead of the declared one
+    pub force_sample_rate: Option<u32>,
+    /// Spread each file's channels over this many measuring threads while
+    /// one thread decodes (see `ChannelSplit`). 0 or 1 measures on the
+    /// decoding thread. The results are the same either way.
+    pub threads_per_file: usize,
+}
+
+/// A section of a file to analyze, in seconds from the start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub start_secs: f64,
+    /// Until the end of the file when `None`
+    pub duration_secs: Option<f64>,
+}
+
+impl TimeRange {
+    /// The range as `[start, end)` frame indices at `sample_rate`.
+    fn frames(&self, sample_rate: usize) -> (usize, usize) {
+        let rate = sample_rate as f64;
+        let start = (self.start_secs * rate).round() as usize;
+        let end = self.duration_secs.map_or(usize::MAX, |d| {
+            start.saturating_add((d * rate).round() as usize)
+        });
+        (start, end)
+    }
+}
+
+/// Convert a stream timestamp to a frame index.
+fn ts_to_frames(ts: u64, time_base: Option<TimeBase>, sample_rate: usize) -> usize {
+    match time_base {
+        Some(tb) => {
+            let time = tb.calc_time(ts);
+            ((time.seconds as f64 + time.frac) * sample_rate as f64).round() as usize
+        }
+        None => ts as usize,
+    }
+}
+
+impl AnalysisOptions {
+    /// Whether these options produce the same report as the defaults, so a
+    /// cached report can stand in for re-analysis. Scanning only some
+    /// `extensions`, or across disc subfolders, measures a different set of
+    /// tracks, so those don't match either.
+    pub fn matches_cached_report(&self) -> bool {
+        self.extensions.is_none()
+            && !self.multi_disc
+            && !self.stereo_stats
+            && !self.noise_floor
+            && !self.apply_existing_gain
+            && !self.per_channel
+            && !self.legacy_dr
+            && self.range.is_none()
+            && self.preview_secs.is_none()
+            && self.trim_edges_secs.is_none()
+            && !self.trim_silence
+            && self.album_agg == AlbumAggregate::default()
+            && !self.skip_silent_in_average
+            && !self.k_weighted
+            && !self.loudness_series
+            && !self.envelope
+            && self.force_channels.is_none()
+            && self.force_sample_rate.is_none()
+            && self.title_source == TitleSource::default()
+    }
+}
+
+/// Source of the track title shown in reports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TitleSource {
+    /// The TrackTitle tag, falling back to the filename stem
+    #[default]
+    Metadata,
+    /// Always the filename stem
+    Filename,
+    /// The TrackTitle tag followed by the filename stem in parentheses
+    Both,
+}
+
 /// Check if a path has a recognized audio file extension.
 fn is_audio_file(path: &Path) -> bool {
     path.extension()
@@ -26,17 +175,232 @@ fn is_audio_file(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
+/// Extensions picked up when scanning directories (unless
+/// `AnalysisOptions::extensions` narrows them).
+pub fn supported_extensions() -> &'static [&'static str] {
+    AUDIO_EXTENSIONS
+}
+
+/// Every audio codec symphonia defines, so the registry (which can't be
+/// iterated) can be asked about each.
+const KNOWN_CODECS: &[CodecType] = {
+    use symphonia::core::codecs::*;
+    &[
+        CODEC_TYPE_PCM_S32LE,
+        CODEC_TYPE_PCM_S32LE_PLANAR,
+        CODEC_TYPE_PCM_S32BE,
+        CODEC_TYPE_PCM_S32BE_PLANAR,
+        CODEC_TYPE_PCM_S24LE,
+        CODEC_TYPE_PCM_S24LE_PLANAR,
+        CODEC_TYPE_PCM_S24BE,
+        CODEC_TYPE_PCM_S24BE_PLANAR,
+        CODEC_TYPE_PCM_S16LE,
+        CODEC_TYPE_PCM_S16LE_PLANAR,
+        CODEC_TYPE_PCM_S16BE,
+        CODEC_TYPE_PCM_S16BE_PLANAR,
+        CODEC_TYPE_PCM_S8,
+        CODEC_TYPE_PCM_S8_PLANAR,
+        CODEC_TYPE_PCM_U32LE,
+        CODEC_TYPE_PCM_U32LE_PLANAR,
+        CODEC_TYPE_PCM_U32BE,
+        CODEC_TYPE_PCM_U32BE_PLANAR,
+        CODEC_TYPE_PCM_U24LE,
+        CODEC_TYPE_PCM_U24LE_PLANAR,
+        CODEC_TYPE_PCM_U24BE,
+        CODEC_TYPE_PCM_U24BE_PLANAR,
+        CODEC_TYPE_PCM_U16LE,
+        CODEC_TYPE_PCM_U16LE_PLANAR,
+        CODEC_TYPE_PCM_U16BE,
+        CODEC_TYPE_PCM_U16BE_PLANAR,
+        CODEC_TYPE_PCM_U8,
+        CODEC_TYPE_PCM_U8_PLANAR,
+        CODEC_TYPE_PCM_F32LE,
+        CODEC_TYPE_PCM_F32LE_PLANAR,
+        CODEC_TYPE_PCM_F32BE,
+        CODEC_TYPE_PCM_F32BE_PLANAR,
+        CODEC_TYPE_PCM_F64LE,
+        CODEC_TYPE_PCM_F64LE_PLANAR,
+        CODEC_TYPE_PCM_F64BE,
+        CODEC_TYPE_PCM_F64BE_PLANAR,
+        CODEC_TYPE_PCM_ALAW,
+        CODEC_TYPE_PCM_MULAW,
+        CODEC_TYPE_ADPCM_G722,
+        CODEC_TYPE_ADPCM_G726,
+        CODEC_TYPE_ADPCM_G726LE,
+        CODEC_TYPE_ADPCM_MS,
+        CODEC_TYPE_ADPCM_IMA_WAV,
+        CODEC_TYPE_ADPCM_IMA_QT,
+        CODEC_TYPE_VORBIS,
+        CODEC_TYPE_MP1,
+        CODEC_TYPE_MP2,
+        CODEC_TYPE_MP3,
+        CODEC_TYPE_AAC,
+        CODEC_TYPE_OPUS,
+        CODEC_TYPE_SPEEX,
+        CODEC_TYPE_MUSEPACK,
+        CODEC_TYPE_ATRAC1,
+        CODEC_TYPE_ATRAC3,
+        CODEC_TYPE_ATRAC3PLUS,
+        CODEC_TYPE_ATRAC9,
+        CODEC_TYPE_EAC3,
+        CODEC_TYPE_AC4,
+        CODEC_TYPE_DCA,
+        CODEC_TYPE_WMA,
+        CODEC_TYPE_FLAC,
+        CODEC_TYPE_WAVPACK,
+        CODEC_TYPE_MONKEYS_AUDIO,
+        CODEC_TYPE_ALAC,
+        CODEC_TYPE_TTA,
+    ]
+};
+
+/// The codecs this build can decode, as (short name, long name) pairs, e.g.
+/// ("flac", "Free Lossless Audio Codec"). Depends on symphonia's enabled features.
+pub fn supported_codecs() -> Vec<(&'static str, &'static str)> {
+    let registry = symphonia::default::get_codecs();
+    KNOWN_CODECS
+        .iter()
+        .filter_map(|&codec| registry.get_codec(codec))
+        .map(|d| (d.short_name, d.long_name))
+        .collect()
+}
+
 /// Scan a directory for audio files, sorted by filename.
-pub fn scan_audio_files(path: &Path) -> Vec<PathBuf> {
-    let mut files: Vec<PathBuf> = std::fs::read_dir(path)
-        .into_iter()
-        .flatten()
-        .filter_map(|e| e.ok())
-        .map(|e| e.path())
-        .filter(|p| p.is_file() && is_audio_file(p))
+pub fn scan_audio_files(path: &Path) -> Result<Vec<PathBuf>> {
+    scan_audio_files_with_options(path, &AnalysisOptions::default())
+}
+
+/// Scan a directory for audio files, sorted by filename, restricted to
+/// `options.extensions` when set. With `options.multi_disc`, a directory with
+/// no audio files of its own is scanned one level down instead: the audio of
+/// each subdirectory (disc), discs in `disc_order`.
+///
+/// A directory (or disc folder) that can't be listed, e.g. for lack of
+/// permission, is an error, so it isn't mistaken for one without audio.
+pub fn scan_audio_files_with_options(
+    path: &Path,
+    options: &AnalysisOptions,
+) -> Result<Vec<PathBuf>> {
+    let entries = |dir: &Path| -> Result<Vec<PathBuf>> {
+        Ok(std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .collect())
+    };
+    let wanted = |p: &Path| match &options.extensions {
+        Some(exts) => p
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| exts.iter().any(|e| e.eq_ignore_ascii_case(ext))),
+        None => is_audio_file(p),
+    };
+    let audio_in = |dir: &Path| -> Result<Vec<PathBuf>> {
+        let mut files: Vec<PathBuf> = entries(dir)?
+            .into_iter()
+            .filter(|p| p.is_file() && wanted(p))
+            .collect();
+        files.sort();
+        Ok(files)
+    };
+
+    let files = audio_in(path)?;
+    if !files.is_empty() || !options.multi_disc {
+        return Ok(files);
+    }
+    let mut discs: Vec<PathBuf> = entries(path)?.into_iter().filter(|p| p.is_dir()).collect();
+    discs.sort_by_cached_key(|d| disc_order(&d.file_name().unwrap_or_default().to_string_lossy()));
+    let mut files = Vec::new();
+    for disc in &discs {
+        files.extend(audio_in(disc)?);
+    }
+    Ok(files)
+}
+
+/// Sort key for disc folders: by the first number in the name ("CD2" before
+/// "CD10"), then by name; folders without a number go last.
+fn disc_order(name: &str) -> (u32, String) {
+    (
+        folder_disc_number(name).unwrap_or(u32::MAX),
+        name.to_lowercase(),
+    )
+}
+
+/// The first number in a disc folder's name: 2 for "CD2" or "Disc 2 - Live".
+fn folder_disc_number(name: &str) -> Option<u32> {
+    let digits: String = name
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// The directory holding all of `files`: their deepest common ancestor.
+fn common_dir(files: &[PathBuf]) -> Option<PathBuf> {
+    let mut common = files.first()?.parent()?.to_path_buf();
+    for file in files {
+        while !file.starts_with(&common) {
+            common = common.parent()?.to_path_buf();
+        }
+    }
+    Some(common)
+}
+
+/// The album subdirectories of a bulk tree, sorted by name. Errors if
+/// `base_path` cannot be read or has no subdirectories.
+pub fn scan_album_dirs(base_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut subdirs: Vec<_> = std::fs::read_dir(base_path)
+        .with_context(|| format!("Failed to read directory: {}", base_path.display()))?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            if entry.path().is_dir() {
+                Some(entry.path())
+            } else {
+                None
+            }
+        })
         .collect();
-    files.sort();
-    files
+
+    subdirs.sort();
+
+    if subdirs.is_empty() {
+        anyhow::bail!("No subdirectories found in '{}'", base_path.display());
+    }
+    Ok(subdirs)
+}
+
+/// Whether `name` matches the glob `pattern`: `*` matches any run of
+/// characters, `?` any one character, and everything else itself
+/// (case-sensitively).
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of `name` it has taken so far
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the `*` take one more character and retry
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 /// Convert a linear amplitude to dBFS.
@@ -48,50 +412,659 @@ fn db_fs(linear: f64) -> f64 {
     }
 }
 
+/// TT DR block length in seconds.
+pub const BLOCK_SECS: f64 = 3.0;
+
+/// Factor on the mean square in the DR-RMS: scaling the RMS by sqrt(2) makes
+/// a full-scale sine measure 0 dB, the same as its peak.
+pub const DR_RMS_CALIBRATION: f64 = 2.0;
+
+/// DR-RMS of `n` samples whose squares sum to `sum_sq`: sqrt(2 * sum_sq / n),
+/// per the Pleasurize Music / TT DR standard. Every block RMS goes through
+/// here, so the calibration is defined once.
+pub fn dr_rms(sum_sq: f64, n: usize) -> f64 {
+    (DR_RMS_CALIBRATION * sum_sq / n as f64).sqrt()
+}
+
+/// Frame index at which block `k` (0-based) starts, i.e. where `k` blocks have
+/// elapsed. Computed from elapsed time rather than by summing integer block
+/// lengths, so boundaries never drift even when `BLOCK_SECS * sample_rate` isn't
+/// a whole number of frames.
+fn block_start_frame(k: usize, sample_rate: usize) -> usize {
+    // The epsilon absorbs float error when the product is meant to be exact
+    (k as f64 * BLOCK_SECS * sample_rate as f64 + 1e-6).floor() as usize
+}
+
+/// Length in frames of block `k` (at least one frame).
+fn block_length(k: usize, sample_rate: usize) -> usize {
+    (block_start_frame(k + 1, sample_rate) - block_start_frame(k, sample_rate)).max(1)
+}
+
 /// Streaming DR state that accumulates statistics block-by-block
 /// without buffering the entire track in memory.
 struct StreamingDrState {
     channels: usize,
     sample_rate: usize,
+    // Length of the in-progress block, and how many blocks have completed
     block_frames: usize,
+    blocks_done: usize,
     // Per-channel accumulators for the current in-progress block
     current_block_frames: usize,
     ch_sum_sq: Vec<f64>,
     ch_peak: Vec<f64>,
+    // Frame each channel's peak in the current block first occurs at
+    ch_peak_frame: Vec<usize>,
     // Completed block stats
     block_rms: Vec<Vec<f64>>,
     block_peaks: Vec<Vec<f64>>,
-    // Global absolute peak (across all samples, all channels)
+    // Absolute peak of the completed blocks (across all channels), and the
+    // frame it first occurs at; `peak` adds the in-progress block
     global_peak: f64,
+    global_peak_frame: usize,
+    // Frames processed so far, complete blocks and the partial one
+    frames_done: usize,
     // Residual buffer for partial frames from packet boundaries
     residual: Vec<f32>,
+    // Stereo correlation accumulators (sum of L*R, L*L, R*R), when enabled
+    stereo: Option<[f64; 3]>,
+    // Oversampled inter-sample peak
+    true_peak: TruePeakMeter,
+    // K-weighted gated loudness
+    loudness: LoudnessMeter,
+    // Block statistics of the K-weighted signal, for the perceptual DR
+    weighted: Option<WeightedBlocks>,
+    // Report the loudness meter's momentary/short-term series
+    loudness_series: bool,
+    // Report the block RMS envelope
+    envelope: bool,
+    // Estimate the noise floor from the block RMS values
+    noise_floor: bool,
+    // Keep each channel's DR alongside the mean
+    per_channel: bool,
+    // Combine the channels before the top-20% selection
+    legacy_dr: bool,
+    // Drops the track's first and last seconds before anything is measured
+    edges: Option<EdgeTrim>,
+    // Leave silent blocks at either end out of the DR
+    trim_silence: bool,
 }
 
-impl StreamingDrState {
+/// Add whole interleaved stereo frames to the sums of L*R, L*L, and R*R.
+fn accumulate_stereo(acc: &mut [f64; 3], frames: &[f32]) {
+    for frame in frames.chunks_exact(2) {
+        let (l, r) = (frame[0] as f64, frame[1] as f64);
+        acc[0] += l * r;
+        acc[1] += l * l;
+        acc[2] += r * r;
+    }
+}
+
+/// A `ChannelSplit` worker: its buffer queue and its thread.
+type SplitWorker = (
+    mpsc::SyncSender<Arc<Vec<f32>>>,
+    std::thread::JoinHandle<StreamingDrState>,
+);
+
+/// `StreamingDrState` spread over worker threads by channel
+/// (`--threads-per-file`), for files whose measurement outpaces one core.
+/// The decoding thread hands each buffer to every worker, and each worker
+/// measures a consecutive group of channels; `finalize` merges the groups
+/// back into the state of the whole stream. Splitting by channel rather than
+/// by time keeps every filter (true peak, K-weighting) running unbroken over
+/// the stream, so the result is the one thread's, bar float rounding in the
+/// loudness sum. Stereo statistics and edge trimming need whole frames and
+/// stay on the decoding thread.
+struct ChannelSplit {
+    channels: usize,
+    workers: Vec<SplitWorker>,
+    // Samples of a frame split across buffers
+    residual: Vec<f32>,
+    stereo: Option<[f64; 3]>,
+    edges: Option<EdgeTrim>,
+}
+
+/// Decoded buffers queued per worker before the decoding thread waits.
+const SPLIT_QUEUE: usize = 8;
+
+impl ChannelSplit {
+    fn new(channels: usize, sample_rate: usize, options: &AnalysisOptions, threads: usize) -> Self {
+        let groups = threads.clamp(1, channels.max(1));
+        let group_options = AnalysisOptions {
+            stereo_stats: false,
+            trim_edges_secs: None,
+            ..options.clone()
+        };
+        let workers = (0..groups)
+            .map(|g| {
+                let range = channels * g / groups..channels * (g + 1) / groups;
+                let (sender, receiver) = mpsc::sync_channel::<Arc<Vec<f32>>>(SPLIT_QUEUE);
+                let mut state = StreamingDrState::new(range.len(), sample_rate, &group_options);
+                state.loudness = LoudnessMeter::for_channels(range.clone(), channels, sample_rate);
+                let worker = std::thread::spawn(move || {
+                    let mut group = Vec::new();
+                    for frames in receiver {
+                        group.clear();
+                        for frame in frames.chunks_exact(channels) {
+                            group.extend_from_slice(&frame[range.clone()]);
+                        }
+                        state.push_untrimmed(&group);
+                    }
+                    state
+                });
+                (sender, worker)
+            })
+            .collect();
+        Self {
+            channels,
+            workers,
+            residual: Vec::new(),
+            stereo: (options.stereo_stats && channels == 2).then_some([0.0; 3]),
+            edges: options
+                .trim_edges_secs
+                .map(|secs| EdgeTrim::new(secs, channels, sample_rate)),
+        }
+    }
+
+    fn push_samples(&mut self, interleaved: &[f32]) {
+        if let Some(mut edges) = self.edges.take() {
+            edges.push(interleaved, |kept| self.push_untrimmed(kept));
+            self.edges = Some(edges);
+        } else {
+            self.push_untrimmed(interleaved);
+        }
+    }
+
+    fn push_untrimmed(&mut self, interleaved: &[f32]) {
+        let mut frames = std::mem::take(&mut self.residual);
+        frames.extend_from_slice(interleaved);
+        let whole = frames.len() / self.channels * self.channels;
+        self.residual = frames.split_off(whole);
+        if frames.is_empty() {
+            return;
+        }
+        if let Some(ref mut acc) = self.stereo {
+            accumulate_stereo(acc, &frames);
+        }
+        let frames = Arc::new(frames);
+        for (sender, _) in &self.workers {
+            // A worker that hung up has panicked; `finalize` passes that on
+            let _ = sender.send(Arc::clone(&frames));
+        }
+    }
+
+    fn finalize(self, total_frames: usize) -> DrStats {
+        let parts = self
+            .workers
+            .into_iter()
+            .map(|(sender, worker)| {
+                drop(sender);
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect();
+        let mut state = StreamingDrState::merge_channels(parts);
+        state.stereo = self.stereo;
+        state.edges = self.edges;
+        state.finalize(total_frames)
+    }
+}
+
+/// The measurement of one section: on the decoding thread, or split by
+/// channel over worker threads.
+enum SectionState {
+    Serial(Box<StreamingDrState>),
+    Split(ChannelSplit),
+}
+
+impl SectionState {
+    fn new(channels: usize, sample_rate: usize, options: &AnalysisOptions, threads: usize) -> Self {
+        if threads > 1 && channels > 1 {
+            SectionState::Split(ChannelSplit::new(channels, sample_rate, options, threads))
+        } else {
+            SectionState::Serial(Box::new(StreamingDrState::new(
+                channels,
+                sample_rate,
+                options,
+            )))
+        }
+    }
+
+    fn push_samples(&mut self, interleaved: &[f32]) {
+        match self {
+            SectionState::Serial(state) => state.push_samples(interleaved),
+            SectionState::Split(split) => split.push_samples(interleaved),
+        }
+    }
+
+    /// The stats so far. Only a serial state can be read mid-stream, so
+    /// snapshots are taken with one thread per file.
+    fn snapshot(&self, total_frames: usize) -> DrStats {
+        match self {
+            SectionState::Serial(state) => state.snapshot(total_frames),
+            SectionState::Split(_) => unreachable!("snapshots measure on the decoding thread"),
+        }
+    }
+
+    fn finalize(self, total_frames: usize) -> DrStats {
+        match self {
+            SectionState::Serial(state) => state.finalize(total_frames),
+            SectionState::Split(split) => split.finalize(total_frames),
+        }
+    }
+}
+
+/// Drops the first and last `secs` seconds of a stream of interleaved samples.
+/// The end isn't known until the stream stops, so the most recent `secs` of
+/// audio is held back and only passed on once more audio arrives after it.
+struct EdgeTrim {
+    secs: f64,
+    // Samples still to drop from the start
+    skip: usize,
+    // Samples to hold back from the end
+    hold: usize,
+    held: Vec<f32>,
+}
+
+impl EdgeTrim {
+    fn new(secs: f64, channels: usize, sample_rate: usize) -> Self {
+        let samples = (secs * sample_rate as f64).round() as usize * channels;
+        Self {
+            secs,
+            skip: samples,
+            hold: samples,
+            held: Vec::new(),
+        }
+    }
+
+    /// Pass on whatever of `samples` is now known to lie between the edges.
+    fn push(&mut self, samples: &[f32], mut out: impl FnMut(&[f32])) {
+        let skipped = self.skip.min(samples.len());
+        self.skip -= skipped;
+        self.held.extend_from_slice(&samples[skipped..]);
+        if self.held.len() > self.hold {
+            let release = self.held.len() - self.hold;
+            out(&self.held[..release]);
+            self.held.drain(..release);
+        }
+    }
+}
+
+/// A logical track inside a file, from the container's cue points (e.g. a
+/// FLAC CUESHEET) or its embedded chapters (see `chapters`).
+#[derive(Debug, Clone, PartialEq)]
+struct Chapter {
+    /// Stream frame at which the chapter begins
+    start: usize,
+    number: u32,
+    title: Option<String>,
+}
+
+/// Measures consecutive sections of one stream, each with its own
+/// `StreamingDrState`. Without chapters the whole stream is one section.
+struct Sections<'a> {
+    options: &'a AnalysisOptions,
+    channels: usize,
+    sample_rate: usize,
+    // Chapters not yet reached, in stream order
+    pending: std::collections::VecDeque<Chapter>,
+    current: Option<Chapter>,
+    // Measuring threads per section (see `ChannelSplit`)
+    threads: usize,
+    state: SectionState,
+    // Frames pushed so far, overall and into the current section
+    position: usize,
+    frames: usize,
+    decode_errors: u32,
+    done: Vec<Section>,
+}
+
+/// One finished section: its chapter (if any), statistics and decode errors.
+struct Section {
+    chapter: Option<Chapter>,
+    stats: DrStats,
+    frames: usize,
+    decode_errors: u32,
+}
+
+impl<'a> Sections<'a> {
+    fn new(
+        channels: usize,
+        sample_rate: usize,
+        options: &'a AnalysisOptions,
+        chapters: Vec<Chapter>,
+        threads: usize,
+    ) -> Self {
+        let mut pending: std::collections::VecDeque<Chapter> = chapters.into();
+        // Audio before the first mark (a pregap) belongs to the first chapter
+        let current = pending.pop_front();
+        Self {
+            options,
+            channels,
+            sample_rate,
+            pending,
+            current,
+            threads,
+            state: SectionState::new(channels, sample_rate, options, threads),
+            position: 0,
+            frames: 0,
+            decode_errors: 0,
+            done: Vec::new(),
+        }
+    }
+
+    /// Start over with a different channel count (before any audio is pushed).
+    fn reset(&mut self, channels: usize) {
+        self.channels = channels;
+        self.state = SectionState::new(channels, self.sample_rate, self.options, self.threads);
+    }
+
+    fn decode_error(&mut self) {
+        self.decode_errors += 1;
+    }
+
+    /// The current section's stats so far, as a partial track.
+    fn snapshot(&self, title: String, filename: String) -> TrackResult {
+        let mut track = self
+            .state
+            .snapshot(self.frames)
+            .into_track(title, filename, 0);
+        track.decode_errors = self.decode_errors;
+        track.confidence = Confidence::of(&track);
+        track
+    }
+
+    /// Feed interleaved samples, starting a new section at each chapter mark.
+    fn push_samples(&mut self, mut samples: &[f32]) {
+        let channels = self.channels.max(1);
+        while let Some(next) = self.pending.front().map(|c| c.start) {
+            let room = (next - self.position) * channels;
+            if samples.len() < room {
+                break;
+            }
+            let (head, rest) = samples.split_at(room);
+            self.push_to_current(head);
+            let chapter = self.pending.pop_front();
+            self.finish_current(chapter);
+            samples = rest;
+        }
+        self.push_to_current(samples);
+    }
+
+    fn push_to_current(&mut self, samples: &[f32]) {
+        let frames = samples.len() / self.channels.max(1);
+        self.state.push_samples(samples);
+        self.position += frames;
+        self.frames += frames;
+    }
+
+    fn finish_current(&mut self, next: Option<Chapter>) {
+        let state = std::mem::replace(
+            &mut self.state,
+            SectionState::new(self.channels, self.sample_rate, self.options, self.threads),
+        );
+        self.done.push(Section {
+            chapter: std::mem::replace(&mut self.current, next),
+            stats: state.finalize(self.frames),
+            frames: std::mem::take(&mut self.frames),
+            decode_errors: std::mem::take(&mut self.decode_errors),
+        });
+    }
+
+    /// The finished sections in stream order. Chapters that received no audio
+    /// (e.g. marks past the end of a truncated file) are left out.
+    fn finish(mut self) -> Vec<Section> {
+        self.finish_current(None);
+        let chaptered = self.done.len() > 1;
+        self.done
+            .into_iter()
+            .filter(|s| !chaptered || s.frames > 0)
+            .collect()
+    }
+}
+
+/// Chapter marks from the container's cue points, or failing that the chapter
+/// marks read from the file, in stream order. Marks at or past the end of the
+/// stream (a CD lead-out) are dropped, and fewer than two chapters means the
+/// file is a single track: the result is then empty.
+fn extract_chapters(
+    format: &dyn FormatReader,
+    marks: &[ChapterMark],
+    time_base: Option<TimeBase>,
+    sample_rate: usize,
+    n_frames: Option<u64>,
+) -> Vec<Chapter> {
+    let end = n_frames.map_or(usize::MAX, |n| ts_to_frames(n, time_base, sample_rate));
+    let mut chapters: Vec<Chapter> = format
+        .cues()
+        .iter()
+        .map(|cue| Chapter {
+            start: ts_to_frames(cue.start_ts, time_base, sample_rate),
+            number: cue.index,
+            title: cue
+                .tags
+                .iter()
+                .find(|tag| tag.std_key == Some(symphonia::core::meta::StandardTagKey::TrackTitle))
+                .map(|tag| tag.value.to_string()),
+        })
+        .filter(|chapter| chapter.start < end)
+        .collect();
+    if chapters.len() < 2 {
+        chapters = marks
+            .iter()
+            .zip(1..)
+            .map(|(mark, number)| Chapter {
+                start: (mark.start_secs * sample_rate as f64).round() as usize,
+                number,
+                title: mark.title.clone(),
+            })
+            .filter(|chapter| chapter.start < end)
+            .collect();
+    }
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters.dedup_by_key(|chapter| chapter.start);
+    if chapters.len() < 2 {
+        chapters.clear();
+    }
+    chapters
+}
+
+/// Per-channel block RMS and peak of the K-weighted signal. Blocks share their
+/// boundaries with the unweighted statistics in `StreamingDrState`.
+struct WeightedBlocks {
+    filter: KWeighting,
+    ch_sum_sq: Vec<f64>,
+    ch_peak: Vec<f64>,
+    block_rms: Vec<Vec<f64>>,
+    block_peaks: Vec<Vec<f64>>,
+}
+
+impl WeightedBlocks {
     fn new(channels: usize, sample_rate: usize) -> Self {
+        Self {
+            filter: KWeighting::new(channels, sample_rate),
+            ch_sum_sq: vec![0.0; channels],
+            ch_peak: vec![0.0; channels],
+            block_rms: (0..channels).map(|_| Vec::new()).collect(),
+            block_peaks: (0..channels).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    /// Filter whole interleaved frames and add them to the current block.
+    fn accumulate(&mut self, frames: &[f32]) {
+        let channels = self.ch_sum_sq.len();
+        for frame in frames.chunks_exact(channels) {
+            for (ch, &s) in frame.iter().enumerate() {
+                let y = self.filter.process(ch, s as f64);
+                self.ch_sum_sq[ch] += y * y;
+                self.ch_peak[ch] = self.ch_peak[ch].max(y.abs());
+            }
+        }
+    }
+
+    /// The blocks of a whole stream from those of its consecutive channel
+    /// groups, in channel order.
+    fn merge_channels(parts: Vec<WeightedBlocks>) -> Self {
+        let mut filters = Vec::new();
+        let mut merged = Self::new(0, 0);
+        for part in parts {
+            filters.push(part.filter);
+            merged.ch_sum_sq.extend(part.ch_sum_sq);
+            merged.ch_peak.extend(part.ch_peak);
+            merged.block_rms.extend(part.block_rms);
+            merged.block_peaks.extend(part.block_peaks);
+        }
+        merged.filter = KWeighting::merge_channels(filters);
+        merged
+    }
+
+    fn finish_block(&mut self, block_frames: usize) {
+        for ch in 0..self.ch_sum_sq.len() {
+            let rms = dr_rms(self.ch_sum_sq[ch], block_frames);
+            self.block_rms[ch].push(rms);
+            self.block_peaks[ch].push(self.ch_peak[ch]);
+        }
+        self.ch_sum_sq.iter_mut().for_each(|v| *v = 0.0);
+        self.ch_peak.iter_mut().for_each(|v| *v = 0.0);
+    }
+}
+
+/// Final statistics produced by `StreamingDrState::finalize`.
+struct DrStats {
+    dr: u32,
+    // `dr` before rounding; `None` without a complete block
+    dr_precise: Option<f64>,
+    peak_db: f64,
+    true_peak_db: f64,
+    rms_db: f64,
+    duration_secs: f64,
+    loudness_lufs: Option<f64>,
+    stereo_correlation: Option<f64>,
+    fingerprint: Option<String>,
+    perceptual_dr: Option<u32>,
+    momentary_lufs: Vec<f64>,
+    short_term_lufs: Vec<f64>,
+    envelope_db: Vec<f64>,
+    peak_at_secs: Option<f64>,
+    loudest_block_secs: Option<f64>,
+    quietest_block_secs: Option<f64>,
+    trim_edges_secs: Option<f64>,
+    noise_floor_db: Option<f64>,
+    // Rounded DR of each channel, in stream order (with `per_channel`)
+    channel_dr: Vec<u32>,
+    num_blocks: u32,
+    silent_blocks_trimmed: Option<u32>,
+    peak_fallback: bool,
+    // Computed by `legacy_block_dr`
+    legacy_dr: bool,
+    sample_rate: u32,
+    channels: u32,
+}
+
+impl DrStats {
+    fn into_track(self, title: String, filename: String, file_bytes: u64) -> TrackResult {
+        let mut track = TrackResult {
+            dr: self.dr,
+            dr_precise: self.dr_precise,
+            peak_db: self.peak_db,
+            true_peak_db: self.true_peak_db,
+            rms_db: self.rms_db,
+            crest_factor_db: crest_factor_db(self.peak_db, self.rms_db),
+            duration_secs: self.duration_secs,
+            title,
+            album: None,
+            filename,
+            track_number: None,
+            disc: None,
+            file_bytes,
+            loudness_lufs: self.loudness_lufs,
+            plr: plr(self.true_peak_db, self.loudness_lufs),
+            stereo_correlation: self.stereo_correlation,
+            fingerprint: self.fingerprint,
+            perceptual_dr: self.perceptual_dr,
+            momentary_lufs: self.momentary_lufs,
+            short_term_lufs: self.short_term_lufs,
+            envelope_db: self.envelope_db,
+            peak_at_secs: self.peak_at_secs,
+            loudest_block_secs: self.loudest_block_secs,
+            quietest_block_secs: self.quietest_block_secs,
+            range_start_secs: None,
+            trim_edges_secs: self.trim_edges_secs,
+            applied_gain_db: None,
+            channel_dr: Vec::new(),
+            missing_tags: Vec::new(),
+            num_blocks: self.num_blocks,
+            silent_blocks_trimmed: self.silent_blocks_trimmed,
+            peak_fallback: self.peak_fallback,
+            legacy_dr: self.legacy_dr,
+            preview: false,
+            confidence: Confidence::High,
+            codec: None,
+            noise_floor_db: self.noise_floor_db,
+            sample_rate: Some(self.sample_rate),
+            channels: Some(self.channels),
+            decode_errors: 0,
+            declared_duration_secs: None,
+            lossy: false,
+            bits_per_sample: None,
+            sample_kind: None,
+        };
+        track.confidence = Confidence::of(&track);
+        track
+    }
+}
+
+impl StreamingDrState {
+    fn new(channels: usize, sample_rate: usize, options: &AnalysisOptions) -> Self {
         Self {
             channels,
             sample_rate,
-            block_frames: 3 * sample_rate,
+            block_frames: block_length(0, sample_rate),
+            blocks_done: 0,
             current_block_frames: 0,
             ch_sum_sq: vec![0.0; channels],
             ch_peak: vec![0.0; channels],
+            ch_peak_frame: vec![0; channels],
             block_rms: (0..channels).map(|_| Vec::new()).collect(),
             block_peaks: (0..channels).map(|_| Vec::new()).collect(),
             global_peak: 0.0,
+            global_peak_frame: 0,
+            frames_done: 0,
             residual: Vec::new(),
+            stereo: (options.stereo_stats && channels == 2).then_some([0.0; 3]),
+            true_peak: TruePeakMeter::new(channels, sample_rate),
+            loudness: LoudnessMeter::new(channels, sample_rate),
+            weighted: options
+                .k_weighted
+                .then(|| WeightedBlocks::new(channels, sample_rate)),
+            loudness_series: options.loudness_series,
+            envelope: options.envelope,
+            noise_floor: options.noise_floor,
+            per_channel: options.per_channel,
+            legacy_dr: options.legacy_dr,
+            edges: options
+                .trim_edges_secs
+                .map(|secs| EdgeTrim::new(secs, channels, sample_rate)),
+            trim_silence: options.trim_silence,
         }
     }
 
     /// Feed interleaved samples into the streaming state.
     /// Processes complete 3-second blocks as they fill up.
     fn push_samples(&mut self, interleaved: &[f32]) {
+        if let Some(mut edges) = self.edges.take() {
+            edges.push(interleaved, |kept| self.push_untrimmed(kept));
+            self.edges = Some(edges);
+        } else {
+            self.push_untrimmed(interleaved);
+        }
+    }
+
+    fn push_untrimmed(&mut self, interleaved: &[f32]) {
         let channels = self.channels;
         if channels == 0 {
             return;
         }
-        let block_size = self.block_frames * channels;
-
         // If we have residual samples from the previous packet, prepend them
         let samples: &[f32] = if self.residual.is_empty() {
             interleaved
@@ -107,20 +1080,20 @@ impl StreamingDrState {
             // Take ownership of the data for processing
             let data = std::mem::take(&mut self.residual);
             // Process and store leftover back
-            self.process_slice(&data, block_size);
+            self.process_slice(&data);
             return;
         } else {
             samples
         };
 
-        self.process_slice(work, block_size);
+        self.process_slice(work);
     }
 
-    fn process_slice(&mut self, data: &[f32], block_size: usize) {
+    fn process_slice(&mut self, data: &[f32]) {
         let channels = self.channels;
         let mut offset = 0;
 
-        while offset + block_size <= data.len() + self.current_block_frames * channels {
+        loop {
             // How many more samples do we need to complete the current block?
             let remaining_frames = self.block_frames - self.current_block_frames;
             let remaining_samples = remaining_frames * channels;
@@ -132,67 +1105,56 @@ impl StreamingDrState {
             let chunk = &data[offset..offset + remaining_samples];
 
             // Accumulate into current block
-            for ch in 0..channels {
-                let mut sum_sq = self.ch_sum_sq[ch];
-                let mut peak = self.ch_peak[ch];
-                for &s in chunk.iter().skip(ch).step_by(channels) {
-                    let v = s as f64;
-                    sum_sq += v * v;
-                    let abs_v = v.abs();
-                    if abs_v > peak {
-                        peak = abs_v;
-                    }
-                    if abs_v > self.global_peak {
-                        self.global_peak = abs_v;
-                    }
-                }
-                self.ch_sum_sq[ch] = sum_sq;
-                self.ch_peak[ch] = peak;
-            }
+            self.accumulate(chunk, self.frames_done + offset / channels);
 
             self.current_block_frames = self.block_frames;
+            if let Some(ref mut weighted) = self.weighted {
+                weighted.accumulate(chunk);
+                weighted.finish_block(self.block_frames);
+            }
 
             // Block complete — store RMS and peak, reset accumulators
-            let block_frames = self.block_frames as f64;
             for ch in 0..channels {
-                let rms = (2.0 * self.ch_sum_sq[ch] / block_frames).sqrt();
+                let rms = dr_rms(self.ch_sum_sq[ch], self.block_frames);
                 self.block_rms[ch].push(rms);
                 self.block_peaks[ch].push(self.ch_peak[ch]);
+                (self.global_peak, self.global_peak_frame) = earliest_peak(
+                    (self.global_peak, self.global_peak_frame),
+                    (self.ch_peak[ch], self.ch_peak_frame[ch]),
+                );
             }
             self.ch_sum_sq.iter_mut().for_each(|v| *v = 0.0);
             self.ch_peak.iter_mut().for_each(|v| *v = 0.0);
             self.current_block_frames = 0;
+            self.blocks_done += 1;
+            self.block_frames = block_length(self.blocks_done, self.sample_rate);
 
             offset += remaining_samples;
         }
 
         // Process leftover partial block samples (update accumulators but don't finalize)
-        let leftover = &data[offset..];
+        // Whole frames only: a trailing partial frame waits in `residual`
+        let leftover = &data[offset..offset + (data.len() - offset) / channels * channels];
         if !leftover.is_empty() {
             let leftover_frames = leftover.len() / channels;
-            for ch in 0..channels {
-                let mut sum_sq = self.ch_sum_sq[ch];
-                let mut peak = self.ch_peak[ch];
-                for &s in leftover.iter().skip(ch).step_by(channels) {
-                    let v = s as f64;
-                    sum_sq += v * v;
-                    let abs_v = v.abs();
-                    if abs_v > peak {
-                        peak = abs_v;
-                    }
-                    if abs_v > self.global_peak {
-                        self.global_peak = abs_v;
-                    }
-                }
-                self.ch_sum_sq[ch] = sum_sq;
-                self.ch_peak[ch] = peak;
-            }
+            self.accumulate(leftover, self.frames_done + offset / channels);
             self.current_block_frames += leftover_frames;
+            if let Some(ref mut weighted) = self.weighted {
+                weighted.accumulate(leftover);
+            }
         }
 
         // Store any sub-frame residual (shouldn't happen with well-formed data,
         // but be safe)
-        let consumed = offset + (leftover.len() / channels) * channels;
+        let consumed = offset + leftover.len();
+        self.frames_done += consumed / channels;
+
+        self.true_peak.push_frames(&data[..consumed]);
+        self.loudness.push_frames(&data[..consumed]);
+
+        if let Some(ref mut acc) = self.stereo {
+            accumulate_stereo(acc, &data[..consumed]);
+        }
         if consumed < data.len() {
             self.residual = data[consumed..].to_vec();
         } else {
@@ -200,69 +1162,436 @@ impl StreamingDrState {
         }
     }
 
+    /// Add whole interleaved frames, the first of them at `first_frame`, to the
+    /// current block's sums and peaks. The per-sample loop only takes maxima;
+    /// where a channel's block peak first occurs is looked up afterwards, and
+    /// only when these frames raise it.
+    fn accumulate(&mut self, frames: &[f32], first_frame: usize) {
+        let channels = self.channels;
+        for ch in 0..channels {
+            let mut sum_sq = self.ch_sum_sq[ch];
+            let mut peak = 0.0f64;
+            for &s in frames.iter().skip(ch).step_by(channels) {
+                let v = s as f64;
+                sum_sq += v * v;
+                peak = peak.max(v.abs());
+            }
+            self.ch_sum_sq[ch] = sum_sq;
+            if peak > self.ch_peak[ch] {
+                let at = frames
+                    .iter()
+                    .skip(ch)
+                    .step_by(channels)
+                    .position(|&s| (s as f64).abs() == peak)
+                    .unwrap_or(0);
+                self.ch_peak[ch] = peak;
+                self.ch_peak_frame[ch] = first_frame + at;
+            }
+        }
+    }
+
+    /// The absolute peak of every sample so far, including the in-progress
+    /// block, and the frame it first occurs at.
+    fn peak(&self) -> (f64, usize) {
+        self.ch_peak.iter().zip(&self.ch_peak_frame).fold(
+            (self.global_peak, self.global_peak_frame),
+            |peak, (&p, &f)| earliest_peak(peak, (p, f)),
+        )
+    }
+
+    /// The state of a whole stream from the states of its consecutive channel
+    /// groups, in channel order, all fed the same frames (see `ChannelSplit`).
+    /// The groups measure without stereo statistics or edge trimming, which
+    /// need the whole frame; the caller fills those in.
+    fn merge_channels(parts: Vec<StreamingDrState>) -> Self {
+        let mut parts = parts.into_iter();
+        let mut merged = parts.next().expect("at least one channel group");
+        let mut true_peaks = Vec::new();
+        let mut loudness = Vec::new();
+        let mut weighted = Vec::new();
+        let mut take_meters = |state: &mut StreamingDrState| {
+            true_peaks.push(std::mem::replace(
+                &mut state.true_peak,
+                TruePeakMeter::new(0, 0),
+            ));
+            loudness.push(std::mem::replace(
+                &mut state.loudness,
+                LoudnessMeter::new(0, 0),
+            ));
+            weighted.extend(state.weighted.take());
+        };
+        take_meters(&mut merged);
+        for mut part in parts {
+            take_meters(&mut part);
+            merged.channels += part.channels;
+            merged.ch_sum_sq.extend(part.ch_sum_sq);
+            merged.ch_peak.extend(part.ch_peak);
+            merged.ch_peak_frame.extend(part.ch_peak_frame);
+            merged.block_rms.extend(part.block_rms);
+            merged.block_peaks.extend(part.block_peaks);
+            (merged.global_peak, merged.global_peak_frame) = earliest_peak(
+                (merged.global_peak, merged.global_peak_frame),
+                (part.global_peak, part.global_peak_frame),
+            );
+        }
+        merged.true_peak = TruePeakMeter::merge_channels(true_peaks);
+        merged.loudness = LoudnessMeter::merge_channels(loudness);
+        merged.weighted = (!weighted.is_empty()).then(|| WeightedBlocks::merge_channels(weighted));
+        merged
+    }
+
     /// Finalize and compute DR stats. Discards any partial final block
     /// per the TT DR standard.
-    fn finalize(self, total_frames: usize) -> (u32, f64, f64, f64) {
+    fn finalize(self, total_frames: usize) -> DrStats {
+        self.snapshot(total_frames)
+    }
+
+    /// Compute DR stats over the blocks completed so far without consuming the
+    /// state, so analysis can continue (e.g. for periodic snapshots of a live stream).
+    fn snapshot(&self, total_frames: usize) -> DrStats {
         let channels = self.channels;
         let duration_secs = total_frames as f64 / self.sample_rate as f64;
 
-        let num_blocks = if channels > 0 {
+        // Pearson correlation of L and R (zero-mean audio assumed)
+        let stereo_correlation = self.stereo.and_then(|[lr, ll, rr]| {
+            let energy = (ll * rr).sqrt();
+            (energy > 0.0).then(|| lr / energy)
+        });
+        let true_peak_db = db_fs(self.true_peak.peak());
+        let loudness_lufs = self.loudness.integrated_lufs();
+        let fingerprint = fingerprint::from_blocks(&self.block_rms, &self.block_peaks);
+        let perceptual_dr = self
+            .weighted
+            .as_ref()
+            .map(|w| block_dr(&w.block_rms, &w.block_peaks).dr);
+        let trim_edges_secs = self.edges.as_ref().map(|e| e.secs);
+        let noise_floor_db = self
+            .noise_floor
+            .then(|| noise_floor(&self.block_rms))
+            .flatten()
+            .map(db_fs);
+        let (momentary_lufs, short_term_lufs) = if self.loudness_series {
+            (
+                self.loudness.momentary_lufs(),
+                self.loudness.short_term_lufs(),
+            )
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        let envelope_db = if self.envelope {
+            envelope(&self.block_rms)
+        } else {
+            Vec::new()
+        };
+        // Times in the track: trimmed edges still count
+        let trimmed_secs = trim_edges_secs.unwrap_or(0.0);
+        let rate = self.sample_rate as f64;
+        let (peak, peak_frame) = self.peak();
+        let peak_at_secs = (peak > 0.0).then(|| peak_frame as f64 / rate + trimmed_secs);
+        let block_secs =
+            |k: usize| block_start_frame(k, self.sample_rate) as f64 / rate + trimmed_secs;
+        let (loudest_block_secs, quietest_block_secs) = match extreme_blocks(&self.block_rms) {
+            Some((loudest, quietest)) => (Some(block_secs(loudest)), Some(block_secs(quietest))),
+            None => (None, None),
+        };
+
+        let all_blocks = if channels > 0 {
             self.block_rms[0].len()
         } else {
             0
         };
+        let (first, last) = if self.trim_silence {
+            audible_blocks(&self.block_rms)
+        } else {
+            (0, all_blocks)
+        };
+        let num_blocks = last - first;
+        let silent_blocks_trimmed = self
+            .trim_silence
+            .then_some((all_blocks - num_blocks) as u32);
 
         if num_blocks == 0 || channels == 0 {
-            return (0, db_fs(self.global_peak), -f64::INFINITY, duration_secs);
+            return DrStats {
+                dr: 0,
+                dr_precise: None,
+                peak_db: db_fs(peak),
+                true_peak_db,
+                rms_db: -f64::INFINITY,
+                duration_secs,
+                loudness_lufs,
+                stereo_correlation,
+                fingerprint,
+                perceptual_dr,
+                momentary_lufs,
+                short_term_lufs,
+                envelope_db,
+                peak_at_secs,
+                loudest_block_secs,
+                quietest_block_secs,
+                trim_edges_secs,
+                noise_floor_db,
+                channel_dr: Vec::new(),
+                num_blocks: 0,
+                silent_blocks_trimmed,
+                peak_fallback: false,
+                legacy_dr: self.legacy_dr,
+                sample_rate: self.sample_rate as u32,
+                channels: channels as u32,
+            };
+        }
+
+        let audible = |blocks: &[Vec<f64>]| -> Vec<Vec<f64>> {
+            blocks.iter().map(|ch| ch[first..last].to_vec()).collect()
+        };
+        let (block_rms, block_peaks) = (audible(&self.block_rms), audible(&self.block_peaks));
+        let mut stats = block_dr(&block_rms, &block_peaks);
+        if self.legacy_dr {
+            stats.mean_dr = legacy_block_dr(&block_rms, &block_peaks);
+            stats.dr = round_to_dr(stats.mean_dr);
         }
+        let channel_dr = if self.per_channel {
+            stats
+                .channel_drs
+                .iter()
+                .map(|&dr| round_to_dr(dr))
+                .collect()
+        } else {
+            Vec::new()
+        };
 
-        let mut channel_drs: Vec<f64> = Vec::with_capacity(channels);
-        let mut report_rms = 0.0f64;
+        DrStats {
+            dr: stats.dr,
+            dr_precise: Some(stats.mean_dr),
+            peak_db: db_fs(peak),
+            true_peak_db,
+            rms_db: db_fs(stats.report_rms),
+            duration_secs,
+            loudness_lufs,
+            stereo_correlation,
+            fingerprint,
+            perceptual_dr,
+            momentary_lufs,
+            short_term_lufs,
+            envelope_db,
+            peak_at_secs,
+            loudest_block_secs,
+            quietest_block_secs,
+            trim_edges_secs,
+            noise_floor_db,
+            channel_dr,
+            num_blocks: num_blocks as u32,
+            silent_blocks_trimmed,
+            // `second_highest` has only the one block's peak to offer
+            peak_fallback: num_blocks == 1,
+            legacy_dr: self.legacy_dr,
+            sample_rate: self.sample_rate as u32,
+            channels: channels as u32,
+        }
+    }
+}
 
-        for ch in 0..channels {
-            let mut ch_rms: Vec<f64> = self.block_rms[ch].clone();
-            let mut ch_peaks: Vec<f64> = self.block_peaks[ch].clone();
+/// Floor of `envelope` levels, so digital silence stays a number in JSON.
+const ENVELOPE_FLOOR_DB: f64 = -120.0;
+
+/// Each block's RMS in dB (DR-RMS scale), the loudest channel's, floored at
+/// `ENVELOPE_FLOOR_DB`.
+fn envelope(block_rms: &[Vec<f64>]) -> Vec<f64> {
+    let num_blocks = block_rms.first().map_or(0, |b| b.len());
+    (0..num_blocks)
+        .map(|i| {
+            let loudest = block_rms.iter().map(|ch| ch[i]).fold(0.0, f64::max);
+            db_fs(loudest).max(ENVELOPE_FLOOR_DB)
+        })
+        .collect()
+}
 
-            // Sort RMS descending
-            ch_rms.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+/// The louder of two `(peak, frame)` pairs, or at an equal level the earlier.
+/// Channels are measured in turn, so an equal peak found later can still be
+/// the first in the stream.
+fn earliest_peak(a: (f64, usize), b: (f64, usize)) -> (f64, usize) {
+    if b.0 > a.0 || (b.0 == a.0 && b.1 < a.1) {
+        b
+    } else {
+        a
+    }
+}
 
-            // Top 20% RMS — combine via quadratic mean (RMS of RMS values)
-            let top_count = ((num_blocks as f64 * 0.2).ceil() as usize).max(1);
-            let sum_sq: f64 = ch_rms.iter().take(top_count).map(|v| v * v).sum();
-            let combined_rms = (sum_sq / top_count as f64).sqrt();
+/// Indices of the loudest and quietest complete blocks, each block judged by
+/// its loudest channel as in `envelope`. The first wins a tie. `None` without
+/// a complete block.
+fn extreme_blocks(block_rms: &[Vec<f64>]) -> Option<(usize, usize)> {
+    let num_blocks = block_rms.first().map_or(0, |b| b.len());
+    let level = |i: usize| block_rms.iter().map(|ch| ch[i]).fold(0.0, f64::max);
+    let mut extremes: Option<(usize, usize)> = None;
+    for i in 0..num_blocks {
+        extremes = Some(match extremes {
+            None => (i, i),
+            Some((loudest, quietest)) => (
+                if level(i) > level(loudest) {
+                    i
+                } else {
+                    loudest
+                },
+                if level(i) < level(quietest) {
+                    i
+                } else {
+                    quietest
+                },
+            ),
+        });
+    }
+    extremes
+}
 
-            // Sort peaks descending, use 2nd-highest (fall back to highest if < 2 blocks)
-            ch_peaks.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
-            let peak = if ch_peaks.len() >= 2 {
-                ch_peaks[1]
-            } else {
-                ch_peaks[0]
-            };
+/// Level (dBFS, DR-RMS scale) below which a block counts as silent for
+/// `--trim-silence`. Low enough that fade-outs and quiet intros are kept, high
+/// enough to catch dithered padding.
+pub const SILENCE_THRESHOLD_DB: f64 = -60.0;
+
+/// The `[first, last)` blocks between the leading and trailing runs of silent
+/// blocks, a block being silent when every channel's RMS is below
+/// `SILENCE_THRESHOLD_DB`. Silence inside the track is kept. `(0, 0)` when
+/// every block is silent.
+fn audible_blocks(block_rms: &[Vec<f64>]) -> (usize, usize) {
+    let threshold = 10f64.powf(SILENCE_THRESHOLD_DB / 20.0);
+    let num_blocks = block_rms.first().map_or(0, |b| b.len());
+    let audible = |i: &usize| block_rms.iter().any(|ch| ch[*i] >= threshold);
+    let Some(first) = (0..num_blocks).find(audible) else {
+        return (0, 0);
+    };
+    let last = (first..num_blocks).rev().find(audible).unwrap_or(first);
+    (first, last + 1)
+}
 
-            // Per-channel DR
-            if peak > 0.0 && combined_rms > 0.0 {
-                channel_drs.push(20.0 * (peak / combined_rms).log10());
-            } else {
-                channel_drs.push(0.0);
-            }
+/// Share of blocks, quietest first, whose RMS makes up the noise floor.
+const NOISE_FLOOR_FRACTION: f64 = 0.05;
+
+/// Linear noise-floor estimate: the median level of the quietest
+/// `NOISE_FLOOR_FRACTION` of blocks (at least one; the lower middle one for
+/// an even count), each block's level being the RMS across channels. Taking
+/// a level rather than averaging power keeps one louder block in that set
+/// from swamping the floor. Digitally silent blocks (lead-in, gaps) are left
+/// out, as they'd put the floor at -inf. `None` if no block has any signal.
+fn noise_floor(block_rms: &[Vec<f64>]) -> Option<f64> {
+    let channels = block_rms.len();
+    let num_blocks = block_rms.first().map_or(0, |b| b.len());
+    let mut levels: Vec<f64> = (0..num_blocks)
+        .map(|i| block_rms.iter().map(|ch| ch[i] * ch[i]).sum::<f64>() / channels as f64)
+        .filter(|&mean_sq| mean_sq > 0.0)
+        .collect();
+    if levels.is_empty() {
+        return None;
+    }
+    levels.sort_by(f64::total_cmp);
+    let quietest = ((levels.len() as f64 * NOISE_FLOOR_FRACTION).ceil() as usize).max(1);
+    Some(levels[(quietest - 1) / 2].sqrt())
+}
 
-            if combined_rms > report_rms {
-                report_rms = combined_rms;
-            }
-        }
+/// Sort descending in a fully specified order: NaN sorts below every number
+/// (including -inf), and equal values keep their original (block) order.
+fn sort_descending(values: &mut [f64]) {
+    values.sort_by(|a, b| match (a.is_nan(), b.is_nan()) {
+        (true, true) => std::cmp::Ordering::Equal,
+        (true, false) => std::cmp::Ordering::Greater,
+        (false, true) => std::cmp::Ordering::Less,
+        (false, false) => b.total_cmp(a),
+    });
+}
 
-        // Final DR = mean of per-channel DR values, rounded
-        let dr = if channel_drs.is_empty() {
-            0
-        } else {
-            let mean_dr: f64 = channel_drs.iter().sum::<f64>() / channel_drs.len() as f64;
-            mean_dr.round() as u32
+/// The 2nd-highest value (the highest if there is only one), ordered as by
+/// `sort_descending`. Sorts `values` in place; must not be empty.
+fn second_highest(values: &mut [f64]) -> f64 {
+    sort_descending(values);
+    values[1.min(values.len() - 1)]
+}
+
+/// DR computed from per-channel block statistics by `block_dr`.
+struct BlockDr {
+    dr: u32,
+    /// `dr` before rounding
+    mean_dr: f64,
+    /// The loudest channel's top-20% RMS
+    report_rms: f64,
+    /// Each channel's unrounded DR, averaged into `dr`
+    channel_drs: Vec<f64>,
+}
+
+/// DR from per-channel block RMS and peak values. Zero throughout if there
+/// are no complete blocks.
+fn block_dr(block_rms: &[Vec<f64>], block_peaks: &[Vec<f64>]) -> BlockDr {
+    let channels = block_rms.len();
+    let num_blocks = block_rms.first().map_or(0, |b| b.len());
+    if num_blocks == 0 {
+        return BlockDr {
+            dr: 0,
+            mean_dr: 0.0,
+            report_rms: 0.0,
+            channel_drs: Vec::new(),
         };
+    }
+    let mut channel_drs: Vec<f64> = Vec::with_capacity(channels);
+    let mut report_rms = 0.0f64;
+
+    for ch in 0..channels {
+        let mut ch_rms: Vec<f64> = block_rms[ch].clone();
+        let mut ch_peaks: Vec<f64> = block_peaks[ch].clone();
+
+        // Sort RMS descending
+        sort_descending(&mut ch_rms);
+
+        // Top 20% RMS — combine via quadratic mean (RMS of RMS values)
+        let top_count = ((num_blocks as f64 * 0.2).ceil() as usize).max(1);
+        let sum_sq: f64 = ch_rms.iter().take(top_count).map(|v| v * v).sum();
+        let combined_rms = (sum_sq / top_count as f64).sqrt();
+
+        // Sort peaks descending, use 2nd-highest (fall back to highest if < 2 blocks)
+        let peak = second_highest(&mut ch_peaks);
+
+        // Per-channel DR
+        if peak > 0.0 && combined_rms > 0.0 {
+            channel_drs.push(20.0 * (peak / combined_rms).log10());
+        } else {
+            channel_drs.push(0.0);
+        }
+
+        if combined_rms > report_rms {
+            report_rms = combined_rms;
+        }
+    }
+
+    // Final DR = mean of per-channel DR values, rounded
+    let mean_dr = if channel_drs.is_empty() {
+        0.0
+    } else {
+        channel_drs.iter().sum::<f64>() / channel_drs.len() as f64
+    };
 
-        (dr, db_fs(self.global_peak), db_fs(report_rms), duration_secs)
+    BlockDr {
+        dr: round_to_dr(mean_dr),
+        mean_dr,
+        report_rms,
+        channel_drs,
     }
 }
 
+/// Unrounded DR by the older single-pass method (`--legacy-dr`), as some historical
+/// reference figures were measured: each block's channels are combined first,
+/// its RMS as their quadratic mean and its peak as the highest, and the top-20%
+/// selection and 2nd-highest peak are then taken once over those combined
+/// blocks. The standard method (`block_dr`) measures each channel on its own
+/// and averages the DRs, so the two differ when the channels' dynamics do.
+fn legacy_block_dr(block_rms: &[Vec<f64>], block_peaks: &[Vec<f64>]) -> f64 {
+    let channels = block_rms.len();
+    let num_blocks = block_rms.first().map_or(0, |b| b.len());
+    let rms: Vec<f64> = (0..num_blocks)
+        .map(|i| (block_rms.iter().map(|ch| ch[i] * ch[i]).sum::<f64>() / channels as f64).sqrt())
+        .collect();
+    let peaks: Vec<f64> = (0..num_blocks)
+        .map(|i| block_peaks.iter().map(|ch| ch[i]).fold(0.0, f64::max))
+        .collect();
+    block_dr(&[rms], &[peaks]).mean_dr
+}
+
 #[cfg(test)]
 /// Per-channel data for a single 3-second block.
 struct BlockStats {
@@ -292,8 +1621,7 @@ fn compute_block_stats(samples: &[f32], channels: usize) -> BlockStats {
                 ch_peak = abs_v;
             }
         }
-        // DR-RMS: sqrt(2) factor calibrates sine peak = sine RMS
-        rms[ch] = (2.0 * sum_sq / frames as f64).sqrt();
+        rms[ch] = dr_rms(sum_sq, frames);
         peak[ch] = ch_peak;
     }
 
@@ -341,7 +1669,7 @@ fn compute_dr(all_samples: &[f32], channels: usize, sample_rate: usize) -> (u32,
         let mut ch_peaks: Vec<f64> = blocks.iter().map(|b| b.peak[ch]).collect();
 
         // Sort RMS descending
-        ch_rms.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+        sort_descending(&mut ch_rms);
 
         // Top 20% RMS — combine via quadratic mean (RMS of RMS values)
         let top_count = ((num_blocks as f64 * 0.2).ceil() as usize).max(1);
@@ -349,12 +1677,7 @@ fn compute_dr(all_samples: &[f32], channels: usize, sample_rate: usize) -> (u32,
         let combined_rms = (sum_sq / top_count as f64).sqrt();
 
         // Sort peaks descending, use 2nd-highest (fall back to highest if < 2 blocks)
-        ch_peaks.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
-        let peak = if ch_peaks.len() >= 2 {
-            ch_peaks[1]
-        } else {
-            ch_peaks[0]
-        };
+        let peak = second_highest(&mut ch_peaks);
 
         // Per-channel DR
         if peak > 0.0 && combined_rms > 0.0 {
@@ -377,7 +1700,7 @@ fn compute_dr(all_samples: &[f32], channels: usize, sample_rate: usize) -> (u32,
         0
     } else {
         let mean_dr: f64 = channel_drs.iter().sum::<f64>() / channel_drs.len() as f64;
-        mean_dr.round() as u32
+        round_to_dr(mean_dr)
     };
 
     // For peak_dB reporting, use the actual absolute peak across entire track
@@ -390,195 +1713,737 @@ fn compute_dr(all_samples: &[f32], channels: usize, sample_rate: usize) -> (u32,
     (dr, db_fs(absolute_peak), db_fs(report_rms))
 }
 
-/// Extract a track title from metadata, falling back to filename stem.
-fn extract_title(format: &mut dyn FormatReader, path: &Path) -> String {
-    // Try metadata from the format reader
-    if let Some(metadata) = format.metadata().current() {
-        for tag in metadata.tags() {
-            if tag.std_key == Some(symphonia::core::meta::StandardTagKey::TrackTitle) {
-                return tag.value.to_string();
+/// Packets read past the header looking for an album tag that only appears in
+/// a later metadata revision (`probe_album_tags`).
+const LATE_METADATA_PACKETS: usize = 16;
+
+/// Smallest rise in a file's progress passed to its progress callback, so a
+/// track reports about a hundred steps rather than one per packet.
+const PROGRESS_STEP: f32 = 0.01;
+
+/// Tags gathered from every metadata revision seen so far. Metadata can show
+/// up after the header: tags ahead of the container (ID3v2) are kept apart
+/// from it, a file can carry several RIFF INFO lists, and Ogg pushes the
+/// comments of each chained stream while packets are read. `current()` is only
+/// the oldest revision, so each one is folded in as it appears. Later values
+/// win.
+#[derive(Debug, Default)]
+struct Tags(Vec<Tag>);
+
+impl Tags {
+    /// Fold in every queued revision of `metadata`, leaving the latest one
+    /// current.
+    fn absorb(&mut self, mut metadata: Metadata) {
+        loop {
+            if let Some(revision) = metadata.current() {
+                self.0.extend(revision.tags().iter().cloned());
+            }
+            if metadata.pop().is_none() {
+                break;
             }
         }
     }
-    // Fall back to filename stem
-    path.file_stem()
+
+    /// The newest value of a standard tag.
+    fn get(&self, key: StandardTagKey) -> Option<String> {
+        let tag = self.0.iter().rev().find(|tag| tag.std_key == Some(key))?;
+        // RIFF INFO values are NUL-terminated and padded
+        Some(tag.value.to_string().trim_end_matches('\0').to_string())
+    }
+}
+
+/// Extract a track title according to `source`: from metadata (falling back to
+/// the filename stem), from the filename stem only, or both.
+fn extract_title(tags: &Tags, path: &Path, source: TitleSource) -> String {
+    let stem = path
+        .file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("Unknown")
-        .to_string()
+        .to_string();
+    if source == TitleSource::Filename {
+        return stem;
+    }
+
+    match (tags.get(StandardTagKey::TrackTitle), source) {
+        (Some(title), TitleSource::Both) if title != stem => format!("{} ({})", title, stem),
+        (Some(title), _) => title,
+        // Fall back to filename stem
+        (None, _) => stem,
+    }
 }
 
 /// Extract album name from metadata.
-fn extract_album(format: &mut dyn FormatReader) -> Option<String> {
-    if let Some(metadata) = format.metadata().current() {
-        for tag in metadata.tags() {
-            if tag.std_key == Some(symphonia::core::meta::StandardTagKey::Album) {
-                return Some(tag.value.to_string());
+fn extract_album(tags: &Tags) -> Option<String> {
+    tags.get(StandardTagKey::Album)
+}
+
+/// Extract the album artist from metadata, falling back to the track artist.
+fn extract_artist(tags: &Tags) -> Option<String> {
+    tags.get(StandardTagKey::AlbumArtist)
+        .or_else(|| tags.get(StandardTagKey::Artist))
+}
+
+/// The tags a report falls back on names for when absent: "title" (the
+/// filename stem) and "album" (the directory name).
+fn missing_tags(tags: &Tags) -> Vec<String> {
+    [
+        (StandardTagKey::TrackTitle, "title"),
+        (StandardTagKey::Album, "album"),
+    ]
+    .into_iter()
+    .filter(|&(key, _)| tags.get(key).is_none())
+    .map(|(_, name)| name.to_string())
+    .collect()
+}
+
+/// The REPLAYGAIN_TRACK_GAIN tag in dB, e.g. "-6.54 dB"; `None` if untagged
+/// or unparseable.
+fn extract_replaygain(tags: &Tags) -> Option<f64> {
+    let value = tags.get(StandardTagKey::ReplayGainTrackGain)?;
+    let value = value.trim();
+    let number = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .or_else(|| value.strip_suffix("DB"))
+        .unwrap_or(value);
+    number.trim().parse().ok().filter(|db: &f64| db.is_finite())
+}
+
+/// Short position labels by `Channels` bit, lowest first (the WAVEFORMATEXTENSIBLE
+/// order, which is also the order of channels in decoded buffers).
+const CHANNEL_LABELS: [&str; 26] = [
+    "FL", "FR", "FC", "LFE", "RL", "RR", "FLC", "FRC", "RC", "SL", "SR", "TC", "TFL", "TFC", "TFR",
+    "TRL", "TRC", "TRR", "RLC", "RRC", "FLW", "FRW", "FLH", "FCH", "FRH", "LFE2",
+];
+
+/// Labels for `count` decoded channels laid out as `layout`: "FL", "FR", "FC",
+/// "LFE", ... A lone channel is "mono". When the layout doesn't account for
+/// every channel, they are numbered instead ("ch0", "ch1", ...).
+fn channel_labels(layout: Channels, count: usize) -> Vec<String> {
+    if count == 1 {
+        return vec!["mono".to_string()];
+    }
+    if layout.count() != count {
+        return (0..count).map(|i| format!("ch{}", i)).collect();
+    }
+    layout
+        .iter()
+        .map(|channel| {
+            let bit = channel.bits().trailing_zeros() as usize;
+            CHANNEL_LABELS
+                .get(bit)
+                .map_or_else(|| format!("ch{}", bit), |l| l.to_string())
+        })
+        .collect()
+}
+
+/// Symphonia's short name for `codec`, e.g. "flac", "mp3", "pcm_s16le".
+fn codec_name(codec: CodecType) -> Option<String> {
+    symphonia::default::get_codecs()
+        .get_codec(codec)
+        .map(|descriptor| descriptor.short_name.to_string())
+}
+
+/// A name for `codec` in errors: symphonia's short name, or for codecs it
+/// knows of but has no decoder for, the format's common name.
+fn codec_label(codec: CodecType) -> String {
+    let unregistered = [
+        (CODEC_TYPE_OPUS, "opus"),
+        (CODEC_TYPE_SPEEX, "speex"),
+        (CODEC_TYPE_MUSEPACK, "musepack"),
+        (CODEC_TYPE_WMA, "wma"),
+        (CODEC_TYPE_WAVPACK, "wavpack"),
+        (CODEC_TYPE_MONKEYS_AUDIO, "monkeys_audio"),
+        (CODEC_TYPE_TTA, "tta"),
+        (CODEC_TYPE_ALAC, "alac"),
+        (CODEC_TYPE_AC4, "ac4"),
+        (CODEC_TYPE_EAC3, "eac3"),
+        (CODEC_TYPE_DCA, "dts"),
+        (CODEC_TYPE_ATRAC3, "atrac3"),
+        (CODEC_TYPE_ATRAC3PLUS, "atrac3plus"),
+    ];
+    codec_name(codec)
+        .or_else(|| {
+            unregistered
+                .iter()
+                .find(|(c, _)| *c == codec)
+                .map(|(_, n)| n.to_string())
+        })
+        .unwrap_or_else(|| format!("codec {}", codec))
+}
+
+/// Create the decoder for a stream. A codec without a built-in decoder is a
+/// `DrError::UnsupportedCodec`, so it isn't mistaken for a corrupt file.
+fn make_decoder(params: &CodecParameters) -> Result<Box<dyn Decoder>> {
+    match symphonia::default::get_codecs().make(params, &DecoderOptions::default()) {
+        Ok(decoder) => Ok(decoder),
+        Err(symphonia::core::errors::Error::Unsupported(_)) => Err(DrError::UnsupportedCodec {
+            codec: codec_label(params.codec),
+        }
+        .into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The sample layout an AIFF or AIFF-C stream declares in its COMM chunk.
+/// Symphonia reads big-endian PCM (8 to 32 bits), 16-bit `sowt` and `twos`,
+/// `fl32`/`fl64` floats, and A-law and µ-law, but rejects everything else
+/// while probing, with a message that doesn't say which stream it was.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AiffFormat {
+    /// The AIFF-C compression type; `NONE` for plain AIFF
+    compression: [u8; 4],
+    sample_size: u16,
+}
+
+impl AiffFormat {
+    /// Whether `err` from the probe is it refusing this layout, rather than
+    /// the file being damaged.
+    fn rejects(&self, err: &symphonia::core::errors::Error) -> bool {
+        use symphonia::core::errors::Error;
+        match err {
+            Error::Unsupported(_) => true,
+            Error::DecodeError(msg) => msg.contains("bits per sample"),
+            _ => false,
+        }
+    }
+
+    /// How `DrError::UnsupportedCodec` names the layout, e.g. "AIFF-C IMA
+    /// 4:1 ADPCM ('ima4')" or "AIFF with 20-bit samples".
+    fn label(&self) -> String {
+        const COMPRESSIONS: &[(&[u8; 4], &str)] = &[
+            (b"ima4", "IMA 4:1 ADPCM"),
+            (b"MAC3", "MACE 3:1"),
+            (b"MAC6", "MACE 6:1"),
+            (b"GSM ", "GSM 06.10"),
+            (b"Qclp", "QUALCOMM PureVoice"),
+        ];
+        let fourcc = String::from_utf8_lossy(&self.compression)
+            .trim_end()
+            .to_string();
+        match COMPRESSIONS.iter().find(|(c, _)| **c == self.compression) {
+            Some((_, name)) => format!("AIFF-C {} ('{}')", name, fourcc),
+            None if self.compression.eq_ignore_ascii_case(b"NONE") => {
+                format!("AIFF with {}-bit samples", self.sample_size)
             }
+            None => format!("AIFF-C '{}' with {}-bit samples", fourcc, self.sample_size),
         }
     }
+}
+
+/// The COMM chunk layout of an AIFF or AIFF-C stream, read from its first
+/// bytes. The bytes are rewound afterwards, so the probe sees the whole
+/// stream. `None` for anything else.
+fn peek_aiff_format(mss: &mut MediaSourceStream) -> Option<AiffFormat> {
+    let mut head = [0u8; 512];
+    let mut len = 0;
+    while len < head.len() {
+        match std::io::Read::read(mss, &mut head[len..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => len += n,
+        }
+    }
+    mss.seek_buffered_rev(len);
+    parse_aiff_format(&head[..len])
+}
+
+fn parse_aiff_format(head: &[u8]) -> Option<AiffFormat> {
+    if head.get(0..4)? != b"FORM" {
+        return None;
+    }
+    let aifc = match head.get(8..12)? {
+        b"AIFF" => false,
+        b"AIFC" => true,
+        _ => return None,
+    };
+    let mut pos = 12;
+    while let Some(chunk) = head.get(pos..pos + 8) {
+        let size = u32::from_be_bytes(chunk[4..8].try_into().ok()?) as usize;
+        if &chunk[0..4] == b"COMM" {
+            let data = head.get(pos + 8..)?;
+            let sample_size = u16::from_be_bytes(data.get(6..8)?.try_into().ok()?);
+            let compression = if aifc {
+                data.get(18..22)?.try_into().ok()?
+            } else {
+                *b"NONE"
+            };
+            return Some(AiffFormat {
+                compression,
+                sample_size,
+            });
+        }
+        // Chunks are padded to an even length
+        pos += 8 + size + size % 2;
+    }
     None
 }
 
+/// Whether `codec` is a lossy one, whose DR may differ from the lossless source.
+fn is_lossy(codec: CodecType) -> bool {
+    [
+        CODEC_TYPE_MP1,
+        CODEC_TYPE_MP2,
+        CODEC_TYPE_MP3,
+        CODEC_TYPE_AAC,
+        CODEC_TYPE_VORBIS,
+        CODEC_TYPE_OPUS,
+    ]
+    .contains(&codec)
+}
+
+/// How the source stores its samples, e.g. 24-bit integer, judged from the
+/// first decoded buffer. Decoders can use a wider buffer than the stored
+/// samples (24-bit FLAC decodes to 32-bit), so an integer source's own
+/// `bits_per_sample` takes precedence. `None` for lossy codecs, which have no
+/// intrinsic bit depth.
+fn sample_format(params: &CodecParameters, decoded: &AudioBufferRef) -> Option<(u32, SampleKind)> {
+    if is_lossy(params.codec) {
+        return None;
+    }
+    let (width, kind) = match decoded {
+        AudioBufferRef::U8(_) | AudioBufferRef::S8(_) => (8, SampleKind::Integer),
+        AudioBufferRef::U16(_) | AudioBufferRef::S16(_) => (16, SampleKind::Integer),
+        AudioBufferRef::U24(_) | AudioBufferRef::S24(_) => (24, SampleKind::Integer),
+        AudioBufferRef::U32(_) | AudioBufferRef::S32(_) => (32, SampleKind::Integer),
+        AudioBufferRef::F32(_) => (32, SampleKind::Float),
+        AudioBufferRef::F64(_) => (64, SampleKind::Float),
+    };
+    let bits = match kind {
+        SampleKind::Integer => params.bits_per_sample.map_or(width, |bits| bits.min(width)),
+        SampleKind::Float => width,
+    };
+    Some((bits, kind))
+}
+
+/// Extract the track number from metadata. Accepts "3" as well as "3/12"
+/// (number/total); `None` if untagged or unparseable.
+fn extract_track_number(tags: &Tags) -> Option<u32> {
+    parse_track_number(&tags.get(StandardTagKey::TrackNumber)?)
+}
+
+/// The DiscNumber tag, written like a track number ("2" or "2/3").
+fn extract_disc_number(tags: &Tags) -> Option<u32> {
+    parse_track_number(&tags.get(StandardTagKey::DiscNumber)?)
+}
+
+fn parse_track_number(value: &str) -> Option<u32> {
+    let number = value.trim_end_matches('\0').split('/').next()?.trim();
+    number.parse().ok().filter(|&n| n > 0)
+}
+
+/// Select the next logical stream after the format reader signals `ResetRequired`
+/// (chained Ogg) and create a decoder for it. Returns `None` when no audio track
+/// remains. The new stream must match the original sample rate and channel count,
+/// since its samples are accumulated into the same DR state.
+fn next_chained_track(
+    format: &dyn FormatReader,
+    sample_rate: usize,
+    channels: usize,
+) -> Result<Option<(u32, Box<dyn Decoder>)>> {
+    let track = match format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
+    {
+        Some(track) => track,
+        None => return Ok(None),
+    };
+
+    let params = &track.codec_params;
+    let next_rate = params.sample_rate.unwrap_or(44100) as usize;
+    let next_channels = params.channels.map(|c| c.count()).unwrap_or(2);
+    if next_rate != sample_rate || next_channels != channels {
+        anyhow::bail!(
+            "Chained stream changes format ({} Hz, {} ch -> {} Hz, {} ch)",
+            sample_rate,
+            channels,
+            next_rate,
+            next_channels
+        );
+    }
+
+    let decoder = make_decoder(params).context("Failed to create decoder for chained stream")?;
+
+    Ok(Some((track.id, decoder)))
+}
+
 /// Analyze a single audio file and return its DR measurement.
 pub fn analyze_file(path: &Path) -> Result<TrackResult> {
     analyze_file_with_progress(path, |_| {})
 }
 
-/// Analyze a single audio file, reporting progress via callback.
+/// Analyze a single audio file, reporting progress via callback: the fraction
+/// of the file decoded, in steps of at least 1%, then 1.0 at the end.
 pub fn analyze_file_with_progress(
     path: &Path,
     on_progress: impl Fn(f32),
 ) -> Result<TrackResult> {
+    analyze_file_with_options(path, &AnalysisOptions::default(), on_progress)
+}
+
+/// Analyze a single audio file with the given options, reporting progress via callback.
+pub fn analyze_file_with_options(
+    path: &Path,
+    options: &AnalysisOptions,
+    on_progress: impl Fn(f32),
+) -> Result<TrackResult> {
+    let mut tracks = analyze_file_sections(path, options, false, on_progress)?;
+    Ok(tracks.remove(0))
+}
+
+/// Analyze a file that may hold several logical tracks, such as a single-file
+/// album or DJ set with an embedded cue sheet, as an album with one track per
+/// chapter. A file without chapters (or analyzed with a time range) gives a
+/// one-track album. Chapters come from FLAC's CUESHEET block, MP4 `chpl`
+/// atoms and QuickTime chapter tracks, and Matroska `Chapters`.
+pub fn analyze_chaptered_file(
+    path: &Path,
+    options: &AnalysisOptions,
+    on_progress: impl Fn(f32),
+) -> Result<AlbumResult> {
+    let tracks = analyze_file_sections(path, options, true, on_progress)?;
+    Ok(build_album_result(
+        tracks,
+        extract_album_from_file(path),
+        options,
+    ))
+}
+
+/// Decode a file into one track, or one per chapter when `split_chapters` is
+/// set and the file has chapters.
+fn analyze_file_sections(
+    path: &Path,
+    options: &AnalysisOptions,
+    split_chapters: bool,
+    on_progress: impl Fn(f32),
+) -> Result<Vec<TrackResult>> {
     let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
     let file_size = file.metadata().map(|m| m.len()).unwrap_or(0);
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let chapter_marks = if split_chapters {
+        chapters::read_chapters(path).unwrap_or_else(|e| {
+            log::warn!("{}: ignoring its chapters: {:#}", path.display(), e);
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
 
     let mut hint = Hint::new();
     if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
         hint.with_extension(ext);
     }
 
-    let probed = symphonia::default::get_probe()
-        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
-        .with_context(|| format!("Failed to probe {}", path.display()))?;
-
-    let mut format = probed.format;
-
-    let track = format
-        .tracks()
-        .iter()
-        .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
-        .context("No audio track found")?;
-
-    let codec_params = track.codec_params.clone();
-    let track_id = track.id;
-    let sample_rate = codec_params.sample_rate.unwrap_or(44100) as usize;
-    let channels = codec_params
-        .channels
-        .map(|c| c.count())
-        .unwrap_or(2);
-
-    let title = extract_title(format.as_mut(), path);
-    let album_name = extract_album(format.as_mut());
-    let _ = album_name; // album is used at directory level
-
-    let mut decoder = symphonia::default::get_codecs()
-        .make(&codec_params, &DecoderOptions::default())
-        .context("Failed to create decoder")?;
-
-    let mut state = StreamingDrState::new(channels, sample_rate);
-    let mut bytes_decoded: u64 = 0;
-    let mut sample_buf: Option<SampleBuffer<f32>> = None;
-    let mut sample_buf_capacity: u64 = 0;
-    let mut total_frames: usize = 0;
+    let filename = path
+        .file_name()
+        .and_then(|f| f.to_str())
+        .unwrap_or("unknown");
+    let source = SourceInfo {
+        name: filename,
+        description: &path.display().to_string(),
+        size: file_size,
+        split_chapters,
+        chapter_marks: &chapter_marks,
+        labels: &StreamLabels::default(),
+    };
+    decode_source(Box::new(file), hint, &source, options, &on_progress, None)
+}
 
-    loop {
-        let packet = match format.next_packet() {
-            Ok(packet) => packet,
-            Err(symphonia::core::errors::Error::IoError(ref e))
-                if e.kind() == std::io::ErrorKind::UnexpectedEof =>
-            {
-                break;
-            }
-            Err(symphonia::core::errors::Error::ResetRequired) => {
-                break;
-            }
-            Err(e) => return Err(e.into()),
-        };
+/// Analyze audio from any media source, e.g. an in-memory buffer or a virtual
+/// filesystem. `name` is reported as the filename and its stem is the fallback
+/// title; `hint` helps the probe pick a format. The file, STDIN, and bytes
+/// entry points all delegate to the same decode loop.
+pub fn analyze_source(
+    source: Box<dyn MediaSource>,
+    hint: Hint,
+    name: &str,
+    options: &AnalysisOptions,
+    on_progress: impl Fn(f32),
+) -> Result<TrackResult> {
+    let size = source.byte_len().unwrap_or(0);
+    let info = SourceInfo {
+        name,
+        description: name,
+        size,
+        split_chapters: false,
+        chapter_marks: &[],
+        labels: &StreamLabels::default(),
+    };
+    let mut tracks = decode_source(source, hint, &info, options, &on_progress, None)?;
+    Ok(tracks.remove(0))
+}
 
-        if packet.track_id() != track_id {
-            continue;
-        }
+/// Analyze an in-memory audio file, with `format_hint` as for STDIN (e.g. "flac").
+pub fn analyze_bytes(
+    data: Vec<u8>,
+    format_hint: &str,
+    options: &AnalysisOptions,
+) -> Result<TrackResult> {
+    let mut hint = Hint::new();
+    hint.with_extension(format_hint);
+    analyze_source(
+        Box::new(std::io::Cursor::new(data)),
+        hint,
+        "bytes",
+        options,
+        |_| {},
+    )
+}
 
-        bytes_decoded += packet.data.len() as u64;
-        if file_size > 0 {
-            on_progress((bytes_decoded as f32 / file_size as f32).min(1.0));
-        }
+/// Analyze audio from STDIN. A `format_hint` (an extension such as "flac")
+/// takes precedence; without one the format is detected from the stream's
+/// first bytes, which works for most containers but not headerless streams
+/// such as raw ADTS AAC.
+pub fn analyze_stdin(format_hint: Option<&str>) -> Result<TrackResult> {
+    analyze_stdin_with_options(format_hint, &AnalysisOptions::default())
+}
 
-        let decoded = match decoder.decode(&packet) {
-            Ok(d) => d,
-            Err(symphonia::core::errors::Error::DecodeError(_)) => continue,
-            Err(e) => return Err(e.into()),
-        };
+/// Analyze audio from STDIN (see `analyze_stdin`) with the given options.
+pub fn analyze_stdin_with_options(
+    format_hint: Option<&str>,
+    options: &AnalysisOptions,
+) -> Result<TrackResult> {
+    analyze_stdin_with_snapshots(format_hint, &StreamLabels::default(), options, None, |_| {})
+}
 
-        let spec = *decoded.spec();
-        let num_frames = decoded.frames() as u64;
-        total_frames += num_frames as usize;
+/// The title and album to report for a stream that has no filename to fall
+/// back on (`--title`, `--album`). Each one set wins over the stream's tags.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamLabels {
+    pub title: Option<String>,
+    pub album: Option<String>,
+}
 
-        // Reuse SampleBuffer across packets; only reallocate if capacity is insufficient
-        let buf = if let Some(ref mut buf) = sample_buf {
-            if sample_buf_capacity < num_frames {
-                *buf = SampleBuffer::new(num_frames, spec);
-                sample_buf_capacity = num_frames;
-            }
-            buf
-        } else {
-            sample_buf = Some(SampleBuffer::new(num_frames, spec));
-            sample_buf_capacity = num_frames;
-            sample_buf.as_mut().unwrap()
-        };
+/// Analyze audio from STDIN, calling `on_snapshot` with the stats so far after
+/// every `snapshot_secs` of decoded audio (for monitoring streams of indefinite
+/// length), and reporting it under `labels`. The returned final result is the
+/// same as `analyze_stdin_with_options` apart from the labels.
+pub fn analyze_stdin_with_snapshots(
+    format_hint: Option<&str>,
+    labels: &StreamLabels,
+    options: &AnalysisOptions,
+    snapshot_secs: Option<f64>,
+    mut on_snapshot: impl FnMut(&TrackResult),
+) -> Result<TrackResult> {
+    let source = ReadOnlySource::new(std::io::stdin());
 
-        buf.copy_interleaved_ref(decoded);
-        state.push_samples(buf.samples());
+    let mut hint = Hint::new();
+    if let Some(ext) = format_hint {
+        hint.with_extension(ext);
     }
 
-    on_progress(1.0);
-
-    let (dr_value, peak_db, rms_db, duration_secs) = state.finalize(total_frames);
-
-    let filename = path
-        .file_name()
-        .and_then(|f| f.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+    let info = SourceInfo {
+        name: "STDIN",
+        description: "STDIN stream",
+        size: 0,
+        split_chapters: false,
+        chapter_marks: &[],
+        labels,
+    };
+    let snapshots = snapshot_secs.map(|secs| Snapshots {
+        every_secs: secs,
+        on_snapshot: &mut on_snapshot,
+    });
+    let mut tracks = decode_source(Box::new(source), hint, &info, options, &|_| {}, snapshots)?;
+    Ok(tracks.remove(0))
+}
 
-    Ok(TrackResult {
-        dr: dr_value,
-        peak_db,
-        rms_db,
-        duration_secs,
-        title,
-        filename,
-        file_bytes: file_size,
-    })
+/// What the decode loop needs to know about a source besides its audio.
+struct SourceInfo<'a> {
+    /// Reported as the track's filename; its stem is the fallback title
+    name: &'a str,
+    /// How error messages refer to the source (e.g. the file's path)
+    description: &'a str,
+    /// Size in bytes, for progress and `file_bytes`; 0 if unknown
+    size: u64,
+    /// Report one track per chapter when the container has chapters
+    split_chapters: bool,
+    /// Chapters read from the file for containers whose reader doesn't expose
+    /// them as cue points
+    chapter_marks: &'a [ChapterMark],
+    /// Title and album overriding the tags
+    labels: &'a StreamLabels,
 }
 
-/// Analyze audio from STDIN with a format hint.
-pub fn analyze_stdin(format_hint: &str) -> Result<TrackResult> {
-    let stdin = std::io::stdin();
-    let source = ReadOnlySource::new(stdin);
-    let mss = MediaSourceStream::new(Box::new(source), Default::default());
+impl SourceInfo<'_> {
+    /// The track title: the `labels` title, or from the tags and name.
+    fn title(&self, tags: &Tags, source: TitleSource) -> String {
+        match &self.labels.title {
+            Some(title) => title.clone(),
+            None => extract_title(tags, Path::new(self.name), source),
+        }
+    }
+}
 
-    let mut hint = Hint::new();
-    hint.with_extension(format_hint);
+/// Periodic partial results while decoding (`--follow`).
+struct Snapshots<'a> {
+    every_secs: f64,
+    on_snapshot: &'a mut dyn FnMut(&TrackResult),
+}
 
-    let probed = symphonia::default::get_probe()
-        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
-        .context("Failed to probe STDIN stream")?;
+/// The decode loop shared by every entry point: probe `source`, decode its
+/// first audio track (following chained streams), and measure it. Returns one
+/// track, or one per chapter (see `SourceInfo::split_chapters`).
+fn decode_source(
+    source: Box<dyn MediaSource>,
+    hint: Hint,
+    info: &SourceInfo,
+    options: &AnalysisOptions,
+    on_progress: &dyn Fn(f32),
+    mut snapshots: Option<Snapshots>,
+) -> Result<Vec<TrackResult>> {
+    let mut mss = MediaSourceStream::new(source, Default::default());
+    let aiff = peek_aiff_format(&mut mss);
+    let probe = symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    );
+    let mut probed = match (probe, aiff) {
+        (Ok(probed), _) => probed,
+        (Err(e), Some(aiff)) if aiff.rejects(&e) => {
+            let unsupported = DrError::UnsupportedCodec {
+                codec: aiff.label(),
+            };
+            return Err(anyhow::Error::from(unsupported))
+                .with_context(|| format!("Failed to probe {}", info.description));
+        }
+        (Err(e), _) => {
+            return Err(e).with_context(|| format!("Failed to probe {}", info.description));
+        }
+    };
 
+    let mut tags = Tags::default();
+    if let Some(metadata) = probed.metadata.get() {
+        tags.absorb(metadata);
+    }
     let mut format = probed.format;
+    tags.absorb(format.metadata());
 
     let track = format
         .tracks()
         .iter()
         .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
-        .context("No audio track found in STDIN")?;
+        .with_context(|| format!("No audio track found in {}", info.description))?;
 
     let codec_params = track.codec_params.clone();
-    let track_id = track.id;
-    let sample_rate = codec_params.sample_rate.unwrap_or(44100) as usize;
-    let channels = codec_params.channels.map(|c| c.count()).unwrap_or(2);
-
-    let title = "STDIN".to_string();
-
-    let mut decoder = symphonia::default::get_codecs()
-        .make(&codec_params, &DecoderOptions::default())
-        .context("Failed to create decoder")?;
+    let mut track_id = track.id;
+    let declared_rate = codec_params.sample_rate.unwrap_or(44100) as usize;
+    let declared_channels = codec_params.channels.map(|c| c.count()).unwrap_or(2);
+    let sample_rate = options
+        .force_sample_rate
+        .map_or(declared_rate, |rate| rate as usize);
+    let mut channels = options.force_channels.unwrap_or(declared_channels);
+    let forced = options.force_channels.is_some() || options.force_sample_rate.is_some();
+    if forced {
+        if channels == 0 || sample_rate == 0 {
+            anyhow::bail!("A forced channel count or sample rate must be positive");
+        }
+        if options.force_channels.is_some()
+            && (options.range.is_some() || options.preview_secs.is_some())
+        {
+            anyhow::bail!("A forced channel count can't be combined with a time range or preview");
+        }
+        log::warn!(
+            "{}: measuring as {} Hz, {} channel(s) instead of the declared {} Hz, {} channel(s); \
+             the DR is only right if the override matches the true layout",
+            info.name,
+            sample_rate,
+            channels,
+            declared_rate,
+            declared_channels
+        );
+    }
 
-    let mut state = StreamingDrState::new(channels, sample_rate);
+    let gain_db = if options.apply_existing_gain {
+        extract_replaygain(&tags)
+    } else {
+        None
+    };
+    let gain = gain_db.map(|db| 10f32.powf(db as f32 / 20.0));
+
+    let mut decoder = make_decoder(&codec_params).context("Failed to create decoder")?;
+    log::debug!(
+        "{}: {} stream, {} Hz, {} channel(s) declared",
+        info.name,
+        codec_name(codec_params.codec)
+            .as_deref()
+            .unwrap_or("unknown"),
+        sample_rate,
+        channels
+    );
+
+    let chapters = if info.split_chapters
+        && options.range.is_none()
+        && options.preview_secs.is_none()
+        && !forced
+    {
+        extract_chapters(
+            format.as_ref(),
+            info.chapter_marks,
+            codec_params.time_base,
+            sample_rate,
+            codec_params.n_frames,
+        )
+    } else {
+        Vec::new()
+    };
+    // Snapshots read the state mid-stream, which a split state can't offer
+    let threads = if snapshots.is_some() {
+        1
+    } else {
+        options.threads_per_file
+    };
+    let mut sections = Sections::new(channels, sample_rate, options, chapters, threads);
+    let mut bytes_decoded: u64 = 0;
+    let mut reported_progress: f32 = 0.0;
     let mut sample_buf: Option<SampleBuffer<f32>> = None;
     let mut sample_buf_capacity: u64 = 0;
     let mut total_frames: usize = 0;
+    let mut source_format = None;
+    let mut layout = None;
+    let snapshot_frames = snapshots
+        .as_ref()
+        .map(|s| ((s.every_secs * sample_rate as f64) as usize).max(1));
+    let mut next_snapshot = snapshot_frames.unwrap_or(usize::MAX);
+    let mut chained = false;
+
+    // With a time range, seek near the start when the container supports it
+    // (otherwise decode and discard), then trim decoded buffers to the exact
+    // frames. `position` is the stream frame index of the next decoded frame.
+    let (range_start, range_end) = options
+        .range
+        .map_or((0, usize::MAX), |r| r.frames(sample_rate));
+    // A preview ends the range early; reaching its end means the track was cut
+    let preview_end = options.preview_secs.map_or(usize::MAX, |secs| {
+        range_start.saturating_add((secs * sample_rate as f64).round() as usize)
+    });
+    let range_end = range_end.min(preview_end);
+    let mut position: usize = 0;
+    if let Some(range) = options.range.filter(|_| range_start > 0) {
+        let seek_to = SeekTo::Time {
+            time: Time::from(range.start_secs),
+            track_id: Some(track_id),
+        };
+        match format.seek(SeekMode::Accurate, seek_to) {
+            Ok(seeked) => {
+                decoder.reset();
+                position = ts_to_frames(seeked.actual_ts, codec_params.time_base, sample_rate);
+                log::debug!(
+                    "{}: seeked to {:.3}s, landed on frame {}",
+                    info.name,
+                    range.start_secs,
+                    position
+                );
+            }
+            Err(e) => log::debug!(
+                "{}: seek to {:.3}s failed ({}); decoding from the start",
+                info.name,
+                range.start_secs,
+                e
+            ),
+        }
+    }
 
     loop {
         let packet = match format.next_packet() {
@@ -588,7 +2453,20 @@ pub fn analyze_stdin(format_hint: &str) -> Result<TrackResult> {
             {
                 break;
             }
-            Err(symphonia::core::errors::Error::ResetRequired) => break,
+            Err(symphonia::core::errors::Error::ResetRequired) => {
+                // Chained streams (e.g. Ogg) start a new logical stream mid-file:
+                // switch to it and keep accumulating into the same DR state.
+                match next_chained_track(format.as_ref(), sample_rate, channels)? {
+                    Some((id, next_decoder)) => {
+                        log::debug!("{}: switching to chained stream {}", info.name, id);
+                        track_id = id;
+                        decoder = next_decoder;
+                        chained = true;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
             Err(e) => return Err(e.into()),
         };
 
@@ -596,16 +2474,72 @@ pub fn analyze_stdin(format_hint: &str) -> Result<TrackResult> {
             continue;
         }
 
+        bytes_decoded += packet.data.len() as u64;
+        if info.size > 0 {
+            let progress = (bytes_decoded as f32 / info.size as f32).min(1.0);
+            if progress - reported_progress >= PROGRESS_STEP {
+                on_progress(progress);
+                reported_progress = progress;
+            }
+        }
+
         let decoded = match decoder.decode(&packet) {
             Ok(d) => d,
-            Err(symphonia::core::errors::Error::DecodeError(_)) => continue,
+            Err(symphonia::core::errors::Error::DecodeError(e)) => {
+                // Recoverable: skip the packet, but count it
+                log::debug!(
+                    "{}: skipping packet at ts {}: {}",
+                    info.name,
+                    packet.ts(),
+                    e
+                );
+                sections.decode_error();
+                continue;
+            }
             Err(e) => return Err(e.into()),
         };
 
         let spec = *decoded.spec();
+        source_format = source_format.or_else(|| sample_format(&codec_params, &decoded));
+        if let Some(forced_channels) = options.force_channels {
+            // The samples are regrouped into frames of the forced width, so the
+            // decoded layout neither names the channels nor overrides the count
+            let decoded_channels = spec.channels.count();
+            if forced_channels % decoded_channels != 0 && decoded_channels % forced_channels != 0 {
+                anyhow::bail!(
+                    "Can't force {} channel(s) onto the {} decoded channel(s) of {}: \
+                     the count must be a multiple or divisor of it",
+                    forced_channels,
+                    decoded_channels,
+                    info.description
+                );
+            }
+        } else {
+            layout.get_or_insert(spec.channels);
+            // The decoded layout is authoritative: the container's channel count can
+            // be missing (the default of 2 is a guess) or disagree with the stream
+            if total_frames == 0 && spec.channels.count() != channels {
+                channels = spec.channels.count();
+                sections.reset(channels);
+            }
+        }
         let num_frames = decoded.frames() as u64;
-        total_frames += num_frames as usize;
 
+        // Frames of this buffer inside the requested range
+        let from = range_start
+            .saturating_sub(position)
+            .min(num_frames as usize);
+        let to = range_end.saturating_sub(position).min(num_frames as usize);
+        position += num_frames as usize;
+        if from >= to {
+            if position >= range_end {
+                break;
+            }
+            continue;
+        }
+        total_frames += to - from;
+
+        // Reuse SampleBuffer across packets; only reallocate if capacity is insufficient
         let buf = if let Some(ref mut buf) = sample_buf {
             if sample_buf_capacity < num_frames {
                 *buf = SampleBuffer::new(num_frames, spec);
@@ -619,20 +2553,130 @@ pub fn analyze_stdin(format_hint: &str) -> Result<TrackResult> {
         };
 
         buf.copy_interleaved_ref(decoded);
-        state.push_samples(buf.samples());
+        if let Some(gain) = gain {
+            // No clamping: peaks above full scale are reported as they are
+            buf.samples_mut().iter_mut().for_each(|s| *s *= gain);
+        }
+        let decoded_channels = spec.channels.count();
+        sections.push_samples(&buf.samples()[from * decoded_channels..to * decoded_channels]);
+
+        if let (Some(every), Some(snapshots)) = (snapshot_frames, snapshots.as_mut()) {
+            if total_frames >= next_snapshot {
+                let mut partial = sections.snapshot(
+                    info.title(&tags, options.title_source),
+                    info.name.to_string(),
+                );
+                partial.album = info.labels.album.clone();
+                (snapshots.on_snapshot)(&partial);
+                next_snapshot = (total_frames / every + 1) * every;
+            }
+        }
+        if position >= range_end {
+            break;
+        }
     }
 
-    let (dr_value, peak_db, rms_db, duration_secs) = state.finalize(total_frames);
+    on_progress(1.0);
+    let preview = position >= preview_end;
+
+    if total_frames == 0 && range_start > 0 {
+        anyhow::bail!(
+            "Start time {:.1}s is past the end of {}",
+            range_start as f64 / sample_rate as f64,
+            info.description
+        );
+    }
 
-    Ok(TrackResult {
-        dr: dr_value,
-        peak_db,
-        rms_db,
-        duration_secs,
-        title,
-        filename: "STDIN".to_string(),
-        file_bytes: 0,
-    })
+    // A decode that ends well short of (or past) the container's length has
+    // likely lost packets. Only a whole single stream can be compared: a range
+    // or preview stops early, and the first stream's length doesn't cover chained ones.
+    let declared_duration_secs = codec_params
+        .n_frames
+        .filter(|&n| n > 0 && options.range.is_none() && !preview && !chained && !forced)
+        .map(|n| ts_to_frames(n, codec_params.time_base, sample_rate) as f64 / sample_rate as f64)
+        .filter(|declared| {
+            (declared - position as f64 / sample_rate as f64).abs() > DURATION_TOLERANCE_SECS
+        });
+
+    // Tags can arrive with the packets; take in what the decode turned up
+    tags.absorb(format.metadata());
+    let title = info.title(&tags, options.title_source);
+    let track_number = extract_track_number(&tags);
+    let disc = extract_disc_number(&tags);
+    let mut missing_tags = missing_tags(&tags);
+    missing_tags.retain(|tag| match tag.as_str() {
+        "title" => info.labels.title.is_none(),
+        "album" => info.labels.album.is_none(),
+        _ => true,
+    });
+
+    let tracks = sections
+        .finish()
+        .into_iter()
+        .map(|mut section| {
+            let whole_file = section.chapter.is_none();
+            let (title, track_number, file_bytes) = match section.chapter {
+                Some(chapter) => (
+                    chapter
+                        .title
+                        .unwrap_or_else(|| format!("{} - Chapter {}", title, chapter.number)),
+                    Some(chapter.number),
+                    // Each chapter's share of the file
+                    (info.size as f64 * section.frames as f64 / total_frames.max(1) as f64) as u64,
+                ),
+                None => (title.clone(), track_number, info.size),
+            };
+            let channel_dr = std::mem::take(&mut section.stats.channel_dr);
+            let mut track = section
+                .stats
+                .into_track(title, info.name.to_string(), file_bytes);
+            track.channel_dr = channel_labels(layout.unwrap_or_default(), channel_dr.len())
+                .into_iter()
+                .zip(channel_dr)
+                .map(|(channel, dr)| ChannelDr { channel, dr })
+                .collect();
+            track.range_start_secs = options.range.map(|r| r.start_secs);
+            // The state counts from the range start; report times in the file
+            if let Some(start) = track.range_start_secs {
+                for secs in [
+                    &mut track.peak_at_secs,
+                    &mut track.loudest_block_secs,
+                    &mut track.quietest_block_secs,
+                ] {
+                    *secs = secs.map(|s| s + start);
+                }
+            }
+            track.preview = preview;
+            track.track_number = track_number;
+            track.disc = disc;
+            track.applied_gain_db = gain_db;
+            track.decode_errors = section.decode_errors;
+            track.declared_duration_secs = declared_duration_secs.filter(|_| whole_file);
+            track.confidence = Confidence::of(&track);
+            track.missing_tags = missing_tags.clone();
+            track.album = info.labels.album.clone();
+            track.lossy = is_lossy(codec_params.codec);
+            track.codec = codec_name(codec_params.codec);
+            (track.bits_per_sample, track.sample_kind) = source_format.unzip();
+            track
+        })
+        .collect();
+    Ok(tracks)
+}
+
+/// Analyze already-decoded interleaved f32 samples (e.g. synthetic signals).
+pub fn analyze_samples(
+    samples: &[f32],
+    channels: usize,
+    sample_rate: usize,
+    options: &AnalysisOptions,
+) -> TrackResult {
+    let mut state = SectionState::new(channels, sample_rate, options, options.threads_per_file);
+    state.push_samples(samples);
+    let total_frames = samples.len().checked_div(channels).unwrap_or(0);
+    state
+        .finalize(total_frames)
+        .into_track("samples".to_string(), "samples".to_string(), 0)
 }
 
 /// Return the default number of parallel jobs (number of CPU cores).
@@ -644,142 +2688,316 @@ pub fn default_jobs() -> usize {
 
 /// Analyze all audio files in a directory in parallel.
 pub fn analyze_directory(path: &Path, jobs: usize) -> Result<AlbumResult> {
-    let files = scan_audio_files(path);
-    if files.is_empty() {
-        anyhow::bail!("No audio files found in {}", path.display());
-    }
+    analyze_directory_with_timing(path, jobs, &AnalysisOptions::default(), |_, _| {})
+}
 
-    let jobs = jobs.max(1);
-    let files = Arc::new(files);
-    let next_index = Arc::new(AtomicUsize::new(0));
-
-    let mut handles = Vec::new();
-    for _ in 0..jobs.min(files.len()) {
-        let files = Arc::clone(&files);
-        let next_index = Arc::clone(&next_index);
-        handles.push(std::thread::spawn(move || {
-            let mut results = Vec::new();
-            loop {
-                let idx = next_index.fetch_add(1, Ordering::SeqCst);
-                if idx >= files.len() {
-                    break;
-                }
-                match analyze_file(&files[idx]) {
-                    Ok(result) => results.push((idx, Ok(result))),
-                    Err(e) => results.push((idx, Err(e))),
-                }
-            }
-            results
-        }));
-    }
+/// Analyze all audio files in a directory in parallel, calling `on_file` with each
+/// track's result and decode time as soon as that track completes.
+pub fn analyze_directory_with_timing<F>(
+    path: &Path,
+    jobs: usize,
+    options: &AnalysisOptions,
+    on_file: F,
+) -> Result<AlbumResult>
+where
+    F: Fn(&TrackResult, Duration) + Sync,
+{
+    analyze_directory_with_events(path, jobs, options, |event, elapsed| {
+        if let AnalysisEvent::TrackCompleted { result, .. } = event {
+            on_file(result, elapsed);
+        }
+    })
+}
 
-    // Collect results from all threads and sort by original index
-    let mut indexed_results: Vec<(usize, Result<TrackResult>)> = Vec::new();
-    for handle in handles {
-        indexed_results.extend(handle.join().unwrap());
+/// Analyze all audio files in a directory in parallel, calling `on_event` with
+/// each file's `TrackStarted`, `TrackProgress`, and `TrackCompleted` (or
+/// `Error`) events as they happen, from the worker threads. Event indices are
+/// positions in scan order. Like `analyze_directory`, any failed file fails
+/// the album; the events of the others are still delivered.
+pub fn analyze_directory_with_progress<F>(
+    path: &Path,
+    jobs: usize,
+    options: &AnalysisOptions,
+    on_event: F,
+) -> Result<AlbumResult>
+where
+    F: Fn(&AnalysisEvent) + Sync,
+{
+    analyze_directory_with_events(path, jobs, options, |event, _| on_event(event))
+}
+
+fn analyze_directory_with_events<F>(
+    path: &Path,
+    jobs: usize,
+    options: &AnalysisOptions,
+    on_event: F,
+) -> Result<AlbumResult>
+where
+    F: Fn(&AnalysisEvent, Duration) + Sync,
+{
+    let files = scan_audio_files_with_options(path, options)?;
+    if files.is_empty() {
+        return Err(DrError::NoAudio(path.to_path_buf()).into());
     }
-    indexed_results.sort_by_key(|(idx, _)| *idx);
+    let tracks = run_file_list(&files, jobs, options, &on_event)
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+    let album_tags = extract_album_from_file(files.first().unwrap());
 
-    let mut tracks = Vec::with_capacity(indexed_results.len());
-    for (_, result) in indexed_results {
-        tracks.push(result?);
+    Ok(build_album_result(tracks, album_tags, options))
+}
+
+/// Analyze every audio file in a directory as an independent single, with no
+/// album aggregation. Results are in scan order, one per file; a file that
+/// fails doesn't stop the others, and its error starts with its path.
+pub fn analyze_singles<F>(
+    path: &Path,
+    jobs: usize,
+    options: &AnalysisOptions,
+    on_file: F,
+) -> Result<Vec<Result<TrackResult>>>
+where
+    F: Fn(&TrackResult, Duration) + Sync,
+{
+    let files = scan_audio_files_with_options(path, options)?;
+    if files.is_empty() {
+        return Err(DrError::NoAudio(path.to_path_buf()).into());
     }
+    Ok(analyze_file_list(&files, jobs, options, on_file)
+        .into_iter()
+        .zip(&files)
+        .map(|(result, file)| result.with_context(|| file.display().to_string()))
+        .collect())
+}
 
-    let album_name = extract_album_from_file(files.first().unwrap());
+/// Analyze `files` on `jobs` worker threads, returning one result per file in
+/// input order. A file that fails doesn't stop the others.
+pub fn analyze_file_list<F>(
+    files: &[PathBuf],
+    jobs: usize,
+    options: &AnalysisOptions,
+    on_file: F,
+) -> Vec<Result<TrackResult>>
+where
+    F: Fn(&TrackResult, Duration) + Sync,
+{
+    run_file_list(files, jobs, options, &|event, elapsed| {
+        if let AnalysisEvent::TrackCompleted { result, .. } = event {
+            on_file(result, elapsed);
+        }
+    })
+}
 
-    let overall_dr = if tracks.is_empty() {
-        0
+/// Analyze `files` through `run_parallel`. `on_event` gets each file's events
+/// along with the time since that file was started.
+fn run_file_list(
+    files: &[PathBuf],
+    jobs: usize,
+    options: &AnalysisOptions,
+    on_event: &(dyn Fn(&AnalysisEvent, Duration) + Sync),
+) -> Vec<Result<TrackResult>> {
+    // Multi-disc tracks are named by their path below the album ("CD1/01.flac"),
+    // which keeps the same filename on two discs apart and orders the discs
+    let root = if options.multi_disc {
+        common_dir(files)
     } else {
-        let sum: f64 = tracks.iter().map(|t| t.dr as f64).sum();
-        (sum / tracks.len() as f64).round() as u32
+        None
     };
-
-    Ok(AlbumResult {
-        tracks,
-        overall_dr,
-        album: album_name,
+    run_parallel(files, jobs, |index, path| {
+        let start = Instant::now();
+        on_event(&AnalysisEvent::TrackStarted { index }, Duration::ZERO);
+        let result = analyze_file_with_options(path, options, |percent| {
+            on_event(
+                &AnalysisEvent::TrackProgress { index, percent },
+                start.elapsed(),
+            );
+        });
+        match result {
+            Ok(mut result) => {
+                if let Some(relative) = root.as_deref().and_then(|r| path.strip_prefix(r).ok()) {
+                    result.filename = relative.to_string_lossy().to_string();
+                    // Untagged discs are numbered by their folder
+                    if result.disc.is_none() {
+                        result.disc = relative
+                            .parent()
+                            .and_then(|d| folder_disc_number(&d.to_string_lossy()));
+                    }
+                }
+                let event = AnalysisEvent::TrackCompleted {
+                    index,
+                    result: result.clone(),
+                };
+                on_event(&event, start.elapsed());
+                Ok(result)
+            }
+            Err(e) => {
+                let message = e.to_string();
+                on_event(&AnalysisEvent::Error { index, message }, start.elapsed());
+                Err(e)
+            }
+        }
     })
 }
 
+/// The worker pool behind every multi-file analysis: `jobs` threads take
+/// files in order and call `per_file` with each one's index and path.
+/// Returns the results in input order.
+fn run_parallel<T, F>(files: &[PathBuf], jobs: usize, per_file: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize, &Path) -> T + Sync,
+{
+    let jobs = jobs.max(1);
+    let next_index = AtomicUsize::new(0);
+
+    // Collect results from all threads and sort by original index
+    let mut indexed_results: Vec<(usize, T)> = std::thread::scope(|scope| {
+        let mut handles = Vec::new();
+        for _ in 0..jobs.min(files.len()) {
+            let next_index = &next_index;
+            let per_file = &per_file;
+            handles.push(scope.spawn(move || {
+                let mut results = Vec::new();
+                loop {
+                    let index = next_index.fetch_add(1, Ordering::SeqCst);
+                    if index >= files.len() {
+                        break;
+                    }
+                    results.push((index, per_file(index, &files[index])));
+                }
+                results
+            }));
+        }
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    indexed_results.sort_by_key(|(index, _)| *index);
+
+    indexed_results
+        .into_iter()
+        .map(|(_, result)| result)
+        .collect()
+}
+
 /// Analyze a directory in parallel, sending progress events through a channel for TUI use.
 pub fn analyze_directory_async(
     path: &Path,
     sender: Sender<AnalysisEvent>,
     jobs: usize,
+    options: &AnalysisOptions,
 ) -> Result<()> {
-    let files = scan_audio_files(path);
+    let files = scan_audio_files_with_options(path, options)?;
     if files.is_empty() {
-        anyhow::bail!("No audio files found in {}", path.display());
+        return Err(DrError::NoAudio(path.to_path_buf()).into());
     }
+    analyze_files_async(files, sender, jobs, options)
+}
 
-    let jobs = jobs.max(1);
-    let files = Arc::new(files);
-    let next_index = Arc::new(AtomicUsize::new(0));
-    let sender = Arc::new(sender);
-
-    let mut handles = Vec::new();
-    for _ in 0..jobs.min(files.len()) {
-        let files = Arc::clone(&files);
-        let next_index = Arc::clone(&next_index);
-        let sender = Arc::clone(&sender);
-        handles.push(std::thread::spawn(move || {
-            let mut results = Vec::new();
-            loop {
-                let index = next_index.fetch_add(1, Ordering::SeqCst);
-                if index >= files.len() {
-                    break;
-                }
-                let _ = sender.send(AnalysisEvent::TrackStarted { index });
-                let sender_ref = &sender;
-                match analyze_file_with_progress(&files[index], |percent| {
-                    let _ = sender_ref.send(AnalysisEvent::TrackProgress { index, percent });
-                }) {
-                    Ok(result) => {
-                        let _ = sender.send(AnalysisEvent::TrackCompleted {
-                            index,
-                            result: result.clone(),
-                        });
-                        results.push(result);
-                    }
-                    Err(e) => {
-                        let _ = sender.send(AnalysisEvent::Error {
-                            index,
-                            message: e.to_string(),
-                        });
-                    }
-                }
+/// Analyze `files` as one album in parallel, sending progress events through a
+/// channel. Event indices are positions in `files`; `files` must not be empty.
+///
+/// As with `analyze_directory`, an album needs every track: if any file fails,
+/// the first failure (in `files` order) is returned. The `AlbumCompleted`
+/// event is still sent, over the tracks that did complete, with
+/// `failed_tracks` saying how many are missing.
+pub fn analyze_files_async(
+    files: Vec<PathBuf>,
+    sender: Sender<AnalysisEvent>,
+    jobs: usize,
+    options: &AnalysisOptions,
+) -> Result<()> {
+    let results = run_file_list(&files, jobs, options, &|event, _| {
+        let _ = sender.send(event.clone());
+    });
+    let mut tracks = Vec::new();
+    let mut first_error = None;
+    let mut failed_tracks = 0;
+    for result in results {
+        match result {
+            Ok(track) => tracks.push(track),
+            Err(e) => {
+                failed_tracks += 1;
+                first_error.get_or_insert(e);
             }
-            results
-        }));
+        }
     }
 
-    let mut tracks: Vec<TrackResult> = Vec::new();
-    for handle in handles {
-        tracks.extend(handle.join().unwrap());
-    }
+    let album_tags = extract_album_from_file(files.first().unwrap());
+    let mut result = build_album_result(tracks, album_tags, options);
+    result.failed_tracks = failed_tracks;
 
-    let album_name = extract_album_from_file(files.first().unwrap());
+    let _ = sender.send(AnalysisEvent::AlbumCompleted { result });
+    first_error.map_or(Ok(()), Err)
+}
 
-    let overall_dr = if tracks.is_empty() {
-        0
-    } else {
-        let sum: f64 = tracks.iter().map(|t| t.dr as f64).sum();
-        (sum / tracks.len() as f64).round() as u32
+/// Build an album result: overall DR combines the track DRs per `album_agg`
+/// (by default their rounded mean), without silent tracks with
+/// `skip_silent_in_average`. Tracks are put in track-number order when
+/// every track is tagged with one; otherwise they stay in scan (filename) order.
+/// Either way, multi-disc tracks stay grouped by disc: by their `disc`, then
+/// their filename's folder.
+fn build_album_result(
+    mut tracks: Vec<TrackResult>,
+    tags: AlbumTags,
+    options: &AnalysisOptions,
+) -> AlbumResult {
+    if tracks.iter().all(|t| t.track_number.is_some()) {
+        tracks.sort_by_cached_key(|t| {
+            let disc = Path::new(&t.filename)
+                .parent()
+                .map(|d| d.to_string_lossy().to_string());
+            // Untagged discs go after the numbered ones
+            (
+                t.disc.unwrap_or(u32::MAX),
+                disc_order(&disc.unwrap_or_default()),
+                t.track_number,
+            )
+        });
+    }
+    let perceptual: Option<Vec<u32>> = tracks.iter().map(|t| t.perceptual_dr).collect();
+    let perceptual_dr = perceptual
+        .filter(|drs| !drs.is_empty())
+        .map(|drs| round_to_dr(drs.iter().map(|&dr| dr as f64).sum::<f64>() / drs.len() as f64));
+    let album_lufs = loudness::combine_lufs(
+        tracks
+            .iter()
+            .filter_map(|t| t.loudness_lufs.map(|l| (l, t.duration_secs))),
+    );
+
+    let mut result = AlbumResult {
+        schema_version: SCHEMA_VERSION,
+        tracks,
+        album: tags.album,
+        artist: tags.artist,
+        album_lufs,
+        perceptual_dr,
+        ..Default::default()
     };
+    result.update_totals();
+    result.recompute_overall_dr_with(
+        options.album_agg,
+        options.skip_silent_in_average,
+        &options.dr_scale,
+    );
+    result
+}
 
-    let _ = sender.send(AnalysisEvent::AlbumCompleted {
-        result: AlbumResult {
-            tracks,
-            overall_dr,
-            album: album_name,
-        },
-    });
+/// The album name and artist (see `extract_album`, `extract_artist`), found by
+/// probing the first file's metadata.
+fn extract_album_from_file(path: &Path) -> AlbumTags {
+    probe_album_tags(path).unwrap_or_default()
+}
 
-    Ok(())
+/// Album-level tags read from one file.
+#[derive(Default)]
+struct AlbumTags {
+    album: Option<String>,
+    artist: Option<String>,
 }
 
-/// Extract album name by probing the first file's metadata.
-fn extract_album_from_file(path: &Path) -> Option<String> {
+fn probe_album_tags(path: &Path) -> Option<AlbumTags> {
     let file = File::open(path).ok()?;
     let mss = MediaSourceStream::new(Box::new(file), Default::default());
     let mut hint = Hint::new();
@@ -794,12 +3012,210 @@ fn extract_album_from_file(path: &Path) -> Option<String> {
             &MetadataOptions::default(),
         )
         .ok()?;
-    extract_album(probed.format.as_mut())
+    let mut tags = Tags::default();
+    if let Some(metadata) = probed.metadata.get() {
+        tags.absorb(metadata);
+    }
+    let format = probed.format.as_mut();
+    tags.absorb(format.metadata());
+    // Some containers only reveal their tags once packets are read
+    for _ in 0..LATE_METADATA_PACKETS {
+        if extract_album(&tags).is_some() {
+            break;
+        }
+        // A chained stream starts with a reset; its tags arrive along with it
+        match format.next_packet() {
+            Ok(_) | Err(symphonia::core::errors::Error::ResetRequired) => {}
+            Err(_) => break,
+        }
+        tags.absorb(format.metadata());
+    }
+    Some(AlbumTags {
+        album: extract_album(&tags),
+        artist: extract_artist(&tags),
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::signals;
+
+    #[test]
+    fn test_block_peak_selection_is_deterministic() {
+        let mut peaks = vec![0.5, f64::NAN, 0.9, 0.2, 0.9, f64::NEG_INFINITY, 0.5];
+        assert_eq!(second_highest(&mut peaks), 0.9);
+        let (numbers, nans) = peaks.split_at(6);
+        assert_eq!(numbers, [0.9, 0.9, 0.5, 0.5, 0.2, f64::NEG_INFINITY]);
+        assert!(nans[0].is_nan());
+
+        // NaN never outranks a real peak, whatever its position or sign
+        assert_eq!(second_highest(&mut [f64::NAN, 0.3, -f64::NAN, 0.7]), 0.3);
+        assert_eq!(second_highest(&mut [0.4]), 0.4);
+        assert!(second_highest(&mut [0.4, f64::NAN]).is_nan());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*Live*", "1994 - Live in Paris"));
+        assert!(glob_match("*Live*", "Live"));
+        assert!(!glob_match("*Live*", "1994 - live in Paris"));
+        assert!(glob_match("19?? - *", "1994 - Album"));
+        assert!(!glob_match("19?? - *", "2001 - Album"));
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(!glob_match("*a*b", "xaxxa"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("?", ""));
+        assert!(glob_match("Album", "Album"));
+    }
+
+    #[test]
+    fn test_tags_prefer_the_newest_revision() {
+        use symphonia::core::meta::{MetadataBuilder, MetadataLog, Value};
+
+        let revision = |key, value: &str| {
+            let mut builder = MetadataBuilder::new();
+            builder.add_tag(Tag::new(Some(key), "", Value::from(value)));
+            builder.metadata()
+        };
+        let mut log = MetadataLog::default();
+        log.push(revision(StandardTagKey::TrackTitle, "First"));
+        log.push(revision(StandardTagKey::Album, "Second\0\0"));
+        log.push(revision(StandardTagKey::TrackTitle, "Third"));
+
+        let mut tags = Tags::default();
+        tags.absorb(log.metadata());
+        assert_eq!(
+            tags.get(StandardTagKey::TrackTitle).as_deref(),
+            Some("Third")
+        );
+        assert_eq!(tags.get(StandardTagKey::Album).as_deref(), Some("Second"));
+        assert_eq!(tags.get(StandardTagKey::Artist), None);
+    }
+
+    #[test]
+    fn test_album_agg_median_resists_outliers() {
+        let album = |drs: &[u32], album_agg| {
+            let tracks = drs
+                .iter()
+                .map(|&dr| TrackResult {
+                    dr,
+                    ..Default::default()
+                })
+                .collect();
+            let options = AnalysisOptions {
+                album_agg,
+                ..Default::default()
+            };
+            build_album_result(tracks, AlbumTags::default(), &options).overall_dr
+        };
+        assert_eq!(album(&[3, 3, 15], AlbumAggregate::Mean), 7);
+        assert_eq!(album(&[3, 3, 15], AlbumAggregate::Median), 3);
+        // Even count: mean of the middle two, rounded half away from zero
+        assert_eq!(album(&[5, 9, 8, 20], AlbumAggregate::Median), 9);
+        assert_eq!(album(&[5, 9, 8, 20], AlbumAggregate::Mean), 11);
+        assert_eq!(album(&[], AlbumAggregate::Median), 0);
+    }
+
+    #[test]
+    fn test_tracks_grouped_by_disc_with_untagged_last() {
+        let track = |disc, track_number, title: &str| TrackResult {
+            disc,
+            track_number: Some(track_number),
+            title: title.to_string(),
+            ..Default::default()
+        };
+        let tracks = vec![
+            track(Some(2), 1, "Three"),
+            track(None, 1, "Bonus"),
+            track(Some(1), 2, "Two"),
+            track(Some(1), 1, "One"),
+        ];
+        let result = build_album_result(tracks, AlbumTags::default(), &AnalysisOptions::default());
+        let titles: Vec<&str> = result.tracks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["One", "Two", "Three", "Bonus"]);
+        let table = crate::format::format_table(&result);
+        let headers: Vec<&str> = table
+            .lines()
+            .filter(|l| l.starts_with("Disc") || l.starts_with("No disc"))
+            .collect();
+        assert_eq!(headers, ["Disc 1", "Disc 2", "No disc"]);
+    }
+
+    #[test]
+    fn test_skip_silent_in_average_leaves_out_silent_tracks() {
+        let track = |dr, rms_db| TrackResult {
+            dr,
+            rms_db,
+            ..Default::default()
+        };
+        let album = |tracks: Vec<TrackResult>, skip_silent_in_average| {
+            let options = AnalysisOptions {
+                skip_silent_in_average,
+                ..Default::default()
+            };
+            let result = build_album_result(tracks, AlbumTags::default(), &options);
+            (
+                result.overall_dr,
+                result.silent_tracks_excluded,
+                result.outliers.len(),
+            )
+        };
+        // A hidden-track gap, and a data track of near-silence
+        let tracks = vec![
+            track(12, -15.0),
+            track(10, -17.0),
+            track(0, f64::NEG_INFINITY),
+            track(0, -75.0),
+        ];
+        assert_eq!(album(tracks.clone(), false), (6, 0, 2));
+        assert_eq!(album(tracks, true), (11, 2, 0));
+        // A DR0 track at a normal level is loud, not silent
+        assert_eq!(
+            album(vec![track(12, -15.0), track(0, -3.0)], true),
+            (6, 0, 1)
+        );
+        // With nothing audible, nothing is left out
+        assert_eq!(album(vec![track(0, f64::NEG_INFINITY)], true), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_noise_floor_uses_quietest_non_silent_blocks() {
+        // 40 blocks: one silent, one at 0.001, the rest loud
+        let mut blocks = vec![0.5; 38];
+        blocks.extend([0.0, 0.001]);
+        let floor = noise_floor(&[blocks.clone(), blocks]).unwrap();
+        // 5% of the 39 non-silent blocks rounds up to two, 0.001 and 0.5; the
+        // loud one doesn't drag the floor up from -60 dB
+        assert!((20.0 * floor.log10() - -60.0).abs() < 1e-9, "{}", floor);
+
+        // Of the quietest five of 100 blocks, the middle one
+        let blocks: Vec<f64> = (1..=100).map(|i| i as f64 / 1000.0).collect();
+        assert!((noise_floor(&[blocks]).unwrap() - 0.003).abs() < 1e-12);
+
+        let floor = noise_floor(&[vec![0.5; 10], vec![0.1; 10]]).unwrap();
+        assert!((floor - ((0.25 + 0.01) / 2.0f64).sqrt()).abs() < 1e-12);
+        assert!(noise_floor(&[vec![0.0; 5]]).is_none());
+        assert!(noise_floor(&[vec![]]).is_none());
+    }
+
+    #[test]
+    fn test_is_lossy() {
+        assert!(is_lossy(CODEC_TYPE_MP3));
+        assert!(is_lossy(CODEC_TYPE_VORBIS));
+        assert!(!is_lossy(symphonia::core::codecs::CODEC_TYPE_FLAC));
+        assert!(!is_lossy(symphonia::core::codecs::CODEC_TYPE_PCM_S16LE));
+    }
+
+    #[test]
+    fn test_parse_track_number() {
+        assert_eq!(parse_track_number("3"), Some(3));
+        assert_eq!(parse_track_number("03/12"), Some(3));
+        assert_eq!(parse_track_number(" 7 \0"), Some(7));
+        assert_eq!(parse_track_number("0"), None);
+        assert_eq!(parse_track_number("A1"), None);
+        assert_eq!(parse_track_number(""), None);
+    }
 
     #[test]
     fn test_is_audio_file() {
@@ -819,6 +3235,18 @@ mod tests {
         assert!(db_fs(0.0).is_infinite());
     }
 
+    #[test]
+    fn test_dr_rms_calibrates_full_scale_sine_to_0_db() {
+        // 1 kHz at 48 kHz is a whole number of cycles per second
+        let sum_sq = |samples: &[f32]| samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>();
+        let full = crate::signals::sine(1000.0, 1.0, 1.0, 48000);
+        assert!(db_fs(dr_rms(sum_sq(&full), full.len())).abs() < 1e-3);
+        let half = crate::signals::sine(1000.0, 0.5, 1.0, 48000);
+        assert!((db_fs(dr_rms(sum_sq(&half), half.len())) + 6.021).abs() < 1e-3);
+        // A full-scale square wave (constant magnitude) reads +3 dB
+        assert!((dr_rms(48000.0, 48000) - 2f64.sqrt()).abs() < 1e-12);
+    }
+
     #[test]
     fn test_compute_block_stats() {
         // Mono silence — RMS should be 0, peak should be 0
@@ -858,13 +3286,7 @@ mod tests {
         // A full-scale sine wave: with the sqrt(2) calibration, DR-RMS equals peak
         // so DR should be ~0 (the crest factor is cancelled out by the sqrt(2) factor)
         let sample_rate = 44100;
-        let duration = 12.0;
-        let num_samples = (sample_rate as f64 * duration) as usize;
-        let mut samples = Vec::with_capacity(num_samples);
-        for i in 0..num_samples {
-            let t = i as f64 / sample_rate as f64;
-            samples.push((2.0 * std::f64::consts::PI * 440.0 * t).sin() as f32);
-        }
+        let samples = signals::sine(440.0, 1.0, 12.0, sample_rate as u32);
         let (dr, peak_db, rms_db) = compute_dr(&samples, 1, sample_rate);
         // Pure sine with sqrt(2) calibration: DR should be 0
         assert!(dr <= 1, "Pure sine DR should be ~0 with sqrt(2) calibration, got DR{}", dr);
@@ -872,4 +3294,365 @@ mod tests {
         // RMS should also be near 0 dBFS due to sqrt(2) factor
         assert!(rms_db > -1.0, "DR-RMS of sine should be near 0 dBFS, got {:.2}", rms_db);
     }
+
+    /// Feed `secs` of 2-channel audio in uneven packet sizes and count blocks.
+    fn streamed_block_count(sample_rate: usize, secs: f64) -> usize {
+        let frames = (sample_rate as f64 * secs) as usize;
+        let samples: Vec<f32> = (0..frames * 2)
+            .map(|i| ((i % 97) as f32 / 97.0) - 0.5)
+            .collect();
+        let mut state = StreamingDrState::new(2, sample_rate, &AnalysisOptions::default());
+        for packet in samples.chunks(1152 * 2 + 2) {
+            state.push_samples(packet);
+        }
+        state.block_rms[0].len()
+    }
+
+    #[test]
+    fn test_legacy_dr_diverges_on_asymmetric_stereo() {
+        let rate = 8000;
+        // A dynamic left channel beside a steady, quiet right one
+        let left = signals::pink_noise_with_dynamics(0.9, 30.0, rate as u32, 3);
+        let right = signals::sine(440.0, 0.05, 30.0, rate as u32);
+        let stereo: Vec<f32> = left
+            .iter()
+            .zip(&right)
+            .flat_map(|(&l, &r)| [l, r])
+            .collect();
+        let legacy = AnalysisOptions {
+            legacy_dr: true,
+            ..Default::default()
+        };
+
+        let standard = analyze_samples(&stereo, 2, rate, &AnalysisOptions::default());
+        let combined = analyze_samples(&stereo, 2, rate, &legacy);
+        // Per channel, the steady sine's DR0 halves the mean; combined, the
+        // quiet channel only lowers the RMS, by about 3 dB
+        assert!(!standard.legacy_dr && combined.legacy_dr);
+        assert!(
+            combined.dr >= standard.dr + 5,
+            "{} vs {}",
+            combined.dr,
+            standard.dr
+        );
+        assert_eq!(combined.rms_db, standard.rms_db);
+
+        // With one channel there is nothing to combine
+        let mono = |options| analyze_samples(&left, 1, rate, options).dr;
+        assert_eq!(mono(&legacy), mono(&AnalysisOptions::default()));
+    }
+
+    #[test]
+    fn test_peak_and_extreme_block_times() {
+        let rate = 8000;
+        let mut samples: Vec<f32> = [0.3, 0.05, 0.6, 0.3]
+            .iter()
+            .flat_map(|&amp| signals::sine(440.0, amp, 3.0, rate as u32))
+            .collect();
+        samples[(7.25 * rate as f64) as usize] = 0.95;
+        // Stereo, with the peak in the right channel only
+        let stereo: Vec<f32> = samples
+            .iter()
+            .flat_map(|&s| [s.clamp(-0.6, 0.6), s])
+            .collect();
+        let times = |options: &AnalysisOptions| {
+            let mut state = StreamingDrState::new(2, rate, options);
+            // Packets that don't line up with blocks
+            stereo
+                .chunks(2 * 1001)
+                .for_each(|packet| state.push_samples(packet));
+            let track = state
+                .finalize(samples.len())
+                .into_track(String::new(), String::new(), 0);
+            (
+                track.peak_at_secs,
+                track.loudest_block_secs,
+                track.quietest_block_secs,
+            )
+        };
+
+        assert_eq!(
+            times(&AnalysisOptions::default()),
+            (Some(7.25), Some(6.0), Some(3.0))
+        );
+        let trimmed = AnalysisOptions {
+            trim_edges_secs: Some(1.0),
+            ..Default::default()
+        };
+        // Blocks start after the trimmed second, but times stay in the track
+        assert_eq!(times(&trimmed), (Some(7.25), Some(7.0), Some(1.0)));
+        let silence = analyze_samples(&[0.0; 8000 * 4], 1, rate, &AnalysisOptions::default());
+        assert_eq!(silence.peak_at_secs, None);
+
+        // A peak in the unfinished last block still counts
+        let mut tail = signals::sine(440.0, 0.3, 4.0, rate as u32);
+        tail[(3.5 * rate as f64) as usize] = -0.9;
+        let track = analyze_samples(&tail, 1, rate, &AnalysisOptions::default());
+        assert_eq!(track.peak_at_secs, Some(3.5));
+        assert_eq!(track.peak_db, db_fs(0.9f32 as f64));
+    }
+
+    #[test]
+    fn test_trim_edges_leaves_out_track_boundaries() {
+        let rate = 8000;
+        let sine =
+            |secs: usize, amplitude: f32| signals::sine(440.0, amplitude, secs as f64, rate as u32);
+        // Loud crossfade regions around a quieter performance
+        let middle = sine(9, 0.3);
+        let mut track = sine(2, 0.9);
+        track.extend(&middle);
+        track.extend(sine(2, 0.9));
+
+        let options = AnalysisOptions {
+            trim_edges_secs: Some(2.0),
+            ..Default::default()
+        };
+        let mut state = StreamingDrState::new(1, rate, &options);
+        for chunk in track.chunks(1234) {
+            state.push_samples(chunk);
+        }
+        let trimmed = state
+            .finalize(track.len())
+            .into_track(String::new(), String::new(), 0);
+        let expected = analyze_samples(&middle, 1, rate, &AnalysisOptions::default());
+
+        assert_eq!(trimmed.dr, expected.dr);
+        assert!((trimmed.peak_db - expected.peak_db).abs() < 1e-9);
+        assert!((trimmed.rms_db - expected.rms_db).abs() < 1e-9);
+        // The reported duration is still the whole track's
+        assert_eq!(trimmed.duration_secs, 13.0);
+        assert_eq!(trimmed.trim_edges_secs, Some(2.0));
+        assert!(analyze_samples(&track, 1, rate, &AnalysisOptions::default()).peak_db > -1.0);
+    }
+
+    #[test]
+    fn test_trim_silence_drops_silent_padding() {
+        let rate = 8000;
+        let mut track = signals::sine(440.0, 0.5, 9.0, rate as u32);
+        let music = analyze_samples(&track, 1, rate, &AnalysisOptions::default());
+        // Three blocks of padding: digital silence, then faint dither
+        track.extend(std::iter::repeat_n(0.0, rate * 6));
+        track.extend((0..rate * 3).map(|i| if i % 2 == 0 { 1e-4 } else { -1e-4 }));
+
+        let options = AnalysisOptions {
+            trim_silence: true,
+            ..Default::default()
+        };
+        let trimmed = analyze_samples(&track, 1, rate, &options);
+        assert_eq!(trimmed.dr, music.dr);
+        assert!((trimmed.rms_db - music.rms_db).abs() < 1e-9);
+        assert_eq!(
+            (trimmed.num_blocks, trimmed.silent_blocks_trimmed),
+            (3, Some(3))
+        );
+        assert_eq!(trimmed.duration_secs, 18.0);
+
+        let padded = analyze_samples(&track, 1, rate, &AnalysisOptions::default());
+        assert_eq!((padded.num_blocks, padded.silent_blocks_trimmed), (6, None));
+    }
+
+    #[test]
+    fn test_audible_blocks_keeps_inner_silence() {
+        let loud = 0.5;
+        assert_eq!(audible_blocks(&[vec![0.0, loud, 0.0, loud, 1e-5]]), (1, 4));
+        // A block is audible if any channel is
+        assert_eq!(audible_blocks(&[vec![0.0, 0.0], vec![0.0, loud]]), (1, 2));
+        assert_eq!(audible_blocks(&[vec![0.0, 1e-6]]), (0, 0));
+        assert_eq!(audible_blocks(&[vec![]]), (0, 0));
+    }
+
+    #[test]
+    fn test_sections_split_at_chapter_marks() {
+        let rate = 8000;
+        let sine =
+            |secs: usize, amplitude: f32| signals::sine(440.0, amplitude, secs as f64, rate as u32);
+        let (first, second) = (sine(4, 0.5), sine(5, 0.1));
+        let stream: Vec<f32> = first.iter().chain(&second).copied().collect();
+        let chapter = |start: usize, number: u32| Chapter {
+            start,
+            number,
+            title: None,
+        };
+
+        let options = AnalysisOptions::default();
+        let mut sections = Sections::new(
+            1,
+            rate,
+            &options,
+            vec![chapter(0, 1), chapter(rate * 4, 2)],
+            1,
+        );
+        for chunk in stream.chunks(1234) {
+            sections.push_samples(chunk);
+        }
+        let sections = sections.finish();
+        assert_eq!(sections.len(), 2);
+        for (section, samples) in sections.iter().zip([&first, &second]) {
+            let expected = analyze_samples(samples, 1, rate, &options);
+            assert_eq!(section.frames, samples.len());
+            assert_eq!(section.stats.dr, expected.dr);
+            assert!((section.stats.peak_db - expected.peak_db).abs() < 1e-9);
+            assert!((section.stats.rms_db - expected.rms_db).abs() < 1e-9);
+        }
+        assert_eq!(sections[1].chapter, Some(chapter(rate * 4, 2)));
+
+        // Without chapters the stream is a single section
+        let mut sections = Sections::new(1, rate, &options, Vec::new(), 1);
+        sections.push_samples(&stream);
+        let sections = sections.finish();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].frames, stream.len());
+        assert!(sections[0].chapter.is_none());
+    }
+
+    #[test]
+    fn test_perceptual_dr_independent_of_packet_size() {
+        let rate = 8000;
+        let samples: Vec<f32> = (0..rate * 7 * 2)
+            .map(|i| ((i % 97) as f32 / 97.0 - 0.5) * if i < rate * 6 { 1.0 } else { 0.2 })
+            .collect();
+        let options = AnalysisOptions {
+            k_weighted: true,
+            ..Default::default()
+        };
+        let perceptual = |packet: usize| {
+            let mut state = StreamingDrState::new(2, rate, &options);
+            for chunk in samples.chunks(packet) {
+                state.push_samples(chunk);
+            }
+            let weighted = state.weighted.as_ref().unwrap();
+            (
+                weighted.block_rms.clone(),
+                state.finalize(samples.len() / 2).perceptual_dr,
+            )
+        };
+        let (whole_rms, whole_dr) = perceptual(samples.len());
+        let (split_rms, split_dr) = perceptual(1152 * 2 + 2);
+        assert!(whole_dr.is_some());
+        assert_eq!(whole_dr, split_dr);
+        for (a, b) in whole_rms[0].iter().zip(&split_rms[0]) {
+            assert!((a - b).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_channel_split_matches_one_thread() {
+        let rate = 8000;
+        let options = AnalysisOptions {
+            stereo_stats: true,
+            k_weighted: true,
+            loudness_series: true,
+            envelope: true,
+            noise_floor: true,
+            per_channel: true,
+            trim_edges_secs: Some(0.5),
+            ..Default::default()
+        };
+        for channels in [6, 2] {
+            let noise: Vec<Vec<f32>> = (0..channels)
+                .map(|ch| {
+                    crate::signals::pink_noise_with_dynamics(
+                        0.9 - 0.1 * ch as f32,
+                        10.0,
+                        rate as u32,
+                        ch as u64,
+                    )
+                })
+                .collect();
+            let mut samples: Vec<f32> = (0..noise[0].len())
+                .flat_map(|i| noise.iter().map(move |ch| ch[i]))
+                .collect();
+            // Equal peaks: the earlier one, in the later channel, is the peak
+            samples[9000 * channels] = 1.0;
+            samples[7000 * channels + channels - 1] = 1.0;
+
+            let measure = |threads: usize| {
+                let mut state = SectionState::new(channels, rate, &options, threads);
+                // Buffers that split frames
+                for chunk in samples.chunks(1001) {
+                    state.push_samples(chunk);
+                }
+                let track = state.finalize(samples.len() / channels).into_track(
+                    String::new(),
+                    String::new(),
+                    0,
+                );
+                let loudness = track.loudness_lufs.unwrap();
+                let mut json = serde_json::to_value(&track).unwrap();
+                for field in ["loudness_lufs", "plr", "momentary_lufs", "short_term_lufs"] {
+                    json.as_object_mut().unwrap().remove(field);
+                }
+                (json, loudness)
+            };
+            let (serial, serial_lufs) = measure(1);
+            assert_eq!(serial["peak_at_secs"], 0.875);
+            for threads in [2, 4, 9] {
+                let (split, split_lufs) = measure(threads);
+                assert_eq!(
+                    split, serial,
+                    "{} channels on {} threads",
+                    channels, threads
+                );
+                assert!((split_lufs - serial_lufs).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_block_count_matches_duration_at_48k_and_96k() {
+        for sample_rate in [48000, 96000] {
+            for secs in [2.9, 3.0, 10.5] {
+                assert_eq!(
+                    streamed_block_count(sample_rate, secs),
+                    (secs / 3.0).floor() as usize,
+                    "{} Hz, {} s",
+                    sample_rate,
+                    secs
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_disc_order_is_numeric() {
+        let mut discs = vec!["CD10", "Bonus", "cd2", "Disc 1"];
+        discs.sort_by_key(|d| disc_order(d));
+        assert_eq!(discs, ["Disc 1", "cd2", "CD10", "Bonus"]);
+        let files = [
+            PathBuf::from("/a/b/CD1/01.flac"),
+            PathBuf::from("/a/b/CD2/x/01.flac"),
+        ];
+        assert_eq!(common_dir(&files), Some(PathBuf::from("/a/b")));
+        assert_eq!(common_dir(&files[..1]), Some(PathBuf::from("/a/b/CD1")));
+    }
+
+    #[test]
+    fn test_channel_labels_follow_layout() {
+        let surround = Channels::FRONT_LEFT
+            | Channels::FRONT_RIGHT
+            | Channels::FRONT_CENTRE
+            | Channels::LFE1
+            | Channels::SIDE_LEFT
+            | Channels::SIDE_RIGHT;
+        assert_eq!(
+            channel_labels(surround, 6),
+            ["FL", "FR", "FC", "LFE", "SL", "SR"]
+        );
+        assert_eq!(channel_labels(Channels::FRONT_LEFT, 1), ["mono"]);
+        // A layout that doesn't match the decoded channel count is ignored
+        assert_eq!(
+            channel_labels(Channels::FRONT_LEFT | Channels::FRONT_RIGHT, 3),
+            ["ch0", "ch1", "ch2"]
+        );
+    }
+
+    #[test]
+    fn test_block_boundaries_from_elapsed_time() {
+        assert_eq!(block_start_frame(0, 44100), 0);
+        assert_eq!(block_start_frame(7, 44100), 7 * 132300);
+        assert_eq!(block_length(0, 48000), 144000);
+        // Boundaries are cumulative, so lengths always sum back to elapsed time
+        let total: usize = (0..1000).map(|k| block_length(k, 96000)).sum();
+        assert_eq!(total, block_start_frame(1000, 96000));
+    }
 }
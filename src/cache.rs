@@ -1,41 +1,315 @@
-use std::io;
-use std::path::Path;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-use crate::models::AlbumResult;
+use crate::analyzer::BLOCK_SECS;
+use crate::models::{crest_factor_db, plr, AlbumResult, Confidence, DrScale, SCHEMA_VERSION};
 
-const CACHE_FILENAME: &str = "dr_report.json";
+/// File stem of the reports unless overridden with `--report-basename`:
+/// `dr_report.json` (the cache) and `dr_report.txt`.
+pub const DEFAULT_REPORT_BASENAME: &str = "dr_report";
 
-/// Load a cached album result from `dr_report.json` in the given directory.
-/// Returns `None` if the file is missing or cannot be parsed.
-pub fn load_cached_report(dir: &Path) -> Option<AlbumResult> {
-    let path = dir.join(CACHE_FILENAME);
+/// Added to the basename of reports measured with options that change the
+/// report (`dr_report.nonstandard.json`), which are never read back as the
+/// cache; see `AnalysisOptions::matches_cached_report`.
+pub const NONSTANDARD_SUFFIX: &str = ".nonstandard";
+
+/// A report basename made from an album name: characters that aren't allowed
+/// in filenames on common systems (`/ \ : * ? " < > |` and control
+/// characters) become `_`, and surrounding spaces and dots are trimmed.
+/// "album" if nothing is left.
+pub fn sanitize_basename(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let trimmed = replaced.trim_matches(|c: char| c == ' ' || c == '.');
+    if trimmed.is_empty() {
+        "album".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// The report basenames handed out in one run to albums sharing a directory
+/// (`--report-into`). A name already taken, ignoring case, gets a " (2)",
+/// " (3)", ... suffix, so albums with the same name don't overwrite each other.
+#[derive(Debug, Default)]
+pub struct CollectionNames {
+    taken: HashSet<String>,
+}
+
+impl CollectionNames {
+    /// A basename for the album called `name` (see `sanitize_basename`) that no
+    /// earlier album in this run got.
+    pub fn claim(&mut self, name: &str) -> String {
+        let base = sanitize_basename(name);
+        let mut candidate = base.clone();
+        let mut n = 1;
+        while !self.taken.insert(candidate.to_lowercase()) {
+            n += 1;
+            candidate = format!("{} ({})", base, n);
+        }
+        candidate
+    }
+}
+
+/// Path of the `<basename>.<ext>` report in `dir`.
+pub fn report_path(dir: &Path, basename: &str, ext: &str) -> PathBuf {
+    dir.join(format!("{}.{}", basename, ext))
+}
+
+/// Load a cached album result from `<basename>.json` in the given directory.
+/// Returns `None` if the file is missing, cannot be parsed, or was written
+/// with a schema version that can't be migrated to the current one (i.e. it
+/// is stale).
+pub fn load_cached_report(dir: &Path, basename: &str) -> Option<AlbumResult> {
+    let path = report_path(dir, basename, "json");
     let data = std::fs::read_to_string(&path).ok()?;
-    serde_json::from_str(&data).ok()
+    let result: AlbumResult = match serde_json::from_str(&data) {
+        Ok(result) => result,
+        Err(e) => {
+            log::debug!("ignoring unreadable report {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let version = result.schema_version;
+    let migrated = migrate(result);
+    match &migrated {
+        None => log::debug!("report {} (schema v{}) is stale", path.display(), version),
+        Some(_) if version != SCHEMA_VERSION => {
+            log::debug!(
+                "migrated report {} from schema v{}",
+                path.display(),
+                version
+            )
+        }
+        Some(_) => log::debug!("using report {}", path.display()),
+    }
+    migrated
 }
 
-/// Save an album result as pretty-printed JSON to `dr_report.json` in the given directory.
-pub fn save_report(dir: &Path, result: &AlbumResult) -> io::Result<()> {
-    let path = dir.join(CACHE_FILENAME);
-    let json = serde_json::to_string_pretty(result)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-    std::fs::write(&path, json)
+/// Every loadable cached report in `base` and its subdirectories, at any
+/// depth, with the directory it was found in, sorted by path. Symlinked
+/// directories are not followed.
+pub fn find_cached_reports(base: &Path, basename: &str) -> Vec<(PathBuf, AlbumResult)> {
+    let mut found = Vec::new();
+    let mut pending = vec![base.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if let Some(report) = load_cached_report(&dir, basename) {
+            found.push((dir.clone(), report));
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        pending.extend(
+            entries
+                .flatten()
+                .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+                .map(|e| e.path()),
+        );
+    }
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    found
 }
 
-const TEXT_REPORT_FILENAME: &str = "dr_report.txt";
+/// Bring a deserialized report up to `SCHEMA_VERSION`, or return `None` if it
+/// can't be (including reports from a newer version of `dr`).
+fn migrate(mut result: AlbumResult) -> Option<AlbumResult> {
+    if result.schema_version == 0 {
+        // Pre-versioned reports lack only the derived rating
+        result.rate(&DrScale::default());
+        result.schema_version = 1;
+    }
+    if (1..=2).contains(&result.schema_version) && result.tracks.is_empty() {
+        // v2 added per-track true peak and v3 loudness, which can only be
+        // recovered by re-analysis
+        result.schema_version = 3;
+    }
+    if result.schema_version == 3 {
+        // v4 added album totals, derived from the tracks
+        result.update_totals();
+        result.schema_version = 4;
+    }
+    if result.schema_version == 4 {
+        // v5 added optional track fingerprints; duplicate detection skips tracks without one
+        result.schema_version = 5;
+    }
+    if result.schema_version == 5 {
+        // v6 added album outliers, derived from the tracks
+        result.update_outliers();
+        result.schema_version = 6;
+    }
+    if result.schema_version == 6 {
+        // v7 added per-track `lossy`. Most extensions settle it; containers
+        // that hold either kind of codec (ogg, m4a, wv) need re-analysis
+        let settled = result.tracks.iter_mut().all(|track| {
+            let lossy = lossy_from_extension(&track.filename);
+            track.lossy = lossy.unwrap_or(false);
+            lossy.is_some()
+        });
+        if settled {
+            result.schema_version = 7;
+        }
+    }
+    if result.schema_version == 7 {
+        // v8 added the optional source sample format, unknown for older reports
+        result.schema_version = 8;
+    }
+    if result.schema_version == 8 {
+        // v9 added per-track sample rate and channel count, unknown for older reports
+        result.schema_version = 9;
+    }
+    if result.schema_version == 9 {
+        // v10 added the optional album artist, unknown for older reports
+        result.schema_version = 10;
+    }
+    if result.schema_version == 10 {
+        // v11 added per-track missing tags, unknown for older reports
+        // (`--strict-metadata` re-analyzes instead of trusting them)
+        result.schema_version = 11;
+    }
+    if result.schema_version == 11 {
+        // v12 added per-track `peak_fallback`: one complete block, i.e. 3-6 seconds
+        for track in &mut result.tracks {
+            track.peak_fallback = (BLOCK_SECS..2.0 * BLOCK_SECS).contains(&track.duration_secs);
+        }
+        result.schema_version = 12;
+    }
+    if result.schema_version == 12 {
+        // v13 added the optional per-track codec, unknown for older reports
+        result.schema_version = 13;
+    }
+    if result.schema_version == 13 {
+        // v14 added the album peak, derived from the tracks
+        result.update_totals();
+        result.schema_version = 14;
+    }
+    if result.schema_version == 14 {
+        // v15 added per-track `num_blocks`: the whole blocks in the measured
+        // audio, which `--trim-edges` shortens at both ends
+        for track in &mut result.tracks {
+            let trimmed = 2.0 * track.trim_edges_secs.unwrap_or(0.0);
+            track.num_blocks = ((track.duration_secs - trimmed).max(0.0) / BLOCK_SECS) as u32;
+        }
+        result.schema_version = 15;
+    }
+    if result.schema_version == 15 {
+        // v16 added per-track `crest_factor_db`, derived from peak and RMS
+        for track in &mut result.tracks {
+            track.crest_factor_db = crest_factor_db(track.peak_db, track.rms_db);
+        }
+        result.schema_version = 16;
+    }
+    if result.schema_version == 16 {
+        // v17 added per-track `plr`, derived from true peak and loudness
+        for track in &mut result.tracks {
+            track.plr = plr(track.true_peak_db, track.loudness_lufs);
+        }
+        result.schema_version = 17;
+    }
+    if result.schema_version == 17 {
+        // v18 added per-track `confidence`, derived from the measurement flags
+        for track in &mut result.tracks {
+            track.confidence = Confidence::of(track);
+        }
+        result.schema_version = 18;
+    }
+    if result.schema_version == 18 {
+        // v19 added the optional per-track declared duration, unknown for older reports
+        result.schema_version = 19;
+    }
+    if result.schema_version == 19 {
+        // v20 added the optional per-track peak and loudest/quietest block times,
+        // unknown for older reports
+        result.schema_version = 20;
+    }
+    if result.schema_version == 20 {
+        // v21 added the optional per-track unrounded DR, unknown for older reports
+        result.schema_version = 21;
+    }
+    if result.schema_version == 21 {
+        // v22 added the optional per-track disc number, unknown for older reports
+        result.schema_version = 22;
+    }
+    if result.schema_version == 22 {
+        // v23 added each block's crest factor to the fingerprint. Older,
+        // level-only fingerprints can't be compared, so they are dropped and
+        // duplicate detection skips those tracks until they are re-analyzed
+        for track in &mut result.tracks {
+            track.fingerprint = None;
+        }
+        result.schema_version = 23;
+    }
+
+    if result.schema_version == SCHEMA_VERSION {
+        Some(result)
+    } else {
+        None
+    }
+}
 
-/// Check if all requested report files already exist in the given directory.
-pub fn reports_exist(dir: &Path, json: bool, txt: bool) -> bool {
-    if json && !dir.join(CACHE_FILENAME).exists() {
-        return false;
+/// Whether a file's extension alone says its codec is lossy; `None` when the
+/// container can hold either.
+fn lossy_from_extension(filename: &str) -> Option<bool> {
+    let ext = Path::new(filename).extension()?.to_str()?.to_lowercase();
+    match ext.as_str() {
+        "mp3" | "opus" => Some(true),
+        "flac" | "wav" | "aif" | "aiff" => Some(false),
+        _ => None,
     }
-    if txt && !dir.join(TEXT_REPORT_FILENAME).exists() {
-        return false;
+}
+
+/// Save an album result as pretty-printed JSON to `<basename>.json` in the given
+/// directory. The report is stamped with the current `SCHEMA_VERSION`.
+pub fn save_report(dir: &Path, basename: &str, result: &AlbumResult) -> io::Result<()> {
+    let path = report_path(dir, basename, "json");
+    let mut result = result.clone();
+    result.schema_version = SCHEMA_VERSION;
+    result.analysis = None;
+    let json = serde_json::to_string_pretty(&result).map_err(io::Error::other)?;
+    write_atomic(&path, json.as_bytes())?;
+    log::debug!("wrote report {}", path.display());
+    Ok(())
+}
+
+/// Write `contents` to a temporary file next to `path`, then rename it into
+/// place, so an interrupted write leaves the previous report intact rather
+/// than a truncated one.
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    let written = (|| {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)
+    })();
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp);
     }
-    true
+    written
+}
+
+/// Check if all requested report files already exist in the given directory.
+pub fn reports_exist(dir: &Path, basename: &str, json: bool, txt: bool, csv: bool) -> bool {
+    [(json, "json"), (txt, "txt"), (csv, "csv")]
+        .iter()
+        .all(|&(wanted, ext)| !wanted || report_path(dir, basename, ext).exists())
+}
+
+/// Save a text report to `<basename>.txt` in the given directory.
+pub fn save_text_report(dir: &Path, basename: &str, content: &str) -> io::Result<()> {
+    let path = report_path(dir, basename, "txt");
+    write_atomic(&path, content.as_bytes())
 }
 
-/// Save a text report to `dr_report.txt` in the given directory.
-pub fn save_text_report(dir: &Path, content: &str) -> io::Result<()> {
-    let path = dir.join(TEXT_REPORT_FILENAME);
-    std::fs::write(&path, content)
+/// Save a CSV report to `<basename>.csv` in the given directory.
+pub fn save_csv_report(dir: &Path, basename: &str, content: &str) -> io::Result<()> {
+    let path = report_path(dir, basename, "csv");
+    write_atomic(&path, content.as_bytes())
 }
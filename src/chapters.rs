@@ -0,0 +1,607 @@
+//! Chapter marks embedded in MP4 and Matroska files.
+//!
+//! Symphonia exposes FLAC's CUESHEET as cue points but parses neither MP4 nor
+//! Matroska chapters, so those are read here straight from the file. MP4 files
+//! (M4A, M4B) carry them as a Nero `chpl` atom under `moov/udta`, or as a
+//! QuickTime chapter track: a text track named by another track's `tref/chap`
+//! reference, with one sample per chapter. Matroska files (MKA, MKV, WebM) list
+//! them under `Segment/Chapters`, as the `ChapterAtom`s of an edition.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// Largest `moov` atom or `Chapters` element read into memory.
+const MAX_HEADER_BYTES: u64 = 64 << 20;
+/// Most chapter samples read from a QuickTime chapter track, which bounds the
+/// work a corrupt sample table can cause.
+const MAX_TRACK_CHAPTERS: usize = 10_000;
+
+const EBML_MAGIC: [u8; 4] = [0x1a, 0x45, 0xdf, 0xa3];
+const SEGMENT: u64 = 0x1853_8067;
+const CHAPTERS: u64 = 0x1043_a770;
+const EDITION_ENTRY: u64 = 0x45b9;
+const EDITION_FLAG_DEFAULT: u64 = 0x45db;
+const CHAPTER_ATOM: u64 = 0xb6;
+const CHAPTER_TIME_START: u64 = 0x91;
+const CHAPTER_FLAG_HIDDEN: u64 = 0x98;
+const CHAPTER_FLAG_ENABLED: u64 = 0x4598;
+const CHAPTER_DISPLAY: u64 = 0x80;
+const CHAP_STRING: u64 = 0x85;
+
+/// A chapter's start and title, as the container declares them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChapterMark {
+    /// Seconds from the start of the stream
+    pub start_secs: f64,
+    pub title: Option<String>,
+}
+
+/// The chapter marks embedded in an MP4 or Matroska file, in the order the
+/// container lists them. The format is recognized from the file's first bytes;
+/// other formats, and files without chapters, give none.
+pub fn read_chapters(path: &Path) -> Result<Vec<ChapterMark>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut magic = [0u8; 8];
+    if reader.read_exact(&mut magic).is_err() {
+        return Ok(Vec::new());
+    }
+    reader.seek(SeekFrom::Start(0))?;
+    if magic[..4] == EBML_MAGIC {
+        matroska_chapters(&mut reader).context("Failed to read Matroska chapters")
+    } else if &magic[4..] == b"ftyp" {
+        mp4_chapters(&mut reader).context("Failed to read MP4 chapters")
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+fn mp4_chapters<R: Read + Seek>(reader: &mut R) -> Result<Vec<ChapterMark>> {
+    let Some(moov) = top_level_atom(reader, b"moov")? else {
+        return Ok(Vec::new());
+    };
+    let nero = child_atom(&moov, b"udta")
+        .and_then(|udta| child_atom(udta, b"chpl"))
+        .map(parse_chpl)
+        .unwrap_or_default();
+    if !nero.is_empty() {
+        return Ok(nero);
+    }
+    chapter_track(reader, &moov)
+}
+
+/// The payload of the first top-level atom of type `kind`, or `None` if the
+/// file has none.
+fn top_level_atom<R: Read + Seek>(reader: &mut R, kind: &[u8; 4]) -> Result<Option<Vec<u8>>> {
+    loop {
+        let mut header = [0u8; 8];
+        if reader.read_exact(&mut header).is_err() {
+            return Ok(None);
+        }
+        let (mut size, mut header_len) = (be_u32(&header, 0).unwrap_or(0) as u64, 8);
+        if size == 1 {
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large)?;
+            (size, header_len) = (u64::from_be_bytes(large), 16);
+        }
+        let found = &header[4..] == kind;
+        if size == 0 {
+            // The last atom, running to the end of the file
+            if !found {
+                return Ok(None);
+            }
+            let mut data = Vec::new();
+            reader.take(MAX_HEADER_BYTES).read_to_end(&mut data)?;
+            return Ok(Some(data));
+        }
+        let len = size
+            .checked_sub(header_len)
+            .context("Malformed atom header")?;
+        if found {
+            if len > MAX_HEADER_BYTES {
+                anyhow::bail!(
+                    "The {} atom is too large to read",
+                    String::from_utf8_lossy(kind)
+                );
+            }
+            let mut data = vec![0; len as usize];
+            reader.read_exact(&mut data)?;
+            return Ok(Some(data));
+        }
+        reader.seek(SeekFrom::Current(len as i64))?;
+    }
+}
+
+/// The atoms inside an atom's payload, as (type, payload) pairs. A truncated
+/// or malformed atom ends the list.
+fn atoms(mut data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut out = Vec::new();
+    while let (Some(size), Some(kind)) = (be_u32(data, 0), data.get(4..8)) {
+        let (header_len, size) = match size {
+            0 => (8, data.len() as u64),
+            1 => (16, be_u64(data, 8).unwrap_or(0)),
+            size => (8, size as u64),
+        };
+        if size < header_len || size > data.len() as u64 {
+            break;
+        }
+        out.push((
+            kind.try_into().unwrap(),
+            &data[header_len as usize..size as usize],
+        ));
+        data = &data[size as usize..];
+    }
+    out
+}
+
+fn child_atom<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    atoms(data)
+        .into_iter()
+        .find(|(k, _)| k == kind)
+        .map(|(_, payload)| payload)
+}
+
+/// Nero chapters: version and flags (and 4 reserved bytes in version 1), a
+/// count, then per chapter its start in 100 ns units and a length-prefixed
+/// title.
+fn parse_chpl(data: &[u8]) -> Vec<ChapterMark> {
+    let mut pos = if data.first() == Some(&1) { 8 } else { 4 };
+    let Some(&count) = data.get(pos) else {
+        return Vec::new();
+    };
+    pos += 1;
+    let mut marks = Vec::new();
+    for _ in 0..count {
+        let (Some(start), Some(&len)) = (be_u64(data, pos), data.get(pos + 8)) else {
+            break;
+        };
+        let Some(title) = data.get(pos + 9..pos + 9 + len as usize) else {
+            break;
+        };
+        marks.push(ChapterMark {
+            start_secs: start as f64 / 1e7,
+            title: text(title),
+        });
+        pos += 9 + len as usize;
+    }
+    marks
+}
+
+/// QuickTime chapters: the samples of the track another track names in its
+/// `tref/chap` reference. Each sample is a chapter title and its time the
+/// chapter's start.
+fn chapter_track<R: Read + Seek>(reader: &mut R, moov: &[u8]) -> Result<Vec<ChapterMark>> {
+    let traks: Vec<&[u8]> = atoms(moov)
+        .into_iter()
+        .filter(|(kind, _)| kind == b"trak")
+        .map(|(_, payload)| payload)
+        .collect();
+    let chapter_id = traks
+        .iter()
+        .find_map(|trak| be_u32(child_atom(child_atom(trak, b"tref")?, b"chap")?, 0));
+    let samples = chapter_id
+        .and_then(|id| traks.iter().find(|trak| track_id(trak) == Some(id)))
+        .and_then(|trak| text_samples(trak))
+        .unwrap_or_default();
+    let mut marks = Vec::new();
+    for (start_secs, offset, size) in samples {
+        let mut sample = vec![0; size.min(1 << 16) as usize];
+        reader.seek(SeekFrom::Start(offset))?;
+        reader
+            .read_exact(&mut sample)
+            .context("Truncated chapter track")?;
+        marks.push(ChapterMark {
+            start_secs,
+            title: quicktime_text(&sample),
+        });
+    }
+    Ok(marks)
+}
+
+fn track_id(trak: &[u8]) -> Option<u32> {
+    let tkhd = child_atom(trak, b"tkhd")?;
+    be_u32(tkhd, if tkhd.first() == Some(&1) { 20 } else { 12 })
+}
+
+/// Each sample of a track as (start in seconds, file offset, size), from its
+/// sample table.
+fn text_samples(trak: &[u8]) -> Option<Vec<(f64, u64, u32)>> {
+    let mdia = child_atom(trak, b"mdia")?;
+    let mdhd = child_atom(mdia, b"mdhd")?;
+    let timescale = be_u32(mdhd, if mdhd.first() == Some(&1) { 20 } else { 12 })?;
+    let stbl = child_atom(child_atom(mdia, b"minf")?, b"stbl")?;
+    if timescale == 0 {
+        return None;
+    }
+
+    let stsz = child_atom(stbl, b"stsz")?;
+    let (uniform, count) = (be_u32(stsz, 4)?, be_u32(stsz, 8)? as usize);
+    let count = count.min(MAX_TRACK_CHAPTERS);
+    let sizes = (0..count)
+        .map(|i| {
+            if uniform > 0 {
+                Some(uniform)
+            } else {
+                be_u32(stsz, 12 + 4 * i)
+            }
+        })
+        .collect::<Option<Vec<u32>>>()?;
+
+    // Sample times from the run-length durations in `stts`
+    let stts = child_atom(stbl, b"stts")?;
+    let mut starts = Vec::with_capacity(count);
+    let mut time = 0u64;
+    for i in 0..be_u32(stts, 4)? as usize {
+        let (run, delta) = (be_u32(stts, 8 + 8 * i)?, be_u32(stts, 12 + 8 * i)?);
+        for _ in 0..run {
+            if starts.len() == count {
+                break;
+            }
+            starts.push(time as f64 / timescale as f64);
+            time += delta as u64;
+        }
+    }
+
+    // Sample offsets: chunks hold runs of consecutive samples, with the
+    // samples per chunk from `stsc`
+    let chunk_offsets: Vec<u64> = if let Some(stco) = child_atom(stbl, b"stco") {
+        (0..be_u32(stco, 4)? as usize)
+            .map(|i| be_u32(stco, 8 + 4 * i).map(u64::from))
+            .collect::<Option<_>>()?
+    } else {
+        let co64 = child_atom(stbl, b"co64")?;
+        (0..be_u32(co64, 4)? as usize)
+            .map(|i| be_u64(co64, 8 + 8 * i))
+            .collect::<Option<_>>()?
+    };
+    let stsc = child_atom(stbl, b"stsc")?;
+    let runs = (0..be_u32(stsc, 4)? as usize)
+        .map(|i| Some((be_u32(stsc, 8 + 12 * i)?, be_u32(stsc, 12 + 12 * i)?)))
+        .collect::<Option<Vec<(u32, u32)>>>()?;
+    let mut offsets = Vec::with_capacity(count);
+    for (index, &chunk_offset) in chunk_offsets.iter().enumerate() {
+        let chunk = index as u32 + 1;
+        let per_chunk = runs
+            .iter()
+            .rev()
+            .find(|(first, _)| *first <= chunk)
+            .map_or(0, |&(_, n)| n);
+        let mut offset = chunk_offset;
+        for _ in 0..per_chunk {
+            let Some(&size) = sizes.get(offsets.len()) else {
+                break;
+            };
+            offsets.push(offset);
+            offset += size as u64;
+        }
+    }
+
+    Some(
+        starts
+            .into_iter()
+            .zip(offsets)
+            .zip(sizes)
+            .map(|((start, offset), size)| (start, offset, size))
+            .collect(),
+    )
+}
+
+/// A QuickTime text sample: a 16-bit length, then the text in UTF-8 or, after
+/// a byte order mark, UTF-16.
+fn quicktime_text(sample: &[u8]) -> Option<String> {
+    let len = be_u16(sample, 0)? as usize;
+    let bytes = sample.get(2..2 + len)?;
+    let utf16 = |rest: &[u8], decode: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = rest.chunks_exact(2).map(|c| decode([c[0], c[1]])).collect();
+        text(String::from_utf16_lossy(&units).as_bytes())
+    };
+    match bytes {
+        [0xfe, 0xff, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xff, 0xfe, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        _ => text(bytes),
+    }
+}
+
+fn matroska_chapters<R: Read + Seek>(reader: &mut R) -> Result<Vec<ChapterMark>> {
+    // The EBML header, then the segment holding everything else
+    loop {
+        let Ok((id, size)) = element_header(reader) else {
+            return Ok(Vec::new());
+        };
+        if id == SEGMENT {
+            let end = size
+                .map(|size| reader.stream_position().map(|pos| pos + size))
+                .transpose()?;
+            return segment_chapters(reader, end);
+        }
+        let Some(size) = size else {
+            return Ok(Vec::new());
+        };
+        reader.seek(SeekFrom::Current(size as i64))?;
+    }
+}
+
+/// Walk the segment's top-level elements (seeking past clusters) up to its
+/// `Chapters`. An element of unknown size, such as a live-streamed cluster,
+/// can't be skipped and ends the search.
+fn segment_chapters<R: Read + Seek>(reader: &mut R, end: Option<u64>) -> Result<Vec<ChapterMark>> {
+    while end.is_none_or(|end| reader.stream_position().is_ok_and(|pos| pos < end)) {
+        let Ok((id, Some(size))) = element_header(reader) else {
+            break;
+        };
+        if id == CHAPTERS {
+            if size > MAX_HEADER_BYTES {
+                anyhow::bail!("The Chapters element is too large to read");
+            }
+            let mut data = vec![0; size as usize];
+            reader.read_exact(&mut data)?;
+            return Ok(parse_chapters(&data));
+        }
+        reader.seek(SeekFrom::Current(size as i64))?;
+    }
+    Ok(Vec::new())
+}
+
+/// The enabled, visible top-level chapters of the default edition (or the
+/// first edition if none is marked default).
+fn parse_chapters(data: &[u8]) -> Vec<ChapterMark> {
+    let editions: Vec<&[u8]> = elements(data)
+        .into_iter()
+        .filter(|&(id, _)| id == EDITION_ENTRY)
+        .map(|(_, payload)| payload)
+        .collect();
+    let edition = editions
+        .iter()
+        .find(|edition| child_element(edition, EDITION_FLAG_DEFAULT).map(uint) == Some(1))
+        .or(editions.first());
+    let Some(edition) = edition else {
+        return Vec::new();
+    };
+    elements(edition)
+        .into_iter()
+        .filter(|&(id, _)| id == CHAPTER_ATOM)
+        .filter(|(_, atom)| {
+            child_element(atom, CHAPTER_FLAG_HIDDEN).map_or(0, uint) == 0
+                && child_element(atom, CHAPTER_FLAG_ENABLED).map_or(1, uint) == 1
+        })
+        .filter_map(|(_, atom)| {
+            let start_ns = uint(child_element(atom, CHAPTER_TIME_START)?);
+            let title = child_element(atom, CHAPTER_DISPLAY)
+                .and_then(|display| child_element(display, CHAP_STRING))
+                .and_then(text);
+            Some(ChapterMark {
+                start_secs: start_ns as f64 / 1e9,
+                title,
+            })
+        })
+        .collect()
+}
+
+/// An EBML element header: the ID (with its length marker) and the payload
+/// size, `None` when the size is unknown.
+fn element_header<R: Read>(reader: &mut R) -> std::io::Result<(u64, Option<u64>)> {
+    let (id, _) = vint(reader)?;
+    let (size, len) = vint(reader)?;
+    let marker = 1u64 << (7 * len);
+    let size = size ^ marker;
+    Ok((id, (size != marker - 1).then_some(size)))
+}
+
+/// An EBML variable-length integer and its length in bytes, which the leading
+/// zero bits of the first byte give.
+fn vint<R: Read>(reader: &mut R) -> std::io::Result<(u64, u32)> {
+    let mut byte = [0u8];
+    reader.read_exact(&mut byte)?;
+    let len = byte[0].leading_zeros() + 1;
+    if len > 8 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Invalid EBML length",
+        ));
+    }
+    let mut value = byte[0] as u64;
+    for _ in 1..len {
+        reader.read_exact(&mut byte)?;
+        value = value << 8 | byte[0] as u64;
+    }
+    Ok((value, len))
+}
+
+/// The elements inside an element's payload, as (ID, payload) pairs. A
+/// truncated element, or one of unknown size, ends the list.
+fn elements(mut data: &[u8]) -> Vec<(u64, &[u8])> {
+    let mut out = Vec::new();
+    while let Ok((id, Some(size))) = element_header(&mut data) {
+        let Some(payload) = data.get(..size as usize) else {
+            break;
+        };
+        out.push((id, payload));
+        data = &data[size as usize..];
+    }
+    out
+}
+
+fn child_element(data: &[u8], id: u64) -> Option<&[u8]> {
+    elements(data)
+        .into_iter()
+        .find(|&(child, _)| child == id)
+        .map(|(_, payload)| payload)
+}
+
+/// A big-endian unsigned integer element.
+fn uint(data: &[u8]) -> u64 {
+    data.iter()
+        .take(8)
+        .fold(0, |value, &byte| value << 8 | byte as u64)
+}
+
+/// A title, or `None` if it is empty.
+fn text(bytes: &[u8]) -> Option<String> {
+    let title = String::from_utf8_lossy(bytes)
+        .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+        .to_string();
+    (!title.is_empty()).then_some(title)
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn atom(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    /// An element with a 1-byte ID or a multi-byte one, and an 8-byte size.
+    fn element(id: u64, payload: &[u8]) -> Vec<u8> {
+        let id_len = (64 - id.leading_zeros()).div_ceil(8) as usize;
+        let mut out = id.to_be_bytes()[8 - id_len..].to_vec();
+        out.push(0x01);
+        out.extend_from_slice(&(payload.len() as u64).to_be_bytes()[1..]);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn chapter_atom(start_ns: u64, title: &str, extra: &[u8]) -> Vec<u8> {
+        let mut payload = element(CHAPTER_TIME_START, &start_ns.to_be_bytes());
+        payload.extend(element(
+            CHAPTER_DISPLAY,
+            &element(CHAP_STRING, title.as_bytes()),
+        ));
+        payload.extend_from_slice(extra);
+        element(CHAPTER_ATOM, &payload)
+    }
+
+    fn mark(start_secs: f64, title: &str) -> ChapterMark {
+        ChapterMark {
+            start_secs,
+            title: Some(title.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_nero_chapters() {
+        let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+        for (start, title) in [(0u64, "Intro"), (45_000_000, "Side B")] {
+            chpl.extend_from_slice(&start.to_be_bytes());
+            chpl.push(title.len() as u8);
+            chpl.extend_from_slice(title.as_bytes());
+        }
+        let mut file = atom(b"ftyp", b"M4A \0\0\0\0");
+        file.extend(atom(b"mdat", &[0; 32]));
+        file.extend(atom(b"moov", &atom(b"udta", &atom(b"chpl", &chpl))));
+        let marks = mp4_chapters(&mut Cursor::new(file)).unwrap();
+        assert_eq!(marks, vec![mark(0.0, "Intro"), mark(4.5, "Side B")]);
+    }
+
+    #[test]
+    fn test_quicktime_chapter_track() {
+        let full = |payload: &[u8]| [&[0u8; 4][..], payload].concat();
+        let words = |values: &[u32]| {
+            values
+                .iter()
+                .flat_map(|v| v.to_be_bytes())
+                .collect::<Vec<u8>>()
+        };
+        let samples: Vec<Vec<u8>> = ["One", "Two"]
+            .iter()
+            .map(|title| [&(title.len() as u16).to_be_bytes()[..], title.as_bytes()].concat())
+            .collect();
+        let ftyp = atom(b"ftyp", b"M4A \0\0\0\0");
+        let mdat_offset = ftyp.len() as u32 + 8;
+
+        // Track 1 refers to track 2, whose two samples share one chunk
+        let audio = [
+            atom(b"tkhd", &words(&[0, 0, 0, 1])),
+            atom(b"tref", &atom(b"chap", &words(&[2]))),
+        ]
+        .concat();
+        let stbl = [
+            atom(b"stts", &full(&words(&[1, 2, 1000]))),
+            atom(b"stsc", &full(&words(&[1, 1, 2, 1]))),
+            atom(
+                b"stsz",
+                &full(&words(&[
+                    0,
+                    2,
+                    samples[0].len() as u32,
+                    samples[1].len() as u32,
+                ])),
+            ),
+            atom(b"stco", &full(&words(&[1, mdat_offset]))),
+        ]
+        .concat();
+        let mdia = [
+            atom(b"mdhd", &words(&[0, 0, 0, 100])),
+            atom(b"minf", &atom(b"stbl", &stbl)),
+        ]
+        .concat();
+        let text = [atom(b"tkhd", &words(&[0, 0, 0, 2])), atom(b"mdia", &mdia)].concat();
+        let moov = [atom(b"trak", &audio), atom(b"trak", &text)].concat();
+
+        let file = [ftyp, atom(b"mdat", &samples.concat()), atom(b"moov", &moov)].concat();
+        let marks = mp4_chapters(&mut Cursor::new(file)).unwrap();
+        assert_eq!(marks, vec![mark(0.0, "One"), mark(10.0, "Two")]);
+    }
+
+    #[test]
+    fn test_matroska_chapters_of_the_default_edition() {
+        let hidden = element(CHAPTER_FLAG_HIDDEN, &[1]);
+        let other = element(EDITION_ENTRY, &chapter_atom(0, "Other", &[]));
+        let default = element(
+            EDITION_ENTRY,
+            &[
+                element(EDITION_FLAG_DEFAULT, &[1]),
+                chapter_atom(0, "First", &[]),
+                chapter_atom(2_500_000_000, "Hidden", &hidden),
+                chapter_atom(90_000_000_000, "Second", &[]),
+            ]
+            .concat(),
+        );
+        let segment = [
+            element(0x1549_a966, &[0; 16]), // Info
+            element(0x1f43_b675, &[0; 64]), // Cluster
+            element(CHAPTERS, &[other, default].concat()),
+        ]
+        .concat();
+        let file = [element(0x1a45_dfa3, &[0; 8]), element(SEGMENT, &segment)].concat();
+        let marks = matroska_chapters(&mut Cursor::new(file)).unwrap();
+        assert_eq!(marks, vec![mark(0.0, "First"), mark(90.0, "Second")]);
+    }
+
+    #[test]
+    fn test_files_without_chapters() {
+        let file = [
+            atom(b"ftyp", b"M4A \0\0\0\0"),
+            atom(b"moov", &atom(b"trak", &[])),
+        ]
+        .concat();
+        assert!(mp4_chapters(&mut Cursor::new(file)).unwrap().is_empty());
+        let file = [
+            element(0x1a45_dfa3, &[]),
+            element(SEGMENT, &element(0x1f43_b675, &[0; 8])),
+        ]
+        .concat();
+        assert!(matroska_chapters(&mut Cursor::new(file))
+            .unwrap()
+            .is_empty());
+    }
+}
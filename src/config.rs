@@ -0,0 +1,208 @@
+//! Defaults loaded from a `dr.toml` config file and `DR_*` environment variables.
+//!
+//! Precedence, highest first: CLI flags > environment > config file > built-in defaults.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::models::DrScale;
+
+pub const CONFIG_FILENAME: &str = "dr.toml";
+
+/// User defaults. Every field is optional; unset fields fall through to the
+/// built-in defaults.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Number of parallel analysis jobs
+    pub jobs: Option<usize>,
+    /// Output formats to write/print by default (`"json"`, `"txt"`, `"csv"`)
+    pub formats: Option<Vec<String>>,
+    /// Color-code DR values in the TUI
+    pub color: Option<bool>,
+    /// Audio file extensions to include when scanning directories
+    pub extensions: Option<Vec<String>>,
+    /// Read and write the cached report in directory mode
+    pub cache: Option<bool>,
+    /// DR thresholds for color coding, `[medium, high]` (e.g. `[8, 14]`)
+    pub dr_scale: Option<DrScale>,
+}
+
+impl Config {
+    /// Load the config file (if any) and apply environment overrides.
+    ///
+    /// `explicit` is a path given with `--config`; it must exist. Otherwise
+    /// `./dr.toml` is tried first, then `$XDG_CONFIG_HOME/dr/dr.toml`
+    /// (falling back to `~/.config/dr/dr.toml`).
+    pub fn load(explicit: Option<&Path>) -> Result<Self> {
+        let mut config = match explicit {
+            Some(path) => Self::from_file(path)?,
+            None => match default_paths().into_iter().find(|p| p.is_file()) {
+                Some(path) => Self::from_file(&path)?,
+                None => Self::default(),
+            },
+        };
+        config.apply_env(|key| std::env::var(key).ok())?;
+        Ok(config)
+    }
+
+    /// Parse a config file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid config file: {}", path.display()))
+    }
+
+    /// Parse config file contents.
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// Override fields from `DR_JOBS`, `DR_FORMATS`, `DR_COLOR`, `DR_EXTENSIONS`,
+    /// and `DR_SCALE` (lists are comma-separated). `NO_COLOR` disables color,
+    /// and `DR_NO_CACHE=1` the cached report.
+    pub fn apply_env(&mut self, get: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(jobs) = get("DR_JOBS") {
+            self.jobs = Some(
+                jobs.trim()
+                    .parse()
+                    .with_context(|| format!("Invalid DR_JOBS: {}", jobs))?,
+            );
+        }
+        if let Some(formats) = get("DR_FORMATS") {
+            self.formats = Some(split_list(&formats));
+        }
+        if get("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            self.color = Some(false);
+        }
+        if let Some(color) = get("DR_COLOR") {
+            self.color = Some(match color.trim() {
+                "1" | "true" => true,
+                "0" | "false" => false,
+                other => bail!("Invalid DR_COLOR: {}", other),
+            });
+        }
+        if let Some(extensions) = get("DR_EXTENSIONS") {
+            self.extensions = Some(split_list(&extensions));
+        }
+        if let Some(no_cache) = get("DR_NO_CACHE") {
+            self.cache = Some(match no_cache.trim() {
+                "1" | "true" => false,
+                "0" | "false" => true,
+                other => bail!("Invalid DR_NO_CACHE: {}", other),
+            });
+        }
+        if let Some(scale) = get("DR_SCALE") {
+            self.dr_scale = Some(scale.parse().context("Invalid DR_SCALE")?);
+        }
+        Ok(())
+    }
+
+    /// Whether `format` is one of the configured default output formats.
+    pub fn has_format(&self, format: &str) -> bool {
+        self.formats
+            .as_ref()
+            .is_some_and(|f| f.iter().any(|x| x.eq_ignore_ascii_case(format)))
+    }
+}
+
+fn default_paths() -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(CONFIG_FILENAME)];
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")));
+    if let Some(dir) = config_home {
+        paths.push(dir.join("dr").join(CONFIG_FILENAME));
+    }
+    paths
+}
+
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            "# defaults\n\
+             jobs = 8\n\
+             formats = [\"json\", \"txt\"]  # both reports\n\
+             color = false\n\
+             extensions = [\"flac\"]\n\
+             dr_scale = [9, 15]\n",
+        )
+        .unwrap();
+        assert_eq!(config.jobs, Some(8));
+        assert!(config.has_format("json") && config.has_format("txt"));
+        assert_eq!(config.color, Some(false));
+        assert_eq!(config.extensions, Some(vec!["flac".to_string()]));
+        assert_eq!(config.dr_scale, Some(DrScale::new(9, 15).unwrap()));
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_keys_and_bad_values() {
+        assert!(Config::parse("jbos = 8").is_err());
+        assert!(Config::parse("jobs = eight").is_err());
+        assert!(Config::parse("jobs").is_err());
+        assert!(Config::parse("formats = [\"json\"").is_err());
+        assert!(Config::parse("dr_scale = [14, 8]").is_err());
+        assert!(Config::parse("dr_scale = [8]").is_err());
+        assert!(Config::parse("jobs = 8\njobs = 4").is_err());
+    }
+
+    #[test]
+    fn test_parse_quoted_strings_in_arrays() {
+        let config =
+            Config::parse("formats = [\"json\", 'txt'] # a, b\nextensions = [\"fl,ac\", 'w#v',]")
+                .unwrap();
+        assert_eq!(
+            config.formats,
+            Some(vec!["json".to_string(), "txt".to_string()])
+        );
+        assert_eq!(
+            config.extensions,
+            Some(vec!["fl,ac".to_string(), "w#v".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_multiline_arrays_and_escapes() {
+        let config =
+            Config::parse("formats = [\n  \"json\",\n  \"t\\u0078t\",\n]\n\"jobs\" = 4\n").unwrap();
+        assert_eq!(
+            config.formats,
+            Some(vec!["json".to_string(), "txt".to_string()])
+        );
+        assert_eq!(config.jobs, Some(4));
+    }
+
+    #[test]
+    fn test_env_overrides_config() {
+        let mut config = Config::parse("jobs = 8\nformats = [\"txt\"]\ncolor = true").unwrap();
+        config
+            .apply_env(|key| match key {
+                "DR_JOBS" => Some("2".to_string()),
+                "DR_FORMATS" => Some("json, txt".to_string()),
+                "NO_COLOR" => Some("1".to_string()),
+                "DR_NO_CACHE" => Some("1".to_string()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(config.jobs, Some(2));
+        assert!(config.has_format("json") && config.has_format("txt"));
+        assert_eq!(config.color, Some(false));
+        assert_eq!(config.cache, Some(false));
+        let invalid =
+            Config::default().apply_env(|key| (key == "DR_NO_CACHE").then(|| "yes".to_string()));
+        assert!(invalid.is_err());
+    }
+}
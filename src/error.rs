@@ -0,0 +1,43 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Typed errors that callers may want to handle rather than just report.
+/// Returned wrapped in `anyhow::Error`; recover with `err.downcast_ref::<DrError>()`.
+#[derive(Debug)]
+pub enum DrError {
+    /// The directory contains no audio files. Not a failure when sweeping many directories.
+    NoAudio(PathBuf),
+    /// No decoder is built in for the file's codec, e.g. Opus or WavPack: the
+    /// format is unsupported, not the file corrupt.
+    UnsupportedCodec { codec: String },
+}
+
+impl fmt::Display for DrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrError::NoAudio(path) => write!(f, "No audio files found in {}", path.display()),
+            DrError::UnsupportedCodec { codec } => {
+                write!(
+                    f,
+                    "Unsupported codec: {} (no decoder is built in for this format)",
+                    codec
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DrError {}
+
+/// Whether `err` is a `DrError::NoAudio`.
+pub fn is_no_audio(err: &anyhow::Error) -> bool {
+    matches!(err.downcast_ref::<DrError>(), Some(DrError::NoAudio(_)))
+}
+
+/// Whether `err` is a `DrError::UnsupportedCodec`.
+pub fn is_unsupported_codec(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<DrError>(),
+        Some(DrError::UnsupportedCodec { .. })
+    )
+}
@@ -0,0 +1,166 @@
+//! Lightweight content fingerprints for spotting duplicate tracks.
+//!
+//! A fingerprint is the track's loudness contour plus a trace of its content:
+//! for each 3-second block, the DR-RMS and the crest factor (peak over DR-RMS),
+//! each averaged across channels, quantized to 1 dB steps and hex-encoded one
+//! byte apiece. The crest factor tells apart material that sits at the same
+//! level, such as a sustained tone and a drum break. Two tracks are likely
+//! duplicates when their fingerprints have about the same length and nearly
+//! every block agrees to within 1 dB in level and 2 dB in crest factor, which
+//! tolerates small edits such as the muted words of a clean version.
+
+use crate::models::TrackResult;
+
+/// Fraction of blocks that must agree for two tracks to count as duplicates.
+const MATCH_THRESHOLD: f64 = 0.9;
+/// Largest block-level difference (in 1 dB steps) still counted as agreeing.
+const LEVEL_TOLERANCE: u8 = 1;
+/// Largest crest-factor difference (in 1 dB steps) still counted as agreeing.
+/// Looser than the level, as peaks shift more than RMS under lossy coding.
+const CREST_TOLERANCE: u8 = 2;
+
+/// Build a fingerprint from per-channel block RMS and peak values (linear, one
+/// Vec per channel). `None` if there are no complete blocks.
+pub fn from_blocks(block_rms: &[Vec<f64>], block_peaks: &[Vec<f64>]) -> Option<String> {
+    let num_blocks = block_rms.first().map_or(0, |b| b.len());
+    if num_blocks == 0 {
+        return None;
+    }
+    let channels = block_rms.len() as f64;
+    let blocks = (0..num_blocks).map(|i| {
+        let rms = block_rms.iter().map(|ch| ch[i]).sum::<f64>() / channels;
+        let peak = block_peaks.iter().map(|ch| ch[i]).sum::<f64>() / channels;
+        let db = if rms > 0.0 {
+            20.0 * rms.log10()
+        } else {
+            -100.0
+        };
+        // The crest factor is at least -3 dB, as the DR-RMS is sqrt(2) times the RMS
+        let crest_db = if rms > 0.0 {
+            20.0 * (peak / rms).log10()
+        } else {
+            -10.0
+        };
+        let level = (db + 100.0).round().clamp(0.0, 255.0) as u8;
+        let crest = (crest_db + 10.0).round().clamp(0.0, 255.0) as u8;
+        format!("{:02x}{:02x}", level, crest)
+    });
+    Some(blocks.collect())
+}
+
+/// The (level, crest) byte pairs of a fingerprint, one per block.
+fn decode(fingerprint: &str) -> Option<Vec<(u8, u8)>> {
+    if !fingerprint.len().is_multiple_of(4) {
+        return None;
+    }
+    let byte = |i: usize| u8::from_str_radix(fingerprint.get(i..i + 2)?, 16).ok();
+    (0..fingerprint.len())
+        .step_by(4)
+        .map(|i| Some((byte(i)?, byte(i + 2)?)))
+        .collect()
+}
+
+/// Fraction of blocks (0.0-1.0) at which two fingerprints agree. Fingerprints
+/// whose lengths differ by more than one block never match.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (Some(a), Some(b)) = (decode(a), decode(b)) else {
+        return 0.0;
+    };
+    if a.is_empty() || b.is_empty() || a.len().abs_diff(b.len()) > 1 {
+        return 0.0;
+    }
+    let agreeing = a
+        .iter()
+        .zip(&b)
+        .filter(|((level_a, crest_a), (level_b, crest_b))| {
+            level_a.abs_diff(*level_b) <= LEVEL_TOLERANCE
+                && crest_a.abs_diff(*crest_b) <= CREST_TOLERANCE
+        })
+        .count();
+    agreeing as f64 / a.len().max(b.len()) as f64
+}
+
+/// Index pairs `(i, j)`, `i < j`, of tracks that are likely the same recording.
+/// Tracks without a fingerprint are skipped.
+pub fn likely_duplicates(tracks: &[TrackResult]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for (i, a) in tracks.iter().enumerate() {
+        let Some(fa) = a.fingerprint.as_deref() else {
+            continue;
+        };
+        for (j, b) in tracks.iter().enumerate().skip(i + 1) {
+            if let Some(fb) = b.fingerprint.as_deref() {
+                if similarity(fa, fb) >= MATCH_THRESHOLD {
+                    pairs.push((i, j));
+                }
+            }
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_quantizes_block_levels_and_crest_factors() {
+        // 1.0 -> 0 dB -> 100 (0x64); 0.1 -> -20 dB -> 80 (0x50); silence -> 0.
+        // Peaks at 0 dB and 10 dB over the RMS -> 10 (0x0a) and 20 (0x14)
+        let fp = from_blocks(
+            &[vec![1.0, 0.1, 0.0]],
+            &[vec![1.0, 0.1f64 * 10f64.sqrt(), 0.0]],
+        )
+        .unwrap();
+        assert_eq!(fp, "640a50140000");
+        assert!(from_blocks(&[vec![]], &[vec![]]).is_none());
+    }
+
+    #[test]
+    fn test_similarity_tolerates_small_differences() {
+        let a = "500a510a520a530a540a550a560a570a580a590a5a0a5b0a5c0a5d0a5e0a5f0a600a610b";
+        let mut b = a.to_string();
+        b.replace_range(0..2, "40"); // one block differs by 16 dB
+        b.replace_range(6..8, "0c"); // and another's peak by 2 dB
+        assert_eq!(similarity(a, a), 1.0);
+        assert!(similarity(a, &b) > 0.9);
+        assert_eq!(similarity(a, "500a510a"), 0.0);
+        // Not a whole number of blocks
+        assert_eq!(similarity(a, &a[..a.len() - 1]), 0.0);
+    }
+
+    #[test]
+    fn test_same_levels_with_different_content_dont_match() {
+        // A steady tone (peak at its DR-RMS) and a spiky signal (peaks 9 dB
+        // over it) at the same levels block by block
+        let rms = vec![vec![0.5, 0.4, 0.3, 0.4, 0.5, 0.6]];
+        let tone: Vec<Vec<f64>> = rms.clone();
+        let spiky: Vec<Vec<f64>> =
+            vec![rms[0].iter().map(|r| r * 10f64.powf(9.0 / 20.0)).collect()];
+        let (a, b) = (
+            from_blocks(&rms, &tone).unwrap(),
+            from_blocks(&rms, &spiky).unwrap(),
+        );
+        assert_eq!(similarity(&a, &b), 0.0);
+        let track = |fp: &str| TrackResult {
+            fingerprint: Some(fp.to_string()),
+            ..Default::default()
+        };
+        assert!(likely_duplicates(&[track(&a), track(&b)]).is_empty());
+    }
+
+    #[test]
+    fn test_likely_duplicates() {
+        let track = |fp: &str| TrackResult {
+            fingerprint: Some(fp.to_string()),
+            ..Default::default()
+        };
+        let tracks = vec![
+            track("500a510a520a530a540a550a"),
+            track("200a210a220a230a240a250a"),
+            track("500a510a520a530a540a550b"),
+            TrackResult::default(),
+        ];
+        assert_eq!(likely_duplicates(&tracks), vec![(0, 2)]);
+    }
+}
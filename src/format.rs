@@ -1,4 +1,8 @@
-use crate::models::{AlbumResult, TrackResult};
+use crate::analyzer::BLOCK_SECS;
+use crate::models::{
+    AlbumAggregate, AlbumResult, DrRating, DrScale, DrSeverity, NormalizationPreview, SampleKind,
+    TrackResult,
+};
 
 /// Format a duration in seconds as "M:SS".
 pub fn format_duration(secs: f64) -> String {
@@ -8,92 +12,1305 @@ pub fn format_duration(secs: f64) -> String {
     format!("{}:{:02}", minutes, seconds)
 }
 
+/// Format a wall-clock span for progress output: "42s", "3m 05s", "1h 02m".
+pub fn format_eta(secs: f64) -> String {
+    let total_secs = secs.round() as u64;
+    let (hours, minutes, seconds) = (total_secs / 3600, total_secs / 60 % 60, total_secs % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{}s", seconds),
+        (0, _) => format!("{}m {:02}s", minutes, seconds),
+        _ => format!("{}h {:02}m", hours, minutes),
+    }
+}
+
+/// Cumulative progress of a bulk run once `done` of `total` albums are
+/// through, e.g. "Progress: 3/47 albums (6.4%), 2m 10s elapsed, ETA 44m 05s".
+/// The ETA is the average time of the `worked` albums actually analyzed,
+/// `work_secs` together, times the albums left; skipped albums cost next to
+/// nothing and would make it optimistic. No ETA before the first or after
+/// the last album.
+pub fn format_bulk_progress(
+    done: usize,
+    total: usize,
+    elapsed_secs: f64,
+    worked: usize,
+    work_secs: f64,
+) -> String {
+    let percent = if total == 0 {
+        100.0
+    } else {
+        done as f64 * 100.0 / total as f64
+    };
+    let mut line = format!(
+        "Progress: {}/{} albums ({:.1}%), {} elapsed",
+        done,
+        total,
+        percent,
+        format_eta(elapsed_secs)
+    );
+    let remaining = total.saturating_sub(done);
+    if worked > 0 && remaining > 0 {
+        let eta = work_secs / worked as f64 * remaining as f64;
+        line.push_str(&format!(", ETA {}", format_eta(eta)));
+    }
+    line
+}
+
+/// A dB value ready for printing to two decimals: anything that would round to
+/// zero prints as `0.00`, never `-0.00`.
+pub fn display_db(db: f64) -> f64 {
+    if db.abs() < 0.005 {
+        0.0
+    } else {
+        db
+    }
+}
+
+/// Width of the table separator line.
+const SEPARATOR_WIDTH: usize = 74;
+
+/// Characters before the title in a full row, and in a compact row.
+const FULL_PREFIX: usize = 62;
+const COMPACT_PREFIX: usize = 26;
+
+/// Narrower than this, a width-limited table drops the true peak and RMS
+/// columns so titles keep some room.
+const COMPACT_BELOW: usize = FULL_PREFIX + 16;
+
+/// Widths of the optional precise DR, crest factor, and PLR columns, with
+/// their leading spaces.
+const PRECISE_COLUMN: usize = 8;
+const CREST_COLUMN: usize = 12;
+const PLR_COLUMN: usize = 10;
+
+/// How a table is laid out: the default full layout, or one fitted to a width.
+#[derive(Debug, Clone, Copy)]
+pub struct TableLayout {
+    /// Target line width; titles are cut to fit and the separator matches
+    width: Option<usize>,
+    /// DR, peak, and duration columns only
+    compact: bool,
+    /// Add an unrounded DR column after DR (full layout only)
+    precise: bool,
+    /// Add a crest factor column after RMS (full layout only)
+    crest_factor: bool,
+    /// Add a PLR column after RMS and any crest factor (full layout only)
+    plr: bool,
+    /// The scale the footer's DR rating is on
+    scale: DrScale,
+}
+
+impl TableLayout {
+    pub const FULL: TableLayout = TableLayout {
+        width: None,
+        compact: false,
+        precise: false,
+        crest_factor: false,
+        plr: false,
+        scale: DrScale::DR_DATABASE,
+    };
+
+    /// Fitted to `width` columns: long titles are cut with an ellipsis, and
+    /// narrow widths get a compact layout.
+    pub fn fitted(width: usize) -> Self {
+        Self {
+            width: Some(width),
+            compact: width < COMPACT_BELOW,
+            precise: false,
+            crest_factor: false,
+            plr: false,
+            scale: DrScale::DR_DATABASE,
+        }
+    }
+
+    /// `fitted` to `width`, or the full layout without one.
+    pub fn for_width(width: Option<usize>) -> Self {
+        width.map_or(Self::FULL, Self::fitted)
+    }
+
+    /// With a "Precise" column (`dr_precise`, to one decimal) after DR, unless compact.
+    pub fn with_precise(self, precise: bool) -> Self {
+        Self { precise, ..self }
+    }
+
+    /// With a "Crest (dB)" column (`crest_factor_db`) after RMS, unless compact.
+    pub fn with_crest_factor(self, crest_factor: bool) -> Self {
+        Self {
+            crest_factor,
+            ..self
+        }
+    }
+
+    /// With a "PLR (dB)" column (`plr`) after RMS, unless compact.
+    pub fn with_plr(self, plr: bool) -> Self {
+        Self { plr, ..self }
+    }
+
+    /// With the footer's DR rating on `scale`.
+    pub fn with_scale(self, scale: DrScale) -> Self {
+        Self { scale, ..self }
+    }
+
+    fn shows_precise(&self) -> bool {
+        self.precise && !self.compact
+    }
+
+    fn shows_crest_factor(&self) -> bool {
+        self.crest_factor && !self.compact
+    }
+
+    fn shows_plr(&self) -> bool {
+        self.plr && !self.compact
+    }
+
+    /// Characters the optional columns add to a full row.
+    fn optional_width(&self) -> usize {
+        let precise = if self.shows_precise() {
+            PRECISE_COLUMN
+        } else {
+            0
+        };
+        let crest = if self.shows_crest_factor() {
+            CREST_COLUMN
+        } else {
+            0
+        };
+        let plr = if self.shows_plr() { PLR_COLUMN } else { 0 };
+        precise + crest + plr
+    }
+
+    fn separator(&self) -> String {
+        "\u{2500}".repeat(
+            self.width
+                .unwrap_or(SEPARATOR_WIDTH + self.optional_width()),
+        )
+    }
+
+    /// The title, shortened with an ellipsis if it (plus `suffix_len` more
+    /// characters) would overrun the width.
+    fn title<'a>(&self, title: &'a str, suffix_len: usize) -> std::borrow::Cow<'a, str> {
+        let prefix = if self.compact {
+            COMPACT_PREFIX
+        } else {
+            FULL_PREFIX + self.optional_width()
+        } + suffix_len;
+        let Some(room) = self.width.map(|w| w.saturating_sub(prefix).max(1)) else {
+            return title.into();
+        };
+        if title.chars().count() <= room {
+            title.into()
+        } else {
+            let cut: String = title.chars().take(room - 1).collect();
+            format!("{}\u{2026}", cut).into()
+        }
+    }
+}
+
+/// Table column header. Sample peak and true peak are labeled with their units
+/// (dBFS vs dBTP) so the two can't be confused.
+fn table_header(layout: TableLayout) -> String {
+    if layout.compact {
+        return format!(
+            "{:<5} {:>9} {:>8}  {}",
+            "DR", "Peak dBFS", "Duration", "Track"
+        );
+    }
+    let mut optional = String::new();
+    if layout.shows_crest_factor() {
+        optional.push_str(&format!(" {:>11}", "Crest (dB)"));
+    }
+    if layout.shows_plr() {
+        optional.push_str(&format!(" {:>9}", "PLR (dB)"));
+    }
+    let precise = if layout.shows_precise() {
+        format!(" {:>7}", "Precise")
+    } else {
+        String::new()
+    };
+    format!(
+        "{:<8}{} {:>12} {:>16} {:>10}{} {:>10}  {}",
+        "DR", precise, "Peak (dBFS)", "True Peak (dBTP)", "RMS (dB)", optional, "Duration", "Track"
+    )
+}
+
+/// One table row, aligned with `table_header`. Lossy tracks are marked with
+/// `LOSSY_MARK`, explained by `LOSSY_NOTE` under the table.
+fn table_row(track: &TrackResult, layout: TableLayout) -> String {
+    let mark = if track.lossy { LOSSY_MARK } else { "" };
+    if layout.compact {
+        return format!(
+            "DR{:<3} {:>9.2} {:>8}  {}{}",
+            track.dr,
+            display_db(track.peak_db),
+            format_duration(track.duration_secs),
+            layout.title(&track.title, mark.len()),
+            mark,
+        );
+    }
+    let mut optional = String::new();
+    if layout.shows_crest_factor() {
+        optional.push_str(&format!(" {:>11.2}", display_db(track.crest_factor_db)));
+    }
+    if layout.shows_plr() {
+        // No PLR without a loudness (silence)
+        let plr = track
+            .plr
+            .map_or("-".to_string(), |plr| format!("{:.2}", display_db(plr)));
+        optional.push_str(&format!(" {:>9}", plr));
+    }
+    // No unrounded DR for tracks too short to measure, or from older reports
+    let precise = match (layout.shows_precise(), track.dr_precise) {
+        (false, _) => String::new(),
+        (true, Some(dr)) => format!(" {:>7.1}", dr),
+        (true, None) => format!(" {:>7}", "-"),
+    };
+    format!(
+        "DR{:<6}{} {:>12.2} {:>16.2} {:>10.2}{} {:>10}  {}{}",
+        track.dr,
+        precise,
+        display_db(track.peak_db),
+        display_db(track.true_peak_db),
+        track.rms_db,
+        optional,
+        format_duration(track.duration_secs),
+        layout.title(&track.title, mark.len()),
+        mark,
+    )
+}
+
+const LOSSY_MARK: &str = " *";
+const LOSSY_NOTE: &str = "* Lossy source: DR may differ slightly from the lossless original";
+
+/// The source sample format, e.g. "24-bit integer" or "32-bit float"; `None`
+/// when unknown (lossy sources, older reports).
+pub fn format_sample_format(track: &TrackResult) -> Option<String> {
+    let kind = match track.sample_kind? {
+        SampleKind::Integer => "integer",
+        SampleKind::Float => "float",
+    };
+    Some(format!("{}-bit {}", track.bits_per_sample?, kind))
+}
+
+/// Format a byte count in megabytes, e.g. "312.4 MB".
+pub fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", megabytes(bytes))
+}
+
+fn megabytes(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
 /// Format a single track result as a DR Database-style table.
 pub fn format_table_single(result: &TrackResult) -> String {
-    let separator = "\u{2500}".repeat(58);
+    table_single(result, TableLayout::FULL)
+}
+
+/// Like `format_table_single`, fitted to `width` columns: long titles are
+/// cut with an ellipsis, and narrow widths get a compact layout.
+pub fn format_table_single_width(result: &TrackResult, width: usize) -> String {
+    table_single(result, TableLayout::fitted(width))
+}
+
+/// Like `format_table_single`, in any `layout`.
+pub fn format_table_single_in(result: &TrackResult, layout: TableLayout) -> String {
+    table_single(result, layout)
+}
+
+fn table_single(result: &TrackResult, layout: TableLayout) -> String {
+    let separator = layout.separator();
     format!(
-        "{:<10} {:>10} {:>10} {:>10}  {}\n\
-         {}\n\
-         DR{:<8} {:>7.2} dB {:>7.2} dB {:>10}  {}\n\
-         {}\n\
-         Official DR value: DR{}",
-        "DR", "Peak", "RMS", "Duration", "Track",
+        "{}\n{}\n{}\n{}\nOfficial DR value: DR{}",
+        table_header(layout),
         separator,
-        result.dr,
-        result.peak_db,
-        result.rms_db,
-        format_duration(result.duration_secs),
-        result.title,
+        table_row(result, layout),
         separator,
         result.dr,
+    ) + &result
+        .album
+        .as_ref()
+        .map(|album| format!("\nAlbum:             {}", album))
+        .unwrap_or_default()
+        + &format_stereo_correlation(result.stereo_correlation)
+            .map(|c| format!("\nStereo correlation: {}", c))
+            .unwrap_or_default()
+        + &format_noise_floor(result)
+            .map(|f| format!("\nNoise floor:       {}", f))
+            .unwrap_or_default()
+        + &format_applied_gain(result)
+            .map(|g| format!("\nGain applied:      {}", g))
+            .unwrap_or_default()
+        + &format_channel_dr(result)
+            .map(|c| format!("\nChannel DR:        {}", c))
+            .unwrap_or_default()
+        + &result
+            .perceptual_dr
+            .map(|dr| format!("\n{} DR{}", PERCEPTUAL_DR_LABEL, dr))
+            .unwrap_or_default()
+        + &format_decode_errors(result)
+            .map(|w| format!("\nWarning: {}", w))
+            .unwrap_or_default()
+        + &format_truncation(result)
+            .map(|w| format!("\nWarning: {}", w))
+            .unwrap_or_default()
+        + &format_peak_fallback(result)
+            .map(|n| format!("\nNote: {}", n))
+            .unwrap_or_default()
+        + &result
+            .range_start_secs
+            .map(|start| {
+                format!(
+                    "\nAnalyzed range:    {}-{} ({:.1}s)",
+                    format_duration(start),
+                    format_duration(start + result.duration_secs),
+                    result.duration_secs
+                )
+            })
+            .unwrap_or_default()
+        + &result
+            .trim_edges_secs
+            .map(|secs| format!("\nEdges trimmed:     {}", format_trim_edges(secs)))
+            .unwrap_or_default()
+        + &result
+            .silent_blocks_trimmed
+            .map(|blocks| format!("\nSilence trimmed:   {}", format_silence_trimmed(blocks)))
+            .unwrap_or_default()
+        + &if result.legacy_dr {
+            format!("\nMethod:            {}", LEGACY_DR_NOTE)
+        } else {
+            String::new()
+        }
+        + &if result.preview {
+            format!("\nPreview:           {}", PREVIEW_NOTE)
+        } else {
+            String::new()
+        }
+        + &if result.lossy {
+            format!("\n\n{}", LOSSY_NOTE)
+        } else {
+            String::new()
+        }
+}
+
+/// Note for a track cut short by `--preview-secs`.
+const PREVIEW_NOTE: &str = "only the start of the track was measured (not a full measurement)";
+
+/// Note for DR measured with `--legacy-dr`, which isn't the official value.
+const LEGACY_DR_NOTE: &str =
+    "channels combined before the top-20% selection (legacy, non-standard DR)";
+
+/// Note for DR measured with `--trim-edges`, which isn't comparable to the
+/// official value.
+fn format_trim_edges(secs: f64) -> String {
+    format!("{:.1}s at each end (non-standard DR)", secs)
+}
+
+/// Note for DR measured with `--trim-silence`, e.g. "2 blocks (6s) of
+/// leading/trailing silence (non-standard DR)".
+fn format_silence_trimmed(blocks: u32) -> String {
+    format!(
+        "{} block{} ({}s) of leading/trailing silence (non-standard DR)",
+        blocks,
+        if blocks == 1 { "" } else { "s" },
+        blocks as f64 * BLOCK_SECS
     )
 }
 
+/// Warning for a track with undecodable packets, if it has any.
+fn format_decode_errors(track: &TrackResult) -> Option<String> {
+    (track.decode_errors > 0).then(|| {
+        format!(
+            "{} packet{} could not be decoded; DR is from incomplete audio",
+            track.decode_errors,
+            if track.decode_errors == 1 { "" } else { "s" }
+        )
+    })
+}
+
+/// Warning for a track whose decoded length disagrees with the container's
+/// declared duration, if it does.
+pub fn format_truncation(track: &TrackResult) -> Option<String> {
+    track.declared_duration_secs.map(|declared| {
+        format!(
+            "decoded {:.1}s of the {:.1}s the container declares; the decode may have stopped early",
+            track.duration_secs, declared
+        )
+    })
+}
+
+/// Note for a track too short for the 2nd-highest block peak, if it is.
+fn format_peak_fallback(track: &TrackResult) -> Option<String> {
+    track.peak_fallback.then(|| {
+        "only one 3-second block, so DR uses the highest peak rather than the 2nd-highest \
+         and is not comparable with longer tracks"
+            .to_string()
+    })
+}
+
+/// Label for the K-weighted DR, worded so it can't pass for the official value.
+const PERCEPTUAL_DR_LABEL: &str = "Perceptual DR (K-weighted, experimental, not official):";
+
+/// The ReplayGain track gain applied with `--apply-existing-gain`, e.g.
+/// "-6.54 dB (ReplayGain track gain)", noting when the gained peak is above
+/// full scale and so would clip on playback.
+fn format_applied_gain(track: &TrackResult) -> Option<String> {
+    track.applied_gain_db.map(|gain| {
+        let clips = if display_db(track.peak_db) > 0.0 {
+            "; peak above 0 dBFS, would clip"
+        } else {
+            ""
+        };
+        format!(
+            "{:+.2} dB (ReplayGain track gain{})",
+            display_db(gain),
+            clips
+        )
+    })
+}
+
+/// Each channel's DR with its position label, e.g. "FL: DR12  FR: DR12  FC: DR9",
+/// when measured with `--per-channel`.
+fn format_channel_dr(track: &TrackResult) -> Option<String> {
+    (!track.channel_dr.is_empty()).then(|| {
+        track
+            .channel_dr
+            .iter()
+            .map(|c| format!("{}: DR{}", c.channel, c.dr))
+            .collect::<Vec<_>>()
+            .join("  ")
+    })
+}
+
+/// The noise floor and its distance below the sample peak, e.g.
+/// "-72.30 dB (71.80 dB below peak)", when it was estimated.
+fn format_noise_floor(track: &TrackResult) -> Option<String> {
+    track.noise_floor_db.map(|floor| {
+        format!(
+            "{:.2} dB ({:.2} dB below peak)",
+            floor,
+            display_db(track.peak_db - floor)
+        )
+    })
+}
+
+/// Correlation below which the channels are mostly out of phase, so a mono
+/// fold-down would cancel much of the signal. Wide mixes and reverb can dip
+/// slightly below zero without audible cancellation.
+const PHASE_WARNING_BELOW: f64 = -0.5;
+
+/// Format a correlation coefficient with sign, flagging likely phase
+/// cancellation (below `PHASE_WARNING_BELOW`).
+fn format_stereo_correlation(correlation: Option<f64>) -> Option<String> {
+    correlation.map(|c| {
+        if c < PHASE_WARNING_BELOW {
+            format!("{:+.2} (possible phase cancellation)", c)
+        } else {
+            format!("{:+.2}", c)
+        }
+    })
+}
+
 /// Format an album result as a DR Database-style table.
 pub fn format_table(result: &AlbumResult) -> String {
-    let separator = "\u{2500}".repeat(58);
+    table(result, TableLayout::FULL)
+}
+
+/// Like `format_table`, fitted to `width` columns: long titles are cut with an
+/// ellipsis, and narrow widths get a compact layout.
+pub fn format_table_width(result: &AlbumResult, width: usize) -> String {
+    table(result, TableLayout::fitted(width))
+}
+
+/// Like `format_table`, in any `layout`.
+pub fn format_table_in(result: &AlbumResult, layout: TableLayout) -> String {
+    table(result, layout)
+}
+
+/// The album table's column header and separator, for printing a table one
+/// row at a time. Followed by `format_table_row` per track and
+/// `format_table_footer` in the same layout, the pieces add up to
+/// `format_table_in`.
+pub fn format_table_head(layout: TableLayout) -> String {
+    format!("{}\n{}", table_header(layout), layout.separator())
+}
+
+/// One track's row of the album table (see `format_table_head`).
+pub fn format_table_row(track: &TrackResult, layout: TableLayout) -> String {
+    table_row(track, layout)
+}
+
+/// Everything in the album table below the track rows (see `format_table_head`).
+pub fn format_table_footer(result: &AlbumResult, layout: TableLayout) -> String {
+    table_footer(result, layout)
+}
+
+fn table(result: &AlbumResult, layout: TableLayout) -> String {
     let mut output = String::new();
 
     // Header
-    output.push_str(&format!(
-        "{:<10} {:>10} {:>10} {:>10}  {}\n",
-        "DR", "Peak", "RMS", "Duration", "Track"
-    ));
-    output.push_str(&separator);
+    output.push_str(&table_header(layout));
+    output.push('\n');
+    output.push_str(&layout.separator());
     output.push('\n');
 
-    // Track rows
-    for track in &result.tracks {
-        output.push_str(&format!(
-            "DR{:<8} {:>7.2} dB {:>7.2} dB {:>10}  {}\n",
-            track.dr,
-            track.peak_db,
-            track.rms_db,
-            format_duration(track.duration_secs),
-            track.title,
-        ));
+    // Track rows, under a header per disc when they span several
+    let by_disc = spans_discs(result);
+    for (i, track) in result.tracks.iter().enumerate() {
+        if by_disc && (i == 0 || result.tracks[i - 1].disc != track.disc) {
+            output.push_str(&disc_header(track.disc));
+            output.push('\n');
+        }
+        output.push_str(&table_row(track, layout));
+        output.push('\n');
     }
 
-    output.push_str(&separator);
+    output.push_str(&table_footer(result, layout));
+    output
+}
+
+/// Whether the tracks carry more than one disc number, so the table groups
+/// them by disc.
+fn spans_discs(result: &AlbumResult) -> bool {
+    let mut discs = result.tracks.iter().filter_map(|t| t.disc);
+    discs.next().is_some_and(|first| discs.any(|d| d != first))
+}
+
+/// The line above a disc's rows: "Disc 2", or "No disc" for untagged tracks.
+fn disc_header(disc: Option<u32>) -> String {
+    disc.map_or("No disc".to_string(), |disc| format!("Disc {}", disc))
+}
+
+fn table_footer(result: &AlbumResult, layout: TableLayout) -> String {
+    let mut output = layout.separator();
     output.push('\n');
 
     // Footer
     output.push_str(&format!(
         "Number of tracks:  {}\n\
-         Official DR value: DR{}",
+         Total duration:    {}\n\
+         Total size:        {}\n\
+         Album peak:        {}\n\
+         Official DR value: DR{}{}{}\n\
+         DR rating:         {}",
         result.tracks.len(),
+        format_duration(result.total_duration_secs),
+        format_size(result.total_bytes),
+        format_album_peak(result),
         result.overall_dr,
+        // The official value is the mean; say when it isn't
+        if result.album_agg == AlbumAggregate::Median {
+            " (median of tracks)"
+        } else {
+            ""
+        },
+        format_contributing_tracks(result)
+            .map(|c| format!(" ({})", c))
+            .unwrap_or_default(),
+        DrRating::from_dr(result.overall_dr, &layout.scale).label(&layout.scale),
+    ));
+    if let Some(reference) = format_reference(result) {
+        output.push_str(&format!("\nReference:         {}", reference));
+    }
+    if let Some(outliers) = format_outliers(result) {
+        output.push_str(&format!("\nOutliers:          {}", outliers));
+    }
+    if let Some(secs) = result.tracks.iter().find_map(|t| t.trim_edges_secs) {
+        output.push_str(&format!("\nEdges trimmed:     {}", format_trim_edges(secs)));
+    }
+    let silent: Vec<u32> = result
+        .tracks
+        .iter()
+        .filter_map(|t| t.silent_blocks_trimmed)
+        .collect();
+    if !silent.is_empty() {
+        output.push_str(&format!(
+            "\nSilence trimmed:   {}",
+            format_silence_trimmed(silent.iter().sum())
+        ));
+    }
+    if result.tracks.iter().any(|t| t.legacy_dr) {
+        output.push_str(&format!("\nMethod:            {}", LEGACY_DR_NOTE));
+    }
+    let previews = result.tracks.iter().filter(|t| t.preview).count();
+    if previews > 0 {
+        output.push_str(&format!(
+            "\nPreview:           {} of {} tracks: {}",
+            previews,
+            result.tracks.len(),
+            PREVIEW_NOTE
+        ));
+    }
+    if result.tracks.iter().any(|t| t.lossy) {
+        output.push_str(&format!("\n\n{}", LOSSY_NOTE));
+    }
+
+    // Stereo statistics, when requested
+    let correlations: Vec<_> = result
+        .tracks
+        .iter()
+        .filter_map(|t| format_stereo_correlation(t.stereo_correlation).map(|c| (t, c)))
+        .collect();
+    if !correlations.is_empty() {
+        output.push_str("\n\nStereo correlation:\n");
+        for (track, c) in correlations {
+            output.push_str(&format!("  {}: {}\n", track.title, c));
+        }
+        output.pop();
+    }
+
+    // Noise floors, when requested
+    let floors: Vec<_> = result
+        .tracks
+        .iter()
+        .filter_map(|t| format_noise_floor(t).map(|f| (t, f)))
+        .collect();
+    if !floors.is_empty() {
+        output.push_str("\n\nNoise floor:\n");
+        for (track, floor) in floors {
+            output.push_str(&format!("  {}: {}\n", track.title, floor));
+        }
+        output.pop();
+    }
+
+    // ReplayGain, when applied
+    let gains: Vec<_> = result
+        .tracks
+        .iter()
+        .filter_map(|t| format_applied_gain(t).map(|g| (t, g)))
+        .collect();
+    if !gains.is_empty() {
+        output.push_str("\n\nGain applied:\n");
+        for (track, gain) in gains {
+            output.push_str(&format!("  {}: {}\n", track.title, gain));
+        }
+        output.pop();
+    }
+
+    // Per-channel DR, when requested
+    let channel_drs: Vec<_> = result
+        .tracks
+        .iter()
+        .filter_map(|t| format_channel_dr(t).map(|c| (t, c)))
+        .collect();
+    if !channel_drs.is_empty() {
+        output.push_str("\n\nChannel DR:\n");
+        for (track, drs) in channel_drs {
+            output.push_str(&format!("  {}: {}\n", track.title, drs));
+        }
+        output.pop();
+    }
+
+    // Tracks too short for the 2nd-highest peak
+    let short: Vec<_> = result
+        .tracks
+        .iter()
+        .filter_map(|t| format_peak_fallback(t).map(|n| (t, n)))
+        .collect();
+    if !short.is_empty() {
+        output.push_str("\n\nNotes:\n");
+        for (track, note) in short {
+            output.push_str(&format!("  {}: {}\n", track.title, note));
+        }
+        output.pop();
+    }
+
+    // Damaged files
+    let damaged: Vec<_> = result
+        .tracks
+        .iter()
+        .flat_map(|t| {
+            [format_decode_errors(t), format_truncation(t)]
+                .into_iter()
+                .flatten()
+                .map(move |w| (t, w))
+        })
+        .collect();
+    if !damaged.is_empty() {
+        output.push_str("\n\nWarnings:\n");
+        for (track, warning) in damaged {
+            output.push_str(&format!("  {}: {}\n", track.title, warning));
+        }
+        output.pop();
+    }
+
+    // Perceptual DR, when requested
+    if let Some(album_dr) = result.perceptual_dr {
+        output.push_str(&format!("\n\n{} DR{}\n", PERCEPTUAL_DR_LABEL, album_dr));
+        for track in &result.tracks {
+            if let Some(dr) = track.perceptual_dr {
+                output.push_str(&format!("  {}: DR{}\n", track.title, dr));
+            }
+        }
+        output.pop();
+    }
+
+    output
+}
+
+/// How many tracks the album DR covers, e.g. "10 of 12 tracks", when some
+/// failed to analyze or silent ones were left out ("9 of 10 tracks, 1 silent
+/// excluded").
+pub fn format_contributing_tracks(result: &AlbumResult) -> Option<String> {
+    let excluded = result.silent_tracks_excluded as usize;
+    (result.failed_tracks > 0 || excluded > 0).then(|| {
+        let listed = result.tracks.len() + result.failed_tracks as usize;
+        let count = result.tracks.len() - excluded.min(result.tracks.len());
+        let mut text = format!("{} of {} tracks", count, listed);
+        if excluded > 0 {
+            text.push_str(&format!(", {} silent excluded", excluded));
+        }
+        text
+    })
+}
+
+/// The album's sample peak, e.g. "-0.10 dB". Results built by hand may not
+/// have `album_peak_db` filled in, so it falls back to the tracks.
+fn format_album_peak(result: &AlbumResult) -> String {
+    result
+        .album_peak_db
+        .or_else(|| result.tracks.iter().map(|t| t.peak_db).reduce(f64::max))
+        .map_or_else(
+            || "-".to_string(),
+            |peak| format!("{:.2} dB", display_db(peak)),
+        )
+}
+
+/// Compare the measured album DR with `result.reference_dr`, e.g.
+/// "measured DR11, database DR9 (differs)".
+fn format_reference(result: &AlbumResult) -> Option<String> {
+    let reference = result.reference_dr?;
+    let verdict = if reference == result.overall_dr {
+        "matches"
+    } else {
+        "differs"
+    };
+    Some(format!(
+        "measured DR{}, database DR{} ({})",
+        result.overall_dr, reference, verdict
+    ))
+}
+
+/// Summarize `result.outliers`, e.g. "track 7 (DR6, album DR11)".
+fn format_outliers(result: &AlbumResult) -> Option<String> {
+    if result.outliers.is_empty() {
+        return None;
+    }
+    let tracks: Vec<String> = result
+        .outliers
+        .iter()
+        .map(|o| format!("track {} (DR{}", o.track, o.dr))
+        .collect();
+    Some(format!(
+        "{}, album DR{})",
+        tracks.join("), "),
+        result.overall_dr
+    ))
+}
+
+/// Format the likely-duplicate track pairs found by `fingerprint::likely_duplicates`.
+pub fn format_duplicates(result: &AlbumResult, pairs: &[(usize, usize)]) -> String {
+    if pairs.is_empty() {
+        return "No likely duplicate tracks found".to_string();
+    }
+    let mut output = String::from("Likely duplicate tracks:");
+    for &(i, j) in pairs {
+        output.push_str(&format!(
+            "\n  {:>2}. {}  \u{2248}  {:>2}. {}",
+            i + 1,
+            result.tracks[i].title,
+            j + 1,
+            result.tracks[j].title,
+        ));
+    }
+    output
+}
+
+/// Layout of `format_dr_log`, after the foobar2000 DR Meter log versions that
+/// DR databases accept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// The original layout: an "Analyzed: artist / album" header, the track
+    /// table, then the track count and official DR
+    V1,
+    /// The newer layout: a "Statistics for:" header with the official DR and
+    /// stream details (sample rate, channels, bits per sample, codec) above
+    /// the track table
+    #[default]
+    V2,
+}
+
+/// Width of the DR log's separator lines.
+const LOG_WIDTH: usize = 80;
+
+/// Format an album as a foobar2000 DR Meter style log in the `version` layout.
+/// Tracks are numbered by tag when they have one, otherwise by position.
+pub fn format_dr_log(result: &AlbumResult, version: LogFormat) -> String {
+    let dashes = "-".repeat(LOG_WIDTH);
+    let equals = "=".repeat(LOG_WIDTH);
+    let artist = result.artist.as_deref().unwrap_or("Unknown Artist");
+    let album = result.album.as_deref().unwrap_or("Unknown Album");
+    let summary = format!(
+        "Number of tracks:  {}\nOfficial DR value: DR{}",
+        result.tracks.len(),
+        result.overall_dr
+    );
+
+    let mut rows = format!(
+        "{:<6}{:>11}{:>12}{:>10} {}\n{}\n",
+        "DR", "Peak", "RMS", "Duration", "Track", dashes
+    );
+    for (i, track) in result.tracks.iter().enumerate() {
+        rows.push_str(&format!(
+            "{:<6}{:>8.2} dB{:>9.2} dB{:>10} {:02}-{}\n",
+            format!("DR{}", track.dr),
+            display_db(track.peak_db),
+            display_db(track.rms_db),
+            format_duration(track.duration_secs),
+            track.track_number.unwrap_or(i as u32 + 1),
+            track.title,
+        ));
+    }
+    rows.push_str(&dashes);
+
+    match version {
+        LogFormat::V1 => format!(
+            "{dashes}\nAnalyzed: {artist} / {album}\n{dashes}\n\n{rows}\n\n{summary}\n{equals}"
+        ),
+        LogFormat::V2 => {
+            let first = result.tracks.first();
+            let detail = |value: Option<String>| value.unwrap_or_else(|| "unknown".to_string());
+            let stream = format!(
+                "Samplerate:        {}\nChannels:          {}\nBits per sample:   {}\nCodec:             {}",
+                detail(first.and_then(|t| t.sample_rate).map(|r| format!("{} Hz", r))),
+                detail(first.and_then(|t| t.channels).map(|c| c.to_string())),
+                detail(first.and_then(|t| t.bits_per_sample).map(|b| b.to_string())),
+                detail(first.and_then(|t| t.codec.as_deref()).map(str::to_uppercase)),
+            );
+            format!(
+                "foo_dr_meter: Dynamic Range Meter log\n{dashes}\nStatistics for: {artist} - {album}\n\
+                 {summary}\n\n{stream}\n{equals}\n\n{rows}\n\n{summary}\n{equals}"
+            )
+        }
+    }
+}
+
+/// Badge color for a DR value, by severity as in the TUI: a shields.io color
+/// name and its hex value.
+fn badge_color(dr: u32, scale: DrScale) -> (&'static str, &'static str) {
+    match scale.classify(dr) {
+        DrSeverity::Low => ("red", "#e05d44"),
+        DrSeverity::Medium => ("yellow", "#dfb317"),
+        DrSeverity::High => ("green", "#97ca00"),
+    }
+}
+
+/// Label on the left half of a badge.
+const BADGE_LABEL: &str = "dynamic range";
+
+/// A shields.io endpoint document for a DR badge, colored by severity on `scale`.
+pub fn format_badge_json(dr: u32, scale: DrScale) -> String {
+    serde_json::json!({
+        "schemaVersion": 1,
+        "label": BADGE_LABEL,
+        "message": format!("DR{}", dr),
+        "color": badge_color(dr, scale).0,
+    })
+    .to_string()
+}
+
+/// A flat SVG badge reading "dynamic range | DR<n>", the value half colored
+/// by severity on `scale`.
+pub fn format_badge_svg(dr: u32, scale: DrScale) -> String {
+    let message = format!("DR{}", dr);
+    // Verdana 11px averages about 7px per character; 10px padding per half
+    let label_width = BADGE_LABEL.len() * 7 + 10;
+    let message_width = message.len() * 7 + 10;
+    let width = label_width + message_width;
+    let color = badge_color(dr, scale).1;
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+  <title>{label}: {message}</title>
+  <rect width="{label_width}" height="20" fill="#555"/>
+  <rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{message_x}" y="14">{message}</text>
+  </g>
+</svg>
+"##,
+        label = BADGE_LABEL,
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2,
+    )
+}
+
+/// Widest bar in `format_histogram`, in characters.
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Format the distribution of album DR values as a text bar chart, one line
+/// per DR from the lowest to the highest (including empty ones), with bars
+/// proportional to the most common value.
+pub fn format_histogram(drs: &[u32]) -> String {
+    let (Some(&min), Some(&max)) = (drs.iter().min(), drs.iter().max()) else {
+        return "No albums".to_string();
+    };
+    let counts: Vec<usize> = (min..=max)
+        .map(|dr| drs.iter().filter(|&&d| d == dr).count())
+        .collect();
+    let most = counts.iter().copied().max().unwrap_or(1);
+    let mut output = format!(
+        "DR distribution ({} album{}):",
+        drs.len(),
+        if drs.len() == 1 { "" } else { "s" }
+    );
+    for (dr, count) in (min..=max).zip(counts) {
+        let bar = "\u{2588}".repeat((count * HISTOGRAM_WIDTH).div_ceil(most));
+        output.push_str(&format!(
+            "\n{:<5} {:<width$} {}",
+            format!("DR{}", dr),
+            bar,
+            count,
+            width = HISTOGRAM_WIDTH
+        ));
+    }
+    output
+}
+
+/// Format a normalization preview as a table of post-gain levels.
+pub fn format_preview(preview: &NormalizationPreview) -> String {
+    let separator = "\u{2500}".repeat(SEPARATOR_WIDTH);
+    let mut output = format!(
+        "Normalization to {:.1} LUFS: measured {:.1} LUFS, gain {:+.2} dB\n\n",
+        preview.target_lufs, preview.source_lufs, preview.gain_db,
+    );
+    output.push_str(&format!(
+        "{:>12} {:>16} {:>10}  {}\n",
+        "Peak (dBFS)", "True Peak (dBTP)", "RMS (dB)", "Track"
     ));
+    output.push_str(&separator);
+    output.push('\n');
+    for track in &preview.tracks {
+        output.push_str(&format!(
+            "{:>12.2} {:>16.2} {:>10.2}  {}{}\n",
+            display_db(track.peak_db),
+            display_db(track.true_peak_db),
+            track.rms_db,
+            track.title,
+            if track.clips { "  (clips)" } else { "" },
+        ));
+    }
+    output.push_str(&separator);
+    output.push('\n');
 
+    match preview.clipping_tracks() {
+        0 => output.push_str("Safe to normalize: true peak stays at or below 0 dBTP"),
+        n => output.push_str(&format!(
+            "Would clip: {} of {} tracks exceed 0 dBTP (a limiter is needed)",
+            n,
+            preview.tracks.len()
+        )),
+    }
     output
 }
 
+/// Format a normalization preview as pretty-printed JSON.
+pub fn format_preview_json(preview: &NormalizationPreview) -> String {
+    serde_json::to_string_pretty(preview).unwrap_or_else(|_| "{}".to_string())
+}
+
 /// Format a single track result as pretty-printed JSON.
 pub fn format_json_single(result: &TrackResult) -> String {
     serde_json::to_string_pretty(result).unwrap_or_else(|_| "{}".to_string())
 }
 
+/// A report format an album result can be rendered to with `render`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// The DR Database table (`format_table`)
+    Text,
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    /// File extension for reports in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+
+    /// Human-readable name, e.g. for a format picker.
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "Text (DR Database table)",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Csv => "CSV",
+        }
+    }
+}
+
+/// Render an album result in `format`, returning the content and its file
+/// extension.
+pub fn render(result: &AlbumResult, format: ExportFormat) -> (String, &'static str) {
+    let content = match format {
+        ExportFormat::Text => format_table(result),
+        ExportFormat::Json => format_json(result),
+        ExportFormat::Csv => format_csv(result),
+    };
+    (content, format.extension())
+}
+
+/// Format independently analyzed tracks (`--singles`) as a pretty-printed JSON array.
+pub fn format_json_tracks(tracks: &[TrackResult]) -> String {
+    serde_json::to_string_pretty(tracks).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Format album results (`--bulk --json-stdout`) as a pretty-printed JSON array.
+pub fn format_json_albums(results: &[AlbumResult]) -> String {
+    serde_json::to_string_pretty(results).unwrap_or_else(|_| "[]".to_string())
+}
+
 /// Format an album result as pretty-printed JSON.
 pub fn format_json(result: &AlbumResult) -> String {
     serde_json::to_string_pretty(result).unwrap_or_else(|_| "{}".to_string())
 }
 
-/// Format an album result as CSV.
+/// Labels for the CSV header row and the totals row: English (the default),
+/// another language, or custom column labels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvHeader {
+    /// One label per column (`CSV_COLUMNS`)
+    pub columns: Vec<String>,
+    /// Word marking the totals row, in its Track column
+    pub total: String,
+}
+
+/// Columns of `format_csv`.
+pub const CSV_COLUMNS: usize = 7;
+
+/// Languages `CsvHeader` has labels for: code, column labels, totals word.
+const CSV_LANGUAGES: &[(&str, [&str; CSV_COLUMNS], &str)] = &[
+    (
+        "en",
+        [
+            "DR",
+            "Peak (dBFS)",
+            "True Peak (dBTP)",
+            "RMS (dB)",
+            "Duration",
+            "Size (MB)",
+            "Track",
+        ],
+        "Total",
+    ),
+    (
+        "de",
+        [
+            "DR",
+            "Spitze (dBFS)",
+            "True Peak (dBTP)",
+            "RMS (dB)",
+            "Dauer",
+            "Größe (MB)",
+            "Titel",
+        ],
+        "Gesamt",
+    ),
+    (
+        "fr",
+        [
+            "DR",
+            "Crête (dBFS)",
+            "Crête vraie (dBTP)",
+            "RMS (dB)",
+            "Durée",
+            "Taille (Mo)",
+            "Piste",
+        ],
+        "Total",
+    ),
+    (
+        "es",
+        [
+            "DR",
+            "Pico (dBFS)",
+            "Pico real (dBTP)",
+            "RMS (dB)",
+            "Duración",
+            "Tamaño (MB)",
+            "Pista",
+        ],
+        "Total",
+    ),
+];
+
+impl CsvHeader {
+    /// The labels for a language code ("en", "de", "fr", "es").
+    pub fn language(code: &str) -> Option<Self> {
+        CSV_LANGUAGES
+            .iter()
+            .find(|(c, _, _)| c.eq_ignore_ascii_case(code))
+            .map(|(_, columns, total)| Self {
+                columns: columns.iter().map(|c| c.to_string()).collect(),
+                total: total.to_string(),
+            })
+    }
+}
+
+impl Default for CsvHeader {
+    fn default() -> Self {
+        Self::language("en").unwrap()
+    }
+}
+
+impl std::str::FromStr for CsvHeader {
+    type Err = anyhow::Error;
+
+    /// A language code, or `CSV_COLUMNS` comma-separated labels (the totals
+    /// row keeps "Total").
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        if let Some(header) = Self::language(s.trim()) {
+            return Ok(header);
+        }
+        let columns: Vec<String> = s.split(',').map(|c| c.trim().to_string()).collect();
+        if columns.len() != CSV_COLUMNS || columns.iter().any(|c| c.is_empty()) {
+            let codes: Vec<&str> = CSV_LANGUAGES.iter().map(|(c, _, _)| *c).collect();
+            anyhow::bail!(
+                "expected a language ({}) or {} comma-separated labels, got {:?}",
+                codes.join(", "),
+                CSV_COLUMNS,
+                s
+            );
+        }
+        Ok(Self {
+            columns,
+            ..Self::default()
+        })
+    }
+}
+
+/// How `format_csv_with` writes the CSV for spreadsheets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CsvStyle {
+    /// Start with a UTF-8 byte order mark, so Excel reads non-ASCII titles
+    /// correctly. Off by default: programs reading the CSV may not expect it.
+    pub bom: bool,
+    pub header: CsvHeader,
+}
+
+/// Format an album result as CSV. The last row holds the album totals (official
+/// DR, total duration, and total size), with "Total" in its Track column.
 pub fn format_csv(result: &AlbumResult) -> String {
-    let mut output = String::from("DR,Peak dB,RMS dB,Duration,Track\n");
+    format_csv_with(result, &CsvStyle::default())
+}
+
+/// `format_csv` with a byte order mark and header labels from `style`.
+pub fn format_csv_with(result: &AlbumResult, style: &CsvStyle) -> String {
+    let mut output = if style.bom {
+        String::from('\u{feff}')
+    } else {
+        String::new()
+    };
+    output.push_str(&style.header.columns.join(","));
+    output.push('\n');
     for track in &result.tracks {
         output.push_str(&format!(
-            "{},{:.2},{:.2},{},{}\n",
+            "{},{:.2},{:.2},{:.2},{},{:.1},{}\n",
             track.dr,
-            track.peak_db,
+            display_db(track.peak_db),
+            display_db(track.true_peak_db),
             track.rms_db,
             format_duration(track.duration_secs),
+            megabytes(track.file_bytes),
             track.title,
         ));
     }
+    output.push_str(&format!(
+        "{},,,,{},{:.1},{}\n",
+        result.overall_dr,
+        format_duration(result.total_duration_secs),
+        megabytes(result.total_bytes),
+        style.header.total,
+    ));
     output
 }
 
+/// Format the block RMS envelopes of `tracks` (the chapters of one file, in
+/// order) as `--envelope` CSV: each block's start time in seconds from the
+/// start of the file, and its RMS in dB.
+pub fn format_envelope_csv(tracks: &[TrackResult]) -> String {
+    let mut output = String::from("Time (s),RMS (dB)\n");
+    let mut offset = 0.0;
+    for track in tracks {
+        let start =
+            offset + track.range_start_secs.unwrap_or(0.0) + track.trim_edges_secs.unwrap_or(0.0);
+        for (i, db) in track.envelope_db.iter().enumerate() {
+            output.push_str(&format!("{:.2},{:.2}\n", start + i as f64 * BLOCK_SECS, db));
+        }
+        offset += track.duration_secs;
+    }
+    output
+}
+
+/// Version of the `--porcelain` format. Fields are only ever appended after
+/// the existing ones, so scripts that read the leading fields keep working.
+pub const PORCELAIN_VERSION: u32 = 1;
+
+/// One `--porcelain` track line: tab-separated filename, DR, sample peak
+/// (dBFS), RMS (dB), true peak (dBTP), duration in seconds, sample rate (Hz),
+/// and channel count. Tabs and newlines in the filename become spaces; a
+/// level of negative infinity (silence) prints as `-inf`, and an unknown
+/// sample rate or channel count (reports cached before schema v9) as an
+/// empty field.
+pub fn format_porcelain_track(track: &TrackResult) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{}\t{:.3}\t{}\t{}",
+        porcelain_text(&track.filename),
+        track.dr,
+        porcelain_db(track.peak_db),
+        porcelain_db(track.rms_db),
+        porcelain_db(track.true_peak_db),
+        track.duration_secs,
+        porcelain_count(track.sample_rate),
+        porcelain_count(track.channels),
+    )
+}
+
+/// `--porcelain` album output: an `#album` line (porcelain version, album DR,
+/// track count, album name), then one `format_porcelain_track` line per track.
+pub fn format_porcelain(result: &AlbumResult) -> String {
+    let mut output = format!(
+        "#album\t{}\t{}\t{}\t{}",
+        PORCELAIN_VERSION,
+        result.overall_dr,
+        result.tracks.len(),
+        porcelain_text(result.album.as_deref().unwrap_or("")),
+    );
+    for track in &result.tracks {
+        output.push('\n');
+        output.push_str(&format_porcelain_track(track));
+    }
+    output
+}
+
+fn porcelain_text(text: &str) -> String {
+    text.replace(['\t', '\n', '\r'], " ")
+}
+
+fn porcelain_count(value: Option<u32>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn porcelain_db(db: f64) -> String {
+    if db == f64::NEG_INFINITY {
+        "-inf".to_string()
+    } else {
+        format!("{:.2}", display_db(db))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,29 +1323,380 @@ mod tests {
         assert_eq!(format_duration(3661.0), "61:01");
     }
 
+    #[test]
+    fn test_phase_warning_only_below_threshold() {
+        assert_eq!(format_stereo_correlation(Some(-0.2)).unwrap(), "-0.20");
+        assert_eq!(format_stereo_correlation(Some(-0.5)).unwrap(), "-0.50");
+        assert_eq!(
+            format_stereo_correlation(Some(-0.8)).unwrap(),
+            "-0.80 (possible phase cancellation)"
+        );
+        assert!(format_stereo_correlation(None).is_none());
+    }
+
+    #[test]
+    fn test_format_bulk_progress() {
+        assert_eq!(format_eta(42.4), "42s");
+        assert_eq!(format_eta(185.0), "3m 05s");
+        assert_eq!(format_eta(3725.0), "1h 02m");
+        // 2 analyzed albums at 30s each, 6 of 10 albums left
+        assert_eq!(
+            format_bulk_progress(4, 10, 62.0, 2, 60.0),
+            "Progress: 4/10 albums (40.0%), 1m 02s elapsed, ETA 3m 00s"
+        );
+        assert_eq!(
+            format_bulk_progress(2, 10, 0.1, 0, 0.0),
+            "Progress: 2/10 albums (20.0%), 0s elapsed"
+        );
+        assert_eq!(
+            format_bulk_progress(10, 10, 300.0, 10, 299.0),
+            "Progress: 10/10 albums (100.0%), 5m 00s elapsed"
+        );
+    }
+
+    #[test]
+    fn test_near_zero_peak_never_prints_negative_zero() {
+        let result = AlbumResult {
+            tracks: vec![TrackResult {
+                dr: 8,
+                peak_db: -0.001,
+                true_peak_db: -0.004,
+                rms_db: -9.5,
+                title: "Hot Master".to_string(),
+                ..Default::default()
+            }],
+            overall_dr: 8,
+            ..Default::default()
+        };
+        for output in [format_table(&result), format_csv(&result)] {
+            assert!(output.contains("0.00"), "{}", output);
+            assert!(!output.contains("-0.00"), "{}", output);
+        }
+        assert_eq!(format!("{:.2}", display_db(-0.006)), "-0.01");
+    }
+
+    #[test]
+    fn test_outliers_in_footer() {
+        let track = |dr| TrackResult {
+            dr,
+            title: format!("DR{} Track", dr),
+            ..Default::default()
+        };
+        let mut result = AlbumResult {
+            tracks: vec![
+                track(12),
+                track(11),
+                track(6),
+                track(12),
+                track(9),
+                track(13),
+            ],
+            overall_dr: 11,
+            ..Default::default()
+        };
+        result.update_outliers();
+        let outliers: Vec<_> = result.outliers.iter().map(|o| (o.track, o.dr)).collect();
+        assert_eq!(outliers, vec![(3, 6), (5, 9)]);
+        assert!(format_table(&result)
+            .contains("Outliers:          track 3 (DR6), track 5 (DR9, album DR11)"));
+
+        result.tracks = vec![track(11), track(10)];
+        result.update_outliers();
+        assert!(result.outliers.is_empty());
+        assert!(!format_table(&result).contains("Outliers:"));
+    }
+
+    fn long_title_album() -> AlbumResult {
+        AlbumResult {
+            tracks: vec![
+                TrackResult {
+                    dr: 12,
+                    peak_db: -0.5,
+                    duration_secs: 300.0,
+                    title: "A Very Long Title That Goes On And On Past Any Reasonable Column"
+                        .into(),
+                    ..Default::default()
+                },
+                TrackResult {
+                    dr: 9,
+                    title: "Short".into(),
+                    ..Default::default()
+                },
+            ],
+            overall_dr: 11,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lossy_tracks_are_footnoted() {
+        let mut track = TrackResult {
+            dr: 8,
+            title: "Encoded".into(),
+            lossy: true,
+            ..Default::default()
+        };
+        let single = format_table_single(&track);
+        assert!(single.contains("  Encoded *\n"));
+        assert!(single.ends_with(LOSSY_NOTE));
+
+        let album = AlbumResult {
+            tracks: vec![
+                track.clone(),
+                TrackResult {
+                    title: "Lossless".into(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let table = format_table(&album);
+        assert!(table.contains("  Encoded *\n") && table.contains("  Lossless\n"));
+        assert!(table.contains(LOSSY_NOTE));
+        // The mark stays within a fitted width
+        track.title = "An Encoded Track With A Long Title".into();
+        let fitted = format_table_single_width(&track, 40);
+        assert!(fitted.lines().nth(2).unwrap().ends_with("\u{2026} *"));
+        assert_eq!(fitted.lines().nth(2).unwrap().chars().count(), 40);
+
+        track.lossy = false;
+        assert!(!format_table_single(&track).contains(LOSSY_NOTE));
+    }
+
+    #[test]
+    fn test_format_table_narrow() {
+        let table = format_table_width(&long_title_album(), 40);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "DR    Peak dBFS Duration  Track");
+        assert_eq!(lines[1], "\u{2500}".repeat(40));
+        assert_eq!(lines[2], "DR12      -0.50     5:00  A Very Long T\u{2026}");
+        assert_eq!(lines[3], "DR9        0.00     0:00  Short");
+        for line in &lines[..5] {
+            assert!(line.chars().count() <= 40, "too wide: {:?}", line);
+        }
+        assert!(table.contains("Official DR value: DR11"));
+        assert!(!table.contains("True Peak"));
+    }
+
+    #[test]
+    fn test_format_table_wide() {
+        let table = format_table_width(&long_title_album(), 120);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].contains("True Peak (dBTP)"));
+        assert_eq!(lines[1], "\u{2500}".repeat(120));
+        // 62 columns before the title leave 58 for it
+        assert!(lines[2]
+            .ends_with("  A Very Long Title That Goes On And On Past Any Reasonable\u{2026}"));
+        assert_eq!(lines[2].chars().count(), 120);
+        assert!(lines[3].ends_with("  Short"));
+
+        // The default layout never cuts titles
+        assert!(format_table(&long_title_album()).contains("Past Any Reasonable Column"));
+    }
+
+    #[test]
+    fn test_table_pieces_add_up_to_table() {
+        let album = long_title_album();
+        for (width, table) in [
+            (None, format_table(&album)),
+            (Some(60), format_table_width(&album, 60)),
+        ] {
+            let layout = TableLayout::for_width(width);
+            let mut pieces = vec![format_table_head(layout)];
+            pieces.extend(album.tracks.iter().map(|t| format_table_row(t, layout)));
+            pieces.push(format_table_footer(&album, layout));
+            assert_eq!(pieces.join("\n"), table);
+        }
+    }
+
+    #[test]
+    fn test_format_porcelain() {
+        let result = AlbumResult {
+            tracks: vec![
+                TrackResult {
+                    dr: 12,
+                    peak_db: -0.001,
+                    true_peak_db: 0.31,
+                    rms_db: -14.257,
+                    duration_secs: 201.5,
+                    filename: "01\tIntro.flac".into(),
+                    sample_rate: Some(44100),
+                    channels: Some(2),
+                    ..Default::default()
+                },
+                TrackResult {
+                    rms_db: f64::NEG_INFINITY,
+                    filename: "02 Silence.flac".into(),
+                    ..Default::default()
+                },
+            ],
+            overall_dr: 6,
+            album: Some("Album".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_porcelain(&result),
+            "#album\t1\t6\t2\tAlbum\n\
+             01 Intro.flac\t12\t0.00\t-14.26\t0.31\t201.500\t44100\t2\n\
+             02 Silence.flac\t0\t0.00\t-inf\t0.00\t0.000\t\t"
+        );
+    }
+
     #[test]
     fn test_format_table_columns() {
         let result = AlbumResult {
             tracks: vec![TrackResult {
                 dr: 14,
                 peak_db: -0.10,
+                true_peak_db: 0.25,
                 rms_db: -16.78,
                 duration_secs: 263.0,
                 title: "Test Track".to_string(),
                 filename: "test.flac".to_string(),
-                file_bytes: 0,
+                ..Default::default()
             }],
             overall_dr: 14,
             album: Some("Test Album".to_string()),
+            // The stored totals, not the tracks', are printed
+            total_duration_secs: 263.0,
+            total_bytes: 3 * 1024 * 1024,
+            ..Default::default()
         };
         let table = format_table(&result);
+        assert!(table.contains("Peak (dBFS)"));
+        assert!(table.contains("True Peak (dBTP)"));
         assert!(table.contains("DR14"));
-        assert!(table.contains("-0.10 dB"));
-        assert!(table.contains("-16.78 dB"));
+        assert!(table.contains("-0.10"));
+        assert!(table.contains("0.25"));
+        assert!(table.contains("-16.78"));
         assert!(table.contains("4:23"));
         assert!(table.contains("Test Track"));
         assert!(table.contains("Official DR value: DR14"));
         assert!(table.contains("Number of tracks:  1"));
+        assert!(table.contains("Total duration:    4:23"));
+        assert!(table.contains("Total size:        3.0 MB"));
+        assert!(table.contains("DR rating:         Excellent (DR14+)"));
+    }
+
+    #[test]
+    fn test_crest_factor_column_only_when_asked() {
+        let track = TrackResult {
+            dr: 9,
+            peak_db: -0.5,
+            rms_db: -12.25,
+            crest_factor_db: 11.75,
+            duration_secs: 200.0,
+            title: "Track".to_string(),
+            ..Default::default()
+        };
+        let crest = TableLayout::FULL.with_crest_factor(true);
+        assert!(format_table_single_in(&track, crest).contains("RMS (dB)  Crest (dB)"));
+        assert!(format_table_row(&track, crest).contains("-12.25       11.75"));
+        assert!(!format_table_single(&track).contains("Crest"));
+        // The compact layout has no room for it
+        let compact = TableLayout::fitted(40).with_crest_factor(true);
+        assert!(!format_table_single_in(&track, compact).contains("Crest"));
+    }
+
+    #[test]
+    fn test_plr_column() {
+        let track = |plr| TrackResult {
+            dr: 9,
+            rms_db: -12.25,
+            crest_factor_db: 11.75,
+            plr,
+            title: "Track".to_string(),
+            ..Default::default()
+        };
+        let both = TableLayout::FULL.with_crest_factor(true).with_plr(true);
+        let head = format_table_head(both);
+        assert!(head.contains("RMS (dB)  Crest (dB)  PLR (dB)   Duration"));
+        // The separator grows with the columns
+        assert_eq!(
+            head.lines().nth(1).unwrap().chars().count(),
+            SEPARATOR_WIDTH + CREST_COLUMN + PLR_COLUMN
+        );
+        assert!(format_table_row(&track(Some(8.5)), both).contains("11.75      8.50"));
+        assert!(format_table_row(&track(None), both).contains("11.75         -"));
+        assert!(!format_table_row(&track(Some(8.5)), TableLayout::FULL).contains("8.50"));
+    }
+
+    #[test]
+    fn test_tracks_grouped_by_disc() {
+        let track = |disc, title: &str| TrackResult {
+            disc,
+            title: title.to_string(),
+            ..Default::default()
+        };
+        let result = |tracks| AlbumResult {
+            tracks,
+            ..Default::default()
+        };
+
+        let table = format_table(&result(vec![
+            track(Some(1), "One"),
+            track(Some(1), "Two"),
+            track(Some(2), "Three"),
+        ]));
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[2], "Disc 1");
+        assert!(lines[3].ends_with("One") && lines[4].ends_with("Two"));
+        assert_eq!(lines[5], "Disc 2");
+        assert!(lines[6].ends_with("Three"));
+        assert!(format_table(&result(vec![
+            track(Some(1), "One"),
+            track(Some(2), "Two"),
+            track(None, "Bonus")
+        ]))
+        .contains("No disc"));
+
+        // One disc, or none tagged, needs no headers
+        assert!(
+            !format_table(&result(vec![track(Some(1), "One"), track(None, "Two")]))
+                .contains("Disc")
+        );
+        assert!(!format_table(&result(vec![track(None, "One")])).contains("Disc"));
+    }
+
+    #[test]
+    fn test_precise_column() {
+        let track = |dr_precise| TrackResult {
+            dr: 11,
+            dr_precise,
+            peak_db: -0.5,
+            title: "Track".to_string(),
+            ..Default::default()
+        };
+        let precise = TableLayout::FULL.with_precise(true);
+        let head = format_table_head(precise);
+        assert!(head.starts_with("DR       Precise  Peak (dBFS)"));
+        assert_eq!(
+            head.lines().nth(1).unwrap().chars().count(),
+            SEPARATOR_WIDTH + PRECISE_COLUMN
+        );
+        assert!(format_table_row(&track(Some(11.43)), precise)
+            .starts_with("DR11        11.4        -0.50"));
+        assert!(
+            format_table_row(&track(None), precise).starts_with("DR11           -        -0.50")
+        );
+        assert!(!format_table_row(&track(Some(11.43)), TableLayout::FULL).contains("11.4"));
+        assert!(!format_table_single_in(
+            &track(Some(11.43)),
+            TableLayout::fitted(40).with_precise(true)
+        )
+        .contains("Precise"));
+    }
+
+    #[test]
+    fn test_dr_rating_bands() {
+        let scale = DrScale::default();
+        assert_eq!(DrRating::from_dr(0, &scale), DrRating::Crushed);
+        assert_eq!(DrRating::from_dr(7, &scale), DrRating::Crushed);
+        assert_eq!(DrRating::from_dr(8, &scale), DrRating::Compressed);
+        assert_eq!(DrRating::from_dr(10, &scale), DrRating::Compressed);
+        assert_eq!(DrRating::from_dr(11, &scale), DrRating::Good);
+        assert_eq!(DrRating::from_dr(13, &scale), DrRating::Good);
+        assert_eq!(DrRating::from_dr(14, &scale), DrRating::Excellent);
     }
 
     #[test]
@@ -140,7 +1708,7 @@ mod tests {
             duration_secs: 225.0,
             title: "My Track".to_string(),
             filename: "track.flac".to_string(),
-            file_bytes: 0,
+            ..Default::default()
         };
         let json = format_json_single(&track);
         let parsed: TrackResult = serde_json::from_str(&json).unwrap();
@@ -159,7 +1727,8 @@ mod tests {
                     duration_secs: 263.0,
                     title: "Track One".to_string(),
                     filename: "01.flac".to_string(),
-                    file_bytes: 0,
+                    file_bytes: 1024 * 1024,
+                    ..Default::default()
                 },
                 TrackResult {
                     dr: 12,
@@ -168,16 +1737,159 @@ mod tests {
                     duration_secs: 225.0,
                     title: "Track Two".to_string(),
                     filename: "02.flac".to_string(),
-                    file_bytes: 0,
+                    file_bytes: 512 * 1024,
+                    ..Default::default()
                 },
             ],
             overall_dr: 13,
             album: None,
+            total_duration_secs: 488.0,
+            total_bytes: 1536 * 1024,
+            ..Default::default()
         };
         let csv = format_csv(&result);
         let lines: Vec<&str> = csv.lines().collect();
-        assert_eq!(lines[0], "DR,Peak dB,RMS dB,Duration,Track");
-        assert!(lines[1].starts_with("14,"));
-        assert!(lines[2].starts_with("12,"));
+        assert_eq!(
+            lines[0],
+            "DR,Peak (dBFS),True Peak (dBTP),RMS (dB),Duration,Size (MB),Track"
+        );
+        assert!(lines[1].starts_with("14,") && lines[1].ends_with(",4:23,1.0,Track One"));
+        assert!(lines[2].starts_with("12,") && lines[2].ends_with(",3:45,0.5,Track Two"));
+        assert_eq!(lines[3], "13,,,,8:08,1.5,Total");
+
+        let german = CsvStyle {
+            bom: true,
+            header: "de".parse().unwrap(),
+        };
+        let csv = format_csv_with(&result, &german);
+        assert!(csv.starts_with(
+            "\u{feff}DR,Spitze (dBFS),True Peak (dBTP),RMS (dB),Dauer,Größe (MB),Titel\n"
+        ));
+        assert!(csv.ends_with("13,,,,8:08,1.5,Gesamt\n"));
+        let custom: CsvHeader = "a, b,c,d,e,f,g".parse().unwrap();
+        assert_eq!(custom.columns[1], "b");
+        assert_eq!(custom.total, "Total");
+        assert!("a,b,c".parse::<CsvHeader>().is_err());
+        assert!("xx".parse::<CsvHeader>().is_err());
+    }
+
+    #[test]
+    fn test_format_envelope_csv_times_from_file_start() {
+        let chapters = [
+            TrackResult {
+                duration_secs: 7.0,
+                envelope_db: vec![-20.0, -18.5, -120.0],
+                ..Default::default()
+            },
+            TrackResult {
+                duration_secs: 6.0,
+                trim_edges_secs: Some(1.0),
+                envelope_db: vec![-9.25],
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            format_envelope_csv(&chapters),
+            "Time (s),RMS (dB)\n0.00,-20.00\n3.00,-18.50\n6.00,-120.00\n8.00,-9.25\n"
+        );
+    }
+
+    #[test]
+    fn test_format_histogram() {
+        let chart = format_histogram(&[9, 7, 9, 9, 10]);
+        let lines: Vec<&str> = chart.lines().collect();
+        assert_eq!(lines[0], "DR distribution (5 albums):");
+        // Every DR in range gets a line, empty ones included
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("DR7   \u{2588}") && lines[1].ends_with(" 1"));
+        assert!(lines[2].starts_with("DR8  ") && lines[2].ends_with(" 0"));
+        assert!(!lines[2].contains('\u{2588}'));
+        assert_eq!(lines[3].matches('\u{2588}').count(), HISTOGRAM_WIDTH);
+        assert!(lines[3].ends_with(" 3"));
+        assert_eq!(lines[4].matches('\u{2588}').count(), 14);
+        assert_eq!(format_histogram(&[]), "No albums");
+    }
+
+    #[test]
+    fn test_format_dr_log_versions() {
+        let result = AlbumResult {
+            tracks: vec![TrackResult {
+                dr: 9,
+                peak_db: -0.1,
+                rms_db: -11.45,
+                duration_secs: 263.0,
+                title: "Opening".to_string(),
+                sample_rate: Some(44100),
+                channels: Some(2),
+                bits_per_sample: Some(16),
+                codec: Some("flac".to_string()),
+                ..Default::default()
+            }],
+            overall_dr: 9,
+            album: Some("Album".to_string()),
+            artist: Some("Artist".to_string()),
+            ..Default::default()
+        };
+        let row = "DR9      -0.10 dB   -11.45 dB      4:23 01-Opening";
+
+        let v1 = format_dr_log(&result, LogFormat::V1);
+        let lines: Vec<&str> = v1.lines().collect();
+        assert_eq!(lines[1], "Analyzed: Artist / Album");
+        assert_eq!(lines[4], "DR           Peak         RMS  Duration Track");
+        assert_eq!(lines[6], row);
+        let summary = format!(
+            "Number of tracks:  1\nOfficial DR value: DR9\n{}",
+            "=".repeat(80)
+        );
+        assert!(v1.trim_end().ends_with(&summary));
+        assert!(!v1.contains("Samplerate"));
+
+        let v2 = format_dr_log(&result, LogFormat::V2);
+        assert!(v2.contains(
+            "Statistics for: Artist - Album\nNumber of tracks:  1\nOfficial DR value: DR9"
+        ));
+        assert!(v2.contains("Samplerate:        44100 Hz\nChannels:          2\nBits per sample:   16\nCodec:             FLAC"));
+        assert!(v2.contains(row));
+    }
+
+    #[test]
+    fn test_badges_colored_by_severity() {
+        let scale = DrScale::default();
+        let json: serde_json::Value = serde_json::from_str(&format_badge_json(14, scale)).unwrap();
+        assert_eq!(json["schemaVersion"], 1);
+        assert_eq!(json["message"], "DR14");
+        assert_eq!(json["color"], "green");
+        assert!(format_badge_json(13, scale).contains("\"yellow\""));
+        assert!(format_badge_json(11, DrScale::new(8, 11).unwrap()).contains("\"green\""));
+
+        let svg = format_badge_svg(6, scale);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(">DR6</text>"));
+        assert!(svg.contains("fill=\"#e05d44\""));
+    }
+
+    #[test]
+    fn test_render_each_format() {
+        let result = AlbumResult {
+            tracks: vec![TrackResult {
+                dr: 11,
+                title: "Only".to_string(),
+                ..Default::default()
+            }],
+            overall_dr: 11,
+            ..Default::default()
+        };
+        assert_eq!(
+            render(&result, ExportFormat::Text),
+            (format_table(&result), "txt")
+        );
+        assert_eq!(
+            render(&result, ExportFormat::Json),
+            (format_json(&result), "json")
+        );
+        assert_eq!(
+            render(&result, ExportFormat::Csv),
+            (format_csv(&result), "csv")
+        );
     }
 }
@@ -1,5 +1,16 @@
 pub mod analyzer;
 pub mod cache;
+pub mod chapters;
+pub mod config;
+pub mod error;
+pub mod fingerprint;
 pub mod format;
+pub mod logging;
+pub mod loudness;
 pub mod models;
+pub mod reference;
+pub mod selftest;
+pub mod signals;
+pub mod testutil;
+pub mod truepeak;
 pub mod tui;
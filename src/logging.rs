@@ -0,0 +1,100 @@
+//! A minimal stderr logger for the `log` facade.
+//!
+//! `dr`'s own diagnostics print as before: warnings as "Warning: ...", errors
+//! as "Error: ...", and info messages unadorned. Debug and trace records are
+//! tagged with their level and module ("[debug dr::cache] ..."). Records from
+//! dependencies (symphonia's demuxer and decoder notes) only show from the
+//! debug level up, so they never clutter normal output.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// The stderr logger; its level is `log::max_level()`.
+struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// The level for `-q` / default / `-v` / `-vv` and up.
+pub fn level_for(quiet: bool, verbose: u8) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::Warn,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }
+}
+
+/// Install the stderr logger at `level`. Later calls only change the level.
+pub fn init(level: LevelFilter) {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
+
+/// Whether a record passes `max`: `dr`'s own records by level alone, those of
+/// dependencies only once `max` is at least debug.
+fn enabled_at(max: LevelFilter, metadata: &Metadata) -> bool {
+    let own = metadata.target() == "dr" || metadata.target().starts_with("dr::");
+    metadata.level() <= max && (own || max >= LevelFilter::Debug)
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        enabled_at(log::max_level(), metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error => eprintln!("Error: {}", record.args()),
+            Level::Warn => eprintln!("Warning: {}", record.args()),
+            Level::Info => eprintln!("{}", record.args()),
+            level => eprintln!(
+                "[{} {}] {}",
+                level.as_str().to_lowercase(),
+                record.target(),
+                record.args()
+            ),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(target: &str, level: Level) -> Metadata<'_> {
+        Metadata::builder().target(target).level(level).build()
+    }
+
+    #[test]
+    fn test_dependency_records_need_debug() {
+        let info = LevelFilter::Info;
+        assert!(enabled_at(info, &metadata("dr::cache", Level::Info)));
+        assert!(enabled_at(info, &metadata("dr", Level::Warn)));
+        assert!(!enabled_at(info, &metadata("dr::analyzer", Level::Debug)));
+        assert!(!enabled_at(
+            info,
+            &metadata("symphonia_core::probe", Level::Warn)
+        ));
+        assert!(!enabled_at(info, &metadata("drift", Level::Info)));
+
+        let debug = LevelFilter::Debug;
+        assert!(enabled_at(
+            debug,
+            &metadata("symphonia_core::probe", Level::Warn)
+        ));
+        assert!(enabled_at(debug, &metadata("dr::analyzer", Level::Debug)));
+        assert!(!enabled_at(debug, &metadata("dr::analyzer", Level::Trace)));
+    }
+
+    #[test]
+    fn test_level_for_flags() {
+        assert_eq!(level_for(true, 0), LevelFilter::Warn);
+        assert_eq!(level_for(false, 0), LevelFilter::Info);
+        assert_eq!(level_for(false, 1), LevelFilter::Debug);
+        assert_eq!(level_for(false, 3), LevelFilter::Trace);
+    }
+}
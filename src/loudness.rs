@@ -0,0 +1,366 @@
+//! Integrated loudness (LUFS) per ITU-R BS.1770-4 / EBU R128.
+//!
+//! Samples are K-weighted (high-shelf + high-pass biquads), mean squares are
+//! taken over 400 ms blocks with 75% overlap, and the blocks are gated at
+//! -70 LUFS (absolute) and -10 LU below the ungated mean (relative).
+
+use std::f64::consts::PI;
+use std::ops::Range;
+
+/// Gating block length in seconds.
+const BLOCK_SECS: f64 = 0.4;
+/// Hop between successive gating blocks (75% overlap).
+const HOPS_PER_BLOCK: usize = 4;
+/// Short-term loudness window (3 s) in hops.
+const HOPS_PER_SHORT_TERM: usize = 30;
+/// Reported floor for the loudness time series, so silence stays a finite number.
+const SERIES_FLOOR_LUFS: f64 = -120.0;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// Convert a weighted mean square to LUFS.
+fn lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// LUFS for a time-series point, floored at `SERIES_FLOOR_LUFS`.
+fn series_lufs(power: f64) -> f64 {
+    if power > 0.0 {
+        lufs(power).max(SERIES_FLOOR_LUFS)
+    } else {
+        SERIES_FLOOR_LUFS
+    }
+}
+
+/// Direct form I biquad.
+#[derive(Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x0: f64) -> f64 {
+        let y0 = self.b[0] * x0 + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[1] * self.y[0]
+            - self.a[2] * self.y[1];
+        self.x = [x0, self.x[0]];
+        self.y = [y0, self.y[0]];
+        y0
+    }
+}
+
+/// The two K-weighting stages for `sample_rate`, derived by bilinear transform
+/// so that rates other than 48 kHz are handled exactly.
+fn k_weighting(sample_rate: usize) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    // Stage 1: high shelf modelling the acoustic effect of the head
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    // Stage 2: RLB high-pass
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, highpass]
+}
+
+/// Per-channel K-weighting filter over interleaved frames. Filter state is
+/// carried between calls, so a stream can be fed packet by packet.
+#[derive(Clone)]
+pub struct KWeighting {
+    filters: Vec<[Biquad; 2]>,
+}
+
+impl KWeighting {
+    pub fn new(channels: usize, sample_rate: usize) -> Self {
+        Self {
+            filters: (0..channels).map(|_| k_weighting(sample_rate)).collect(),
+        }
+    }
+
+    /// Filter the next sample of `channel`.
+    pub fn process(&mut self, channel: usize, x: f64) -> f64 {
+        let [shelf, highpass] = &mut self.filters[channel];
+        highpass.process(shelf.process(x))
+    }
+
+    /// The filter of a whole stream from the filters of its consecutive
+    /// channel groups, in channel order. Each channel keeps its state.
+    pub fn merge_channels(parts: impl IntoIterator<Item = KWeighting>) -> Self {
+        Self {
+            filters: parts.into_iter().flat_map(|p| p.filters).collect(),
+        }
+    }
+}
+
+/// BS.1770 channel weight by position: surrounds (channels 5 and 6 of a 5.1
+/// layout) count +1.5 dB and the LFE channel is excluded.
+fn channel_weight(channel: usize, channels: usize) -> f64 {
+    match (channels, channel) {
+        (6, 3) => 0.0,
+        (6, 4) | (6, 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+/// Streaming K-weighted loudness meter over interleaved samples.
+pub struct LoudnessMeter {
+    channels: usize,
+    weights: Vec<f64>,
+    filter: KWeighting,
+    hop_frames: usize,
+    // Frames and per-channel sum of squares accumulated in the current hop
+    hop_fill: usize,
+    hop_sum_sq: Vec<f64>,
+    // Channel-weighted mean square of the most recent hops
+    recent_hops: Vec<f64>,
+    // Channel-weighted mean square of every complete 400 ms block
+    block_powers: Vec<f64>,
+    // Channel-weighted mean square of every complete 100 ms hop
+    hop_powers: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(channels: usize, sample_rate: usize) -> Self {
+        Self::for_channels(0..channels, channels, sample_rate)
+    }
+
+    /// A meter of just `channels` of a `total`-channel stream, weighted as
+    /// those positions are in the full layout. It is fed frames of only those
+    /// channels; `merge_channels` combines the groups of a stream.
+    pub fn for_channels(channels: Range<usize>, total: usize, sample_rate: usize) -> Self {
+        let hop_frames = ((BLOCK_SECS * sample_rate as f64) / HOPS_PER_BLOCK as f64)
+            .round()
+            .max(1.0) as usize;
+        let count = channels.len();
+        Self {
+            channels: count,
+            weights: channels.map(|ch| channel_weight(ch, total)).collect(),
+            filter: KWeighting::new(count, sample_rate),
+            hop_frames,
+            hop_fill: 0,
+            hop_sum_sq: vec![0.0; count],
+            recent_hops: Vec::with_capacity(HOPS_PER_BLOCK),
+            block_powers: Vec::new(),
+            hop_powers: Vec::new(),
+        }
+    }
+
+    /// Feed whole interleaved frames.
+    pub fn push_frames(&mut self, interleaved: &[f32]) {
+        if self.channels == 0 {
+            return;
+        }
+        for frame in interleaved.chunks_exact(self.channels) {
+            for (ch, &s) in frame.iter().enumerate() {
+                let y = self.filter.process(ch, s as f64);
+                self.hop_sum_sq[ch] += y * y;
+            }
+            self.hop_fill += 1;
+            if self.hop_fill == self.hop_frames {
+                self.finish_hop();
+            }
+        }
+    }
+
+    fn finish_hop(&mut self) {
+        let frames = self.hop_frames as f64;
+        let power: f64 = self
+            .hop_sum_sq
+            .iter()
+            .zip(&self.weights)
+            .map(|(sum, w)| w * sum / frames)
+            .sum();
+        self.hop_sum_sq.iter_mut().for_each(|v| *v = 0.0);
+        self.hop_fill = 0;
+
+        if self.recent_hops.len() == HOPS_PER_BLOCK {
+            self.recent_hops.remove(0);
+        }
+        self.recent_hops.push(power);
+        self.hop_powers.push(power);
+        if self.recent_hops.len() == HOPS_PER_BLOCK {
+            let block: f64 = self.recent_hops.iter().sum::<f64>() / HOPS_PER_BLOCK as f64;
+            self.block_powers.push(block);
+        }
+    }
+
+    /// The meter of a whole stream from the meters of its consecutive channel
+    /// groups (see `for_channels`), in channel order, all fed the same frames.
+    /// Channel-weighted powers add up across the groups.
+    pub fn merge_channels(parts: Vec<LoudnessMeter>) -> Self {
+        let add = |sum: &mut Vec<f64>, part: &[f64]| {
+            sum.iter_mut().zip(part).for_each(|(s, p)| *s += p);
+        };
+        let mut parts = parts.into_iter();
+        let mut merged = parts.next().expect("at least one channel group");
+        let mut filters = vec![std::mem::replace(&mut merged.filter, KWeighting::new(0, 0))];
+        for part in parts {
+            merged.channels += part.channels;
+            merged.weights.extend(&part.weights);
+            merged.hop_sum_sq.extend(&part.hop_sum_sq);
+            add(&mut merged.recent_hops, &part.recent_hops);
+            add(&mut merged.block_powers, &part.block_powers);
+            add(&mut merged.hop_powers, &part.hop_powers);
+            filters.push(part.filter);
+        }
+        merged.filter = KWeighting::merge_channels(filters);
+        merged
+    }
+
+    /// Channel-weighted mean square of each complete 400 ms block, in order.
+    pub fn block_powers(&self) -> &[f64] {
+        &self.block_powers
+    }
+
+    /// Momentary loudness (400 ms window) in LUFS every 100 ms; value `i`
+    /// covers the window ending at `(i + 4) * 100` ms.
+    pub fn momentary_lufs(&self) -> Vec<f64> {
+        self.block_powers.iter().map(|&p| series_lufs(p)).collect()
+    }
+
+    /// Short-term loudness (3 s window) in LUFS every 100 ms; value `i` covers
+    /// the window ending at `(i + 30) * 100` ms.
+    pub fn short_term_lufs(&self) -> Vec<f64> {
+        self.hop_powers
+            .windows(HOPS_PER_SHORT_TERM)
+            .map(|w| series_lufs(w.iter().sum::<f64>() / HOPS_PER_SHORT_TERM as f64))
+            .collect()
+    }
+
+    /// Gated integrated loudness in LUFS, or `None` if no block is above the
+    /// absolute gate (silence, or shorter than 400 ms).
+    pub fn integrated_lufs(&self) -> Option<f64> {
+        let above_absolute: Vec<f64> = self
+            .block_powers
+            .iter()
+            .copied()
+            .filter(|&p| p > 0.0 && lufs(p) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if above_absolute.is_empty() {
+            return None;
+        }
+        let ungated_mean = above_absolute.iter().sum::<f64>() / above_absolute.len() as f64;
+        let relative_gate = lufs(ungated_mean) + RELATIVE_GATE_LU;
+
+        let gated: Vec<f64> = above_absolute
+            .into_iter()
+            .filter(|&p| lufs(p) > relative_gate)
+            .collect();
+        let mean = gated.iter().sum::<f64>() / gated.len() as f64;
+        Some(lufs(mean))
+    }
+}
+
+/// Combine per-track loudness into an album value, weighting each track's
+/// energy by its duration. Tracks without a loudness value are ignored.
+pub fn combine_lufs(tracks: impl IntoIterator<Item = (f64, f64)>) -> Option<f64> {
+    let (energy, secs) = tracks.into_iter().fold((0.0, 0.0), |(e, s), (lufs, secs)| {
+        (e + secs * 10f64.powf((lufs + 0.691) / 10.0), s + secs)
+    });
+    (secs > 0.0).then(|| lufs(energy / secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(amplitude: f64, freq: f64, secs: f64, rate: usize) -> Vec<f32> {
+        (0..(secs * rate as f64) as usize)
+            .map(|i| (amplitude * (2.0 * PI * freq * i as f64 / rate as f64).sin()) as f32)
+            .collect()
+    }
+
+    #[test]
+    fn test_1khz_sine_loudness() {
+        // A 1 kHz sine reads ~20*log10(A) - 3.01 LUFS on one channel
+        for rate in [44100, 48000, 96000] {
+            let mut meter = LoudnessMeter::new(1, rate);
+            meter.push_frames(&sine(0.1, 1000.0, 5.0, rate));
+            let lufs = meter.integrated_lufs().unwrap();
+            assert!(
+                (lufs - -23.01).abs() < 0.2,
+                "{} Hz: got {:.2} LUFS",
+                rate,
+                lufs
+            );
+        }
+    }
+
+    #[test]
+    fn test_silence_has_no_loudness() {
+        let mut meter = LoudnessMeter::new(2, 44100);
+        meter.push_frames(&vec![0.0; 44100 * 2 * 2]);
+        assert!(meter.integrated_lufs().is_none());
+    }
+
+    #[test]
+    fn test_relative_gate_ignores_quiet_passages() {
+        let rate = 48000;
+        let mut samples = sine(0.1, 1000.0, 5.0, rate);
+        samples.extend(sine(0.001, 1000.0, 5.0, rate));
+        let mut meter = LoudnessMeter::new(1, rate);
+        meter.push_frames(&samples);
+        // The -63 LUFS half is below the relative gate, so only the loud half counts
+        assert!((meter.integrated_lufs().unwrap() - -23.01).abs() < 0.3);
+    }
+
+    #[test]
+    fn test_loudness_series() {
+        let rate = 48000;
+        let mut samples = sine(0.1, 1000.0, 4.0, rate);
+        samples.extend(vec![0.0; rate * 2]);
+        let mut meter = LoudnessMeter::new(1, rate);
+        meter.push_frames(&samples);
+
+        // 60 hops of 100 ms: 57 momentary windows and 31 short-term windows
+        let momentary = meter.momentary_lufs();
+        let short_term = meter.short_term_lufs();
+        assert_eq!((momentary.len(), short_term.len()), (57, 31));
+        assert!((momentary[10] - -23.01).abs() < 0.2);
+        assert!((short_term[0] - -23.01).abs() < 0.2);
+        assert_eq!(*momentary.last().unwrap(), SERIES_FLOOR_LUFS);
+        // The last short-term window is 1 s of tone and 2 s of silence
+        assert!((short_term[30] - (-23.01 + 10.0 * (1.0f64 / 3.0).log10())).abs() < 0.3);
+    }
+
+    #[test]
+    fn test_combine_lufs_weights_by_duration() {
+        assert!((combine_lufs([(-14.0, 100.0)]).unwrap() - -14.0).abs() < 1e-9);
+        let album = combine_lufs([(-10.0, 100.0), (-20.0, 100.0)]).unwrap();
+        assert!(album > -13.0 && album < -12.0);
+        assert!(combine_lufs([]).is_none());
+    }
+}
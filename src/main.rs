@@ -1,13 +1,40 @@
-use std::path::Path;
-use std::time::Instant;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use clap::Parser;
 
-use dr::analyzer;
+use dr::analyzer::{self, AnalysisOptions, StreamLabels, TimeRange, TitleSource};
 use dr::cache;
-use dr::format;
-use dr::models::AlbumResult;
+use dr::config::Config;
+use dr::format::{self, TableLayout};
+use dr::models::{
+    AlbumAggregate, AlbumResult, AnalysisEvent, AnalysisTiming, DrScale, NormalizationPreview,
+    TrackResult,
+};
+use dr::reference::ReferenceDb;
+
+/// Target width for printed tables (`--width`).
+#[derive(Debug, Clone, Copy)]
+enum TableWidth {
+    /// The terminal's width; the full layout when stdout isn't a terminal
+    Auto,
+    Columns(usize),
+}
+
+fn parse_width(value: &str) -> Result<TableWidth, String> {
+    if value == "auto" {
+        return Ok(TableWidth::Auto);
+    }
+    match value.parse() {
+        Ok(cols) if cols > 0 => Ok(TableWidth::Columns(cols)),
+        _ => Err(format!(
+            "expected a column count or \"auto\", got '{}'",
+            value
+        )),
+    }
+}
 
 #[derive(Parser)]
 #[command(name = "dr", about = "Dynamic range meter for audio files")]
@@ -15,15 +42,24 @@ struct Cli {
     /// Audio file, directory, or "-" for STDIN
     path: Option<String>,
 
-    /// Format hint for STDIN (e.g. flac, mp3, opus)
+    /// Format of STDIN (e.g. flac, mp3, opus); detected from the stream when omitted
     #[arg(long)]
     format: Option<String>,
 
+    /// Title to report for STDIN instead of its tag or "STDIN"
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Album to report for STDIN, shown below the table and as `album` in JSON
+    #[arg(long)]
+    album: Option<String>,
+
     /// Output as JSON instead of table
     #[arg(long)]
     json: bool,
 
-    /// Launch interactive TUI
+    /// Launch interactive TUI (on a file, an album directory, or with --bulk a
+    /// tree of albums)
     #[arg(long)]
     tui: bool,
 
@@ -31,195 +67,1450 @@ struct Cli {
     #[arg(short = 'j', long)]
     jobs: Option<usize>,
 
+    /// Measure each file on N threads, split by channel, while one thread
+    /// decodes it. For long multichannel files where a single file is the
+    /// bottleneck; the results are unchanged. Multiplies with --jobs
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    threads_per_file: usize,
+
     /// Re-analyze even if a cached report exists
     #[arg(long)]
     regenerate: bool,
 
+    /// In directory mode, neither read nor write the cached report (also
+    /// DR_NO_CACHE=1 or `cache = false` in dr.toml)
+    #[arg(long, overrides_with = "cache", conflicts_with_all = ["bulk", "recompute_album_dr", "histogram"])]
+    no_cache: bool,
+
+    /// Use the cached report even if DR_NO_CACHE or dr.toml turn it off
+    #[arg(long, overrides_with = "no_cache")]
+    cache: bool,
+
     /// Analyze all immediate subdirectories as separate albums
     #[arg(long)]
     bulk: bool,
 
+    /// Read newline-separated file paths from STDIN and analyze each one, printing
+    /// one result per file (NDJSON with --json). Unreadable entries are skipped
+    #[arg(long, conflicts_with_all = ["path", "bulk", "tui", "follow"])]
+    stdin_list: bool,
+
+    /// Treat a directory with no audio files of its own as one album spanning
+    /// its subdirectories (e.g. CD1/, CD2/), ordered by disc then track. With
+    /// --bulk, applies to each album directory
+    #[arg(long)]
+    multi_disc: bool,
+
+    /// Treat each file in the directory as its own single instead of one album
+    /// (no album DR; JSON output is an array of tracks)
+    #[arg(long, conflicts_with_all = ["bulk", "tui"])]
+    singles: bool,
+
+    /// With --bulk --json, also print every album as one JSON array on stdout:
+    /// those analyzed, and those skipped, read back from their reports. The
+    /// per-album report files are written as usual
+    #[arg(long, requires = "bulk", conflicts_with_all = ["tui", "dry_run"])]
+    json_stdout: bool,
+
+    /// With --bulk, only process subdirectories whose names match this glob
+    /// (`*` any run of characters, `?` any one; case-sensitive), e.g. "*Live*".
+    /// The others are left alone, and the summary counts them as filtered out
+    #[arg(long, value_name = "GLOB", requires = "bulk", conflicts_with = "tui")]
+    bulk_filter: Option<String>,
+
+    /// With --bulk, list which albums would be analyzed, skipped (reports exist),
+    /// or are empty, without decoding anything
+    #[arg(long, requires = "bulk", conflicts_with = "tui")]
+    dry_run: bool,
+
     /// Write a text report (dr_report.txt) alongside JSON
     #[arg(long)]
     txt: bool,
+
+    /// Write a CSV report (dr_report.csv) alongside JSON; combines with --json
+    /// and --txt
+    #[arg(long)]
+    csv: bool,
+
+    /// Start CSV reports with a UTF-8 byte order mark, so spreadsheets like
+    /// Excel show non-ASCII titles correctly
+    #[arg(long)]
+    csv_bom: bool,
+
+    /// Labels for the CSV header row: a language (en, de, fr, es) or seven
+    /// comma-separated column labels
+    #[arg(long, value_name = "LANG|LABELS")]
+    csv_header: Option<format::CsvHeader>,
+
+    /// Add an `analysis` object (elapsed_secs, total_bytes, mb_per_sec) to the
+    /// album JSON. Cached reports are not reused, since there would be nothing
+    /// to time
+    #[arg(long)]
+    include_timing: bool,
+
+    /// Suppress informational stderr output (benchmark, cache notices, bulk progress)
+    #[arg(short = 'q', long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// In directory mode, stream analysis events (TrackStarted, TrackProgress,
+    /// TrackCompleted, Error, AlbumCompleted) to stderr as NDJSON
+    #[arg(long)]
+    progress_json: bool,
+
+    /// Print tab-separated lines with a fixed field order for scripts: an
+    /// `#album` line, then per track: filename, DR, peak, RMS, true peak,
+    /// duration_secs, sample_rate, channels (see README for the contract)
+    #[arg(long, conflicts_with_all = ["json", "tui", "preview_normalized", "dedupe_report"])]
+    porcelain: bool,
+
+    /// In directory mode, print each track's row as soon as it is analyzed (in
+    /// completion order), then the footer once the album is done
+    #[arg(long, conflicts_with_all = ["json", "porcelain", "bulk", "singles", "tui", "preview_normalized"])]
+    stream: bool,
+
+    /// Print per-file decode time and throughput to stderr as tracks complete.
+    /// Repeat for debug logging (-vv: trace) of decoding, seeks, and cache
+    /// decisions
+    #[arg(short = 'v', long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Analyze built-in synthetic signals with known DR values and report throughput
+    #[arg(long)]
+    selftest: bool,
+
+    /// List the file extensions scanned in directories and the codecs this
+    /// build can decode, then exit
+    #[arg(long)]
+    list_formats: bool,
+
+    /// Report the L/R correlation coefficient for stereo files (bypasses the cache)
+    #[arg(long)]
+    stereo_stats: bool,
+
+    /// Estimate each track's noise floor from its quietest blocks, and the
+    /// peak-to-floor range (bypasses the cache)
+    #[arg(long)]
+    noise_floor: bool,
+
+    /// Also report each channel's DR, labeled by position (FL, FR, FC, LFE, ...)
+    /// (bypasses the cache)
+    #[arg(long)]
+    per_channel: bool,
+
+    /// Compute DR by the older method that combines the channels before the
+    /// top-20% selection, for comparing with historical figures (non-standard
+    /// DR; bypasses the cache)
+    #[arg(long)]
+    legacy_dr: bool,
+
+    /// Scale each file by its REPLAYGAIN_TRACK_GAIN tag before analysis, to
+    /// measure levels as played back. Off by default: peaks and RMS then differ
+    /// from the file's own levels (bypasses the cache)
+    #[arg(long)]
+    apply_existing_gain: bool,
+
+    /// Read the decoded samples as N interleaved channels, for a file whose
+    /// header declares the wrong count. The DR is only right if N is the true
+    /// count (bypasses the cache)
+    #[arg(long, value_name = "N", conflicts_with_all = ["start", "duration", "preview_secs"])]
+    force_channels: Option<usize>,
+
+    /// Measure at RATE Hz instead of the sample rate the header declares. The
+    /// DR is only right if RATE is the true rate (bypasses the cache)
+    #[arg(long, value_name = "RATE")]
+    force_sample_rate: Option<u32>,
+
+    /// Also report an experimental "perceptual DR" measured on the K-weighted
+    /// (BS.1770) signal, separate from the official DR (bypasses the cache)
+    #[arg(long)]
+    k_weighted: bool,
+
+    /// Include momentary (400 ms) and short-term (3 s) loudness time series, one
+    /// value per 100 ms, in JSON output (bypasses the cache)
+    #[arg(long)]
+    loudness_series: bool,
+
+    /// Write the track's level envelope to FILE as CSV: each 3-second block's
+    /// start time and RMS (loudest channel) in dB (bypasses the cache)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["bulk", "tui", "singles", "stdin_list"])]
+    envelope: Option<PathBuf>,
+
+    /// Instead of the DR report, show levels after loudness normalization to LUFS
+    /// (default -18, the ReplayGain 2.0 reference; e.g. --preview-normalized=-14)
+    #[arg(
+        long,
+        value_name = "LUFS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "-18"
+    )]
+    preview_normalized: Option<f64>,
+
+    /// With STDIN, print a partial DR/peak/RMS snapshot to stderr every SECS of
+    /// decoded audio (NDJSON with --json)
+    #[arg(long, value_name = "SECS")]
+    follow: Option<f64>,
+
+    /// Print (and save with --txt) a foobar2000 DR Meter style log instead of
+    /// the table: v1 (original layout) or v2 (newer, with stream details)
+    #[arg(long, value_enum, value_name = "VERSION", conflicts_with_all = ["json", "porcelain", "stream"])]
+    log_format: Option<format::LogFormat>,
+
+    /// Print only the DR as a badge token (e.g. DR11), or with --json a
+    /// shields.io endpoint document colored by rating. Uses the cached report
+    /// when there is one
+    #[arg(long, conflicts_with_all = ["bulk", "tui", "singles", "stdin_list", "porcelain", "preview_normalized", "stream"])]
+    badge: bool,
+
+    /// Print only the DR as an integer (e.g. 11), for capturing in a shell
+    /// variable: the track's DR for a file, the album's for a directory
+    #[arg(long, conflicts_with_all = ["json", "porcelain", "badge", "badge_svg", "log_format", "bulk", "tui", "singles", "stdin_list", "preview_normalized", "stream"])]
+    value: bool,
+
+    /// Write an SVG badge ("dynamic range" and the DR, colored by rating) to FILE
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["bulk", "tui", "singles", "stdin_list", "porcelain", "preview_normalized", "stream"])]
+    badge_svg: Option<PathBuf>,
+
+    /// DR thresholds for color coding in the TUI and badges: values below
+    /// MEDIUM are red, from HIGH up green, and between them yellow. The default
+    /// 8,14 is the Dynamic Range Database's scale
+    #[arg(long, value_name = "MEDIUM,HIGH")]
+    dr_scale: Option<DrScale>,
+
+    /// Recombine the album DR of every cached report in the directory and its
+    /// subdirectories from the stored track DRs with --album-agg, and rewrite
+    /// the reports (no audio is decoded). Other than the mean, the results are
+    /// written beside the cache as dr_report.nonstandard.*
+    #[arg(long, conflicts_with_all = ["bulk", "tui", "singles", "stdin_list", "histogram"])]
+    recompute_album_dr: bool,
+
+    /// Print how many albums have each DR value, as a bar chart, from the cached
+    /// reports in the directory and all its subdirectories (nothing is analyzed)
+    #[arg(long, conflicts_with_all = ["bulk", "tui", "singles", "stdin_list"])]
+    histogram: bool,
+
+    /// Flag tracks in an album that are likely the same recording (e.g. clean and
+    /// explicit versions), from their loudness-contour fingerprints
+    #[arg(long)]
+    dedupe_report: bool,
+
+    /// For a single file, start analysis this many seconds in
+    #[arg(long, value_name = "SECS")]
+    start: Option<f64>,
+
+    /// For a single file, analyze only this many seconds (from --start, or the beginning)
+    #[arg(long, value_name = "SECS")]
+    duration: Option<f64>,
+
+    /// Measure only the first SECS seconds of each track, for a quick rough
+    /// DR over a large library. Reports mark cut tracks as previews, and
+    /// previews saved as reports are never reused as full measurements
+    #[arg(long, value_name = "SECS")]
+    preview_secs: Option<f64>,
+
+    /// Fit printed tables to this many columns ("auto": the terminal's width),
+    /// cutting long titles and switching to a compact layout when narrow.
+    /// Saved text reports always use the full layout
+    #[arg(long, value_name = "COLS|auto", value_parser = parse_width)]
+    width: Option<TableWidth>,
+
+    /// Add a "Precise" column to tables after DR: each track's DR before
+    /// rounding, to one decimal, for telling apart masters with the same DR.
+    /// Left out of the compact layout
+    #[arg(long)]
+    precise: bool,
+
+    /// Add a "Crest (dB)" column to tables: each track's peak minus its RMS,
+    /// the crest factor mastering engineers reason about. Left out of the
+    /// compact layout
+    #[arg(long)]
+    crest_factor: bool,
+
+    /// Add a "PLR (dB)" column to tables: each track's peak-to-loudness ratio,
+    /// true peak minus integrated loudness. Left out of the compact layout
+    #[arg(long)]
+    plr: bool,
+
+    /// Leave out the first and last SECS seconds of every track, e.g. crossfades
+    /// on a live album. Produces non-standard DR; reports say so
+    #[arg(long, value_name = "SECS")]
+    trim_edges: Option<f64>,
+
+    /// Leave silent blocks (below -60 dBFS) at the start and end of each track
+    /// out of the DR, e.g. trailing padding. The duration still includes them.
+    /// Produces non-standard DR; reports say so
+    #[arg(long)]
+    trim_silence: bool,
+
+    /// How album DR combines the track DRs: mean (default, as in the DR Database)
+    /// or median (less swayed by an outlier track)
+    #[arg(long, value_enum, default_value_t = AlbumAggregate::Mean)]
+    album_agg: AlbumAggregate,
+
+    /// Measure exactly as the TT DR Meter does, for DR Database submissions:
+    /// switches off every option that changes the DR (--legacy-dr,
+    /// --album-agg, --skip-silent-in-average, --trim-edges, --trim-silence,
+    /// --apply-existing-gain, --preview-secs, --start/--duration), with a
+    /// warning naming each one overridden. Always re-analyzes; can't be
+    /// combined with --force-sample-rate or --recompute-album-dr
+    #[arg(long)]
+    strict_dr_database: bool,
+
+    /// Leave silent tracks (DR0 below -60 dB, e.g. a hidden-track gap) out of
+    /// the album DR while still listing them; reports say how many (bypasses
+    /// the cache)
+    #[arg(long)]
+    skip_silent_in_average: bool,
+
+    /// Where track titles come from: metadata (default; falls back to the filename),
+    /// filename, or both
+    #[arg(long, value_enum, default_value_t = TitleSource::Metadata)]
+    title_source: TitleSource,
+
+    /// File stem for the cached JSON and text reports (default: dr_report, giving
+    /// dr_report.json / dr_report.txt)
+    #[arg(long, value_name = "NAME", default_value = cache::DEFAULT_REPORT_BASENAME)]
+    report_basename: String,
+
+    /// Write reports into DIR (created if needed) as "<Album>.json" / ".txt" /
+    /// ".csv", named after the album tag or the directory, instead of into
+    /// each album directory. Albums with the same name in one run get a
+    /// " (2)" suffix. Reports in DIR are never read back, so albums are always
+    /// analyzed
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["report_basename", "tui", "singles", "recompute_album_dr", "histogram"]
+    )]
+    report_into: Option<PathBuf>,
+
+    /// Exit with an error after reporting if any file lacks a title or album
+    /// tag (and so fell back to its filename or directory name), listing them
+    /// on stderr. Cached reports are re-analyzed
+    #[arg(long)]
+    strict_metadata: bool,
+
+    /// Compare each album's DR with its known value in this local JSON or CSV
+    /// file of artist/album/DR entries, e.g. to spot rips of a different master
+    #[arg(long, value_name = "FILE")]
+    reference: Option<PathBuf>,
+
+    /// Read defaults from this config file instead of ./dr.toml or ~/.config/dr/dr.toml
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Defaults from the config file and environment, merged in by `apply_config`
+    #[arg(skip)]
+    defaults: Config,
+
+    /// The `--reference` file, loaded in `main`
+    #[arg(skip)]
+    reference_db: Option<ReferenceDb>,
 }
 
-fn print_benchmark(result: &AlbumResult, elapsed: std::time::Duration) {
-    let total_bytes: u64 = result.tracks.iter().map(|t| t.file_bytes).sum();
-    let total_mb = total_bytes as f64 / (1024.0 * 1024.0);
-    let secs = elapsed.as_secs_f64();
+impl Cli {
+    /// Fill in anything not given on the command line from `config`.
+    fn apply_config(&mut self, config: Config) {
+        self.jobs = self.jobs.or(config.jobs);
+        // Output format flags on the command line replace the configured set
+        if !self.json && !self.txt && !self.csv {
+            self.json = config.has_format("json");
+            self.txt = config.has_format("txt");
+            self.csv = config.has_format("csv");
+        }
+        self.defaults = config;
+    }
+
+    /// `--strict-dr-database`: pin the analysis to the TT DR Meter's, whatever
+    /// else was asked for. Options that only add to the report (loudness
+    /// series, stereo stats, ...) and `--force-channels` are left alone, as
+    /// they don't change the DR of a correctly read file. `--force-sample-rate`
+    /// changes the 3-second block length, and `--recompute-album-dr` trusts
+    /// stored track DRs, so neither can be combined with the preset. Cached
+    /// reports are never reused (see `reuse_reports`): one may hold a
+    /// non-standard measurement saved before such reports were kept apart.
+    fn apply_strict_dr_database(&mut self) -> Result<()> {
+        if !self.strict_dr_database {
+            return Ok(());
+        }
+        if self.force_sample_rate.is_some() {
+            anyhow::bail!("--force-sample-rate changes the 3-second block length; it can't be combined with --strict-dr-database");
+        }
+        if self.recompute_album_dr {
+            anyhow::bail!("--recompute-album-dr reuses stored track DRs; it can't be combined with --strict-dr-database");
+        }
+        let overridden: Vec<&str> = [
+            (self.legacy_dr, "--legacy-dr"),
+            (self.album_agg != AlbumAggregate::default(), "--album-agg"),
+            (self.skip_silent_in_average, "--skip-silent-in-average"),
+            (self.trim_edges.is_some(), "--trim-edges"),
+            (self.trim_silence, "--trim-silence"),
+            (self.apply_existing_gain, "--apply-existing-gain"),
+            (self.preview_secs.is_some(), "--preview-secs"),
+            (
+                self.start.is_some() || self.duration.is_some(),
+                "--start/--duration",
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(set, flag)| set.then_some(flag))
+        .collect();
+        if !overridden.is_empty() {
+            log::warn!("--strict-dr-database overrides {}", overridden.join(", "));
+        }
+        self.legacy_dr = false;
+        self.album_agg = AlbumAggregate::default();
+        self.skip_silent_in_average = false;
+        self.trim_edges = None;
+        self.trim_silence = false;
+        self.apply_existing_gain = false;
+        self.preview_secs = None;
+        self.start = None;
+        self.duration = None;
+        Ok(())
+    }
+
+    /// The thresholds for color-coding DR values: `--dr-scale`, the config
+    /// file's, or the default.
+    fn dr_scale(&self) -> DrScale {
+        self.dr_scale.or(self.defaults.dr_scale).unwrap_or_default()
+    }
+
+    /// Whether directory mode reads and writes the cached report. The last of
+    /// `--cache` / `--no-cache` wins over the environment and config file.
+    fn use_cache(&self) -> bool {
+        if self.cache || self.no_cache {
+            self.cache
+        } else {
+            self.defaults.cache.unwrap_or(true)
+        }
+    }
+
+    /// Columns to fit printed tables to, if any.
+    fn table_width(&self) -> Option<usize> {
+        match self.width? {
+            TableWidth::Columns(cols) => Some(cols),
+            TableWidth::Auto => std::io::stdout()
+                .is_terminal()
+                .then(crossterm::terminal::size)
+                .and_then(|size| size.ok())
+                .map(|(cols, _)| cols as usize),
+        }
+    }
+
+    /// The layout of printed tables: fitted to `--width`, with `--precise`,
+    /// `--crest-factor`, and `--plr`.
+    fn table_layout(&self) -> TableLayout {
+        self.with_columns(TableLayout::for_width(self.table_width()))
+    }
+
+    /// `layout` with the optional columns asked for.
+    fn with_columns(&self, layout: TableLayout) -> TableLayout {
+        layout
+            .with_precise(self.precise)
+            .with_crest_factor(self.crest_factor)
+            .with_plr(self.plr)
+            .with_scale(self.dr_scale())
+    }
+
+    /// A single track's table as printed to stdout.
+    fn track_table(&self, result: &TrackResult) -> String {
+        if let Some(version) = self.log_format {
+            let album = AlbumResult {
+                tracks: vec![result.clone()],
+                overall_dr: result.dr,
+                ..Default::default()
+            };
+            return format::format_dr_log(&album, version);
+        }
+        format::format_table_single_in(result, self.table_layout())
+    }
+
+    /// An album's table as printed to stdout.
+    fn album_table(&self, result: &AlbumResult) -> String {
+        if self.log_format.is_some() {
+            return self.text_report(result);
+        }
+        format::format_table_in(result, self.table_layout())
+    }
+
+    /// The saved text report: the DR log with `--log-format`, else the table.
+    fn text_report(&self, result: &AlbumResult) -> String {
+        match self.log_format {
+            Some(version) => format::format_dr_log(result, version),
+            None => format::format_table_in(result, self.with_columns(TableLayout::FULL)),
+        }
+    }
+
+    /// The saved CSV report, with `--csv-bom` and `--csv-header` applied.
+    fn csv_report(&self, result: &AlbumResult) -> String {
+        let style = format::CsvStyle {
+            bom: self.csv_bom,
+            header: self.csv_header.clone().unwrap_or_default(),
+        };
+        format::format_csv_with(result, &style)
+    }
+
+    /// Look the album up in the `--reference` file, noting on stderr when it
+    /// has no entry.
+    fn apply_reference(&self, result: &mut AlbumResult) {
+        let Some(db) = &self.reference_db else {
+            return;
+        };
+        let album = result.album.as_deref().unwrap_or("");
+        result.reference_dr = db.lookup(result.artist.as_deref(), album);
+        if result.reference_dr.is_none() {
+            let artist = result
+                .artist
+                .as_deref()
+                .map(|a| format!("{} - ", a))
+                .unwrap_or_default();
+            let album = result.album.as_deref().unwrap_or("(untagged album)");
+            log::info!("Note: no reference entry for {}{}", artist, album);
+        }
+    }
+
+    /// Whether an existing report may stand in for analysis.
+    fn reuse_reports(&self) -> bool {
+        !self.regenerate
+            && !self.strict_metadata
+            && !self.strict_dr_database
+            && !self.include_timing
+            && self.report_into.is_none()
+            && self.analysis_options().matches_cached_report()
+    }
+
+    /// Whether this run's reports are the standard ones that later runs may
+    /// reuse (`AnalysisOptions::matches_cached_report`). Reports from other
+    /// runs are saved under `cache::NONSTANDARD_SUFFIX` and never cached.
+    fn writes_standard_reports(&self) -> bool {
+        self.analysis_options().matches_cached_report()
+    }
+
+    /// The directory and basename for the reports of the album analyzed from
+    /// `album_dir`: the album directory itself and `--report-basename`, or with
+    /// `--report-into` the collection directory and a name claimed for the album.
+    /// Non-standard runs add `cache::NONSTANDARD_SUFFIX`, so their reports can't be
+    /// mistaken for the standard ones.
+    fn report_location(
+        &self,
+        album_dir: &Path,
+        result: &AlbumResult,
+        names: &mut cache::CollectionNames,
+    ) -> (PathBuf, String) {
+        let (dir, basename) = match &self.report_into {
+            Some(into) => {
+                let name = result
+                    .album
+                    .clone()
+                    .unwrap_or_else(|| album_dir_name(album_dir));
+                (into.clone(), names.claim(&name))
+            }
+            None => (album_dir.to_path_buf(), self.report_basename.clone()),
+        };
+        if self.writes_standard_reports() {
+            (dir, basename)
+        } else {
+            (dir, basename + cache::NONSTANDARD_SUFFIX)
+        }
+    }
+
+    /// With `--strict-metadata`, list the tracks missing tags on stderr and
+    /// fail if there are any.
+    fn check_metadata<'a>(&self, tracks: impl IntoIterator<Item = &'a TrackResult>) -> Result<()> {
+        if !self.strict_metadata {
+            return Ok(());
+        }
+        let mut untagged = 0;
+        for track in tracks.into_iter().filter(|t| !t.missing_tags.is_empty()) {
+            log::warn!(
+                "Missing tags: {} ({})",
+                track.filename,
+                track.missing_tags.join(", ")
+            );
+            untagged += 1;
+        }
+        if untagged > 0 {
+            anyhow::bail!(
+                "--strict-metadata: {} file(s) missing title or album tags",
+                untagged
+            );
+        }
+        Ok(())
+    }
+
+    fn analysis_options(&self) -> AnalysisOptions {
+        AnalysisOptions {
+            stereo_stats: self.stereo_stats,
+            extensions: self.defaults.extensions.clone(),
+            multi_disc: self.multi_disc,
+            title_source: self.title_source,
+            k_weighted: self.k_weighted,
+            loudness_series: self.loudness_series,
+            envelope: self.envelope.is_some(),
+            range: (self.start.is_some() || self.duration.is_some()).then(|| TimeRange {
+                start_secs: self.start.unwrap_or(0.0),
+                duration_secs: self.duration,
+            }),
+            preview_secs: self.preview_secs,
+            trim_edges_secs: self.trim_edges,
+            trim_silence: self.trim_silence,
+            album_agg: self.album_agg,
+            skip_silent_in_average: self.skip_silent_in_average,
+            dr_scale: self.dr_scale(),
+            noise_floor: self.noise_floor,
+            apply_existing_gain: self.apply_existing_gain,
+            force_channels: self.force_channels,
+            force_sample_rate: self.force_sample_rate,
+            threads_per_file: self.threads_per_file,
+            per_channel: self.per_channel,
+            legacy_dr: self.legacy_dr,
+        }
+    }
+}
+
+fn print_benchmark(result: &AlbumResult, elapsed: Duration) {
+    let timing = AnalysisTiming::new(elapsed, result.total_bytes);
     let avg_per_track = if result.tracks.is_empty() {
         0.0
     } else {
-        secs / result.tracks.len() as f64
+        timing.elapsed_secs / result.tracks.len() as f64
     };
-    let mb_per_sec = if secs > 0.0 { total_mb / secs } else { 0.0 };
 
-    eprintln!(
+    log::info!(
         "Processed {} tracks ({:.1} MB) in {:.2}s | {:.2}s/track | {:.1} MB/s",
         result.tracks.len(),
-        total_mb,
-        secs,
+        timing.total_mb(),
+        timing.elapsed_secs,
         avg_per_track,
+        timing.mb_per_sec,
+    );
+}
+
+/// `--list-formats`: the scanned extensions, then one line per decodable codec.
+fn print_formats() {
+    println!("Extensions scanned in directories:");
+    println!("  {}", analyzer::supported_extensions().join(", "));
+    println!("\nCodecs this build can decode:");
+    for (name, description) in analyzer::supported_codecs() {
+        println!("  {:<14} {}", name, description);
+    }
+}
+
+/// Per-file timing line for `--verbose`, with the source sample format when known.
+fn print_file_timing(track: &TrackResult, elapsed: Duration) {
+    let mb = track.file_bytes as f64 / (1024.0 * 1024.0);
+    let secs = elapsed.as_secs_f64();
+    let mb_per_sec = if secs > 0.0 { mb / secs } else { 0.0 };
+    let sample_format = format::format_sample_format(track)
+        .map(|f| format!(", {}", f))
+        .unwrap_or_default();
+    log::info!(
+        "  {}: {:.2}s ({:.1} MB, {:.1} MB/s, {} block(s)){}",
+        track.filename,
+        secs,
+        mb,
         mb_per_sec,
+        track.num_blocks,
+        sample_format
     );
 }
 
-fn run_bulk(base_path: &Path, jobs: usize, write_json: bool, write_txt: bool, regenerate: bool) -> Result<()> {
-    let mut subdirs: Vec<_> = std::fs::read_dir(base_path)
-        .with_context(|| format!("Failed to read directory: {}", base_path.display()))?
-        .filter_map(|entry| {
-            let entry = entry.ok()?;
-            if entry.path().is_dir() {
-                Some(entry.path())
-            } else {
-                None
+/// One `--follow` snapshot line on stderr.
+fn print_snapshot(partial: &TrackResult, json: bool) {
+    if json {
+        eprintln!("{}", serde_json::to_string(partial).unwrap_or_default());
+    } else {
+        eprintln!(
+            "[{}] DR{}  peak {:.2} dBFS  rms {:.2} dB",
+            format::format_duration(partial.duration_secs),
+            partial.dr,
+            partial.peak_db,
+            partial.rms_db,
+        );
+    }
+}
+
+/// Print a normalization preview, or explain why there isn't one.
+fn print_preview(preview: Option<NormalizationPreview>, cli: &Cli) -> Result<()> {
+    let preview =
+        preview.context("Cannot preview normalization: no measurable loudness (silence?)")?;
+    if cli.json {
+        println!("{}", format::format_preview_json(&preview));
+    } else {
+        println!("{}", format::format_preview(&preview));
+    }
+    Ok(())
+}
+
+/// With JSON on stdout, repeat decode-error and truncation warnings on stderr
+/// so they aren't buried in the report.
+fn warn_decode_errors<'a>(tracks: impl IntoIterator<Item = &'a TrackResult>) {
+    for track in tracks {
+        if track.decode_errors > 0 {
+            log::warn!(
+                "{}: {} packet(s) could not be decoded; DR is from incomplete audio",
+                track.filename,
+                track.decode_errors
+            );
+        }
+        if let Some(warning) = format::format_truncation(track) {
+            log::warn!("{}: {}", track.filename, warning);
+        }
+    }
+}
+
+/// `--badge` / `--badge-svg`: print the DR as a badge token (or shields.io
+/// endpoint JSON) and/or write it as an SVG badge.
+fn print_badge(dr: u32, cli: &Cli) -> Result<()> {
+    if let Some(path) = &cli.badge_svg {
+        std::fs::write(path, format::format_badge_svg(dr, cli.dr_scale()))
+            .with_context(|| format!("Failed to write badge: {}", path.display()))?;
+        log::info!("Wrote badge to {}", path.display());
+    }
+    if cli.badge && cli.json {
+        println!("{}", format::format_badge_json(dr, cli.dr_scale()));
+    } else if cli.badge {
+        println!("DR{}", dr);
+    }
+    Ok(())
+}
+
+/// Print a single-track report (or its normalization preview, or badge) to stdout.
+fn print_track(result: &TrackResult, cli: &Cli) -> Result<()> {
+    if cli.value {
+        println!("{}", result.dr);
+        return Ok(());
+    }
+    if cli.badge || cli.badge_svg.is_some() {
+        return print_badge(result.dr, cli);
+    }
+    if let Some(target) = cli.preview_normalized {
+        return print_preview(NormalizationPreview::for_track(result, target), cli);
+    }
+    if cli.json {
+        println!("{}", format::format_json_single(result));
+        warn_decode_errors([result]);
+    } else if cli.porcelain {
+        println!("{}", format::format_porcelain_track(result));
+    } else {
+        println!("{}", cli.track_table(result));
+    }
+    Ok(())
+}
+
+/// Print an album report (or its normalization preview, badge, or bare DR) to stdout. When
+/// `streamed`, the table's head and rows are already out and only the footer
+/// is left.
+fn print_album(result: &AlbumResult, cli: &Cli, streamed: bool) -> Result<()> {
+    if cli.value {
+        println!("{}", result.overall_dr);
+        return Ok(());
+    }
+    if cli.badge || cli.badge_svg.is_some() {
+        return print_badge(result.overall_dr, cli);
+    }
+    if let Some(target) = cli.preview_normalized {
+        return print_preview(NormalizationPreview::for_album(result, target), cli);
+    }
+    if cli.json {
+        println!("{}", format::format_json(result));
+        warn_decode_errors(&result.tracks);
+    } else if cli.porcelain {
+        println!("{}", format::format_porcelain(result));
+    } else if streamed {
+        println!(
+            "{}",
+            format::format_table_footer(result, cli.table_layout())
+        );
+    } else {
+        println!("{}", cli.album_table(result));
+    }
+    if cli.dedupe_report {
+        let pairs = dr::fingerprint::likely_duplicates(&result.tracks);
+        let report = format::format_duplicates(result, &pairs);
+        // Keep stdout valid JSON
+        if cli.json {
+            eprintln!("{}", report);
+        } else {
+            println!("\n{}", report);
+        }
+    }
+    Ok(())
+}
+
+/// One `--progress-json` event line on stderr.
+fn print_event(event: &AnalysisEvent) {
+    eprintln!("{}", serde_json::to_string(event).unwrap_or_default());
+}
+
+/// Analyze a directory, printing per-file timing when `--verbose` is set, the
+/// event stream when `--progress-json` is set, or the table's rows as tracks
+/// complete when `--stream` is set.
+fn analyze_dir(path: &Path, cli: &Cli, jobs: usize) -> Result<AlbumResult> {
+    let options = cli.analysis_options();
+    if cli.progress_json || cli.stream {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let dir = path.to_path_buf();
+        let handle =
+            std::thread::spawn(move || analyzer::analyze_directory_async(&dir, tx, jobs, &options));
+        let layout = cli.table_layout();
+        let mut album = None;
+        for (n, event) in rx.into_iter().enumerate() {
+            if cli.progress_json {
+                print_event(&event);
             }
-        })
-        .collect();
+            if cli.stream {
+                if n == 0 {
+                    println!("{}", format::format_table_head(layout));
+                }
+                match &event {
+                    AnalysisEvent::TrackCompleted { result, .. } => {
+                        println!("{}", format::format_table_row(result, layout));
+                    }
+                    AnalysisEvent::Error { index, message } => {
+                        log::warn!("track {} failed: {}", index + 1, message);
+                    }
+                    _ => {}
+                }
+            }
+            if let AnalysisEvent::AlbumCompleted { result } = event {
+                album = Some(result);
+            }
+        }
+        handle.join().expect("analysis thread panicked")?;
+        return album.context("Analysis finished without an album result");
+    }
+    if cli.verbose > 0 {
+        analyzer::analyze_directory_with_timing(path, jobs, &options, print_file_timing)
+    } else {
+        analyzer::analyze_directory_with_timing(path, jobs, &options, |_, _| {})
+    }
+}
 
-    subdirs.sort();
+/// Analyze each file in a directory independently and print the tracks.
+/// Files that fail are reported and skipped, and fail the run once the
+/// rest are printed.
+fn run_singles(path: &Path, cli: &Cli, jobs: usize) -> Result<()> {
+    let options = cli.analysis_options();
+    let results = if cli.verbose > 0 {
+        analyzer::analyze_singles(path, jobs, &options, print_file_timing)?
+    } else {
+        analyzer::analyze_singles(path, jobs, &options, |_, _| {})?
+    };
+    let total = results.len();
+    let mut tracks = Vec::new();
+    for result in results {
+        match result {
+            Ok(track) => tracks.push(track),
+            Err(e) => log::warn!("Skipping {:#}", e),
+        }
+    }
+    if cli.json {
+        println!("{}", format::format_json_tracks(&tracks));
+    } else if cli.porcelain {
+        for track in &tracks {
+            println!("{}", format::format_porcelain_track(track));
+        }
+    } else {
+        let tables: Vec<String> = tracks.iter().map(|t| cli.track_table(t)).collect();
+        println!("{}", tables.join("\n\n"));
+    }
+    cli.check_metadata(&tracks)?;
+    if tracks.len() < total {
+        anyhow::bail!(
+            "{} of {} files failed to analyze",
+            total - tracks.len(),
+            total
+        );
+    }
+    Ok(())
+}
+
+/// Analyze the files listed on STDIN, one path per line.
+fn run_stdin_list(cli: &Cli, jobs: usize) -> Result<()> {
+    let mut files = Vec::new();
+    for line in std::io::stdin().lines() {
+        let line = line.context("Failed to read file list from STDIN")?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let path = PathBuf::from(line);
+        if path.is_file() {
+            files.push(path);
+        } else {
+            log::warn!("Skipping {}: not a file", path.display());
+        }
+    }
 
-    if subdirs.is_empty() {
-        anyhow::bail!("No subdirectories found in '{}'", base_path.display());
+    let options = cli.analysis_options();
+    let results = if cli.verbose > 0 {
+        analyzer::analyze_file_list(&files, jobs, &options, print_file_timing)
+    } else {
+        analyzer::analyze_file_list(&files, jobs, &options, |_, _| {})
+    };
+    let mut tracks = Vec::new();
+    for (path, result) in files.iter().zip(results) {
+        match &result {
+            Ok(track) if cli.json => {
+                println!("{}", serde_json::to_string(track).unwrap_or_default());
+            }
+            Ok(track) if cli.porcelain => println!("{}", format::format_porcelain_track(track)),
+            Ok(track) => println!("{}\n", cli.track_table(track)),
+            Err(e) => log::warn!("Skipping {}: {:#}", path.display(), e),
+        }
+        tracks.extend(result);
     }
+    cli.check_metadata(&tracks)
+}
+
+/// What kind of special file `path` is (a FIFO, a device, ...), or `None` for
+/// a regular file, a directory, or a path that doesn't exist. `is_file` is
+/// false for these, and probing one could block on a pipe with no writer.
+fn special_file_kind(path: &Path) -> Option<&'static str> {
+    let file_type = std::fs::metadata(path).ok()?.file_type();
+    if file_type.is_file() || file_type.is_dir() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return Some("a FIFO (named pipe)");
+        }
+        if file_type.is_char_device() {
+            return Some("a character device");
+        }
+        if file_type.is_block_device() {
+            return Some("a block device");
+        }
+        if file_type.is_socket() {
+            return Some("a socket");
+        }
+    }
+    Some("a special file")
+}
+
+/// Display name of a bulk album subdirectory.
+fn album_dir_name(subdir: &Path) -> String {
+    subdir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| subdir.display().to_string())
+}
+
+/// `--histogram`: the DR distribution of the cached reports under `base_path`.
+fn run_histogram(base_path: &Path, cli: &Cli) -> Result<()> {
+    let reports = cache::find_cached_reports(base_path, &cli.report_basename);
+    if reports.is_empty() {
+        anyhow::bail!("No cached reports found under '{}'", base_path.display());
+    }
+    let drs: Vec<u32> = reports.iter().map(|(_, r)| r.overall_dr).collect();
+    println!("{}", format::format_histogram(&drs));
+    Ok(())
+}
+
+/// `--recompute-album-dr`: recombine the album DR of every cached report under
+/// `base_path` and rewrite the reports that exist beside it. The mean (with
+/// every track) rewrites the cache itself; any other combination is written
+/// beside it under `cache::NONSTANDARD_SUFFIX`, leaving the cache standard.
+fn run_recompute_album_dr(base_path: &Path, cli: &Cli) -> Result<()> {
+    let reports = cache::find_cached_reports(base_path, &cli.report_basename);
+    if reports.is_empty() {
+        anyhow::bail!("No cached reports found under '{}'", base_path.display());
+    }
+    let basename = if cli.writes_standard_reports() {
+        cli.report_basename.clone()
+    } else {
+        cli.report_basename.clone() + cache::NONSTANDARD_SUFFIX
+    };
+    let total = reports.len();
+    let mut changed = 0usize;
+    for (dir, mut result) in reports {
+        let before = result.overall_dr;
+        result.recompute_overall_dr_with(
+            cli.album_agg,
+            cli.skip_silent_in_average,
+            &cli.dr_scale(),
+        );
+        let rewritten = cache::save_report(&dir, &basename, &result).and_then(|()| {
+            if cache::report_path(&dir, &cli.report_basename, "txt").exists() {
+                cache::save_text_report(&dir, &basename, &cli.text_report(&result))?;
+            }
+            if cache::report_path(&dir, &cli.report_basename, "csv").exists() {
+                cache::save_csv_report(&dir, &basename, &cli.csv_report(&result))?;
+            }
+            Ok(())
+        });
+        if let Err(e) = rewritten {
+            log::warn!("failed to rewrite reports in {}: {}", dir.display(), e);
+        }
+        if result.overall_dr != before {
+            changed += 1;
+        }
+        println!(
+            "DR{:<3} (was DR{:<3})  {}",
+            result.overall_dr,
+            before,
+            dir.display()
+        );
+    }
+    log::info!(
+        "Done: {} album DR(s) recomputed, {} changed",
+        total,
+        changed
+    );
+    Ok(())
+}
+
+/// The album directories a bulk run processes: the subdirectories of
+/// `base_path` matching `--bulk-filter`, and how many didn't match.
+fn bulk_album_dirs(base_path: &Path, cli: &Cli) -> Result<(Vec<PathBuf>, usize)> {
+    let mut subdirs = analyzer::scan_album_dirs(base_path)?;
+    let all = subdirs.len();
+    if let Some(pattern) = &cli.bulk_filter {
+        subdirs.retain(|dir| analyzer::glob_match(pattern, &album_dir_name(dir)));
+    }
+    let filtered = all - subdirs.len();
+    Ok((subdirs, filtered))
+}
+
+/// The summary's note on albums left out by `--bulk-filter`, if any were.
+fn filtered_note(filtered: usize) -> String {
+    if filtered > 0 {
+        format!("; {} filtered out", filtered)
+    } else {
+        String::new()
+    }
+}
+
+/// `--bulk --dry-run`: report what a bulk run would do without decoding anything.
+fn run_bulk_dry_run(base_path: &Path, cli: &Cli) -> Result<()> {
+    let (subdirs, filtered) = bulk_album_dirs(base_path, cli)?;
+    let options = cli.analysis_options();
+    let reuse = cli.reuse_reports();
+    let (mut analyze, mut skip, mut empty, mut unreadable) = (0usize, 0usize, 0usize, 0usize);
+
+    for subdir in &subdirs {
+        let name = album_dir_name(subdir);
+        if reuse && cache::reports_exist(subdir, &cli.report_basename, cli.json, cli.txt, cli.csv) {
+            println!("skip     {} (reports exist)", name);
+            skip += 1;
+            continue;
+        }
+        match analyzer::scan_audio_files_with_options(subdir, &options).map(|files| files.len()) {
+            Ok(0) => {
+                println!("empty    {} (no audio files)", name);
+                empty += 1;
+            }
+            Ok(n) => {
+                println!("analyze  {} ({} files)", name, n);
+                analyze += 1;
+            }
+            Err(e) => {
+                println!("error    {} ({:#})", name, e);
+                unreadable += 1;
+            }
+        }
+    }
+
+    let unreadable = if unreadable > 0 {
+        format!(", {} unreadable", unreadable)
+    } else {
+        String::new()
+    };
+    log::info!(
+        "Dry run: {} to analyze, {} to skip, {} empty{} (out of {} total{})",
+        analyze,
+        skip,
+        empty,
+        unreadable,
+        subdirs.len(),
+        filtered_note(filtered)
+    );
+    Ok(())
+}
+
+fn run_bulk(base_path: &Path, cli: &Cli, jobs: usize) -> Result<()> {
+    let (write_json, write_txt, write_csv) = (cli.json, cli.txt, cli.csv);
+    let (subdirs, filtered) = bulk_album_dirs(base_path, cli)?;
 
     let total = subdirs.len();
     let mut analyzed = 0usize;
     let mut skipped = 0usize;
+    let mut empty = 0usize;
     let mut failed = 0usize;
+    let mut untagged = 0usize;
+    let mut names = cache::CollectionNames::default();
+    // Albums for `--json-stdout`
+    let mut albums = Vec::new();
+    // Wall time of the albums actually analyzed, for the ETA
+    let start = Instant::now();
+    let mut work_time = Duration::ZERO;
 
     for (i, subdir) in subdirs.iter().enumerate() {
-        let album_name = subdir
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_else(|| subdir.display().to_string());
+        let album_name = album_dir_name(subdir);
 
-        if !regenerate && cache::reports_exist(subdir, write_json, write_txt) {
-            eprintln!("[{}/{}] Skipping (reports exist): {}", i + 1, total, album_name);
+        let reuse = cli.reuse_reports();
+        if reuse
+            && cache::reports_exist(
+                subdir,
+                &cli.report_basename,
+                write_json,
+                write_txt,
+                write_csv,
+            )
+        {
+            log::info!(
+                "[{}/{}] Skipping (reports exist): {}",
+                i + 1,
+                total,
+                album_name
+            );
             skipped += 1;
+            if cli.json_stdout {
+                match cache::load_cached_report(subdir, &cli.report_basename) {
+                    Some(mut cached) => {
+                        cached.rate(&cli.dr_scale());
+                        cli.apply_reference(&mut cached);
+                        albums.push(cached);
+                    }
+                    None => log::warn!(
+                        "{}: report unreadable, left out of the JSON output",
+                        album_name
+                    ),
+                }
+            }
             continue;
         }
 
-        eprintln!("[{}/{}] Analyzing: {}", i + 1, total, album_name);
+        log::info!("[{}/{}] Analyzing: {}", i + 1, total, album_name);
 
-        match analyzer::analyze_directory(subdir, jobs) {
-            Ok(result) => {
+        let album_start = Instant::now();
+        let analysis = analyze_dir(subdir, cli, jobs);
+        work_time += album_start.elapsed();
+        match analysis {
+            Ok(mut result) => {
+                cli.apply_reference(&mut result);
+                if let Err(e) = cli.check_metadata(&result.tracks) {
+                    log::warn!("{}: {}", album_name, e);
+                    untagged += 1;
+                }
+                let (dir, basename) = cli.report_location(subdir, &result, &mut names);
                 if write_json {
-                    if let Err(e) = cache::save_report(subdir, &result) {
-                        eprintln!("  Warning: failed to save JSON report: {}", e);
+                    if let Err(e) = cache::save_report(&dir, &basename, &result) {
+                        log::warn!("{}: failed to save JSON report: {}", album_name, e);
                     }
                 }
                 if write_txt {
-                    if let Err(e) = cache::save_text_report(subdir, &format::format_table(&result)) {
-                        eprintln!("  Warning: failed to save text report: {}", e);
+                    if let Err(e) =
+                        cache::save_text_report(&dir, &basename, &cli.text_report(&result))
+                    {
+                        log::warn!("{}: failed to save text report: {}", album_name, e);
                     }
                 }
+                if write_csv {
+                    if let Err(e) =
+                        cache::save_csv_report(&dir, &basename, &cli.csv_report(&result))
+                    {
+                        log::warn!("{}: failed to save CSV report: {}", album_name, e);
+                    }
+                }
+                if cli.json_stdout {
+                    albums.push(result);
+                }
                 analyzed += 1;
             }
+            Err(e) if dr::error::is_no_audio(&e) => {
+                log::info!("  No audio files, skipping");
+                empty += 1;
+            }
             Err(e) => {
-                eprintln!("  Warning: failed to analyze: {}", e);
+                log::warn!("{}: failed to analyze: {:#}", album_name, e);
                 failed += 1;
             }
         }
+        log::info!(
+            "  {}",
+            format::format_bulk_progress(
+                i + 1,
+                total,
+                start.elapsed().as_secs_f64(),
+                analyzed + failed + empty,
+                work_time.as_secs_f64(),
+            )
+        );
+    }
+
+    if cli.json_stdout {
+        println!("{}", format::format_json_albums(&albums));
     }
 
-    eprintln!(
-        "Done: {} analyzed, {} skipped, {} failed (out of {} total)",
-        analyzed, skipped, failed, total
+    let empty_note = if empty > 0 {
+        format!(", {} empty", empty)
+    } else {
+        String::new()
+    };
+    log::info!(
+        "Done: {} analyzed, {} skipped, {} failed{} (out of {} total{})",
+        analyzed,
+        skipped,
+        failed,
+        empty_note,
+        total,
+        filtered_note(filtered)
     );
+    if untagged > 0 {
+        anyhow::bail!(
+            "--strict-metadata: {} album(s) have files missing title or album tags",
+            untagged
+        );
+    }
 
     Ok(())
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    // Nothing may write to stderr under the TUI's alternate screen
+    dr::logging::init(if cli.tui {
+        log::LevelFilter::Off
+    } else {
+        dr::logging::level_for(cli.quiet, cli.verbose)
+    });
+
+    if cli.selftest {
+        return dr::selftest::run();
+    }
+    if cli.list_formats {
+        print_formats();
+        return Ok(());
+    }
+
+    let config = Config::load(cli.config.as_deref())?;
+    cli.apply_config(config);
+    cli.apply_strict_dr_database()?;
+    if let Some(path) = &cli.reference {
+        cli.reference_db = Some(ReferenceDb::load(path)?);
+    }
 
-    if cli.bulk && cli.tui {
-        anyhow::bail!("--bulk and --tui cannot be used together");
+    if cli.bulk && !cli.tui && !cli.json && !cli.txt && !cli.csv {
+        anyhow::bail!("--bulk requires at least one output format: --json, --txt, and/or --csv");
     }
-    if cli.bulk && !cli.json && !cli.txt {
-        anyhow::bail!("--bulk requires at least one output format: --json and/or --txt");
+    if cli.json_stdout && !cli.json {
+        anyhow::bail!("--json-stdout prints the albums --bulk --json writes; add --json");
+    }
+    if let Some(dir) = cli.report_into.as_ref().filter(|_| !cli.dry_run) {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create report directory: {}", dir.display()))?;
+    }
+
+    if cli.report_basename.is_empty() || cli.report_basename.contains(['/', '\\']) {
+        anyhow::bail!("--report-basename must be a plain file name without a path or extension");
+    }
+    if cli.trim_edges.is_some_and(|secs| secs <= 0.0) {
+        anyhow::bail!("--trim-edges must be positive");
+    }
+    if cli.preview_secs.is_some_and(|secs| secs <= 0.0) {
+        anyhow::bail!("--preview-secs must be positive");
+    }
+    if cli.threads_per_file == 0 {
+        anyhow::bail!("--threads-per-file must be positive");
+    }
+    if cli.force_channels == Some(0) || cli.force_sample_rate == Some(0) {
+        anyhow::bail!("--force-channels and --force-sample-rate must be positive");
     }
 
     let jobs = cli.jobs.unwrap_or_else(analyzer::default_jobs);
+    if cli.stdin_list {
+        return run_stdin_list(&cli, jobs);
+    }
     let path_str = cli.path.as_deref().unwrap_or(".");
+    if cli.follow.is_some() && path_str != "-" {
+        anyhow::bail!("--follow only applies when reading from STDIN (\"-\")");
+    }
+    if cli.follow.is_some_and(|secs| secs <= 0.0) {
+        anyhow::bail!("--follow interval must be positive");
+    }
+    if (cli.title.is_some() || cli.album.is_some()) && path_str != "-" {
+        anyhow::bail!("--title and --album only apply when reading from STDIN (\"-\")");
+    }
 
     // STDIN mode
     if path_str == "-" {
-        let fmt = cli
-            .format
-            .as_deref()
-            .context("--format is required when reading from STDIN (e.g. --format flac)")?;
-        let result = analyzer::analyze_stdin(fmt)?;
-        if cli.json {
-            println!("{}", format::format_json_single(&result));
-        } else {
-            println!("{}", format::format_table_single(&result));
+        let fmt = cli.format.as_deref();
+        let labels = StreamLabels {
+            title: cli.title.clone(),
+            album: cli.album.clone(),
+        };
+        let result = analyzer::analyze_stdin_with_snapshots(
+            fmt,
+            &labels,
+            &cli.analysis_options(),
+            cli.follow,
+            |partial| print_snapshot(partial, cli.json),
+        );
+        // With no hint, a stream symphonia can't place needs one
+        let undetected = |e: &anyhow::Error| {
+            matches!(
+                e.root_cause()
+                    .downcast_ref::<symphonia::core::errors::Error>(),
+                Some(symphonia::core::errors::Error::Unsupported(_))
+            )
+        };
+        let result = match result {
+            Err(e) if fmt.is_none() && undetected(&e) => {
+                return Err(e.context(
+                    "Could not detect the STDIN format; pass --format (e.g. --format flac)",
+                ));
+            }
+            result => result?,
+        };
+        if cli.verbose > 0 && fmt.is_none() {
+            log::info!(
+                "Detected format: {}",
+                result.codec.as_deref().unwrap_or("unknown")
+            );
         }
-        return Ok(());
+        print_track(&result, &cli)?;
+        return cli.check_metadata([&result]);
     }
 
     let path = Path::new(path_str);
+    if let Some(kind) = special_file_kind(path) {
+        anyhow::bail!(
+            "'{}' is {}, not a regular file. To analyze a stream, pipe it to STDIN with its format, e.g.: dr - --format flac < {}",
+            path_str,
+            kind,
+            path_str
+        );
+    }
 
-    // Single file mode
+    if (cli.start.is_some() || cli.duration.is_some()) && !path.is_file() {
+        anyhow::bail!("--start and --duration only apply to a single file");
+    }
+    if cli.start.is_some_and(|s| !s.is_finite() || s < 0.0)
+        || cli.duration.is_some_and(|d| !d.is_finite() || d <= 0.0)
+    {
+        anyhow::bail!("--start must not be negative and --duration must be positive, both finite");
+    }
+
+    if cli.histogram && !path.is_dir() {
+        anyhow::bail!("--histogram reads the cached reports under a directory");
+    }
+    if cli.envelope.is_some() && !path.is_file() {
+        anyhow::bail!("--envelope needs a single file");
+    }
+    if cli.recompute_album_dr && !path.is_dir() {
+        anyhow::bail!("--recompute-album-dr rewrites the cached reports under a directory");
+    }
+
+    if cli.tui && (path.is_file() || path.is_dir()) {
+        return dr::tui::run(
+            path,
+            cli.bulk,
+            jobs,
+            cli.regenerate || cli.strict_dr_database,
+            &cli.analysis_options(),
+            cli.defaults.color.unwrap_or(true),
+            &cli.report_basename,
+        );
+    }
+
+    // Single file mode; a file with chapters is reported as an album of them
     if path.is_file() {
-        let result = analyzer::analyze_file(path)?;
-        if cli.json {
-            println!("{}", format::format_json_single(&result));
+        let start = Instant::now();
+        let mut result = analyzer::analyze_chaptered_file(path, &cli.analysis_options(), |_| {})?;
+        if cli.verbose > 0 {
+            print_file_timing(&result.tracks[0], start.elapsed());
+        }
+        if let Some(envelope) = &cli.envelope {
+            std::fs::write(envelope, format::format_envelope_csv(&result.tracks))
+                .with_context(|| format!("Failed to write envelope: {}", envelope.display()))?;
+            log::info!("Wrote envelope to {}", envelope.display());
+        }
+        if result.tracks.len() > 1 {
+            cli.apply_reference(&mut result);
+            if cli.include_timing {
+                result.analysis = Some(AnalysisTiming::new(start.elapsed(), result.total_bytes));
+            }
+            print_album(&result, &cli, false)?;
         } else {
-            println!("{}", format::format_table_single(&result));
+            print_track(&result.tracks[0], &cli)?;
         }
-        return Ok(());
+        return cli.check_metadata(&result.tracks);
     }
 
     // Directory mode
     if path.is_dir() {
-        if cli.tui {
-            return dr::tui::run(path, jobs, cli.regenerate);
+        if cli.histogram {
+            return run_histogram(path, &cli);
+        }
+        if cli.recompute_album_dr {
+            return run_recompute_album_dr(path, &cli);
+        }
+        if cli.bulk && cli.dry_run {
+            return run_bulk_dry_run(path, &cli);
         }
-
         if cli.bulk {
-            return run_bulk(path, jobs, cli.json, cli.txt, cli.regenerate);
+            return run_bulk(path, &cli, jobs);
+        }
+
+        if cli.singles {
+            return run_singles(path, &cli, jobs);
         }
 
-        // Check for cached report
-        if !cli.regenerate {
-            if let Some(cached) = cache::load_cached_report(path) {
-                eprintln!("(loaded from cached report)");
-                if cli.json {
-                    println!("{}", format::format_json(&cached));
-                } else {
-                    println!("{}", format::format_table(&cached));
+        // Check for cached report (unless options change what would be reported)
+        if cli.use_cache() && cli.reuse_reports() {
+            if let Some(mut cached) = cache::load_cached_report(path, &cli.report_basename) {
+                log::info!("(loaded from cached report)");
+                cached.rate(&cli.dr_scale());
+                cli.apply_reference(&mut cached);
+                if cli.progress_json {
+                    print_event(&AnalysisEvent::AlbumCompleted {
+                        result: cached.clone(),
+                    });
                 }
-                return Ok(());
+                return print_album(&cached, &cli, false);
             }
+        } else {
+            log::debug!("not reading the cached report: disabled, --regenerate, or options that change the report");
         }
 
         let start = Instant::now();
-        let result = analyzer::analyze_directory(path, jobs)?;
+        let mut result = analyze_dir(path, &cli, jobs)?;
         let elapsed = start.elapsed();
 
-        // Auto-save cache
-        if let Err(e) = cache::save_report(path, &result) {
-            eprintln!("Warning: failed to save cache: {}", e);
+        // Auto-save cache, for runs whose report a later plain run may reuse
+        let (dir, basename) =
+            cli.report_location(path, &result, &mut cache::CollectionNames::default());
+        if cli.use_cache() && cli.writes_standard_reports() {
+            if let Err(e) = cache::save_report(&dir, &basename, &result) {
+                log::warn!("failed to save cache: {}", e);
+            }
         }
 
         if cli.txt {
-            if let Err(e) = cache::save_text_report(path, &format::format_table(&result)) {
-                eprintln!("Warning: failed to save text report: {}", e);
+            if let Err(e) = cache::save_text_report(&dir, &basename, &cli.text_report(&result)) {
+                log::warn!("failed to save text report: {}", e);
+            }
+        }
+        if cli.csv {
+            if let Err(e) = cache::save_csv_report(&dir, &basename, &cli.csv_report(&result)) {
+                log::warn!("failed to save CSV report: {}", e);
             }
         }
 
-        if cli.json {
-            println!("{}", format::format_json(&result));
-        } else {
-            println!("{}", format::format_table(&result));
+        cli.apply_reference(&mut result);
+        if cli.include_timing {
+            result.analysis = Some(AnalysisTiming::new(elapsed, result.total_bytes));
         }
+        print_album(&result, &cli, cli.stream)?;
 
         print_benchmark(&result, elapsed);
 
-        return Ok(());
+        return cli.check_metadata(&result.tracks);
     }
 
     anyhow::bail!("Path '{}' is not a file or directory", path_str);
@@ -1,25 +1,720 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::analyzer::SILENCE_THRESHOLD_DB;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TrackResult {
     pub dr: u32,
+    /// `dr` before rounding: the mean of the channels' DRs (shown with
+    /// `--precise`). `None` for tracks too short to measure, and in reports
+    /// from before schema v21
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dr_precise: Option<f64>,
+    /// Sample peak in dBFS
     pub peak_db: f64,
+    /// True (inter-sample) peak in dBTP, from 4x oversampling
+    #[serde(default)]
+    pub true_peak_db: f64,
     pub rms_db: f64,
+    /// Crest factor in dB: how far the sample peak sits above the DR-RMS
+    /// (`peak_db - rms_db`; see `crest_factor_db`)
+    #[serde(default)]
+    pub crest_factor_db: f64,
     pub duration_secs: f64,
     pub title: String,
+    /// Album given for a STDIN stream with `--album`; files report theirs on
+    /// the album result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
     pub filename: String,
+    /// Sample rate in Hz of the decoded audio (`None` in reports from before schema v9)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    /// Channel count of the decoded audio (`None` in reports from before schema v9)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u32>,
+    /// Position on the album from the TrackNumber tag ("3" or "3/12"), when tagged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_number: Option<u32>,
+    /// Disc of a multi-disc album, from the DiscNumber tag ("2" or "2/3"),
+    /// or with `--multi-disc` the number in an untagged track's disc folder
+    /// name. `None` in reports from before schema v22
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disc: Option<u32>,
     #[serde(default)]
     pub file_bytes: u64,
+    /// Integrated loudness (BS.1770 / EBU R128) in LUFS; `None` for silence
+    #[serde(default)]
+    pub loudness_lufs: Option<f64>,
+    /// Peak-to-loudness ratio in dB: true peak minus integrated loudness
+    /// (see `plr`); `None` without a loudness
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plr: Option<f64>,
+    /// L/R correlation coefficient in [-1, 1] (2-channel files with `--stereo-stats`).
+    /// Values near -1 indicate phase cancellation when summed to mono.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stereo_correlation: Option<f64>,
+    /// Block level and crest-factor fingerprint for duplicate detection (see
+    /// `fingerprint`)
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// Experimental DR over the K-weighted signal (`--k-weighted`). Not the
+    /// official DR value and not comparable with the DR Database.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perceptual_dr: Option<u32>,
+    /// Momentary loudness (400 ms windows) in LUFS every 100 ms, aligned with
+    /// EBU R128 (`--loudness-series`; floored at -120 LUFS)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub momentary_lufs: Vec<f64>,
+    /// Short-term loudness (3 s windows) in LUFS every 100 ms (`--loudness-series`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub short_term_lufs: Vec<f64>,
+    /// RMS in dB of each 3-second block, the loudest channel's (`--envelope`;
+    /// floored at -120 dB). Covers every block, silent ones included
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub envelope_db: Vec<f64>,
+    /// Where the analyzed section starts when only part of the file was
+    /// analyzed (`--start`/`--duration`); `duration_secs` covers that section
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range_start_secs: Option<f64>,
+    /// Seconds left out at each end of the track (`--trim-edges`), making the
+    /// DR non-standard
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trim_edges_secs: Option<f64>,
+    /// ReplayGain track gain in dB applied to the samples before analysis
+    /// (`--apply-existing-gain`), so levels are as played back. Peaks can then
+    /// exceed 0 dBFS: they are reported unclamped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applied_gain_db: Option<f64>,
+    /// Noise-floor estimate in dB (same scale as `rms_db`): the RMS of the
+    /// quietest blocks (`--noise-floor`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noise_floor_db: Option<f64>,
+    /// Each channel's DR, labeled by position (`--per-channel`). `dr` averages
+    /// the unrounded values, so it can differ from the mean of these
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channel_dr: Vec<ChannelDr>,
+    /// Tags the file lacks ("title", "album"), so the report fell back to the
+    /// filename or directory name for them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_tags: Vec<String>,
+    /// Packets skipped as corrupt during decoding. Nonzero means the DR was
+    /// computed from incomplete audio.
+    #[serde(default)]
+    pub decode_errors: u32,
+    /// Where in the track the sample peak occurs, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_at_secs: Option<f64>,
+    /// Start of the loudest complete 3-second block (by its loudest channel's
+    /// RMS), in seconds into the track: where to audition the compression
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loudest_block_secs: Option<f64>,
+    /// Start of the quietest complete 3-second block, in seconds into the track
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quietest_block_secs: Option<f64>,
+    /// Duration the container declares, when it differs from the decoded
+    /// length by more than `DURATION_TOLERANCE_SECS`: the decode may have
+    /// stopped early, leaving `duration_secs` short and the DR skewed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declared_duration_secs: Option<f64>,
+    /// Decoded from a lossy codec (MP3, AAC, Vorbis, Opus). Its DR can differ
+    /// slightly from the lossless source's published figure.
+    #[serde(default)]
+    pub lossy: bool,
+    /// Complete 3-second blocks the DR was computed from; 0 means the track
+    /// was too short to measure and reports DR0
+    #[serde(default)]
+    pub num_blocks: u32,
+    /// Silent blocks at the start and end left out of the DR (`--trim-silence`);
+    /// `num_blocks` counts only the rest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub silent_blocks_trimmed: Option<u32>,
+    /// Only one complete 3-second block, so the DR uses the highest block peak
+    /// rather than the 2nd-highest, which makes it less comparable (usually
+    /// lower) than that of longer tracks
+    #[serde(default)]
+    pub peak_fallback: bool,
+    /// The DR was computed by the older channel-combined method (`--legacy-dr`)
+    /// rather than per channel, so it isn't the official value
+    #[serde(default)]
+    pub legacy_dr: bool,
+    /// Only the start of the track was measured (`--preview-secs`), so the DR
+    /// is a rough figure rather than a full measurement
+    #[serde(default)]
+    pub preview: bool,
+    /// How far the DR can be trusted, from the block count, decode errors,
+    /// and silence (see `Confidence::of`)
+    #[serde(default)]
+    pub confidence: Confidence,
+    /// Codec of the decoded stream, as symphonia names it ("flac", "mp3",
+    /// "vorbis", "pcm_s16le", ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+    /// Bit depth of the source samples before decoding (`None` for lossy codecs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bits_per_sample: Option<u32>,
+    /// Whether the source samples are integer or floating point
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_kind: Option<SampleKind>,
+}
+
+/// One channel's DR (`--per-channel`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelDr {
+    /// Position label from the decoded channel layout ("FL", "FR", "FC", "LFE",
+    /// "SL", ...), "mono", or "ch<index>" when the layout is unknown
+    pub channel: String,
+    pub dr: u32,
+}
+
+/// How a lossless source stores its samples (all are analyzed as f32).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleKind {
+    Integer,
+    Float,
+}
+
+/// How far a track's DR can be trusted, summing up the edge cases met while
+/// measuring it, so unreliable figures can be filtered out of bulk results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    #[default]
+    High,
+    Medium,
+    Low,
+}
+
+/// Complete blocks (30 seconds) a track needs for `Confidence::High`.
+pub const CONFIDENT_BLOCKS: u32 = 10;
+
+/// Decode errors from which a track has `Confidence::Low`.
+pub const LOW_CONFIDENCE_DECODE_ERRORS: u32 = 10;
+
+/// How far the decoded length may stray from the container's declared
+/// duration before the track records `declared_duration_secs`.
+pub const DURATION_TOLERANCE_SECS: f64 = 0.5;
+
+impl Confidence {
+    /// The confidence in `track`'s DR. Low when there was nothing to measure
+    /// (no complete block, or silence), only one block (the peak fallback),
+    /// or `LOW_CONFIDENCE_DECODE_ERRORS` or more undecodable packets. Medium
+    /// when shorter than `CONFIDENT_BLOCKS` blocks or with any decode errors.
+    pub fn of(track: &TrackResult) -> Self {
+        if track.num_blocks == 0
+            || !track.rms_db.is_finite()
+            || track.peak_fallback
+            || track.decode_errors >= LOW_CONFIDENCE_DECODE_ERRORS
+        {
+            Confidence::Low
+        } else if track.num_blocks < CONFIDENT_BLOCKS || track.decode_errors > 0 {
+            Confidence::Medium
+        } else {
+            Confidence::High
+        }
+    }
+
+    /// Name as stored in JSON reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Confidence::High => "high",
+            Confidence::Medium => "medium",
+            Confidence::Low => "low",
+        }
+    }
+}
+
+impl TrackResult {
+    /// Whether the track is silent or nearly so, e.g. a hidden-track gap or a
+    /// data track: DR0 with its DR-RMS below `SILENCE_THRESHOLD_DB` (-inf when
+    /// there is none).
+    pub fn is_silent(&self) -> bool {
+        self.dr == 0 && self.rms_db < SILENCE_THRESHOLD_DB
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Version of the serialized report format. Bump whenever `AlbumResult` or
+/// `TrackResult` gain fields that older reports can't supply, and teach
+/// `cache::load_cached_report` how (or whether) to migrate older versions.
+pub const SCHEMA_VERSION: u32 = 23;
+
+/// The crest factor for a sample peak and DR-RMS in dB: `peak_db - rms_db`,
+/// or 0 when either is infinite (silence, or no complete block).
+pub fn crest_factor_db(peak_db: f64, rms_db: f64) -> f64 {
+    if peak_db.is_finite() && rms_db.is_finite() {
+        peak_db - rms_db
+    } else {
+        0.0
+    }
+}
+
+/// The peak-to-loudness ratio for a true peak in dBTP and an integrated
+/// loudness in LUFS: `true_peak_db - loudness_lufs`, `None` without a loudness.
+pub fn plr(true_peak_db: f64, loudness_lufs: Option<f64>) -> Option<f64> {
+    loudness_lufs.map(|lufs| true_peak_db - lufs)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AlbumResult {
+    /// Report format version; 0 for reports written before versioning existed
+    #[serde(default)]
+    pub schema_version: u32,
     pub tracks: Vec<TrackResult>,
     pub overall_dr: u32,
     pub album: Option<String>,
+    /// Album artist (or the first track's artist) from the first file's tags
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    /// Qualitative band for `overall_dr` (see `DrRating`)
+    #[serde(default)]
+    pub dr_rating: String,
+    /// Album loudness in LUFS (duration-weighted energy mean of track loudness)
+    #[serde(default)]
+    pub album_lufs: Option<f64>,
+    /// Sum of track durations (see `AlbumResult::update_totals`)
+    #[serde(default)]
+    pub total_duration_secs: f64,
+    /// Sum of track file sizes
+    #[serde(default)]
+    pub total_bytes: u64,
+    /// Highest track sample peak in dBFS, i.e. the peak of the whole album
+    /// played back to back (`None` without tracks)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_peak_db: Option<f64>,
+    /// Rounded mean of the tracks' `perceptual_dr`, when every track has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perceptual_dr: Option<u32>,
+    /// Tracks pulling the album DR down the most (see `AlbumResult::update_outliers`)
+    #[serde(default)]
+    pub outliers: Vec<DrOutlier>,
+    /// How `overall_dr` was combined from the track DRs
+    #[serde(default, skip_serializing_if = "AlbumAggregate::is_mean")]
+    pub album_agg: AlbumAggregate,
+    /// Files that failed to analyze and so are missing from `tracks` and
+    /// `overall_dr` (only in partial albums sent by `analyze_files_async`)
+    #[serde(default)]
+    pub failed_tracks: u32,
+    /// Silent tracks listed in `tracks` but left out of `overall_dr`
+    /// (`--skip-silent-in-average`)
+    #[serde(default)]
+    pub silent_tracks_excluded: u32,
+    /// The album's DR in the `--reference` file, when it has an entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_dr: Option<u32>,
+    /// How long the analysis took (`--include-timing`). Never saved to the
+    /// cached report, which stays about the audio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<AnalysisTiming>,
+}
+
+/// Round a DR value in dB to the whole DR it reports as, halves away from
+/// zero. Out-of-range values saturate instead of wrapping: negative values
+/// (and NaN) give 0, values beyond `u32` give `u32::MAX`.
+pub fn round_to_dr(value: f64) -> u32 {
+    if value.is_nan() {
+        return 0;
+    }
+    value.round().clamp(0.0, u32::MAX as f64) as u32
+}
+
+/// How track DR values combine into the album's `overall_dr`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AlbumAggregate {
+    /// The rounded mean, as the DR Database computes it
+    #[default]
+    Mean,
+    /// The median, less swayed by an outlier track. For an even number of
+    /// tracks, the mean of the two middle values, rounded
+    Median,
+}
+
+impl AlbumAggregate {
+    /// Combine track DR values (0 for no tracks). Halves round away from zero.
+    pub fn combine(self, drs: &[u32]) -> u32 {
+        if drs.is_empty() {
+            return 0;
+        }
+        match self {
+            AlbumAggregate::Mean => {
+                let sum: f64 = drs.iter().map(|&dr| dr as f64).sum();
+                round_to_dr(sum / drs.len() as f64)
+            }
+            AlbumAggregate::Median => {
+                let mut sorted = drs.to_vec();
+                sorted.sort_unstable();
+                let mid = sorted.len() / 2;
+                if sorted.len() % 2 == 1 {
+                    sorted[mid]
+                } else {
+                    round_to_dr((sorted[mid - 1] as f64 + sorted[mid] as f64) / 2.0)
+                }
+            }
+        }
+    }
+
+    fn is_mean(&self) -> bool {
+        *self == AlbumAggregate::Mean
+    }
+}
+
+/// Wall-clock cost of an analysis, as `--include-timing` reports it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisTiming {
+    pub elapsed_secs: f64,
+    /// Size of the analyzed files
+    pub total_bytes: u64,
+    /// Throughput in MiB (2^20 bytes) per second; 0 when no time elapsed
+    pub mb_per_sec: f64,
+}
+
+impl AnalysisTiming {
+    pub fn new(elapsed: std::time::Duration, total_bytes: u64) -> Self {
+        let elapsed_secs = elapsed.as_secs_f64();
+        let total_mb = total_bytes as f64 / (1024.0 * 1024.0);
+        Self {
+            elapsed_secs,
+            total_bytes,
+            mb_per_sec: if elapsed_secs > 0.0 {
+                total_mb / elapsed_secs
+            } else {
+                0.0
+            },
+        }
+    }
+
+    pub fn total_mb(&self) -> f64 {
+        self.total_bytes as f64 / (1024.0 * 1024.0)
+    }
 }
 
-/// Sent from analysis thread to TUI for live progress
+/// A track whose DR is well below the album's.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrOutlier {
+    /// 1-based position in the album
+    pub track: usize,
+    pub title: String,
+    pub dr: u32,
+}
+
+/// At most this many outliers are reported.
+const MAX_OUTLIERS: usize = 2;
+/// A track counts as an outlier only this many DR steps (or more) below the album.
+const OUTLIER_MIN_GAP: u32 = 2;
+
+impl AlbumResult {
+    /// Recompute `outliers`: the (up to two) tracks furthest below
+    /// `overall_dr`, lowest DR first.
+    pub fn update_outliers(&mut self) {
+        // Tracks left out of the album DR can't pull it down
+        let excluded = |t: &TrackResult| self.silent_tracks_excluded > 0 && t.is_silent();
+        let mut below: Vec<(usize, &TrackResult)> = self
+            .tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.dr + OUTLIER_MIN_GAP <= self.overall_dr && !excluded(t))
+            .collect();
+        below.sort_by_key(|(_, t)| t.dr);
+        self.outliers = below
+            .into_iter()
+            .take(MAX_OUTLIERS)
+            .map(|(i, t)| DrOutlier {
+                track: i + 1,
+                title: t.title.clone(),
+                dr: t.dr,
+            })
+            .collect();
+    }
+
+    /// Recombine `overall_dr` (and its rating and outliers) from the stored
+    /// track DRs with `album_agg`, without re-analyzing anything. The rating
+    /// is on the default `DrScale`.
+    pub fn recompute_overall_dr(&mut self, album_agg: AlbumAggregate) {
+        self.recompute_overall_dr_with(album_agg, false, &DrScale::default());
+    }
+
+    /// `recompute_overall_dr`, with `skip_silent` leaving silent tracks
+    /// (`TrackResult::is_silent`) out of the combination and counting them in
+    /// `silent_tracks_excluded`, and the rating on `scale`. An album of nothing
+    /// but silent tracks keeps them all, at DR0.
+    pub fn recompute_overall_dr_with(
+        &mut self,
+        album_agg: AlbumAggregate,
+        skip_silent: bool,
+        scale: &DrScale,
+    ) {
+        let audible: Vec<u32> = self
+            .tracks
+            .iter()
+            .filter(|t| !t.is_silent())
+            .map(|t| t.dr)
+            .collect();
+        let drs: Vec<u32> = if skip_silent && !audible.is_empty() {
+            audible
+        } else {
+            self.tracks.iter().map(|t| t.dr).collect()
+        };
+        self.silent_tracks_excluded = (self.tracks.len() - drs.len()) as u32;
+        self.album_agg = album_agg;
+        self.overall_dr = album_agg.combine(&drs);
+        self.rate(scale);
+        self.update_outliers();
+    }
+
+    /// Set `dr_rating` from `overall_dr` on `scale`.
+    pub fn rate(&mut self, scale: &DrScale) {
+        self.dr_rating = DrRating::from_dr(self.overall_dr, scale).name().to_string();
+    }
+
+    /// Recompute `total_duration_secs` and `total_bytes` from the tracks.
+    pub fn update_totals(&mut self) {
+        self.total_duration_secs = self.tracks.iter().map(|t| t.duration_secs).sum();
+        self.total_bytes = self.tracks.iter().map(|t| t.file_bytes).sum();
+        self.album_peak_db = self.tracks.iter().map(|t| t.peak_db).reduce(f64::max);
+    }
+}
+
+/// One track's levels after a normalization gain is applied.
+#[derive(Debug, Clone, Serialize)]
+pub struct NormalizedTrack {
+    pub title: String,
+    pub peak_db: f64,
+    pub true_peak_db: f64,
+    pub rms_db: f64,
+    /// True peak would exceed 0 dBTP
+    pub clips: bool,
+}
+
+/// What a report's levels would be after loudness normalization to
+/// `target_lufs` (album gain for albums, track gain for single files). This is
+/// a derivation over the measured loudness and peaks; no audio is modified.
+#[derive(Debug, Clone, Serialize)]
+pub struct NormalizationPreview {
+    pub target_lufs: f64,
+    /// Measured loudness the gain is computed from
+    pub source_lufs: f64,
+    pub gain_db: f64,
+    pub tracks: Vec<NormalizedTrack>,
+}
+
+impl NormalizationPreview {
+    /// Preview album-gain normalization. `None` if the album has no loudness (silence).
+    pub fn for_album(result: &AlbumResult, target_lufs: f64) -> Option<Self> {
+        let source_lufs = result.album_lufs?;
+        Some(Self::with_gain(&result.tracks, target_lufs, source_lufs))
+    }
+
+    /// Preview track-gain normalization of a single track.
+    pub fn for_track(track: &TrackResult, target_lufs: f64) -> Option<Self> {
+        let source_lufs = track.loudness_lufs?;
+        Some(Self::with_gain(
+            std::slice::from_ref(track),
+            target_lufs,
+            source_lufs,
+        ))
+    }
+
+    fn with_gain(tracks: &[TrackResult], target_lufs: f64, source_lufs: f64) -> Self {
+        let gain_db = target_lufs - source_lufs;
+        let tracks = tracks
+            .iter()
+            .map(|t| NormalizedTrack {
+                title: t.title.clone(),
+                peak_db: t.peak_db + gain_db,
+                true_peak_db: t.true_peak_db + gain_db,
+                rms_db: t.rms_db + gain_db,
+                clips: t.true_peak_db + gain_db > 0.0,
+            })
+            .collect();
+        Self {
+            target_lufs,
+            source_lufs,
+            gain_db,
+            tracks,
+        }
+    }
+
+    /// Number of tracks that would clip without a limiter.
+    pub fn clipping_tracks(&self) -> usize {
+        self.tracks.iter().filter(|t| t.clips).count()
+    }
+}
+
+/// Qualitative DR band, named in the report footer and stored as `dr_rating`.
+///
+/// The bands come from the same `DrScale` as the colors: `Crushed` is its low
+/// range, `Excellent` its high range, and the medium range is split at its
+/// midpoint into `Compressed` and `Good` (see `DrScale::good_from`). On the
+/// default scale that gives DR7 and below, DR8-10, DR11-13 and DR14+.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrRating {
+    Excellent,
+    Good,
+    Compressed,
+    Crushed,
+}
+
+impl DrRating {
+    /// The band `dr` falls in on `scale`.
+    pub fn from_dr(dr: u32, scale: &DrScale) -> Self {
+        match scale.classify(dr) {
+            DrSeverity::Low => DrRating::Crushed,
+            DrSeverity::Medium if dr < scale.good_from() => DrRating::Compressed,
+            DrSeverity::Medium => DrRating::Good,
+            DrSeverity::High => DrRating::Excellent,
+        }
+    }
+
+    /// The color severity of the band's DR values.
+    pub fn severity(&self) -> DrSeverity {
+        match self {
+            DrRating::Crushed => DrSeverity::Low,
+            DrRating::Compressed | DrRating::Good => DrSeverity::Medium,
+            DrRating::Excellent => DrSeverity::High,
+        }
+    }
+
+    /// Short name stored in JSON reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DrRating::Excellent => "Excellent",
+            DrRating::Good => "Good",
+            DrRating::Compressed => "Compressed",
+            DrRating::Crushed => "Crushed",
+        }
+    }
+
+    /// Name plus the DR range it covers on `scale`, e.g. "Good (DR11-13)".
+    pub fn label(&self, scale: &DrScale) -> String {
+        let range = match self {
+            DrRating::Crushed => scale.range_label(DrSeverity::Low),
+            DrRating::Compressed => dr_range(scale.medium_from, scale.good_from()),
+            DrRating::Good => dr_range(scale.good_from(), scale.high_from),
+            DrRating::Excellent => scale.range_label(DrSeverity::High),
+        };
+        format!("{} ({})", self.name(), range)
+    }
+}
+
+/// The DR values from `from` up to (not including) `until`, e.g. "DR8-10".
+fn dr_range(from: u32, until: u32) -> String {
+    if until - from <= 1 {
+        format!("DR{}", from)
+    } else {
+        format!("DR{}-{}", from, until - 1)
+    }
+}
+
+/// How good a DR value is for color coding (TUI, badges), by a `DrScale`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+/// DR thresholds for `DrSeverity`: values below `medium_from` are low, values
+/// from `high_from` up high, and those in between medium.
+///
+/// The default is the color scale of the Dynamic Range Database
+/// (dr.loudness-war.info): DR7 and below red, DR8-13 amber, DR14 and up green.
+/// The `DrRating` bands that name the DR in reports are derived from the same
+/// scale, so a rating and its color always agree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "[u32; 2]")]
+pub struct DrScale {
+    pub medium_from: u32,
+    pub high_from: u32,
+}
+
+impl DrScale {
+    /// The Dynamic Range Database scale.
+    pub const DR_DATABASE: DrScale = DrScale {
+        medium_from: 8,
+        high_from: 14,
+    };
+
+    /// A scale from its two thresholds; `0 < medium_from < high_from`.
+    pub fn new(medium_from: u32, high_from: u32) -> anyhow::Result<Self> {
+        if medium_from == 0 || medium_from >= high_from {
+            anyhow::bail!(
+                "DR scale thresholds must be increasing and above 0, got {},{}",
+                medium_from,
+                high_from
+            );
+        }
+        Ok(Self {
+            medium_from,
+            high_from,
+        })
+    }
+
+    /// The severity of `dr` on this scale.
+    pub fn classify(&self, dr: u32) -> DrSeverity {
+        if dr >= self.high_from {
+            DrSeverity::High
+        } else if dr >= self.medium_from {
+            DrSeverity::Medium
+        } else {
+            DrSeverity::Low
+        }
+    }
+
+    /// Where `DrRating::Good` starts: the midpoint of the medium range
+    /// (DR11 on the default scale), below which it is `Compressed`.
+    pub fn good_from(&self) -> u32 {
+        self.medium_from + (self.high_from - self.medium_from) / 2
+    }
+
+    /// The DR values of `severity`, e.g. "DR8-13", for legends.
+    pub fn range_label(&self, severity: DrSeverity) -> String {
+        match severity {
+            DrSeverity::Low => format!("DR{} and below", self.medium_from - 1),
+            DrSeverity::Medium => dr_range(self.medium_from, self.high_from),
+            DrSeverity::High => format!("DR{}+", self.high_from),
+        }
+    }
+}
+
+impl Default for DrScale {
+    fn default() -> Self {
+        Self::DR_DATABASE
+    }
+}
+
+impl TryFrom<[u32; 2]> for DrScale {
+    type Error = anyhow::Error;
+
+    fn try_from([medium_from, high_from]: [u32; 2]) -> anyhow::Result<Self> {
+        Self::new(medium_from, high_from)
+    }
+}
+
+/// Parses `MEDIUM,HIGH`, e.g. "8,14".
+impl std::str::FromStr for DrScale {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let parse = |n: &str| {
+            n.trim()
+                .trim_start_matches(['D', 'R', 'd', 'r'])
+                .parse::<u32>()
+                .map_err(|_| anyhow::anyhow!("expected MEDIUM,HIGH (e.g. 8,14), got {:?}", s))
+        };
+        match s.split_once(',') {
+            Some((medium, high)) => Self::new(parse(medium)?, parse(high)?),
+            None => anyhow::bail!("expected MEDIUM,HIGH (e.g. 8,14), got {:?}", s),
+        }
+    }
+}
+
+/// Sent from analysis thread to TUI for live progress. Serializes with an
+/// `event` tag naming the variant (`--progress-json`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event")]
+// Events are few and short-lived; boxing the result would only complicate matching
+#[allow(clippy::large_enum_variant)]
 pub enum AnalysisEvent {
     TrackStarted { index: usize },
     TrackProgress { index: usize, percent: f32 },
@@ -27,3 +722,77 @@ pub enum AnalysisEvent {
     AlbumCompleted { result: AlbumResult },
     Error { index: usize, message: String },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_to_dr_saturates() {
+        assert_eq!(round_to_dr(-3.7), 0);
+        assert_eq!(round_to_dr(-0.4), 0);
+        assert_eq!(round_to_dr(0.0), 0);
+        assert_eq!(round_to_dr(11.5), 12);
+        assert_eq!(round_to_dr(11.49), 11);
+        assert_eq!(round_to_dr(1e12), u32::MAX);
+        assert_eq!(round_to_dr(f64::INFINITY), u32::MAX);
+        assert_eq!(round_to_dr(f64::NEG_INFINITY), 0);
+        assert_eq!(round_to_dr(f64::NAN), 0);
+    }
+
+    #[test]
+    fn test_median_of_large_values_does_not_overflow() {
+        let drs = [u32::MAX, u32::MAX];
+        assert_eq!(AlbumAggregate::Median.combine(&drs), u32::MAX);
+        assert_eq!(AlbumAggregate::Mean.combine(&drs), u32::MAX);
+    }
+
+    #[test]
+    fn test_analysis_timing_throughput() {
+        let timing = AnalysisTiming::new(std::time::Duration::from_millis(500), 3 * 1024 * 1024);
+        assert_eq!(timing.elapsed_secs, 0.5);
+        assert_eq!(timing.total_mb(), 3.0);
+        assert_eq!(timing.mb_per_sec, 6.0);
+        assert_eq!(
+            AnalysisTiming::new(std::time::Duration::ZERO, 1).mb_per_sec,
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_dr_scale_classifies_and_labels() {
+        let scale = DrScale::default();
+        assert_eq!(scale.classify(7), DrSeverity::Low);
+        assert_eq!(scale.classify(8), DrSeverity::Medium);
+        assert_eq!(scale.classify(13), DrSeverity::Medium);
+        assert_eq!(scale.classify(14), DrSeverity::High);
+        assert_eq!(scale.range_label(DrSeverity::Low), "DR7 and below");
+        assert_eq!(scale.range_label(DrSeverity::Medium), "DR8-13");
+        assert_eq!(scale.range_label(DrSeverity::High), "DR14+");
+
+        let narrow: DrScale = "DR10, DR11".parse().unwrap();
+        assert_eq!(narrow.range_label(DrSeverity::Medium), "DR10");
+        assert_eq!(narrow.classify(11), DrSeverity::High);
+        assert!("14,8".parse::<DrScale>().is_err());
+        assert!("0,8".parse::<DrScale>().is_err());
+        assert!("8".parse::<DrScale>().is_err());
+    }
+
+    #[test]
+    fn test_dr_rating_follows_the_color_scale() {
+        for scale in [DrScale::default(), DrScale::new(9, 15).unwrap()] {
+            for dr in 0..20 {
+                let rating = DrRating::from_dr(dr, &scale);
+                assert_eq!(rating.severity(), scale.classify(dr), "DR{}", dr);
+            }
+        }
+        let scale = DrScale::new(9, 15).unwrap();
+        assert_eq!(DrRating::from_dr(11, &scale), DrRating::Compressed);
+        assert_eq!(DrRating::from_dr(12, &scale), DrRating::Good);
+        assert_eq!(DrRating::Good.label(&scale), "Good (DR12-14)");
+        assert_eq!(DrRating::Crushed.label(&scale), "Crushed (DR8 and below)");
+        let narrow = DrScale::new(10, 11).unwrap();
+        assert_eq!(DrRating::from_dr(10, &narrow), DrRating::Good);
+        assert_eq!(DrRating::Good.label(&narrow), "Good (DR10)");
+    }
+}
@@ -0,0 +1,162 @@
+//! Known album DR values from a local reference file (`--reference`), for
+//! spotting rips that don't match the expected master.
+//!
+//! The file is a JSON array of `{"artist": ..., "album": ..., "dr": ...}`
+//! objects, or, with a `.csv` extension, CSV with an `artist,album,dr` header
+//! (columns in any order; fields may be double-quoted). Albums and artists
+//! match ignoring case and surrounding whitespace. An entry without an artist
+//! matches the album by any artist, and so does every entry when the measured
+//! album's artist isn't tagged.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+/// One known album DR value.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ReferenceEntry {
+    #[serde(default)]
+    pub artist: Option<String>,
+    pub album: String,
+    pub dr: u32,
+}
+
+/// The entries of a reference file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReferenceDb {
+    pub entries: Vec<ReferenceEntry>,
+}
+
+impl ReferenceDb {
+    /// Load a reference file: CSV if the extension is `.csv`, JSON otherwise.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read reference file: {}", path.display()))?;
+        let is_csv = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+        let parsed = if is_csv {
+            Self::parse_csv(&text)
+        } else {
+            Self::parse_json(&text)
+        };
+        parsed.with_context(|| format!("Invalid reference file: {}", path.display()))
+    }
+
+    /// Parse a JSON array of entries.
+    pub fn parse_json(text: &str) -> Result<Self> {
+        Ok(Self {
+            entries: serde_json::from_str(text)?,
+        })
+    }
+
+    /// Parse CSV with a header row naming the `album` and `dr` columns, and
+    /// optionally `artist`.
+    pub fn parse_csv(text: &str) -> Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+        let Some((_, header)) = lines.next() else {
+            return Ok(Self::default());
+        };
+        let header: Vec<String> = split_csv_line(header)
+            .iter()
+            .map(|h| h.trim().to_lowercase())
+            .collect();
+        let column = |name: &str| header.iter().position(|h| h == name);
+        let (Some(album), Some(dr)) = (column("album"), column("dr")) else {
+            bail!("header must name the `album` and `dr` columns");
+        };
+        let artist = column("artist");
+
+        let mut entries = Vec::new();
+        for (i, line) in lines {
+            let fields = split_csv_line(line);
+            let field = |index: usize| fields.get(index).map(|f| f.trim()).unwrap_or("");
+            let value = field(dr);
+            entries.push(ReferenceEntry {
+                artist: artist
+                    .map(field)
+                    .filter(|a| !a.is_empty())
+                    .map(str::to_string),
+                album: field(album).to_string(),
+                dr: value
+                    .trim_start_matches(['D', 'R', 'd', 'r'])
+                    .parse()
+                    .with_context(|| format!("line {}: invalid DR value {:?}", i + 1, value))?,
+            });
+        }
+        Ok(Self { entries })
+    }
+
+    /// The known DR of `album` by `artist`. An entry naming the same artist
+    /// wins over one without an artist.
+    pub fn lookup(&self, artist: Option<&str>, album: &str) -> Option<u32> {
+        let same = |a: &str, b: &str| a.trim().eq_ignore_ascii_case(b.trim());
+        let candidates = || self.entries.iter().filter(|e| same(&e.album, album));
+        let exact = artist.and_then(|artist| {
+            candidates().find(|e| e.artist.as_deref().is_some_and(|a| same(a, artist)))
+        });
+        exact
+            .or_else(|| candidates().find(|e| e.artist.is_none() || artist.is_none()))
+            .map(|e| e.dr)
+    }
+}
+
+/// Split one CSV line into fields. Double-quoted fields may contain commas,
+/// and `""` inside them is a literal quote.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_with_quoted_fields() {
+        let db = ReferenceDb::parse_csv(
+            "album,artist,dr\n\"Brothers in Arms\",Dire Straits,DR14\n\"Hello, World\",\"The \"\"Band\"\"\",9\n",
+        )
+        .unwrap();
+        assert_eq!(db.entries.len(), 2);
+        assert_eq!(db.entries[0].dr, 14);
+        assert_eq!(db.entries[1].album, "Hello, World");
+        assert_eq!(db.entries[1].artist.as_deref(), Some("The \"Band\""));
+        assert!(ReferenceDb::parse_csv("title,dr\nx,1\n").is_err());
+        assert!(ReferenceDb::parse_csv("album,dr\nx,loud\n").is_err());
+    }
+
+    #[test]
+    fn test_lookup_prefers_matching_artist() {
+        let db = ReferenceDb::parse_json(
+            r#"[{"artist": "A", "album": "Greatest Hits", "dr": 7},
+                {"album": "Greatest Hits", "dr": 10},
+                {"artist": "B", "album": "Live", "dr": 12}]"#,
+        )
+        .unwrap();
+        assert_eq!(db.lookup(Some("a"), " greatest hits"), Some(7));
+        assert_eq!(db.lookup(Some("C"), "Greatest Hits"), Some(10));
+        assert_eq!(db.lookup(None, "Live"), Some(12));
+        assert_eq!(db.lookup(Some("C"), "Live"), None);
+        assert_eq!(db.lookup(Some("B"), "Unknown"), None);
+    }
+}
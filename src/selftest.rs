@@ -0,0 +1,83 @@
+//! `dr --selftest`: analyze synthetic signals with known DR values and report
+//! whether the results match, plus analysis throughput.
+
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::analyzer::{analyze_samples, AnalysisOptions};
+use crate::signals;
+
+const SAMPLE_RATE: u32 = 44100;
+
+struct Case {
+    name: &'static str,
+    samples: Vec<f32>,
+    expected_dr: std::ops::RangeInclusive<u32>,
+}
+
+fn cases() -> Vec<Case> {
+    vec![
+        Case {
+            // sqrt(2) calibration cancels a sine's crest factor
+            name: "Full-scale sine 440 Hz",
+            samples: signals::sine(440.0, 1.0, 30.0, SAMPLE_RATE),
+            expected_dr: 0..=1,
+        },
+        Case {
+            name: "Half-scale sine 1 kHz",
+            samples: signals::sine(1000.0, 0.5, 30.0, SAMPLE_RATE),
+            expected_dr: 0..=1,
+        },
+        Case {
+            name: "Pink noise with dynamics",
+            samples: signals::pink_noise_with_dynamics(0.9, 60.0, SAMPLE_RATE, 0x5eed),
+            expected_dr: 9..=11,
+        },
+    ]
+}
+
+/// Run all self-test cases, printing one line per case and a throughput summary.
+/// Returns an error if any case falls outside its expected DR range.
+pub fn run() -> Result<()> {
+    let mut failures = 0usize;
+    let mut audio_secs = 0.0f64;
+    let start = Instant::now();
+
+    for case in cases() {
+        let result = analyze_samples(
+            &case.samples,
+            1,
+            SAMPLE_RATE as usize,
+            &AnalysisOptions::default(),
+        );
+        audio_secs += result.duration_secs;
+
+        let pass = case.expected_dr.contains(&result.dr);
+        if !pass {
+            failures += 1;
+        }
+        println!(
+            "{}  {:<28} DR{:<3} (expected DR{}-{})  peak {:.2} dB  rms {:.2} dB",
+            if pass { "PASS" } else { "FAIL" },
+            case.name,
+            result.dr,
+            case.expected_dr.start(),
+            case.expected_dr.end(),
+            result.peak_db,
+            result.rms_db,
+        );
+    }
+
+    let secs = start.elapsed().as_secs_f64();
+    let realtime = if secs > 0.0 { audio_secs / secs } else { 0.0 };
+    println!(
+        "Analyzed {:.0}s of audio in {:.3}s ({:.0}x realtime)",
+        audio_secs, secs, realtime
+    );
+
+    if failures > 0 {
+        anyhow::bail!("{} self-test case(s) failed", failures);
+    }
+    Ok(())
+}
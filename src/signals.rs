@@ -0,0 +1,104 @@
+//! Synthetic test signals with known DR characteristics.
+//!
+//! Used by `dr --selftest` and the integration tests to exercise the analyzer
+//! without a music library.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Generate a mono sine wave as f32 samples in [-1.0, 1.0].
+pub fn sine(frequency: f64, amplitude: f32, duration_secs: f64, sample_rate: u32) -> Vec<f32> {
+    let num_samples = (sample_rate as f64 * duration_secs) as usize;
+    (0..num_samples)
+        .map(|i| {
+            let t = i as f64 / sample_rate as f64;
+            amplitude * (2.0 * std::f64::consts::PI * frequency * t).sin() as f32
+        })
+        .collect()
+}
+
+/// Generate mono pink noise whose level alternates between loud and quiet
+/// 3-second sections (0 dB and -20 dB), from a deterministic seed.
+///
+/// Pink noise is produced by filtering white noise with Paul Kellet's
+/// economy filter, then normalized so the loudest sample sits at `amplitude`.
+pub fn pink_noise_with_dynamics(
+    amplitude: f32,
+    duration_secs: f64,
+    sample_rate: u32,
+    seed: u64,
+) -> Vec<f32> {
+    let num_samples = (sample_rate as f64 * duration_secs) as usize;
+    let section = 3 * sample_rate as usize;
+    let mut rng = seed.max(1);
+    let (mut b0, mut b1, mut b2) = (0.0f64, 0.0f64, 0.0f64);
+
+    let mut samples: Vec<f32> = (0..num_samples)
+        .map(|i| {
+            // xorshift64 -> uniform white noise in [-1, 1)
+            rng ^= rng << 13;
+            rng ^= rng >> 7;
+            rng ^= rng << 17;
+            let white = (rng >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0;
+
+            b0 = 0.99765 * b0 + white * 0.0990460;
+            b1 = 0.96300 * b1 + white * 0.2965164;
+            b2 = 0.57000 * b2 + white * 1.0526913;
+            let pink = b0 + b1 + b2 + white * 0.1848;
+
+            let gain = if (i / section).is_multiple_of(2) {
+                1.0
+            } else {
+                0.1
+            };
+            (pink * gain) as f32
+        })
+        .collect();
+
+    let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    if peak > 0.0 {
+        let scale = amplitude / peak;
+        samples.iter_mut().for_each(|s| *s *= scale);
+    }
+    samples
+}
+
+/// Write interleaved f32 samples as a 16-bit PCM WAV file.
+pub fn write_wav_16(
+    path: &Path,
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+) -> io::Result<()> {
+    let bits_per_sample: u16 = 16;
+    let byte_rate = sample_rate * channels as u32 * bits_per_sample as u32 / 8;
+    let block_align = channels * bits_per_sample / 8;
+    let data_size = (samples.len() * (bits_per_sample as usize / 8)) as u32;
+
+    let mut out = Vec::with_capacity(44 + data_size as usize);
+
+    // RIFF header
+    out.write_all(b"RIFF")?;
+    out.write_all(&(36 + data_size).to_le_bytes())?;
+    out.write_all(b"WAVE")?;
+
+    // fmt chunk
+    out.write_all(b"fmt ")?;
+    out.write_all(&16u32.to_le_bytes())?; // chunk size
+    out.write_all(&1u16.to_le_bytes())?; // PCM
+    out.write_all(&channels.to_le_bytes())?;
+    out.write_all(&sample_rate.to_le_bytes())?;
+    out.write_all(&byte_rate.to_le_bytes())?;
+    out.write_all(&block_align.to_le_bytes())?;
+    out.write_all(&bits_per_sample.to_le_bytes())?;
+
+    // data chunk
+    out.write_all(b"data")?;
+    out.write_all(&data_size.to_le_bytes())?;
+    for &s in samples {
+        let sample_i16 = (s * i16::MAX as f32) as i16;
+        out.write_all(&sample_i16.to_le_bytes())?;
+    }
+
+    std::fs::write(path, out)
+}
@@ -0,0 +1,94 @@
+//! Fixture files for tests of `dr` and of crates built on it: WAV files of
+//! the `signals` test signals, and whole albums of them.
+//!
+//! The helpers panic when a file can't be written, as a test would anyway.
+//! The signals are deterministic, so a fixture has the same DR on every run:
+//! a sine is DR0 or DR1 (its DR-RMS equals its peak), and the pink noise with
+//! 20 dB sections about DR8 to DR11, depending on length and sample rate.
+
+use std::path::{Path, PathBuf};
+
+use crate::signals;
+
+/// Write a mono 16-bit WAV of a sine wave to `dir/filename` and return its path.
+pub fn generate_sine_wav(
+    dir: &Path,
+    filename: &str,
+    frequency: f64,
+    amplitude: f32,
+    duration_secs: f64,
+    sample_rate: u32,
+) -> PathBuf {
+    let samples = signals::sine(frequency, amplitude, duration_secs, sample_rate);
+    write_wav(dir, filename, &samples, sample_rate)
+}
+
+/// Write a mono 16-bit WAV of `signals::pink_noise_with_dynamics` to
+/// `dir/filename` and return its path. The same `seed` gives the same file.
+pub fn generate_pink_noise(
+    dir: &Path,
+    filename: &str,
+    amplitude: f32,
+    duration_secs: f64,
+    sample_rate: u32,
+    seed: u64,
+) -> PathBuf {
+    let samples = signals::pink_noise_with_dynamics(amplitude, duration_secs, sample_rate, seed);
+    write_wav(dir, filename, &samples, sample_rate)
+}
+
+/// Write an album of sine tracks into `dir` (created if needed), one per
+/// `(frequency, amplitude, duration_secs)`, named "01.wav", "02.wav", ... so
+/// they sort in order. Returns the track paths.
+pub fn generate_album(dir: &Path, tracks: &[(f64, f32, f64)], sample_rate: u32) -> Vec<PathBuf> {
+    std::fs::create_dir_all(dir).unwrap_or_else(|e| panic!("create {}: {}", dir.display(), e));
+    tracks
+        .iter()
+        .enumerate()
+        .map(|(i, &(frequency, amplitude, secs))| {
+            generate_sine_wav(
+                dir,
+                &format!("{:02}.wav", i + 1),
+                frequency,
+                amplitude,
+                secs,
+                sample_rate,
+            )
+        })
+        .collect()
+}
+
+fn write_wav(dir: &Path, filename: &str, samples: &[f32], sample_rate: u32) -> PathBuf {
+    let path = dir.join(filename);
+    signals::write_wav_16(&path, samples, 1, sample_rate)
+        .unwrap_or_else(|e| panic!("write {}: {}", path.display(), e));
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer;
+
+    #[test]
+    fn test_fixtures_have_known_dr() {
+        let dir = std::env::temp_dir().join(format!("dr-testutil-{}", std::process::id()));
+        let tracks = generate_album(&dir, &[(440.0, 0.5, 6.0), (880.0, 0.25, 6.0)], 8000);
+        std::fs::create_dir_all(dir.join("noise")).unwrap();
+        let noise = generate_pink_noise(&dir.join("noise"), "noise.wav", 0.9, 12.0, 8000, 7);
+
+        let album = analyzer::analyze_directory(&dir, 1).unwrap();
+        let noise_dr = analyzer::analyze_file(&noise).map(|t| t.dr);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            tracks
+                .iter()
+                .map(|p| p.file_name().unwrap())
+                .collect::<Vec<_>>(),
+            ["01.wav", "02.wav"]
+        );
+        assert!(album.tracks.iter().all(|t| t.dr <= 1));
+        assert!((8..=11).contains(&noise_dr.unwrap()));
+    }
+}
@@ -1,30 +1,55 @@
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
-use crate::models::{AlbumResult, TrackResult};
+use crate::analyzer::AnalysisOptions;
+use crate::cache;
+use crate::format::ExportFormat;
+use crate::models::{round_to_dr, AlbumResult, AnalysisTiming, TrackResult};
 
 #[derive(Debug, Clone)]
 pub enum TrackStatus {
     Pending,
     Analyzing(f32),
-    Complete(TrackResult),
+    Complete(Box<TrackResult>),
     Error(String),
 }
 
+/// What the TUI was opened on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// One album directory.
+    Directory,
+    /// A single audio file; `App::path` is the file itself.
+    File,
+    /// A tree of album directories, picked from the album list.
+    Bulk,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum View {
     Main,
+    /// Album list has focus (bulk mode)
+    Albums,
     About,
     Export,
     Info,
+    /// Path prompt for opening another file or directory
+    Open,
+    /// Path prompt for a report to compare the album with
+    CompareOpen,
+    /// The album's tracks side by side with a compared report's
+    Compare,
     RegenerateConfirm,
+    QuitConfirm,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum ExportFormat {
-    Text,
-    Json,
-    Csv,
+/// One album directory in the bulk album list.
+#[derive(Debug, Clone)]
+pub struct AlbumEntry {
+    pub path: PathBuf,
+    pub name: String,
+    /// Overall DR, once known from a cached report or a finished analysis
+    pub dr: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -40,9 +65,14 @@ pub struct BenchmarkStats {
 }
 
 impl BenchmarkStats {
+    /// The totals as `--include-timing` serializes them.
+    pub fn timing(&self) -> AnalysisTiming {
+        let total_bytes = self.track_timings.iter().map(|t| t.file_bytes).sum();
+        AnalysisTiming::new(self.total_elapsed, total_bytes)
+    }
+
     pub fn total_mb(&self) -> f64 {
-        let total_bytes: u64 = self.track_timings.iter().map(|t| t.file_bytes).sum();
-        total_bytes as f64 / (1024.0 * 1024.0)
+        self.timing().total_mb()
     }
 
     pub fn avg_per_track(&self) -> Duration {
@@ -54,12 +84,7 @@ impl BenchmarkStats {
     }
 
     pub fn mb_per_sec(&self) -> f64 {
-        let secs = self.total_elapsed.as_secs_f64();
-        if secs > 0.0 {
-            self.total_mb() / secs
-        } else {
-            0.0
-        }
+        self.timing().mb_per_sec
     }
 }
 
@@ -67,13 +92,30 @@ pub struct App {
     pub tracks: Vec<(String, TrackStatus)>,
     pub album_result: Option<AlbumResult>,
     pub view: View,
+    /// Where closing an overlay returns to: `Main`, or `Albums` in bulk mode
+    pub home_view: View,
     pub selected: usize,
     pub scroll_offset: usize,
     pub should_quit: bool,
     pub album_title: Option<String>,
+    /// Album directory being shown (or the file, for `Source::File`)
     pub path: PathBuf,
+    pub source: Source,
+    /// Album directories of a bulk tree, empty otherwise
+    pub albums: Vec<AlbumEntry>,
+    pub album_selected: usize,
     pub export_format: ExportFormat,
     pub export_message: Option<String>,
+    /// Text typed into the open prompt
+    pub open_input: String,
+    /// Why the last path entered in the open prompt couldn't be opened
+    pub open_error: Option<String>,
+    /// Report the album is compared with in the compare view
+    pub compare: Option<AlbumResult>,
+    /// Where the compared report was loaded from
+    pub compare_path: PathBuf,
+    /// First visible row of the compare view
+    pub compare_scroll: usize,
     /// Visible height of the track table (updated each frame by the renderer)
     pub visible_rows: usize,
     pub loaded_from_cache: bool,
@@ -82,10 +124,25 @@ pub struct App {
     pub track_start_times: Vec<Option<Instant>>,
     pub track_elapsed: Vec<Option<Duration>>,
     pub jobs: usize,
+    pub options: AnalysisOptions,
+    /// Color-code DR values by severity on `options.dr_scale`
+    pub color: bool,
+    /// Show true peak (dBTP) instead of sample peak (dBFS) in the track table
+    pub show_true_peak: bool,
+    /// File stem for saved and exported reports
+    pub report_basename: String,
+    /// Running sum/count of completed track DRs, updated as tracks complete
+    pub running_dr_sum: u32,
+    pub running_dr_count: usize,
 }
 
 impl App {
-    pub fn new(filenames: Vec<String>, path: PathBuf, jobs: usize) -> Self {
+    pub fn new(
+        filenames: Vec<String>,
+        path: PathBuf,
+        jobs: usize,
+        options: AnalysisOptions,
+    ) -> Self {
         let count = filenames.len();
         let tracks = filenames
             .into_iter()
@@ -95,13 +152,22 @@ impl App {
             tracks,
             album_result: None,
             view: View::Main,
+            home_view: View::Main,
             selected: 0,
             scroll_offset: 0,
             should_quit: false,
             album_title: None,
             path,
+            source: Source::Directory,
+            albums: Vec::new(),
+            album_selected: 0,
             export_format: ExportFormat::Text,
             export_message: None,
+            open_input: String::new(),
+            open_error: None,
+            compare: None,
+            compare_path: PathBuf::new(),
+            compare_scroll: 0,
             visible_rows: 20,
             loaded_from_cache: false,
             benchmark: None,
@@ -109,13 +175,20 @@ impl App {
             track_start_times: vec![None; count],
             track_elapsed: vec![None; count],
             jobs,
+            options,
+            color: true,
+            show_true_peak: false,
+            report_basename: crate::cache::DEFAULT_REPORT_BASENAME.to_string(),
+            running_dr_sum: 0,
+            running_dr_count: 0,
         }
     }
 
-    pub fn load_from_cache(&mut self, result: AlbumResult) {
+    pub fn load_from_cache(&mut self, mut result: AlbumResult) {
+        result.rate(&self.options.dr_scale);
         for (i, track_result) in result.tracks.iter().enumerate() {
             if let Some(track) = self.tracks.get_mut(i) {
-                track.1 = TrackStatus::Complete(track_result.clone());
+                track.1 = TrackStatus::Complete(Box::new(track_result.clone()));
             }
         }
         self.album_title = result.album.clone();
@@ -138,8 +211,105 @@ impl App {
         self.analysis_start = Some(Instant::now());
         self.track_start_times = vec![None; count];
         self.track_elapsed = vec![None; count];
+        self.running_dr_sum = 0;
+        self.running_dr_count = 0;
         self.export_message = None;
-        self.view = View::Main;
+        self.compare = None;
+        self.view = self.home_view.clone();
+    }
+
+    /// Switch to a non-overlay view and make it the one overlays return to.
+    pub fn show(&mut self, view: View) {
+        self.home_view = view.clone();
+        self.view = view;
+    }
+
+    /// Show another album directory of a bulk tree, with every track pending.
+    pub fn switch_album(&mut self, path: PathBuf, filenames: Vec<String>) {
+        self.reset_for_regeneration(filenames);
+        self.path = path;
+        self.analysis_start = None;
+    }
+
+    /// Show the open prompt, starting from the directory being shown.
+    pub fn prompt_open(&mut self) {
+        let dir = match self.source {
+            Source::File | Source::Bulk => self.path.parent().unwrap_or(&self.path),
+            Source::Directory => &self.path,
+        };
+        self.open_input = format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR);
+        self.open_error = None;
+        self.view = View::Open;
+    }
+
+    /// Show the compare prompt, starting from the directory holding the album
+    /// (where another version of it most likely sits).
+    pub fn prompt_compare(&mut self) {
+        let dir = self.path.parent().unwrap_or(&self.path);
+        self.open_input = format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR);
+        self.open_error = None;
+        self.view = View::CompareOpen;
+    }
+
+    /// Show the album side by side with `report`, loaded from `path`.
+    pub fn start_compare(&mut self, path: PathBuf, report: AlbumResult) {
+        self.compare = Some(report);
+        self.compare_path = path;
+        self.compare_scroll = 0;
+        self.open_error = None;
+        self.view = View::Compare;
+    }
+
+    /// Show another file or directory in place of the current one, with every
+    /// track pending. A `Source::Bulk` tree starts with no albums; the caller
+    /// fills them in.
+    pub fn open(&mut self, path: PathBuf, source: Source, filenames: Vec<String>) {
+        self.source = source;
+        self.albums.clear();
+        self.album_selected = 0;
+        self.open_error = None;
+        self.show(View::Main);
+        self.switch_album(path, filenames);
+    }
+
+    /// Record a finished album's DR in the bulk album list.
+    pub fn record_album_dr(&mut self, dr: u32) {
+        if let Some(entry) = self.albums.iter_mut().find(|a| a.path == self.path) {
+            entry.dr = Some(dr);
+        }
+    }
+
+    /// Where an export in the given extension is written: beside the file in
+    /// single-file mode, otherwise in the album directory.
+    pub fn export_path(&self, ext: &str) -> PathBuf {
+        match self.source {
+            Source::File => self.path.with_extension(ext),
+            Source::Directory | Source::Bulk => {
+                cache::report_path(&self.path, &self.report_basename, ext)
+            }
+        }
+    }
+
+    /// Fold a newly completed track into the running album DR estimate.
+    pub fn record_track_dr(&mut self, dr: u32) {
+        self.running_dr_sum += dr;
+        self.running_dr_count += 1;
+    }
+
+    /// Rounded mean of the track DRs completed so far, if any.
+    pub fn running_dr(&self) -> Option<u32> {
+        if self.running_dr_count == 0 {
+            None
+        } else {
+            Some(round_to_dr(
+                self.running_dr_sum as f64 / self.running_dr_count as f64,
+            ))
+        }
+    }
+
+    /// Whether analysis is still in flight (started, no album result yet).
+    pub fn is_analyzing(&self) -> bool {
+        self.analysis_start.is_some() && self.album_result.is_none()
     }
 
     pub fn completed_count(&self) -> usize {
@@ -161,6 +331,18 @@ impl App {
         self.ensure_visible();
     }
 
+    /// Record the track table height for this frame and bring the scroll
+    /// position back in range. Called by the renderer, so a terminal resized
+    /// down to nothing and back up again recovers its view of the selection.
+    pub fn set_visible_rows(&mut self, rows: usize) {
+        self.visible_rows = rows;
+        self.selected = self.selected.min(self.tracks.len().saturating_sub(1));
+        self.scroll_offset = self
+            .scroll_offset
+            .min(self.tracks.len().saturating_sub(rows));
+        self.ensure_visible();
+    }
+
     /// Adjust scroll_offset so that self.selected is within the visible window.
     fn ensure_visible(&mut self) {
         if self.visible_rows == 0 {
@@ -173,6 +355,24 @@ impl App {
         }
     }
 
+    pub fn select_next_album(&mut self) {
+        if !self.albums.is_empty() {
+            self.album_selected = (self.album_selected + 1).min(self.albums.len() - 1);
+        }
+    }
+
+    pub fn select_prev_album(&mut self) {
+        self.album_selected = self.album_selected.saturating_sub(1);
+    }
+
+    pub fn scroll_compare(&mut self, down: bool) {
+        self.compare_scroll = if down {
+            self.compare_scroll + 1
+        } else {
+            self.compare_scroll.saturating_sub(1)
+        };
+    }
+
     pub fn cycle_export_format(&mut self) {
         self.export_format = match self.export_format {
             ExportFormat::Text => ExportFormat::Json,
@@ -181,3 +381,58 @@ impl App {
         };
     }
 }
+
+/// One row of the compare view: a track of the shown album and its
+/// counterpart in the compared report. A side is `None` for a track found in
+/// only one of them.
+#[derive(Debug, Clone, Copy)]
+pub struct CompareRow<'a> {
+    pub left: Option<&'a TrackResult>,
+    pub right: Option<&'a TrackResult>,
+}
+
+impl CompareRow<'_> {
+    /// Compared DR minus shown DR: positive when the compared report has more
+    /// dynamic range. `None` unless both sides are present.
+    pub fn delta(&self) -> Option<i64> {
+        Some(self.right?.dr as i64 - self.left?.dr as i64)
+    }
+}
+
+/// Pair the tracks of `left` with those of `right`: by track number when both
+/// are tagged with one, otherwise by title (ignoring case). Rows follow
+/// `left`'s order; tracks of `right` left without a partner come last.
+pub fn compare_rows<'a>(left: &'a AlbumResult, right: &'a AlbumResult) -> Vec<CompareRow<'a>> {
+    let same = |a: &TrackResult, b: &TrackResult| match (a.track_number, b.track_number) {
+        (Some(x), Some(y)) => x == y,
+        _ => a.title.trim().eq_ignore_ascii_case(b.title.trim()),
+    };
+    let mut paired = vec![false; right.tracks.len()];
+    let mut rows: Vec<CompareRow> = left
+        .tracks
+        .iter()
+        .map(|track| {
+            let partner =
+                (0..right.tracks.len()).find(|&i| !paired[i] && same(track, &right.tracks[i]));
+            if let Some(i) = partner {
+                paired[i] = true;
+            }
+            CompareRow {
+                left: Some(track),
+                right: partner.map(|i| &right.tracks[i]),
+            }
+        })
+        .collect();
+    rows.extend(
+        right
+            .tracks
+            .iter()
+            .zip(paired)
+            .filter(|(_, paired)| !paired)
+            .map(|(track, _)| CompareRow {
+                left: None,
+                right: Some(track),
+            }),
+    );
+    rows
+}
@@ -2,86 +2,310 @@ pub mod app;
 pub mod ui;
 
 use std::io;
-use std::path::Path;
-use std::sync::mpsc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
 use crossterm::ExecutableCommand;
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
+use signal_hook::consts::{SIGINT, SIGTERM};
 
-use crate::analyzer::{analyze_directory_async, scan_audio_files};
+use crate::analyzer::{self, analyze_files_async, scan_audio_files_with_options, AnalysisOptions};
 use crate::cache;
-use crate::format;
-use crate::models::AnalysisEvent;
+use crate::error::DrError;
+use crate::format::{self, ExportFormat};
+use crate::models::{AlbumResult, AnalysisEvent};
 
-use app::{App, BenchmarkStats, ExportFormat, TrackStatus, TrackTiming, View};
+use app::{AlbumEntry, App, BenchmarkStats, Source, TrackStatus, TrackTiming, View};
 
-pub fn run(path: &Path, jobs: usize, regenerate: bool) -> Result<()> {
-    let files = scan_audio_files(path);
-    if files.is_empty() {
-        anyhow::bail!("No audio files found in {}", path.display());
+/// Run the TUI on `path`: a single audio file, an album directory, or (with
+/// `bulk`) a tree of album directories browsed from an album list.
+pub fn run(
+    path: &Path,
+    bulk: bool,
+    jobs: usize,
+    regenerate: bool,
+    options: &AnalysisOptions,
+    color: bool,
+    report_basename: &str,
+) -> Result<()> {
+    let source = if path.is_file() {
+        Source::File
+    } else if bulk {
+        Source::Bulk
+    } else {
+        Source::Directory
+    };
+
+    let mut app = App::new(Vec::new(), path.to_path_buf(), jobs, options.clone());
+    app.color = color;
+    app.report_basename = report_basename.to_string();
+    app.source = source;
+
+    let rx = if app.source == Source::Bulk {
+        app.albums = album_entries(&app, path, regenerate)?;
+        browse_albums(&mut app, regenerate)
+    } else {
+        let files = source_files(&app)?;
+        if files.is_empty() {
+            return Err(DrError::NoAudio(path.to_path_buf()).into());
+        }
+        app.reset_for_regeneration(file_names(&files));
+        app.analysis_start = None;
+        open_current(&mut app, regenerate)
+    };
+
+    // SIGINT/SIGTERM from outside (raw mode turns Ctrl-C into a key press)
+    // end the loop instead of killing the process with the terminal in raw mode
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let handlers = [SIGINT, SIGTERM]
+        .into_iter()
+        .map(|signal| signal_hook::flag::register(signal, Arc::clone(&interrupted)))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let result = {
+        let _guard = TerminalGuard::enter()?;
+        let backend = CrosstermBackend::new(io::stdout());
+        let mut terminal = Terminal::new(backend)?;
+        run_loop(&mut terminal, &mut app, rx, regenerate, &interrupted)
+    };
+
+    for id in handlers {
+        signal_hook::low_level::unregister(id);
     }
+    result
+}
 
-    let filenames: Vec<String> = files
-        .iter()
-        .filter_map(|p| p.file_name().and_then(|f| f.to_str()).map(String::from))
-        .collect();
+/// Raw mode and the alternate screen for as long as the guard lives. Dropping
+/// it restores the terminal on every exit path: normal return, `?` errors, and
+/// panics (a panic hook restores it first, so the message is readable).
+struct TerminalGuard;
 
-    let mut app = App::new(filenames, path.to_path_buf(), jobs);
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        enable_raw_mode()?;
+        let guard = TerminalGuard;
+        io::stdout().execute(EnterAlternateScreen)?;
 
-    // Check for cached report
-    let rx = if !regenerate {
-        if let Some(cached) = cache::load_cached_report(path) {
-            app.load_from_cache(cached);
-            // Create a dummy channel that will never receive
-            let (_tx, rx) = mpsc::channel::<AnalysisEvent>();
-            rx
-        } else {
-            spawn_analysis(&mut app, path, jobs)
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            default_hook(info);
+        }));
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // The hook can't be swapped back while unwinding; it's harmless then
+        if !std::thread::panicking() {
+            let _ = std::panic::take_hook();
         }
+        restore_terminal();
+    }
+}
+
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = io::stdout().execute(LeaveAlternateScreen);
+}
+
+/// The album directories under `path`, with their DR from cached reports
+/// unless those are bypassed.
+fn album_entries(app: &App, path: &Path, regenerate: bool) -> Result<Vec<AlbumEntry>> {
+    let reuse = !regenerate && app.options.matches_cached_report();
+    Ok(analyzer::scan_album_dirs(path)?
+        .into_iter()
+        .map(|dir| AlbumEntry {
+            name: dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| dir.display().to_string()),
+            dr: reuse
+                .then(|| cache::load_cached_report(&dir, &app.report_basename))
+                .flatten()
+                .map(|r| r.overall_dr),
+            path: dir,
+        })
+        .collect())
+}
+
+/// Show the bulk album list, previewing its first album.
+fn browse_albums(app: &mut App, regenerate: bool) -> mpsc::Receiver<AnalysisEvent> {
+    app.show(View::Albums);
+    preview_album(app, regenerate);
+    // Nothing is analyzed until an album is opened
+    let (_tx, rx) = mpsc::channel::<AnalysisEvent>();
+    rx
+}
+
+/// Open `input` from the open prompt in place of what is shown: an audio file,
+/// an album directory, or a directory of album directories (browsed as with
+/// `--bulk`). A leading `~/` is the home directory. On error nothing changes.
+fn open_path(
+    app: &mut App,
+    input: &str,
+    regenerate: bool,
+) -> Result<mpsc::Receiver<AnalysisEvent>> {
+    let input = input.trim();
+    if input.is_empty() {
+        anyhow::bail!("Enter a file or directory");
+    }
+    let path = expand_home(input);
+    if path.is_file() {
+        let names = file_names(std::slice::from_ref(&path));
+        app.open(path, Source::File, names);
+        return Ok(open_current(app, regenerate));
+    }
+    if !path.is_dir() {
+        anyhow::bail!("No such file or directory: {}", path.display());
+    }
+    let files = scan_audio_files_with_options(&path, &app.options)?;
+    if !files.is_empty() {
+        app.open(path, Source::Directory, file_names(&files));
+        return Ok(open_current(app, regenerate));
+    }
+    // Without subdirectories either, the directory has nothing to show
+    let albums = album_entries(app, &path, regenerate).unwrap_or_default();
+    if albums.is_empty() {
+        return Err(DrError::NoAudio(path).into());
+    }
+    app.open(path, Source::Bulk, Vec::new());
+    app.albums = albums;
+    Ok(browse_albums(app, regenerate))
+}
+
+/// `input` as a path, with a leading `~/` as the home directory.
+fn expand_home(input: &str) -> PathBuf {
+    match (input.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(input),
+    }
+}
+
+/// Load the report named in the compare prompt: a `.json` report file, or
+/// the cached report of an album directory. Returns where it was found.
+fn load_compare_report(app: &App, input: &str) -> Result<(PathBuf, AlbumResult)> {
+    let input = input.trim();
+    if input.is_empty() {
+        anyhow::bail!("Enter a report file or album directory");
+    }
+    let path = expand_home(input);
+    let (dir, basename) = if path.is_dir() {
+        (path.as_path(), app.report_basename.as_str())
     } else {
-        spawn_analysis(&mut app, path, jobs)
+        let is_json = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        let stem = path.file_stem().and_then(|s| s.to_str());
+        match (path.is_file() && is_json, stem) {
+            (true, Some(stem)) => (path.parent().unwrap_or(Path::new(".")), stem),
+            _ => anyhow::bail!("Not a JSON report or album directory: {}", path.display()),
+        }
     };
+    let report = cache::load_cached_report(dir, basename).ok_or_else(|| {
+        anyhow::anyhow!(
+            "No usable report at {} (missing, unreadable, stale, or a preview)",
+            cache::report_path(dir, basename, "json").display()
+        )
+    })?;
+    Ok((cache::report_path(dir, basename, "json"), report))
+}
 
-    // Setup terminal
-    enable_raw_mode()?;
-    io::stdout().execute(EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(io::stdout());
-    let mut terminal = Terminal::new(backend)?;
+/// The audio files behind the current view: the file itself in single-file
+/// mode, otherwise the current album directory's files.
+fn source_files(app: &App) -> Result<Vec<PathBuf>> {
+    match app.source {
+        Source::File => Ok(vec![app.path.clone()]),
+        Source::Directory | Source::Bulk => scan_audio_files_with_options(&app.path, &app.options),
+    }
+}
 
-    let result = run_loop(&mut terminal, &mut app, rx);
+fn file_names(files: &[PathBuf]) -> Vec<String> {
+    files
+        .iter()
+        .filter_map(|p| p.file_name().and_then(|f| f.to_str()).map(String::from))
+        .collect()
+}
 
-    // Restore terminal
-    disable_raw_mode()?;
-    io::stdout().execute(LeaveAlternateScreen)?;
+/// Show the current album from its cached report, or start analyzing it.
+fn open_current(app: &mut App, regenerate: bool) -> mpsc::Receiver<AnalysisEvent> {
+    if !regenerate && app.source != Source::File && app.options.matches_cached_report() {
+        if let Some(cached) = cache::load_cached_report(&app.path, &app.report_basename) {
+            app.load_from_cache(cached);
+            // Create a dummy channel that will never receive
+            let (_tx, rx) = mpsc::channel::<AnalysisEvent>();
+            return rx;
+        }
+    }
+    spawn_analysis(app)
+}
 
-    result
+/// Point the track table at the album selected in the bulk album list,
+/// filled in from its cached report when there is one. Nothing is analyzed.
+fn preview_album(app: &mut App, regenerate: bool) {
+    let Some(entry) = app.albums.get(app.album_selected) else {
+        return;
+    };
+    let path = entry.path.clone();
+    // An album that can't be listed shows no tracks
+    let files = scan_audio_files_with_options(&path, &app.options).unwrap_or_default();
+    app.switch_album(path, file_names(&files));
+    if !regenerate && app.options.matches_cached_report() {
+        if let Some(cached) = cache::load_cached_report(&app.path, &app.report_basename) {
+            app.load_from_cache(cached);
+        }
+    }
 }
 
-fn spawn_analysis(
-    app: &mut App,
-    path: &Path,
-    jobs: usize,
-) -> mpsc::Receiver<AnalysisEvent> {
+fn spawn_analysis(app: &mut App) -> mpsc::Receiver<AnalysisEvent> {
     let (tx, rx) = mpsc::channel::<AnalysisEvent>();
-    let analysis_path = path.to_path_buf();
+    let files = source_files(app).unwrap_or_default();
+    let jobs = app.jobs;
+    let options = app.options.clone();
     app.analysis_start = Some(Instant::now());
     std::thread::spawn(move || {
-        let _ = analyze_directory_async(&analysis_path, tx, jobs);
+        // Failures reach the UI as `Error` events; no album is built then
+        if !files.is_empty() {
+            let _ = analyze_files_async(files, tx, jobs, &options);
+        }
     });
     rx
 }
 
+/// Report content and file extension for an export of `result`. A single
+/// file exports its one-track report rather than an album table.
+fn export_content(app: &App, result: &AlbumResult) -> (String, &'static str) {
+    let single = match app.source {
+        Source::File => result.tracks.first(),
+        Source::Directory | Source::Bulk => None,
+    };
+    let format = app.export_format;
+    match (format, single) {
+        (ExportFormat::Text, Some(track)) => {
+            (format::format_table_single(track), format.extension())
+        }
+        (ExportFormat::Json, Some(track)) => {
+            (format::format_json_single(track), format.extension())
+        }
+        _ => format::render(result, format),
+    }
+}
+
 fn run_loop(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     app: &mut App,
     rx: mpsc::Receiver<AnalysisEvent>,
+    regenerate: bool,
+    interrupted: &AtomicBool,
 ) -> Result<()> {
     let mut rx = rx;
 
@@ -114,8 +338,9 @@ fn run_loop(
                             }
                         }
                     }
+                    app.record_track_dr(result.dr);
                     if let Some(track) = app.tracks.get_mut(index) {
-                        track.1 = TrackStatus::Complete(result);
+                        track.1 = TrackStatus::Complete(Box::new(result));
                     }
                 }
                 AnalysisEvent::AlbumCompleted { result } => {
@@ -142,8 +367,24 @@ fn run_loop(
                         });
                     }
 
-                    // Auto-save cache
-                    let _ = cache::save_report(&app.path, &result);
+                    // Auto-save cache (complete album directories measured
+                    // with options that give the standard report only)
+                    if result.failed_tracks == 0 {
+                        if app.source != Source::File && app.options.matches_cached_report() {
+                            let _ = cache::save_report(&app.path, &app.report_basename, &result);
+                        }
+                        app.record_album_dr(result.overall_dr);
+                    }
+
+                    // Rows follow the album's final (track-number) order
+                    if result.tracks.len() == app.tracks.len() {
+                        for (row, track) in app.tracks.iter_mut().zip(&result.tracks) {
+                            *row = (
+                                track.filename.clone(),
+                                TrackStatus::Complete(Box::new(track.clone())),
+                            );
+                        }
+                    }
 
                     app.album_title = result.album.clone();
                     app.album_result = Some(result);
@@ -162,70 +403,176 @@ fn run_loop(
                 if key.kind != KeyEventKind::Press {
                     continue;
                 }
+                if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    break;
+                }
                 match app.view {
                     View::Main => match key.code {
+                        KeyCode::Char('q') if app.is_analyzing() => {
+                            app.view = View::QuitConfirm;
+                        }
                         KeyCode::Char('q') => {
                             app.should_quit = true;
                             break;
                         }
-                        KeyCode::Char('e') => {
-                            if app.album_result.is_some() {
-                                app.view = View::Export;
-                                app.export_message = None;
-                            }
+                        KeyCode::Char('e') if app.album_result.is_some() => {
+                            app.view = View::Export;
+                            app.export_message = None;
                         }
                         KeyCode::Char('i') => {
-                            if app.album_result.is_some() {
-                                app.view = View::Info;
-                            }
+                            app.view = View::Info;
                         }
-                        KeyCode::Char('r') => {
-                            if app.album_result.is_some() {
-                                app.view = View::RegenerateConfirm;
-                            }
+                        KeyCode::Char('r') if app.album_result.is_some() => {
+                            app.view = View::RegenerateConfirm;
                         }
                         KeyCode::Char('a') => {
                             app.view = View::About;
                         }
+                        KeyCode::Char('p') => {
+                            app.show_true_peak = !app.show_true_peak;
+                        }
+                        KeyCode::Char('o') if !app.is_analyzing() => {
+                            app.prompt_open();
+                        }
+                        KeyCode::Char('c') if app.album_result.is_some() => {
+                            app.prompt_compare();
+                        }
                         KeyCode::Char('j') | KeyCode::Down => {
                             app.select_next();
                         }
                         KeyCode::Char('k') | KeyCode::Up => {
                             app.select_prev();
                         }
+                        KeyCode::Esc | KeyCode::Left | KeyCode::Char('h')
+                            if app.source == Source::Bulk =>
+                        {
+                            app.show(View::Albums);
+                        }
+                        _ => {}
+                    },
+                    View::Albums => match key.code {
+                        KeyCode::Char('q') if app.is_analyzing() => {
+                            app.view = View::QuitConfirm;
+                        }
+                        KeyCode::Char('q') => {
+                            app.should_quit = true;
+                            break;
+                        }
+                        // The track table belongs to the album being analyzed
+                        // until it finishes
+                        KeyCode::Char('j') | KeyCode::Down if !app.is_analyzing() => {
+                            app.select_next_album();
+                            preview_album(app, regenerate);
+                        }
+                        KeyCode::Char('k') | KeyCode::Up if !app.is_analyzing() => {
+                            app.select_prev_album();
+                            preview_album(app, regenerate);
+                        }
+                        KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                            if app.album_result.is_none()
+                                && !app.is_analyzing()
+                                && !app.tracks.is_empty()
+                            {
+                                rx = spawn_analysis(app);
+                            }
+                            app.show(View::Main);
+                        }
+                        KeyCode::Char('a') => {
+                            app.view = View::About;
+                        }
+                        KeyCode::Char('o') if !app.is_analyzing() => {
+                            app.prompt_open();
+                        }
+                        _ => {}
+                    },
+                    View::Open => match key.code {
+                        KeyCode::Esc => {
+                            app.view = app.home_view.clone();
+                        }
+                        KeyCode::Enter => {
+                            let input = app.open_input.clone();
+                            match open_path(app, &input, regenerate) {
+                                Ok(new_rx) => rx = new_rx,
+                                Err(e) => app.open_error = Some(format!("{:#}", e)),
+                            }
+                        }
+                        KeyCode::Backspace => {
+                            app.open_input.pop();
+                            app.open_error = None;
+                        }
+                        KeyCode::Char(c) => {
+                            app.open_input.push(c);
+                            app.open_error = None;
+                        }
+                        _ => {}
+                    },
+                    View::CompareOpen => match key.code {
+                        KeyCode::Esc => {
+                            app.view = app.home_view.clone();
+                        }
+                        KeyCode::Enter => {
+                            let input = app.open_input.clone();
+                            match load_compare_report(app, &input) {
+                                Ok((path, report)) => app.start_compare(path, report),
+                                Err(e) => app.open_error = Some(format!("{:#}", e)),
+                            }
+                        }
+                        KeyCode::Backspace => {
+                            app.open_input.pop();
+                            app.open_error = None;
+                        }
+                        KeyCode::Char(c) => {
+                            app.open_input.push(c);
+                            app.open_error = None;
+                        }
+                        _ => {}
+                    },
+                    View::Compare => match key.code {
+                        KeyCode::Esc | KeyCode::Char('q') => {
+                            app.view = app.home_view.clone();
+                        }
+                        KeyCode::Char('j') | KeyCode::Down => {
+                            app.scroll_compare(true);
+                        }
+                        KeyCode::Char('k') | KeyCode::Up => {
+                            app.scroll_compare(false);
+                        }
+                        KeyCode::Char('c') => {
+                            app.prompt_compare();
+                        }
                         _ => {}
                     },
                     View::About | View::Info => match key.code {
                         KeyCode::Esc | KeyCode::Char('q') => {
-                            app.view = View::Main;
+                            app.view = app.home_view.clone();
                         }
                         _ => {}
                     },
                     View::RegenerateConfirm => match key.code {
                         KeyCode::Char('y') | KeyCode::Enter => {
                             // Rescan files and regenerate
-                            let files = scan_audio_files(&app.path);
-                            let filenames: Vec<String> = files
-                                .iter()
-                                .filter_map(|p| {
-                                    p.file_name()
-                                        .and_then(|f| f.to_str())
-                                        .map(String::from)
-                                })
-                                .collect();
-                            let jobs = app.jobs;
-                            let path = app.path.clone();
-                            app.reset_for_regeneration(filenames);
-                            rx = spawn_analysis(app, &path, jobs);
+                            let files = source_files(app).unwrap_or_default();
+                            app.reset_for_regeneration(file_names(&files));
+                            rx = spawn_analysis(app);
                         }
                         KeyCode::Char('n') | KeyCode::Esc => {
-                            app.view = View::Main;
+                            app.view = app.home_view.clone();
+                        }
+                        _ => {}
+                    },
+                    View::QuitConfirm => match key.code {
+                        KeyCode::Char('y') => {
+                            app.should_quit = true;
+                            break;
+                        }
+                        KeyCode::Char('n') | KeyCode::Esc | KeyCode::Char('q') => {
+                            app.view = app.home_view.clone();
                         }
                         _ => {}
                     },
                     View::Export => match key.code {
                         KeyCode::Esc => {
-                            app.view = View::Main;
+                            app.view = app.home_view.clone();
                         }
                         KeyCode::Tab => {
                             app.cycle_export_format();
@@ -233,12 +580,8 @@ fn run_loop(
                         }
                         KeyCode::Enter => {
                             if let Some(ref result) = app.album_result {
-                                let (content, ext) = match app.export_format {
-                                    ExportFormat::Text => (format::format_table(result), "txt"),
-                                    ExportFormat::Json => (format::format_json(result), "json"),
-                                    ExportFormat::Csv => (format::format_csv(result), "csv"),
-                                };
-                                let output_path = app.path.join(format!("dr_report.{}", ext));
+                                let (content, ext) = export_content(app, result);
+                                let output_path = app.export_path(ext);
                                 match std::fs::write(&output_path, &content) {
                                     Ok(_) => {
                                         app.export_message = Some(format!(
@@ -259,10 +602,61 @@ fn run_loop(
             }
         }
 
-        if app.should_quit {
+        if app.should_quit || interrupted.load(Ordering::Relaxed) {
             break;
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::generate_album;
+
+    #[test]
+    fn test_open_path_switches_to_album_or_reports_error() {
+        let base = std::env::temp_dir().join(format!("dr-tui-open-{}", std::process::id()));
+        generate_album(&base.join("Album"), &[(440.0, 0.5, 6.0)], 8000);
+        let mut app = App::new(
+            Vec::new(),
+            base.join("Elsewhere"),
+            1,
+            AnalysisOptions::default(),
+        );
+
+        let missing = open_path(&mut app, &base.join("Nope").display().to_string(), false);
+        assert!(missing
+            .unwrap_err()
+            .to_string()
+            .starts_with("No such file or directory"));
+        assert_eq!(app.path, base.join("Elsewhere"));
+
+        // A directory of albums is browsed as with --bulk
+        open_path(&mut app, &format!("  {}  ", base.display()), false).unwrap();
+        assert_eq!(app.source, Source::Bulk);
+        assert_eq!(app.view, View::Albums);
+        assert_eq!(app.albums.len(), 1);
+
+        let rx = open_path(&mut app, &base.join("Album").display().to_string(), false).unwrap();
+        assert_eq!(
+            (app.source.clone(), app.path.clone()),
+            (Source::Directory, base.join("Album"))
+        );
+        assert!(app.albums.is_empty());
+        assert_eq!(app.tracks.len(), 1);
+        let finished = rx
+            .iter()
+            .any(|event| matches!(event, AnalysisEvent::AlbumCompleted { .. }));
+
+        let empty = base.join("Album").join("empty");
+        std::fs::create_dir(&empty).unwrap();
+        let no_audio = open_path(&mut app, &empty.display().to_string(), false);
+        std::fs::remove_dir_all(&base).unwrap();
+
+        assert!(finished);
+        assert!(crate::error::is_no_audio(&no_audio.unwrap_err()));
+        assert_eq!(app.path, base.join("Album"));
+    }
+}
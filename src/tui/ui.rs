@@ -1,11 +1,14 @@
 use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table};
+use ratatui::widgets::{
+    Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table,
+};
 use ratatui::Frame;
 
-use super::app::{App, ExportFormat, TrackStatus, View};
-use crate::format::format_duration;
+use super::app::{compare_rows, App, CompareRow, Source, TrackStatus, View};
+use crate::format::{display_db, format_contributing_tracks, format_duration};
+use crate::models::{Confidence, DrRating, DrScale, DrSeverity};
 
 const ACCENT: Color = Color::Cyan;
 const DIM: Color = Color::DarkGray;
@@ -25,17 +28,33 @@ pub fn render(frame: &mut Frame, app: &mut App) {
         .split(frame.area());
 
     render_header(frame, app, chunks[0]);
-    render_track_table(frame, app, chunks[1]);
+    if app.view == View::Compare {
+        render_compare_table(frame, app, chunks[1]);
+    } else if app.source == Source::Bulk {
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+            .split(chunks[1]);
+        render_album_list(frame, app, panes[0]);
+        render_track_table(frame, app, panes[1]);
+    } else {
+        render_track_table(frame, app, chunks[1]);
+    }
     render_summary(frame, app, chunks[2]);
     render_footer(frame, app, chunks[3]);
 
     // Overlays
     match app.view {
-        View::About => render_about_overlay(frame),
+        View::About => render_about_overlay(frame, app),
         View::Export => render_export_overlay(frame, app),
         View::Info => render_info_overlay(frame, app),
+        View::Open => render_open_overlay(frame, app, "Open File or Directory", "open"),
+        View::CompareOpen => {
+            render_open_overlay(frame, app, "Compare With Report or Album", "compare")
+        }
         View::RegenerateConfirm => render_regenerate_overlay(frame),
-        View::Main => {}
+        View::QuitConfirm => render_quit_overlay(frame),
+        View::Main | View::Albums | View::Compare => {}
     }
 }
 
@@ -77,10 +96,40 @@ fn render_header(frame: &mut Frame, app: &App, area: Rect) {
     frame.render_widget(paragraph, area);
 }
 
+fn render_album_list(frame: &mut Frame, app: &App, area: Rect) {
+    let items: Vec<ListItem> = app
+        .albums
+        .iter()
+        .map(|album| {
+            let mut spans = vec![Span::raw(album.name.as_str())];
+            if let Some(dr) = album.dr {
+                spans.push(Span::raw("  "));
+                spans.push(Span::styled(format!("DR{}", dr), dr_style(app, dr)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let focused = app.view == View::Albums || app.home_view == View::Albums;
+    let block = Block::default()
+        .title(Span::styled(
+            format!(" Albums ({}) ", app.albums.len()),
+            Style::default().fg(DIM),
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(if focused { ACCENT } else { DIM }));
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(Color::DarkGray));
+    let mut state = ListState::default().with_selected(Some(app.album_selected));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
 fn render_track_table(frame: &mut Frame, app: &mut App, area: Rect) {
     // 2 for borders, 1 for header
     let inner_height = area.height.saturating_sub(3) as usize;
-    app.visible_rows = inner_height;
+    app.set_visible_rows(inner_height);
 
     // Build a scroll indicator for the block title
     let total = app.tracks.len();
@@ -101,7 +150,12 @@ fn render_track_table(frame: &mut Frame, app: &mut App, area: Rect) {
         Cell::from("#").style(Style::default().fg(DIM)),
         Cell::from("Track").style(Style::default().fg(DIM)),
         Cell::from("DR").style(Style::default().fg(DIM)),
-        Cell::from("Peak").style(Style::default().fg(DIM)),
+        Cell::from(if app.show_true_peak {
+            "TP dBTP"
+        } else {
+            "Peak dBFS"
+        })
+        .style(Style::default().fg(DIM)),
         Cell::from("RMS").style(Style::default().fg(DIM)),
         Cell::from("Duration").style(Style::default().fg(DIM)),
         Cell::from("").style(Style::default().fg(DIM)),
@@ -109,15 +163,21 @@ fn render_track_table(frame: &mut Frame, app: &mut App, area: Rect) {
     .height(1);
 
     // Only render the visible slice of tracks
-    let end = (app.scroll_offset + inner_height).min(app.tracks.len());
-    let visible_slice = &app.tracks[app.scroll_offset..end];
+    let start = app.scroll_offset.min(app.tracks.len());
+    let end = (start + inner_height).min(app.tracks.len());
+    let visible_slice = &app.tracks[start..end];
 
     let rows: Vec<Row> = visible_slice
         .iter()
         .enumerate()
         .map(|(vi, (name, status))| {
             let actual_index = app.scroll_offset + vi;
-            let num = format!("{}", actual_index + 1);
+            let num = match status {
+                TrackStatus::Complete(result) => result.track_number,
+                _ => None,
+            }
+            .unwrap_or(actual_index as u32 + 1)
+            .to_string();
             let is_selected = actual_index == app.selected;
             let style = if is_selected {
                 Style::default().bg(Color::DarkGray)
@@ -160,12 +220,18 @@ fn render_track_table(frame: &mut Frame, app: &mut App, area: Rect) {
                 TrackStatus::Complete(result) => Row::new(vec![
                     Cell::from(num),
                     Cell::from(result.title.as_str()),
-                    Cell::from(format!("DR{}", result.dr))
-                        .style(Style::default().fg(dr_color(result.dr))),
-                    Cell::from(format!("{:.2}dB", result.peak_db)),
+                    Cell::from(format!("DR{}", result.dr)).style(dr_style(app, result.dr)),
+                    Cell::from(format!(
+                        "{:.2}",
+                        display_db(if app.show_true_peak {
+                            result.true_peak_db
+                        } else {
+                            result.peak_db
+                        })
+                    )),
                     Cell::from(format!("{:.2}dB", result.rms_db)),
                     Cell::from(format_duration(result.duration_secs)),
-                    Cell::from("\u{2713}").style(Style::default().fg(COMPLETE_COLOR)),
+                    confidence_cell(result.confidence),
                 ])
                 .style(style),
                 TrackStatus::Error(msg) => Row::new(vec![
@@ -208,28 +274,164 @@ fn render_track_table(frame: &mut Frame, app: &mut App, area: Rect) {
     frame.render_widget(table, area);
 }
 
+/// The album (A) and the compared report (B) side by side, one row per paired
+/// track with the DR change from A to B: green where B has more dynamic
+/// range, red where it has less. A track found in only one of them is marked
+/// in yellow, with the other side left blank.
+fn render_compare_table(frame: &mut Frame, app: &mut App, area: Rect) {
+    let (Some(album), Some(other)) = (&app.album_result, &app.compare) else {
+        return;
+    };
+    let rows = compare_rows(album, other);
+    // 2 for borders, 1 for header
+    let inner_height = area.height.saturating_sub(3) as usize;
+    let scroll = app
+        .compare_scroll
+        .min(rows.len().saturating_sub(inner_height));
+
+    let side = |track: Option<&crate::models::TrackResult>, index: usize| match track {
+        Some(t) => (
+            Cell::from(t.track_number.unwrap_or(index as u32 + 1).to_string()),
+            Cell::from(t.title.clone()),
+            Cell::from(format!("DR{}", t.dr)).style(dr_style(app, t.dr)),
+        ),
+        None => (
+            Cell::from(""),
+            Cell::from("\u{2014}").style(Style::default().fg(DIM)),
+            Cell::from(""),
+        ),
+    };
+    let delta_cell = |row: &CompareRow| match (row.delta(), row.left) {
+        (Some(0), _) => Cell::from("=").style(Style::default().fg(DIM)),
+        (Some(d), _) => Cell::from(format!("{:+}", d)).style(Style::default().fg(if d > 0 {
+            COMPLETE_COLOR
+        } else {
+            ERROR_COLOR
+        })),
+        (None, Some(_)) => Cell::from("A only").style(Style::default().fg(PROGRESS_COLOR)),
+        (None, None) => Cell::from("B only").style(Style::default().fg(PROGRESS_COLOR)),
+    };
+
+    let table_rows: Vec<Row> = rows
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(inner_height)
+        .map(|(i, row)| {
+            let (a_num, a_title, a_dr) = side(row.left, i);
+            let (b_num, b_title, b_dr) = side(row.right, i);
+            let style = if row.delta().is_none() {
+                Style::default().fg(PROGRESS_COLOR)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                a_num,
+                a_title,
+                a_dr,
+                delta_cell(row),
+                b_dr,
+                b_title,
+                b_num,
+            ])
+            .style(style)
+        })
+        .collect();
+
+    let header = Row::new(
+        ["#", "A", "DR", "\u{0394}", "DR", "B", "#"]
+            .map(|h| Cell::from(h).style(Style::default().fg(DIM))),
+    );
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Min(10),
+        Constraint::Length(5),
+        Constraint::Length(7),
+        Constraint::Length(5),
+        Constraint::Min(10),
+        Constraint::Length(4),
+    ];
+
+    let a_name = album
+        .album
+        .clone()
+        .unwrap_or_else(|| app.path.display().to_string());
+    let b_name = other
+        .album
+        .clone()
+        .unwrap_or_else(|| app.compare_path.display().to_string());
+    let block = Block::default()
+        .title(Span::styled(
+            format!(" A: {}  vs  B: {} ", a_name, b_name),
+            Style::default().fg(DIM),
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(ACCENT));
+    frame.render_widget(
+        Table::new(table_rows, widths).header(header).block(block),
+        area,
+    );
+    app.compare_scroll = scroll;
+}
+
 fn render_summary(frame: &mut Frame, app: &App, area: Rect) {
     let completed = app.completed_count();
     let total = app.tracks.len();
 
+    let compared = app.album_result.as_ref().zip(app.compare.as_ref());
+    if let (View::Compare, Some((album, other))) = (&app.view, compared) {
+        let rows = compare_rows(album, other);
+        let matched = rows.iter().filter(|r| r.delta().is_some()).count();
+        let text = format!(
+            "Overall DR: A DR{} \u{2192} B DR{} ({:+})  |  {} matched, {} only in A, {} only in B",
+            album.overall_dr,
+            other.overall_dr,
+            other.overall_dr as i64 - album.overall_dr as i64,
+            matched,
+            rows.iter().filter(|r| r.right.is_none()).count(),
+            rows.iter().filter(|r| r.left.is_none()).count(),
+        );
+        let paragraph = Paragraph::new(text).alignment(Alignment::Center).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(ACCENT)),
+        );
+        frame.render_widget(paragraph, area);
+        return;
+    }
+
     let dr_text = if let Some(ref album) = app.album_result {
-        format!("Overall DR: DR{}", album.overall_dr)
-    } else if completed > 0 {
-        // Compute running average
-        let sum: u32 = app
-            .tracks
-            .iter()
-            .filter_map(|(_, s)| match s {
-                TrackStatus::Complete(r) => Some(r.dr),
-                _ => None,
-            })
-            .sum();
-        format!("Overall DR: ~DR{}", sum / completed as u32)
+        format!(
+            "Overall DR: DR{} \u{2014} {}{}",
+            album.overall_dr,
+            DrRating::from_dr(album.overall_dr, &app.options.dr_scale).name(),
+            format_contributing_tracks(album)
+                .map(|c| format!(" ({})", c))
+                .unwrap_or_default()
+        )
+    } else if let Some(running) = app.running_dr() {
+        format!("Overall DR: ~DR{} (estimated)", running)
     } else {
         "Overall DR: --".to_string()
     };
 
-    let text = format!("{} ({}/{} complete)", dr_text, completed, total);
+    let mut text = format!("{} ({}/{} complete)", dr_text, completed, total);
+    if let Some(ref bench) = app.benchmark {
+        text.push_str(&format!(
+            "  |  {:.1} MB in {:.2}s  |  {:.2}s/track  |  {:.1} MB/s",
+            bench.total_mb(),
+            bench.total_elapsed.as_secs_f64(),
+            bench.avg_per_track().as_secs_f64(),
+            bench.mb_per_sec(),
+        ));
+    } else if let Some(start) = app.analysis_start {
+        if app.album_result.is_none() {
+            text.push_str(&format!(
+                "  |  Elapsed: {:.1}s",
+                start.elapsed().as_secs_f64()
+            ));
+        }
+    }
     let paragraph = Paragraph::new(text)
         .alignment(Alignment::Center)
         .block(
@@ -242,14 +444,25 @@ fn render_summary(frame: &mut Frame, app: &App, area: Rect) {
 
 fn render_footer(frame: &mut Frame, app: &App, area: Rect) {
     let keys = match app.view {
+        View::Main if app.source == Source::Bulk => {
+            if app.album_result.is_some() {
+                "[Esc] albums  [e]xport  [i]nfo  [p]eak/true peak  [c]ompare  [r]egenerate  [o]pen  [a]bout  [q]uit"
+            } else {
+                "[Esc] albums  [i]nfo  [p]eak/true peak  [a]bout  [q]uit"
+            }
+        }
         View::Main => {
             if app.album_result.is_some() {
-                "[e]xport  [i]nfo  [r]egenerate  [a]bout  [q]uit"
+                "[e]xport  [i]nfo  [p]eak/true peak  [c]ompare  [r]egenerate  [o]pen  [a]bout  [q]uit"
             } else {
-                "[a]bout  [q]uit"
+                "[i]nfo  [p]eak/true peak  [a]bout  [q]uit"
             }
         }
-        View::RegenerateConfirm => "[y]es  [n]o",
+        View::Albums => "[j/k] select album  [Enter] open  [o]pen path  [a]bout  [q]uit",
+        View::Open => "[Enter] open  [Esc] cancel",
+        View::CompareOpen => "[Enter] compare  [Esc] cancel",
+        View::Compare => "[j/k] scroll  [c]ompare with another  [Esc] close",
+        View::RegenerateConfirm | View::QuitConfirm => "[y]es  [n]o",
         View::About | View::Export | View::Info => "[Esc] close",
     };
     let footer = Paragraph::new(keys)
@@ -258,11 +471,13 @@ fn render_footer(frame: &mut Frame, app: &App, area: Rect) {
     frame.render_widget(footer, area);
 }
 
-fn render_about_overlay(frame: &mut Frame) {
-    let area = centered_rect(40, 10, frame.area());
+fn render_about_overlay(frame: &mut Frame, app: &App) {
+    let legend = app.color.then(|| dr_legend(app.options.dr_scale));
+    let height = if legend.is_some() { 14 } else { 12 };
+    let area = centered_rect(40, height, frame.area());
     frame.render_widget(Clear, area);
 
-    let text = vec![
+    let mut text = vec![
         Line::from(Span::styled(
             "DR Meter",
             Style::default()
@@ -275,8 +490,15 @@ fn render_about_overlay(frame: &mut Frame) {
         Line::from("Dynamic range analyzer for audio files."),
         Line::from("Uses the Pleasurize Music / DR Database algorithm."),
         Line::from(""),
-        Line::from(Span::styled("[Esc] close", Style::default().fg(DIM))),
     ];
+    if let Some(legend) = legend {
+        text.extend([legend, Line::from("")]);
+    }
+    text.extend([confidence_legend(), Line::from("")]);
+    text.push(Line::from(Span::styled(
+        "[Esc] close",
+        Style::default().fg(DIM),
+    )));
 
     let block = Block::default()
         .title(" About ")
@@ -289,23 +511,65 @@ fn render_about_overlay(frame: &mut Frame) {
     frame.render_widget(paragraph, area);
 }
 
-fn render_export_overlay(frame: &mut Frame, app: &App) {
-    let area = centered_rect(50, 12, frame.area());
+/// The path prompt of the open and compare dialogs.
+fn render_open_overlay(frame: &mut Frame, app: &App, heading: &str, action: &str) {
+    let area = centered_rect(60, 9, frame.area());
     frame.render_widget(Clear, area);
 
-    let format_name = match app.export_format {
-        ExportFormat::Text => "Text (DR Database table)",
-        ExportFormat::Json => "JSON",
-        ExportFormat::Csv => "CSV",
-    };
+    // The end of a long path is the part being typed
+    let room = area.width.saturating_sub(4) as usize;
+    let chars = app.open_input.chars().count();
+    let shown: String = app
+        .open_input
+        .chars()
+        .skip((chars + 1).saturating_sub(room))
+        .collect();
 
-    let ext = match app.export_format {
-        ExportFormat::Text => "txt",
-        ExportFormat::Json => "json",
-        ExportFormat::Csv => "csv",
-    };
+    let mut text = vec![
+        Line::from(Span::styled(
+            heading.to_string(),
+            Style::default().fg(ACCENT).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(shown, Style::default().fg(Color::White)),
+            Span::styled("_", Style::default().fg(ACCENT)),
+        ])
+        .alignment(Alignment::Left),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("[Enter] {}  [Esc] cancel", action),
+            Style::default().fg(DIM),
+        )),
+    ];
+    if let Some(ref error) = app.open_error {
+        text.push(Line::from(Span::styled(
+            error.as_str(),
+            Style::default().fg(ERROR_COLOR),
+        )));
+    }
+
+    let block = Block::default()
+        .title(if app.view == View::CompareOpen {
+            " Compare "
+        } else {
+            " Open "
+        })
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(ACCENT));
+
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .block(block);
+    frame.render_widget(paragraph, area);
+}
+
+fn render_export_overlay(frame: &mut Frame, app: &App) {
+    let area = centered_rect(50, 12, frame.area());
+    frame.render_widget(Clear, area);
 
-    let output_path = app.path.join(format!("dr_report.{}", ext));
+    let format_name = app.export_format.name();
+    let output_path = app.export_path(app.export_format.extension());
 
     let mut text = vec![
         Line::from(Span::styled(
@@ -418,6 +682,23 @@ fn render_info_overlay(frame: &mut Frame, app: &App) {
         ]));
     } else {
         text.push(Line::from("Analysis in progress..."));
+        if let Some(start) = app.analysis_start {
+            text.push(Line::from(""));
+            text.push(Line::from(vec![
+                Span::styled("Elapsed: ", Style::default().fg(DIM)),
+                Span::styled(
+                    format!("{:.1}s", start.elapsed().as_secs_f64()),
+                    Style::default().fg(Color::White),
+                ),
+            ]));
+            text.push(Line::from(vec![
+                Span::styled("Tracks: ", Style::default().fg(DIM)),
+                Span::styled(
+                    format!("{}/{}", app.completed_count(), app.tracks.len()),
+                    Style::default().fg(Color::White),
+                ),
+            ]));
+        }
     }
 
     text.push(Line::from(""));
@@ -467,17 +748,288 @@ fn render_regenerate_overlay(frame: &mut Frame) {
     frame.render_widget(paragraph, area);
 }
 
+fn render_quit_overlay(frame: &mut Frame) {
+    let area = centered_rect(50, 8, frame.area());
+    frame.render_widget(Clear, area);
+
+    let text = vec![
+        Line::from(Span::styled(
+            "Quit",
+            Style::default().fg(ACCENT).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from("Analysis in progress \u{2014} quit anyway?"),
+        Line::from("Unfinished results will not be saved."),
+        Line::from(""),
+        Line::from(Span::styled("[y]es  [n]o", Style::default().fg(DIM))),
+    ];
+
+    let block = Block::default()
+        .title(" Confirm ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(ACCENT));
+
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .block(block);
+    frame.render_widget(paragraph, area);
+}
+
 fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
     let x = area.x + area.width.saturating_sub(width) / 2;
     let y = area.y + area.height.saturating_sub(height) / 2;
     Rect::new(x, y, width.min(area.width), height.min(area.height))
 }
 
-/// Color-code DR values: high DR = green, medium = yellow, low = red.
-fn dr_color(dr: u32) -> Color {
-    match dr {
-        0..=7 => Color::Red,
-        8..=11 => Color::Yellow,
-        _ => Color::Green,
+/// Style for a DR value: color-coded by severity unless color is disabled.
+fn dr_style(app: &App, dr: u32) -> Style {
+    if app.color {
+        Style::default().fg(severity_color(app.options.dr_scale.classify(dr)))
+    } else {
+        Style::default()
+    }
+}
+
+/// Status mark of a finished track, by the confidence in its DR.
+fn confidence_mark(confidence: Confidence) -> (&'static str, Color) {
+    match confidence {
+        Confidence::High => ("\u{2713}", COMPLETE_COLOR),
+        Confidence::Medium => ("~", Color::Yellow),
+        Confidence::Low => ("!", ERROR_COLOR),
+    }
+}
+
+fn confidence_cell(confidence: Confidence) -> Cell<'static> {
+    let (mark, color) = confidence_mark(confidence);
+    Cell::from(mark).style(Style::default().fg(color))
+}
+
+/// What the status marks of finished tracks mean.
+fn confidence_legend() -> Line<'static> {
+    let mut spans = Vec::new();
+    for confidence in [Confidence::High, Confidence::Medium, Confidence::Low] {
+        let (mark, color) = confidence_mark(confidence);
+        if !spans.is_empty() {
+            spans.push(Span::raw("  "));
+        }
+        spans.push(Span::styled(mark, Style::default().fg(color)));
+        spans.push(Span::raw(format!(" {}", confidence.name())));
+    }
+    spans.push(Span::raw(" confidence"));
+    Line::from(spans)
+}
+
+/// The DR values of each color on `scale`, in its colors.
+fn dr_legend(scale: DrScale) -> Line<'static> {
+    let mut spans = Vec::new();
+    for severity in [DrSeverity::Low, DrSeverity::Medium, DrSeverity::High] {
+        if !spans.is_empty() {
+            spans.push(Span::raw("  "));
+        }
+        spans.push(Span::styled(
+            scale.range_label(severity),
+            Style::default().fg(severity_color(severity)),
+        ));
+    }
+    Line::from(spans)
+}
+
+/// Color for a DR severity: high = green, medium = yellow, low = red.
+fn severity_color(severity: DrSeverity) -> Color {
+    match severity {
+        DrSeverity::Low => Color::Red,
+        DrSeverity::Medium => Color::Yellow,
+        DrSeverity::High => Color::Green,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::AnalysisOptions;
+    use std::path::PathBuf;
+
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn draw(terminal: &mut Terminal<TestBackend>, app: &mut App) {
+        terminal.draw(|frame| render(frame, app)).unwrap();
+    }
+
+    #[test]
+    fn test_render_survives_tiny_terminals() {
+        let names = (1..=30).map(|i| format!("{:02} Track.flac", i)).collect();
+        let mut app = App::new(
+            names,
+            PathBuf::from("/music"),
+            1,
+            AnalysisOptions::default(),
+        );
+        let mut terminal = Terminal::new(TestBackend::new(80, 40)).unwrap();
+        draw(&mut terminal, &mut app);
+        for _ in 0..25 {
+            app.select_next();
+        }
+
+        app.source = Source::Bulk;
+        let views = [
+            View::Main,
+            View::Albums,
+            View::About,
+            View::Export,
+            View::Info,
+            View::Open,
+            View::CompareOpen,
+            View::Compare,
+            View::RegenerateConfirm,
+            View::QuitConfirm,
+        ];
+        for (width, height) in [(0, 0), (80, 0), (0, 24), (1, 1), (10, 3), (80, 5), (80, 12)] {
+            terminal.backend_mut().resize(width, height);
+            terminal.resize(Rect::new(0, 0, width, height)).unwrap();
+            for view in &views {
+                app.view = view.clone();
+                draw(&mut terminal, &mut app);
+            }
+        }
+
+        // Back to a usable size, the selection is scrolled into view again
+        app.view = View::Main;
+        terminal.backend_mut().resize(80, 20);
+        terminal.resize(Rect::new(0, 0, 80, 20)).unwrap();
+        draw(&mut terminal, &mut app);
+        assert_eq!(app.selected, 25);
+        assert!(app.scroll_offset <= 25 && 25 < app.scroll_offset + app.visible_rows);
+    }
+
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        buffer.content().iter().map(|cell| cell.symbol()).collect()
+    }
+
+    #[test]
+    fn test_render_bulk_album_list() {
+        use super::super::app::AlbumEntry;
+
+        let mut app = App::new(
+            vec!["01 Intro.flac".to_string()],
+            PathBuf::from("/music/Second"),
+            1,
+            AnalysisOptions::default(),
+        );
+        app.source = Source::Bulk;
+        app.albums = ["First", "Second"]
+            .iter()
+            .zip([Some(12), None])
+            .map(|(name, dr)| AlbumEntry {
+                path: PathBuf::from("/music").join(name),
+                name: name.to_string(),
+                dr,
+            })
+            .collect();
+        app.album_selected = 1;
+        app.show(View::Albums);
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        draw(&mut terminal, &mut app);
+        let text = screen(&terminal);
+        assert!(text.contains("Albums (2)"));
+        assert!(text.contains("First  DR12"));
+        assert!(text.contains("01 Intro.flac"));
+        assert!(text.contains("[Enter] open"));
+    }
+
+    #[test]
+    fn test_about_shows_color_legend() {
+        let mut app = App::new(
+            Vec::new(),
+            PathBuf::from("/music"),
+            1,
+            AnalysisOptions::default(),
+        );
+        app.view = View::About;
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        draw(&mut terminal, &mut app);
+        assert!(screen(&terminal).contains("DR7 and below  DR8-13  DR14+"));
+        assert!(screen(&terminal).contains("\u{2713} high  ~ medium  ! low confidence"));
+
+        app.color = false;
+        draw(&mut terminal, &mut app);
+        assert!(!screen(&terminal).contains("DR14+"));
+    }
+
+    #[test]
+    fn test_render_compare_view_pairs_tracks() {
+        use crate::models::{AlbumResult, TrackResult};
+
+        let album = |album: &str, tracks: &[(Option<u32>, &str, u32)]| AlbumResult {
+            album: Some(album.to_string()),
+            tracks: tracks
+                .iter()
+                .map(|&(track_number, title, dr)| TrackResult {
+                    track_number,
+                    title: title.to_string(),
+                    dr,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let original = album(
+            "Original",
+            &[
+                (Some(1), "Intro", 8),
+                (Some(2), "Song", 9),
+                (None, "Demo", 11),
+            ],
+        );
+        let remaster = album(
+            "Remaster",
+            &[
+                (Some(2), "Song", 6),
+                (Some(1), "Intro", 10),
+                (Some(3), "Bonus", 7),
+            ],
+        );
+
+        let rows = compare_rows(&original, &remaster);
+        let deltas: Vec<_> = rows.iter().map(|r| r.delta()).collect();
+        assert_eq!(deltas, [Some(2), Some(-3), None, None]);
+        assert_eq!(rows[2].left.map(|t| t.title.as_str()), Some("Demo"));
+        assert_eq!(rows[3].right.map(|t| t.title.as_str()), Some("Bonus"));
+
+        let mut app = App::new(
+            Vec::new(),
+            PathBuf::from("/music/Original"),
+            1,
+            AnalysisOptions::default(),
+        );
+        app.album_result = Some(original);
+        app.start_compare(PathBuf::from("/music/Remaster/dr_report.json"), remaster);
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        draw(&mut terminal, &mut app);
+        let text = screen(&terminal);
+        assert!(text.contains("A: Original  vs  B: Remaster"));
+        assert!(text.contains("A only"));
+        assert!(text.contains("B only"));
+        assert!(text.contains("A DR0 \u{2192} B DR0 (+0)  |  2 matched, 1 only in A, 1 only in B"));
+    }
+
+    #[test]
+    fn test_render_after_track_list_shrinks() {
+        let names = (1..=30).map(|i| format!("{:02} Track.flac", i)).collect();
+        let mut app = App::new(
+            names,
+            PathBuf::from("/music"),
+            1,
+            AnalysisOptions::default(),
+        );
+        app.selected = 29;
+        app.scroll_offset = 20;
+        app.tracks.truncate(3);
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        draw(&mut terminal, &mut app);
+        assert_eq!(app.selected, 2);
+        assert_eq!(app.scroll_offset, 0);
     }
 }
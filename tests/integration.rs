@@ -1,58 +1,602 @@
-use std::io::Write;
 use std::path::PathBuf;
 
 use assert_cmd::cargo::cargo_bin_cmd;
 use dr::analyzer;
 use dr::cache;
 use dr::format;
-use dr::models::{AlbumResult, TrackResult};
+use dr::models::{AlbumResult, Confidence, TrackResult};
+use dr::signals;
+use dr::testutil::{generate_album, generate_pink_noise, generate_sine_wav};
+use predicates::prelude::*;
 
-/// Generate a WAV file with a pure sine wave at a given frequency and amplitude.
-/// Returns the path to the temporary WAV file.
-fn generate_sine_wav(
+// --- Ogg FLAC fixture helpers ---
+
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &b in data {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &b in data {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+fn ogg_crc(data: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for &b in data {
+        crc ^= (b as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Write a single-packet Ogg page.
+fn write_ogg_page(
+    out: &mut Vec<u8>,
+    header_type: u8,
+    granule: u64,
+    serial: u32,
+    seq: u32,
+    packet: &[u8],
+) {
+    let mut lacing = vec![255u8; packet.len() / 255];
+    lacing.push((packet.len() % 255) as u8);
+
+    let start = out.len();
+    out.extend_from_slice(b"OggS");
+    out.push(0);
+    out.push(header_type);
+    out.extend_from_slice(&granule.to_le_bytes());
+    out.extend_from_slice(&serial.to_le_bytes());
+    out.extend_from_slice(&seq.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.push(lacing.len() as u8);
+    out.extend_from_slice(&lacing);
+    out.extend_from_slice(packet);
+
+    let crc = ogg_crc(&out[start..]);
+    out[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Encode one mono 16-bit FLAC frame using a verbatim subframe.
+fn flac_frame(frame_number: u32, samples: &[i16]) -> Vec<u8> {
+    let mut frame = vec![0xff, 0xf8, 0x79, 0x08];
+    // Frame number, UTF-8 coded (up to 11 bits is plenty for test fixtures)
+    if frame_number < 0x80 {
+        frame.push(frame_number as u8);
+    } else {
+        frame.push(0xc0 | (frame_number >> 6) as u8);
+        frame.push(0x80 | (frame_number & 0x3f) as u8);
+    }
+    frame.extend_from_slice(&((samples.len() - 1) as u16).to_be_bytes());
+    frame.push(crc8(&frame));
+
+    frame.push(0x02); // verbatim subframe, no wasted bits
+    for &s in samples {
+        frame.extend_from_slice(&s.to_be_bytes());
+    }
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&crc.to_be_bytes());
+    frame
+}
+
+/// Generate a chained Ogg FLAC file: one logical stream per segment, each a mono
+/// 16-bit sine of `(frequency, amplitude, duration_secs)`, laid end to end.
+fn generate_chained_ogg_flac(
+    dir: &std::path::Path,
+    filename: &str,
+    segments: &[(f64, f32, f64)],
+    sample_rate: u32,
+) -> PathBuf {
+    generate_ogg_flac_with_corrupt_frames(dir, filename, segments, sample_rate, &[])
+}
+
+/// Like `generate_chained_ogg_flac`, but the FLAC frames numbered in `corrupt`
+/// (within each segment) get an invalid subframe header, so the container is
+/// intact but those frames fail to decode.
+fn generate_ogg_flac_with_corrupt_frames(
+    dir: &std::path::Path,
+    filename: &str,
+    segments: &[(f64, f32, f64)],
+    sample_rate: u32,
+    corrupt: &[usize],
+) -> PathBuf {
+    generate_ogg_flac(dir, filename, segments, sample_rate, corrupt, &[])
+}
+
+/// Like `generate_chained_ogg_flac`, with `comments` (e.g. "ALBUM=X") in the
+/// Vorbis comment block of every stream but the first, whose block is empty.
+/// The tags only turn up once decoding reaches the second stream.
+fn generate_late_tagged_ogg_flac(
+    dir: &std::path::Path,
+    filename: &str,
+    segments: &[(f64, f32, f64)],
+    sample_rate: u32,
+    comments: &[&str],
+) -> PathBuf {
+    let mut per_stream = vec![comments; segments.len()];
+    per_stream[0] = &[];
+    generate_ogg_flac(dir, filename, segments, sample_rate, &[], &per_stream)
+}
+
+/// Like `generate_chained_ogg_flac`, with `comments` (e.g. "ALBUM=X") in each
+/// stream's Vorbis comment block.
+fn generate_tagged_ogg_flac(
+    dir: &std::path::Path,
+    filename: &str,
+    segments: &[(f64, f32, f64)],
+    sample_rate: u32,
+    comments: &[&str],
+) -> PathBuf {
+    generate_ogg_flac(
+        dir,
+        filename,
+        segments,
+        sample_rate,
+        &[],
+        &vec![comments; segments.len()],
+    )
+}
+
+fn generate_ogg_flac(
     dir: &std::path::Path,
     filename: &str,
-    frequency: f64,
-    amplitude: f32,
-    duration_secs: f64,
+    segments: &[(f64, f32, f64)],
     sample_rate: u32,
+    corrupt: &[usize],
+    comments: &[&[&str]],
+) -> PathBuf {
+    const BLOCK: usize = 4096;
+    let mut out = Vec::new();
+
+    for (serial, &(frequency, amplitude, duration_secs)) in segments.iter().enumerate() {
+        let serial = serial as u32 + 1;
+        let num_samples = (sample_rate as f64 * duration_secs) as usize;
+
+        // Identification packet: Ogg FLAC mapping header + STREAMINFO
+        let mut id = vec![0x7f];
+        id.extend_from_slice(b"FLAC");
+        id.extend_from_slice(&[1, 0]);
+        id.extend_from_slice(&1u16.to_be_bytes());
+        id.extend_from_slice(b"fLaC");
+        id.extend_from_slice(&[0x00, 0x00, 0x00, 34]);
+        id.extend_from_slice(&(BLOCK as u16).to_be_bytes());
+        id.extend_from_slice(&(BLOCK as u16).to_be_bytes());
+        id.extend_from_slice(&[0; 6]);
+        let packed: u64 = ((sample_rate as u64) << 44) | (15u64 << 36) | num_samples as u64;
+        id.extend_from_slice(&packed.to_be_bytes());
+        id.extend_from_slice(&[0; 16]);
+
+        // Vorbis comment packet (last metadata block)
+        let comments = comments
+            .get(serial as usize - 1)
+            .copied()
+            .unwrap_or_default();
+        let vendor = b"dr-test";
+        let mut comment = Vec::new();
+        comment.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        comment.extend_from_slice(vendor);
+        comment.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for text in comments {
+            comment.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            comment.extend_from_slice(text.as_bytes());
+        }
+        let mut tags = vec![0x84];
+        tags.extend_from_slice(&(comment.len() as u32).to_be_bytes()[1..]);
+        tags.extend_from_slice(&comment);
+
+        let mut seq = 0;
+        write_ogg_page(&mut out, 0x02, 0, serial, seq, &id);
+        seq += 1;
+        write_ogg_page(&mut out, 0x00, 0, serial, seq, &tags);
+        seq += 1;
+
+        let samples: Vec<i16> = (0..num_samples)
+            .map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                let v = amplitude * (2.0 * std::f64::consts::PI * frequency * t).sin() as f32;
+                (v * i16::MAX as f32) as i16
+            })
+            .collect();
+
+        let chunks: Vec<&[i16]> = samples.chunks(BLOCK).collect();
+        let mut granule = 0u64;
+        for (n, chunk) in chunks.iter().enumerate() {
+            granule += chunk.len() as u64;
+            let header_type = if n + 1 == chunks.len() { 0x04 } else { 0x00 };
+            let mut frame = flac_frame(n as u32, chunk);
+            if corrupt.contains(&n) {
+                // Set the subframe padding bit, which must be zero
+                let subframe = if n < 0x80 { 8 } else { 9 };
+                frame[subframe] |= 0x80;
+            }
+            write_ogg_page(&mut out, header_type, granule, serial, seq, &frame);
+            seq += 1;
+        }
+    }
+
+    let path = dir.join(filename);
+    std::fs::write(&path, out).unwrap();
+    path
+}
+
+/// Generate a native mono 16-bit FLAC file with a (non-CD) CUESHEET block
+/// marking one track per segment of `(frequency, amplitude, duration_secs)`.
+fn generate_flac_with_cuesheet(
+    dir: &std::path::Path,
+    filename: &str,
+    segments: &[(f64, f32, f64)],
+) -> PathBuf {
+    const BLOCK: usize = 4096;
+    const RATE: u32 = 44100; // the rate coded in `flac_frame` headers
+    let (samples, starts) = sine_segments(segments, RATE);
+
+    let mut out = b"fLaC".to_vec();
+    out.extend_from_slice(&[0x00, 0x00, 0x00, 34]);
+    out.extend_from_slice(&(BLOCK as u16).to_be_bytes());
+    out.extend_from_slice(&(BLOCK as u16).to_be_bytes());
+    out.extend_from_slice(&[0; 6]);
+    let packed: u64 = ((RATE as u64) << 44) | (15u64 << 36) | samples.len() as u64;
+    out.extend_from_slice(&packed.to_be_bytes());
+    out.extend_from_slice(&[0; 16]);
+
+    let mut cuesheet = vec![0u8; 128 + 8 + 1 + 258];
+    cuesheet.push(starts.len() as u8 + 1);
+    let mut track = |offset: u64, number: u8, indices: u8| {
+        cuesheet.extend_from_slice(&offset.to_be_bytes());
+        cuesheet.push(number);
+        cuesheet.extend_from_slice(&[0; 12 + 2 + 12]);
+        cuesheet.push(indices);
+        if indices > 0 {
+            cuesheet.extend_from_slice(&0u64.to_be_bytes());
+            cuesheet.extend_from_slice(&[1, 0, 0, 0]);
+        }
+    };
+    for (n, &start) in starts.iter().enumerate() {
+        track(start as u64, n as u8 + 1, 1);
+    }
+    track(samples.len() as u64, 255, 0); // lead-out
+    out.push(0x85); // last metadata block, CUESHEET
+    out.extend_from_slice(&(cuesheet.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(&cuesheet);
+
+    for (n, chunk) in samples.chunks(BLOCK).enumerate() {
+        out.extend_from_slice(&flac_frame(n as u32, chunk));
+    }
+
+    let path = dir.join(filename);
+    std::fs::write(&path, out).unwrap();
+    path
+}
+
+/// Mono 16-bit samples at `rate` of consecutive `(frequency, amplitude,
+/// duration_secs)` sines, with the frame at which each segment starts.
+fn sine_segments(segments: &[(f64, f32, f64)], rate: u32) -> (Vec<i16>, Vec<usize>) {
+    let mut samples: Vec<i16> = Vec::new();
+    let mut starts = Vec::new();
+    for &(frequency, amplitude, duration_secs) in segments {
+        starts.push(samples.len());
+        let n = (rate as f64 * duration_secs) as usize;
+        samples.extend((0..n).map(|i| {
+            let t = i as f64 / rate as f64;
+            let v = amplitude * (2.0 * std::f64::consts::PI * frequency * t).sin() as f32;
+            (v * i16::MAX as f32) as i16
+        }));
+    }
+    (samples, starts)
+}
+
+/// Generate a PCM M4A with a Nero `chpl` chapter list: one mono 16-bit 8 kHz
+/// sine per `(frequency, amplitude, duration_secs)` segment, each a chapter
+/// titled "Part N".
+fn generate_m4a_with_chapters(
+    dir: &std::path::Path,
+    filename: &str,
+    segments: &[(f64, f32, f64)],
+) -> PathBuf {
+    const RATE: u32 = 8000;
+    let (samples, starts) = sine_segments(segments, RATE);
+    let atom = |kind: &[u8; 4], payload: &[u8]| {
+        let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(payload);
+        out
+    };
+    let words = |values: &[u32]| {
+        values
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect::<Vec<u8>>()
+    };
+    let n = samples.len() as u32;
+
+    let ftyp = atom(b"ftyp", b"M4A \0\0\0\0M4A mp42");
+    let mdat = atom(
+        b"mdat",
+        &samples
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect::<Vec<u8>>(),
+    );
+    // One chunk per second of audio
+    let chunk_offsets: Vec<u32> = (0..n.div_ceil(RATE))
+        .map(|c| ftyp.len() as u32 + 8 + c * RATE * 2)
+        .collect();
+    let mut stsc = words(&[0, 1, 1, RATE, 1]);
+    if !n.is_multiple_of(RATE) {
+        stsc = words(&[0, 2, 1, RATE, 1, chunk_offsets.len() as u32, n % RATE, 1]);
+    }
+    let mut sowt = vec![0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+    sowt.extend_from_slice(&[0, 1, 0, 16, 0, 0, 0, 0]);
+    sowt.extend_from_slice(&(RATE << 16).to_be_bytes());
+    let stbl = [
+        atom(b"stsd", &[words(&[0, 1]), atom(b"sowt", &sowt)].concat()),
+        atom(b"stts", &words(&[0, 1, n, 1])),
+        atom(b"stsc", &stsc),
+        atom(b"stsz", &words(&[0, 2, n])),
+        atom(
+            b"stco",
+            &[
+                words(&[0, chunk_offsets.len() as u32]),
+                words(&chunk_offsets),
+            ]
+            .concat(),
+        ),
+    ]
+    .concat();
+    let minf = [atom(b"smhd", &[0; 8]), atom(b"stbl", &stbl)].concat();
+    let mdia = [
+        atom(b"mdhd", &[words(&[0, 0, 0, RATE, n]), vec![0; 4]].concat()),
+        atom(
+            b"hdlr",
+            &[words(&[0, 0]), b"soun".to_vec(), vec![0; 13]].concat(),
+        ),
+        atom(b"minf", &minf),
+    ]
+    .concat();
+    let mut tkhd = words(&[7, 0, 0, 1, 0, n]);
+    tkhd.resize(84, 0);
+    let mut mvhd = words(&[0, 0, 0, RATE, n, 0x0001_0000]);
+    mvhd.resize(100, 0);
+
+    let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, starts.len() as u8];
+    for (i, &start) in starts.iter().enumerate() {
+        let title = format!("Part {}", i + 1);
+        chpl.extend_from_slice(&(start as u64 * 10_000_000 / RATE as u64).to_be_bytes());
+        chpl.push(title.len() as u8);
+        chpl.extend_from_slice(title.as_bytes());
+    }
+    let trak = atom(
+        b"trak",
+        &[atom(b"tkhd", &tkhd), atom(b"mdia", &mdia)].concat(),
+    );
+    let moov = [
+        atom(b"mvhd", &mvhd),
+        trak,
+        atom(b"udta", &atom(b"chpl", &chpl)),
+    ]
+    .concat();
+
+    let path = dir.join(filename);
+    std::fs::write(&path, [ftyp, mdat, atom(b"moov", &moov)].concat()).unwrap();
+    path
+}
+
+/// Generate a FLAC-in-Matroska audio file with `Chapters`: one mono 16-bit
+/// 44.1 kHz sine per `(frequency, amplitude, duration_secs)` segment, each a
+/// chapter titled "Part N". (Symphonia can't decode PCM in Matroska.)
+fn generate_mka_with_chapters(
+    dir: &std::path::Path,
+    filename: &str,
+    segments: &[(f64, f32, f64)],
 ) -> PathBuf {
-    let num_channels: u16 = 1;
-    let bits_per_sample: u16 = 16;
-    let num_samples = (sample_rate as f64 * duration_secs) as usize;
-    let byte_rate = sample_rate * num_channels as u32 * bits_per_sample as u32 / 8;
-    let block_align = num_channels * bits_per_sample / 8;
-    let data_size = (num_samples * num_channels as usize * (bits_per_sample as usize / 8)) as u32;
+    const BLOCK: usize = 4096;
+    const RATE: u32 = 44100; // the rate coded in `flac_frame` headers
+    let (samples, starts) = sine_segments(segments, RATE);
+    // An element with an 8-byte size
+    let element = |id: u32, payload: &[u8]| {
+        let id = id.to_be_bytes();
+        let mut out = id[id.iter().position(|&b| b != 0).unwrap()..].to_vec();
+        out.push(0x01);
+        out.extend_from_slice(&(payload.len() as u64).to_be_bytes()[1..]);
+        out.extend_from_slice(payload);
+        out
+    };
+
+    let header = element(0x1a45_dfa3, &element(0x4282, b"matroska"));
+    let duration_ms = samples.len() as f64 * 1000.0 / RATE as f64;
+    let info = element(
+        0x1549_a966,
+        &[
+            element(0x2a_d7b1, &1_000_000u32.to_be_bytes()),
+            element(0x4489, &duration_ms.to_be_bytes()),
+        ]
+        .concat(),
+    );
+    let audio = [
+        element(0xb5, &(RATE as f64).to_be_bytes()),
+        element(0x9f, &[1]),
+        element(0x6264, &[16]),
+    ]
+    .concat();
+    let mut streaminfo = b"fLaC".to_vec();
+    streaminfo.extend_from_slice(&[0x80, 0x00, 0x00, 34]);
+    streaminfo.extend_from_slice(&(BLOCK as u16).to_be_bytes());
+    streaminfo.extend_from_slice(&(BLOCK as u16).to_be_bytes());
+    streaminfo.extend_from_slice(&[0; 6]);
+    let packed: u64 = ((RATE as u64) << 44) | (15u64 << 36) | samples.len() as u64;
+    streaminfo.extend_from_slice(&packed.to_be_bytes());
+    streaminfo.extend_from_slice(&[0; 16]);
+    let track = [
+        element(0xd7, &[1]),
+        element(0x73c5, &[1]),
+        element(0x83, &[2]),
+        element(0x86, b"A_FLAC"),
+        element(0x63a2, &streaminfo),
+        element(0xe1, &audio),
+    ]
+    .concat();
+    let tracks = element(0x1654_ae6b, &element(0xae, &track));
+    let atoms: Vec<u8> = starts
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &start)| {
+            let start_ns = start as u64 * 1_000_000_000 / RATE as u64;
+            let display = element(0x85, format!("Part {}", i + 1).as_bytes());
+            element(
+                0xb6,
+                &[
+                    element(0x73c4, &[i as u8 + 1]),
+                    element(0x91, &start_ns.to_be_bytes()),
+                    element(0x80, &display),
+                ]
+                .concat(),
+            )
+        })
+        .collect();
+    let chapters = element(0x1043_a770, &element(0x45b9, &atoms));
+    // One cluster per FLAC frame
+    let clusters: Vec<u8> = samples
+        .chunks(BLOCK)
+        .enumerate()
+        .flat_map(|(i, chunk)| {
+            let mut block = vec![0x81, 0, 0, 0x80];
+            block.extend(flac_frame(i as u32, chunk));
+            let timestamp_ms = (i * BLOCK) as u64 * 1000 / RATE as u64;
+            element(
+                0x1f43_b675,
+                &[
+                    element(0xe7, &timestamp_ms.to_be_bytes()),
+                    element(0xa3, &block),
+                ]
+                .concat(),
+            )
+        })
+        .collect();
+    let segment = element(0x1853_8067, &[info, tracks, chapters, clusters].concat());
+
+    let path = dir.join(filename);
+    std::fs::write(&path, [header, segment].concat()).unwrap();
+    path
+}
+
+/// Write an Ogg Opus file: valid headers and one (silent) audio packet.
+/// Symphonia demuxes Opus but has no decoder for it.
+fn generate_ogg_opus(dir: &std::path::Path, filename: &str) -> PathBuf {
+    let mut head = b"OpusHead".to_vec();
+    head.push(1);
+    head.push(1);
+    head.extend_from_slice(&312u16.to_le_bytes());
+    head.extend_from_slice(&48000u32.to_le_bytes());
+    head.extend_from_slice(&0u16.to_le_bytes());
+    head.push(0);
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend_from_slice(&0u32.to_le_bytes());
+    tags.extend_from_slice(&0u32.to_le_bytes());
+
+    let mut out = Vec::new();
+    write_ogg_page(&mut out, 0x02, 0, 1, 0, &head);
+    write_ogg_page(&mut out, 0x00, 0, 1, 1, &tags);
+    write_ogg_page(&mut out, 0x04, 960, 1, 2, &[0xf8, 0xff, 0xfe]);
 
     let path = dir.join(filename);
-    let mut file = std::fs::File::create(&path).unwrap();
-
-    // RIFF header
-    file.write_all(b"RIFF").unwrap();
-    file.write_all(&(36 + data_size).to_le_bytes()).unwrap();
-    file.write_all(b"WAVE").unwrap();
-
-    // fmt chunk
-    file.write_all(b"fmt ").unwrap();
-    file.write_all(&16u32.to_le_bytes()).unwrap(); // chunk size
-    file.write_all(&1u16.to_le_bytes()).unwrap(); // PCM
-    file.write_all(&num_channels.to_le_bytes()).unwrap();
-    file.write_all(&sample_rate.to_le_bytes()).unwrap();
-    file.write_all(&byte_rate.to_le_bytes()).unwrap();
-    file.write_all(&block_align.to_le_bytes()).unwrap();
-    file.write_all(&bits_per_sample.to_le_bytes()).unwrap();
-
-    // data chunk
-    file.write_all(b"data").unwrap();
-    file.write_all(&data_size.to_le_bytes()).unwrap();
-
-    for i in 0..num_samples {
-        let t = i as f64 / sample_rate as f64;
-        let sample = amplitude * (2.0 * std::f64::consts::PI * frequency * t).sin() as f32;
-        let sample_i16 = (sample * i16::MAX as f32) as i16;
-        file.write_all(&sample_i16.to_le_bytes()).unwrap();
+    std::fs::write(&path, out).unwrap();
+    path
+}
+
+/// An 80-bit IEEE extended float, as AIFF stores its sample rate.
+fn extended_be(value: u32) -> [u8; 10] {
+    let exponent = 31 - value.leading_zeros();
+    let mut out = [0u8; 10];
+    out[..2].copy_from_slice(&(16383 + exponent as u16).to_be_bytes());
+    out[2..].copy_from_slice(&((value as u64) << (63 - exponent)).to_be_bytes());
+    out
+}
+
+/// A mono AIFF file (`compression` `None`) or AIFF-C file of `samples`, with
+/// `bits` per sample in the COMM chunk. Samples are stored as the compression
+/// type says: little-endian 16-bit for `sowt`, big-endian floats for `fl32`,
+/// big-endian 24-bit at 24 bits, and big-endian 16-bit otherwise.
+fn generate_aiff(
+    dir: &std::path::Path,
+    filename: &str,
+    compression: Option<&[u8; 4]>,
+    bits: u16,
+    samples: &[f32],
+    sample_rate: u32,
+) -> PathBuf {
+    let mut sound = vec![0u8; 8]; // offset and block size
+    for &s in samples {
+        match (compression, bits) {
+            (Some(b"fl32"), _) => sound.extend_from_slice(&s.to_be_bytes()),
+            (Some(b"sowt"), _) => {
+                sound.extend_from_slice(&((s * i16::MAX as f32) as i16).to_le_bytes())
+            }
+            (_, 24) => sound.extend_from_slice(&((s * 8_388_607.0) as i32).to_be_bytes()[1..]),
+            _ => sound.extend_from_slice(&((s * i16::MAX as f32) as i16).to_be_bytes()),
+        }
+    }
+
+    let mut comm = Vec::new();
+    comm.extend_from_slice(&1i16.to_be_bytes());
+    comm.extend_from_slice(&(samples.len() as u32).to_be_bytes());
+    comm.extend_from_slice(&bits.to_be_bytes());
+    comm.extend_from_slice(&extended_be(sample_rate));
+    let mut chunks: Vec<(&[u8; 4], Vec<u8>)> = Vec::new();
+    if let Some(compression) = compression {
+        // An empty compression name, padded to an even length
+        comm.extend_from_slice(compression);
+        comm.extend_from_slice(&[0, 0]);
+        chunks.push((b"FVER", 0xA280_5140u32.to_be_bytes().to_vec()));
+    }
+    chunks.push((b"COMM", comm));
+    chunks.push((b"SSND", sound));
+
+    let mut body = if compression.is_some() {
+        b"AIFC".to_vec()
+    } else {
+        b"AIFF".to_vec()
+    };
+    for (id, data) in chunks {
+        body.extend_from_slice(id);
+        body.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(&data);
+        if data.len() % 2 == 1 {
+            body.push(0);
+        }
     }
+    let mut out = b"FORM".to_vec();
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(&body);
 
+    let path = dir.join(filename);
+    std::fs::write(&path, out).unwrap();
     path
 }
 
@@ -83,6 +627,137 @@ fn test_sine_wave_dr() {
     );
 }
 
+#[test]
+fn test_analyze_bytes_matches_analyze_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "tone.wav", 440.0, 0.5, 9.0, 8000);
+    let from_file = analyzer::analyze_file(&path).unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    let options = analyzer::AnalysisOptions::default();
+    let from_bytes = analyzer::analyze_bytes(data, "wav", &options).unwrap();
+
+    assert_eq!(from_bytes.filename, "bytes");
+    assert_eq!(from_bytes.dr, from_file.dr);
+    assert_eq!(from_bytes.peak_db, from_file.peak_db);
+    assert_eq!(from_bytes.rms_db, from_file.rms_db);
+    assert_eq!(from_bytes.duration_secs, from_file.duration_secs);
+    assert_eq!(from_bytes.file_bytes, from_file.file_bytes);
+}
+
+#[test]
+fn test_chained_ogg_measures_full_duration() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_chained_ogg_flac(
+        dir.path(),
+        "chained.ogg",
+        &[(440.0, 0.5, 9.0), (880.0, 0.9, 9.0)],
+        44100,
+    );
+
+    let result = analyzer::analyze_file(&path).unwrap();
+
+    // Both logical streams must be decoded, not just the first
+    assert!(
+        (result.duration_secs - 18.0).abs() < 0.1,
+        "Expected ~18s across both chained streams, got {:.2}s",
+        result.duration_secs
+    );
+    // The louder second segment sets the peak
+    assert!(
+        result.peak_db > -1.5,
+        "Peak should come from the second stream (~-0.9 dBFS), got {:.2}",
+        result.peak_db
+    );
+}
+
+#[test]
+fn test_undecodable_codec_is_unsupported_not_corrupt() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_ogg_opus(dir.path(), "track.opus");
+
+    let err = analyzer::analyze_file(&path).unwrap_err();
+    assert!(dr::error::is_unsupported_codec(&err), "{:#}", err);
+    assert!(
+        format!("{:#}", err).contains("Unsupported codec: opus"),
+        "{:#}",
+        err
+    );
+
+    cargo_bin_cmd!("dr")
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unsupported codec: opus"));
+}
+
+#[test]
+fn test_aiff_variants_decode_to_the_same_samples_as_wav() {
+    let dir = tempfile::tempdir().unwrap();
+    let samples = signals::pink_noise_with_dynamics(0.9, 12.0, 8000, 3);
+    let wav = dir.path().join("reference.wav");
+    signals::write_wav_16(&wav, &samples, 1, 8000).unwrap();
+    let reference = analyzer::analyze_file(&wav).unwrap();
+
+    // Byte-swapped or misread samples would land nowhere near the WAV's
+    // peak and RMS
+    let variants: [(&str, Option<&[u8; 4]>, u16); 6] = [
+        ("be16.aiff", None, 16),
+        ("be24.aif", None, 24),
+        ("none.aifc", Some(b"NONE"), 16),
+        ("twos.aifc", Some(b"twos"), 16),
+        ("sowt.aifc", Some(b"sowt"), 16),
+        ("float.aifc", Some(b"fl32"), 32),
+    ];
+    for (name, compression, bits) in variants {
+        let path = generate_aiff(dir.path(), name, compression, bits, &samples, 8000);
+        let track = analyzer::analyze_file(&path).unwrap_or_else(|e| panic!("{}: {:#}", name, e));
+        assert_eq!(track.dr, reference.dr, "{}", name);
+        assert!(
+            (track.peak_db - reference.peak_db).abs() < 0.01,
+            "{}: {} dB",
+            name,
+            track.peak_db
+        );
+        assert!(
+            (track.rms_db - reference.rms_db).abs() < 0.01,
+            "{}: {} dB",
+            name,
+            track.rms_db
+        );
+        assert!(
+            (track.duration_secs - reference.duration_secs).abs() < 0.01,
+            "{}",
+            name
+        );
+    }
+    let scanned = analyzer::scan_audio_files(dir.path()).unwrap();
+    assert_eq!(scanned.len(), variants.len() + 1);
+
+    // Layouts the decoder can't read are unsupported, not corrupt
+    let ima = generate_aiff(
+        dir.path(),
+        "ima4.aifc",
+        Some(b"ima4"),
+        16,
+        &samples[..64],
+        8000,
+    );
+    let odd = generate_aiff(dir.path(), "odd.aiff", None, 20, &samples[..64], 8000);
+    for (path, label) in [
+        (ima, "AIFF-C IMA 4:1 ADPCM ('ima4')"),
+        (odd, "AIFF with 20-bit samples"),
+    ] {
+        let err = analyzer::analyze_file(&path).unwrap_err();
+        assert!(dr::error::is_unsupported_codec(&err), "{:#}", err);
+        assert!(
+            format!("{:#}", err).contains(&format!("Unsupported codec: {}", label)),
+            "{:#}",
+            err
+        );
+    }
+}
+
 #[test]
 fn test_album_dr_is_average() {
     let dir = tempfile::tempdir().unwrap();
@@ -116,7 +791,7 @@ fn test_table_formatter_columns() {
                 duration_secs: 263.0,
                 title: "First Track".to_string(),
                 filename: "01.flac".to_string(),
-                file_bytes: 0,
+                ..Default::default()
             },
             TrackResult {
                 dr: 12,
@@ -125,11 +800,12 @@ fn test_table_formatter_columns() {
                 duration_secs: 225.0,
                 title: "Second Track".to_string(),
                 filename: "02.flac".to_string(),
-                file_bytes: 0,
+                ..Default::default()
             },
         ],
         overall_dr: 13,
         album: Some("Test Album".to_string()),
+        ..Default::default()
     };
 
     let table = format::format_table(&result);
@@ -163,7 +839,7 @@ fn test_json_roundtrip() {
         duration_secs: 263.0,
         title: "Test Track".to_string(),
         filename: "test.flac".to_string(),
-        file_bytes: 0,
+        ..Default::default()
     };
 
     let json = format::format_json_single(&track);
@@ -186,10 +862,12 @@ fn test_album_json_roundtrip() {
             duration_secs: 180.0,
             title: "A Track".to_string(),
             filename: "a.flac".to_string(),
-            file_bytes: 0,
+            ..Default::default()
         }],
         overall_dr: 10,
         album: Some("My Album".to_string()),
+        dr_rating: "Compressed".to_string(),
+        ..Default::default()
     };
 
     let json = format::format_json(&result);
@@ -197,6 +875,7 @@ fn test_album_json_roundtrip() {
 
     assert_eq!(parsed.overall_dr, 10);
     assert_eq!(parsed.album, Some("My Album".to_string()));
+    assert_eq!(parsed.dr_rating, "Compressed");
     assert_eq!(parsed.tracks.len(), 1);
 }
 
@@ -210,23 +889,83 @@ fn test_scan_audio_files() {
     std::fs::write(dir.path().join("cover.jpg"), b"fake").unwrap();
     std::fs::write(dir.path().join("notes.txt"), b"fake").unwrap();
 
-    let files = analyzer::scan_audio_files(dir.path());
+    let files = analyzer::scan_audio_files(dir.path()).unwrap();
     assert_eq!(files.len(), 2);
     assert!(files[0].extension().unwrap() == "flac");
     assert!(files[1].extension().unwrap() == "mp3");
 }
 
+#[test]
+fn test_unreadable_directory_is_not_empty() {
+    let dir = tempfile::tempdir().unwrap();
+    // Listing a regular file fails like listing a directory without permission
+    let not_a_dir = dir.path().join("album");
+    std::fs::write(&not_a_dir, b"fake").unwrap();
+
+    let err = analyzer::scan_audio_files(&not_a_dir).unwrap_err();
+    assert!(format!("{:#}", err).starts_with(&format!(
+        "Failed to read directory: {}: ",
+        not_a_dir.display()
+    )));
+    let err = analyzer::analyze_directory(&not_a_dir, 1).unwrap_err();
+    assert!(!dr::error::is_no_audio(&err));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let locked = dir.path().join("locked");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+        // Root can list it anyway
+        if std::fs::read_dir(&locked).is_err() {
+            let err = analyzer::scan_audio_files(&locked).unwrap_err();
+            assert!(
+                format!("{:#}", err).contains("ermission denied"),
+                "{:#}",
+                err
+            );
+        }
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
+
 // --- Cache helper tests ---
 
+const BASENAME: &str = cache::DEFAULT_REPORT_BASENAME;
+
 #[test]
 fn test_reports_exist_no_files() {
     let dir = tempfile::tempdir().unwrap();
     // Neither file exists
-    assert!(!cache::reports_exist(dir.path(), true, false));
-    assert!(!cache::reports_exist(dir.path(), false, true));
-    assert!(!cache::reports_exist(dir.path(), true, true));
+    assert!(!cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        true,
+        false,
+        false
+    ));
+    assert!(!cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        false,
+        true,
+        false
+    ));
+    assert!(!cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        true,
+        true,
+        false
+    ));
     // Neither requested — vacuously true
-    assert!(cache::reports_exist(dir.path(), false, false));
+    assert!(cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        false,
+        false,
+        false
+    ));
 }
 
 #[test]
@@ -234,9 +973,27 @@ fn test_reports_exist_json_only() {
     let dir = tempfile::tempdir().unwrap();
     std::fs::write(dir.path().join("dr_report.json"), "{}").unwrap();
 
-    assert!(cache::reports_exist(dir.path(), true, false));
-    assert!(!cache::reports_exist(dir.path(), true, true));
-    assert!(!cache::reports_exist(dir.path(), false, true));
+    assert!(cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        true,
+        false,
+        false
+    ));
+    assert!(!cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        true,
+        true,
+        false
+    ));
+    assert!(!cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        false,
+        true,
+        false
+    ));
 }
 
 #[test]
@@ -244,9 +1001,27 @@ fn test_reports_exist_txt_only() {
     let dir = tempfile::tempdir().unwrap();
     std::fs::write(dir.path().join("dr_report.txt"), "report").unwrap();
 
-    assert!(cache::reports_exist(dir.path(), false, true));
-    assert!(!cache::reports_exist(dir.path(), true, true));
-    assert!(!cache::reports_exist(dir.path(), true, false));
+    assert!(cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        false,
+        true,
+        false
+    ));
+    assert!(!cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        true,
+        true,
+        false
+    ));
+    assert!(!cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        true,
+        false,
+        false
+    ));
 }
 
 #[test]
@@ -255,215 +1030,3450 @@ fn test_reports_exist_both() {
     std::fs::write(dir.path().join("dr_report.json"), "{}").unwrap();
     std::fs::write(dir.path().join("dr_report.txt"), "report").unwrap();
 
-    assert!(cache::reports_exist(dir.path(), true, true));
-    assert!(cache::reports_exist(dir.path(), true, false));
-    assert!(cache::reports_exist(dir.path(), false, true));
+    assert!(cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        true,
+        true,
+        false
+    ));
+    assert!(cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        true,
+        false,
+        false
+    ));
+    assert!(cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        false,
+        true,
+        false
+    ));
+}
+
+#[test]
+fn test_reports_exist_considers_csv() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("dr_report.json"), "{}").unwrap();
+
+    assert!(!cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        true,
+        false,
+        true
+    ));
+    std::fs::write(dir.path().join("dr_report.csv"), "DR").unwrap();
+    assert!(cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        true,
+        false,
+        true
+    ));
+    assert!(!cache::reports_exist(
+        dir.path(),
+        BASENAME,
+        false,
+        true,
+        true
+    ));
 }
 
 #[test]
 fn test_save_text_report() {
     let dir = tempfile::tempdir().unwrap();
     let content = "DR14  -0.10 dB  -16.78 dB  4:23  Test Track";
-    cache::save_text_report(dir.path(), content).unwrap();
+    cache::save_text_report(dir.path(), BASENAME, content).unwrap();
 
     let path = dir.path().join("dr_report.txt");
     assert!(path.exists());
     assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
 }
 
-// --- CLI validation tests ---
-
 #[test]
-fn test_bulk_and_tui_conflict() {
-    cargo_bin_cmd!("dr")
-        .args([".", "--bulk", "--tui"])
-        .assert()
-        .failure()
-        .stderr(predicates::str::contains("--bulk and --tui cannot be used together"));
+fn test_save_replaces_reports_without_leftovers() {
+    let dir = tempfile::tempdir().unwrap();
+    for dr in [9, 12] {
+        let result = AlbumResult {
+            overall_dr: dr,
+            ..Default::default()
+        };
+        cache::save_report(dir.path(), BASENAME, &result).unwrap();
+        cache::save_text_report(dir.path(), BASENAME, &format!("DR{}", dr)).unwrap();
+    }
+
+    assert_eq!(
+        cache::load_cached_report(dir.path(), BASENAME)
+            .unwrap()
+            .overall_dr,
+        12
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("dr_report.txt")).unwrap(),
+        "DR12"
+    );
+    let mut names: Vec<String> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["dr_report.json", "dr_report.txt"]);
 }
 
 #[test]
-fn test_bulk_requires_output_format() {
+fn test_badge_prints_album_dr() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01.wav", 440.0, 0.5, 6.0, 8000);
+    let dr = analyzer::analyze_directory(dir.path(), 1)
+        .unwrap()
+        .overall_dr;
+    let svg = dir.path().join("badge.svg");
+
     cargo_bin_cmd!("dr")
-        .args([".", "--bulk"])
+        .arg(dir.path())
+        .arg("--badge")
         .assert()
-        .failure()
-        .stderr(predicates::str::contains(
-            "--bulk requires at least one output format",
-        ));
+        .success()
+        .stdout(format!("DR{}\n", dr));
+    // The second run reads the cached report
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--badge", "--json", "--badge-svg"])
+        .arg(&svg)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"schemaVersion\":1"))
+        .stderr(predicates::str::contains("loaded from cached report"));
+    let svg = std::fs::read_to_string(&svg).unwrap();
+    assert!(svg.contains(&format!(">DR{}</text>", dr)));
 }
 
-// --- Bulk mode integration tests ---
-
-/// Helper: create a temp directory with two "album" subdirectories, each containing a sine WAV.
-fn setup_bulk_dir() -> tempfile::TempDir {
-    let base = tempfile::tempdir().unwrap();
-
-    let album_a = base.path().join("Album A");
-    let album_b = base.path().join("Album B");
-    std::fs::create_dir(&album_a).unwrap();
-    std::fs::create_dir(&album_b).unwrap();
+#[test]
+fn test_include_timing_adds_analysis_to_json_only() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_album(dir.path(), &[(440.0, 0.5, 6.0), (440.0, 0.5, 6.0)], 8000);
 
-    generate_sine_wav(&album_a, "01-track.wav", 440.0, 0.99, 12.0, 44100);
-    generate_sine_wav(&album_b, "01-track.wav", 880.0, 0.99, 12.0, 44100);
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--json", "--include-timing"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let analysis = &json["analysis"];
+    let total_bytes = json["total_bytes"].as_u64().unwrap();
+    assert_eq!(analysis["total_bytes"].as_u64(), Some(total_bytes));
+    assert!(analysis["elapsed_secs"].as_f64().unwrap() > 0.0);
+    assert!(analysis["mb_per_sec"].as_f64().unwrap() > 0.0);
 
-    base
+    // The cached report stays about the audio
+    let saved = std::fs::read_to_string(dir.path().join("dr_report.json")).unwrap();
+    assert!(!saved.contains("\"analysis\""));
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"analysis\"").not());
 }
 
 #[test]
-fn test_bulk_json_only() {
-    let base = setup_bulk_dir();
-
+fn test_list_formats() {
     cargo_bin_cmd!("dr")
-        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .arg("--list-formats")
         .assert()
-        .success();
-
-    // JSON reports should exist in both album directories
-    assert!(base.path().join("Album A/dr_report.json").exists());
-    assert!(base.path().join("Album B/dr_report.json").exists());
-    // Text reports should NOT exist
-    assert!(!base.path().join("Album A/dr_report.txt").exists());
-    assert!(!base.path().join("Album B/dr_report.txt").exists());
-
-    // Verify JSON is valid
-    let json_a = std::fs::read_to_string(base.path().join("Album A/dr_report.json")).unwrap();
-    let parsed: AlbumResult = serde_json::from_str(&json_a).unwrap();
-    assert_eq!(parsed.tracks.len(), 1);
+        .success()
+        .stdout(predicates::str::contains("flac, mp3, wav"))
+        .stdout(predicates::str::contains(
+            "  flac           Free Lossless Audio Codec",
+        ))
+        .stdout(predicates::str::contains("  pcm_s16le "));
+    let codecs = analyzer::supported_codecs();
+    assert!(codecs.iter().any(|&(name, _)| name == "vorbis"));
 }
 
 #[test]
-fn test_bulk_txt_only() {
-    let base = setup_bulk_dir();
+fn test_log_format_prints_and_saves_dr_log() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01.wav", 440.0, 0.5, 6.0, 8000);
 
     cargo_bin_cmd!("dr")
-        .args([base.path().to_str().unwrap(), "--bulk", "--txt"])
+        .arg(dir.path())
+        .args(["--log-format", "v1", "--txt"])
         .assert()
-        .success();
-
-    // Text reports should exist
-    assert!(base.path().join("Album A/dr_report.txt").exists());
-    assert!(base.path().join("Album B/dr_report.txt").exists());
-    // JSON reports should NOT exist
-    assert!(!base.path().join("Album A/dr_report.json").exists());
-    assert!(!base.path().join("Album B/dr_report.json").exists());
+        .success()
+        .stdout(predicates::str::contains(
+            "Analyzed: Unknown Artist / Unknown Album",
+        ))
+        .stdout(predicates::str::contains("Official DR value: DR"));
+    let saved = std::fs::read_to_string(dir.path().join("dr_report.txt")).unwrap();
+    assert!(saved.contains("Analyzed: Unknown Artist / Unknown Album"));
 
-    // Verify text content has expected table structure
-    let txt = std::fs::read_to_string(base.path().join("Album A/dr_report.txt")).unwrap();
-    assert!(txt.contains("DR"));
-    assert!(txt.contains("Official DR value"));
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--log-format", "v2", "--regenerate"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Statistics for: Unknown Artist - Unknown Album",
+        ))
+        .stdout(predicates::str::contains("Samplerate:        8000 Hz"));
 }
 
 #[test]
-fn test_bulk_json_and_txt() {
-    let base = setup_bulk_dir();
+fn test_value_prints_bare_dr() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "01.wav", 440.0, 0.5, 6.0, 8000);
+    let track_dr = analyzer::analyze_file(&path).unwrap().dr;
 
     cargo_bin_cmd!("dr")
-        .args([base.path().to_str().unwrap(), "--bulk", "--json", "--txt"])
+        .arg(&path)
+        .arg("--value")
         .assert()
-        .success();
-
-    // Both report types should exist in both albums
-    for album in &["Album A", "Album B"] {
+        .success()
+        .stdout(format!("{}\n", track_dr));
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--value")
+        .assert()
+        .success()
+        .stdout(format!("{}\n", track_dr));
+    cargo_bin_cmd!("dr")
+        .arg(&path)
+        .args(["--value", "--json"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_histogram_from_nested_reports() {
+    let base = tempfile::tempdir().unwrap();
+    for (dir, dr) in [("a", 8), ("b/disc1", 12), ("b/disc2", 12), ("c/d/e", 8)] {
+        let dir = base.path().join(dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let result = AlbumResult {
+            overall_dr: dr,
+            ..Default::default()
+        };
+        cache::save_report(&dir, BASENAME, &result).unwrap();
+    }
+    std::fs::create_dir(base.path().join("empty")).unwrap();
+
+    let found = cache::find_cached_reports(base.path(), BASENAME);
+    assert_eq!(found.len(), 4);
+    assert_eq!(found[0].0, base.path().join("a"));
+
+    cargo_bin_cmd!("dr")
+        .arg(base.path())
+        .arg("--histogram")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("DR distribution (4 albums):"))
+        .stdout(predicates::str::contains("DR8  "))
+        .stdout(predicates::str::contains("DR12 "));
+    cargo_bin_cmd!("dr")
+        .arg(base.path().join("empty"))
+        .arg("--histogram")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No cached reports found"));
+}
+
+#[test]
+fn test_dr_rating_follows_dr_scale() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_pink_noise(dir.path(), "01.wav", 0.9, 12.0, 8000, 7);
+    let dr = analyzer::analyze_file(&path).unwrap().dr;
+    assert!(dr > 0);
+
+    // DR is the bottom of the scale's medium range, below its midpoint
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--json", "--dr-scale", &format!("{},{}", dr, dr + 2)])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"dr_rating\": \"Compressed\""));
+
+    // The cached report is re-rated on the current scale
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--json", "--dr-scale", &format!("{},{}", dr + 1, dr + 3)])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"dr_rating\": \"Crushed\""));
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--dr-scale", &format!("1,{}", dr)])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "DR rating:         Excellent (DR{}+)",
+            dr
+        )));
+}
+
+#[test]
+fn test_recompute_album_dr_rewrites_cached_reports() {
+    let base = tempfile::tempdir().unwrap();
+    let album = base.path().join("Album");
+    std::fs::create_dir(&album).unwrap();
+    let track = |dr: u32, title: &str| TrackResult {
+        dr,
+        title: title.to_string(),
+        filename: format!("{}.flac", title),
+        ..Default::default()
+    };
+    let mut result = AlbumResult {
+        tracks: vec![track(6, "a"), track(7, "b"), track(14, "c")],
+        ..Default::default()
+    };
+    result.recompute_overall_dr(dr::models::AlbumAggregate::Mean);
+    assert_eq!(result.overall_dr, 9);
+    cache::save_report(&album, BASENAME, &result).unwrap();
+    cache::save_text_report(&album, BASENAME, &format::format_table(&result)).unwrap();
+
+    cargo_bin_cmd!("dr")
+        .arg(base.path())
+        .args(["--recompute-album-dr", "--album-agg", "median"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("DR7   (was DR9  )"))
+        .stderr(predicates::str::contains(
+            "1 album DR(s) recomputed, 1 changed",
+        ));
+
+    // The median is written beside the cache, which keeps the standard mean
+    let nonstandard = format!("{}{}", BASENAME, cache::NONSTANDARD_SUFFIX);
+    let report = cache::load_cached_report(&album, &nonstandard).unwrap();
+    assert_eq!(report.overall_dr, 7);
+    assert_eq!(report.album_agg, dr::models::AlbumAggregate::Median);
+    assert_eq!(report.dr_rating, "Crushed");
+    let text = std::fs::read_to_string(album.join("dr_report.nonstandard.txt")).unwrap();
+    assert!(
+        text.contains("Official DR value: DR7 (median of tracks)"),
+        "{}",
+        text
+    );
+    assert!(
+        !album.join("dr_report.nonstandard.csv").exists(),
+        "only existing reports are rewritten"
+    );
+    assert_eq!(
+        cache::load_cached_report(&album, BASENAME)
+            .unwrap()
+            .overall_dr,
+        9
+    );
+    assert!(!std::fs::read_to_string(album.join("dr_report.txt"))
+        .unwrap()
+        .contains("median"));
+
+    // The mean rewrites the cache itself
+    let mut skewed = result.clone();
+    skewed.overall_dr = 12;
+    cache::save_report(&album, BASENAME, &skewed).unwrap();
+    cargo_bin_cmd!("dr")
+        .arg(base.path())
+        .arg("--recompute-album-dr")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("DR9   (was DR12 )"));
+    assert_eq!(
+        cache::load_cached_report(&album, BASENAME)
+            .unwrap()
+            .overall_dr,
+        9
+    );
+}
+
+#[test]
+fn test_save_report_stamps_schema_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = AlbumResult {
+        overall_dr: 12,
+        ..Default::default()
+    };
+    cache::save_report(dir.path(), BASENAME, &result).unwrap();
+
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.schema_version, dr::models::SCHEMA_VERSION);
+    assert_eq!(loaded.overall_dr, 12);
+}
+
+#[test]
+fn test_load_migrates_unversioned_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let legacy = r#"{"tracks": [], "overall_dr": 9, "album": null}"#;
+    std::fs::write(dir.path().join("dr_report.json"), legacy).unwrap();
+
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.schema_version, dr::models::SCHEMA_VERSION);
+    assert_eq!(loaded.dr_rating, "Compressed");
+}
+
+#[test]
+fn test_load_migrates_v3_totals() {
+    let dir = tempfile::tempdir().unwrap();
+    let v3 = r#"{"schema_version": 3, "tracks": [
+        {"dr": 10, "peak_db": -0.5, "rms_db": -12.0, "duration_secs": 60.0, "title": "A", "filename": "a.flac", "file_bytes": 1000},
+        {"dr": 12, "peak_db": -0.5, "rms_db": -12.0, "duration_secs": 90.0, "title": "B", "filename": "b.flac", "file_bytes": 500}],
+        "overall_dr": 11, "album": null, "dr_rating": "Good"}"#;
+    std::fs::write(dir.path().join("dr_report.json"), v3).unwrap();
+
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.schema_version, dr::models::SCHEMA_VERSION);
+    assert!((loaded.total_duration_secs - 150.0).abs() < 1e-9);
+    assert_eq!(loaded.total_bytes, 1500);
+}
+
+#[test]
+fn test_load_migrates_v22_fingerprints_away() {
+    let dir = tempfile::tempdir().unwrap();
+    let v22 = r#"{"schema_version": 22, "tracks": [
+        {"dr": 10, "peak_db": -0.5, "rms_db": -14.0, "duration_secs": 60.0, "title": "A", "filename": "a.flac", "fingerprint": "505152"}],
+        "overall_dr": 10, "album": null, "dr_rating": "Compressed"}"#;
+    std::fs::write(dir.path().join("dr_report.json"), v22).unwrap();
+
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.schema_version, dr::models::SCHEMA_VERSION);
+    assert!(loaded.tracks[0].fingerprint.is_none());
+}
+
+#[test]
+fn test_load_migrates_v5_outliers() {
+    let dir = tempfile::tempdir().unwrap();
+    let v5 = r#"{"schema_version": 5, "tracks": [
+        {"dr": 13, "peak_db": -0.5, "rms_db": -14.0, "duration_secs": 60.0, "title": "A", "filename": "a.flac"},
+        {"dr": 6, "peak_db": -0.1, "rms_db": -7.0, "duration_secs": 60.0, "title": "B", "filename": "b.flac"},
+        {"dr": 14, "peak_db": -0.5, "rms_db": -15.0, "duration_secs": 60.0, "title": "C", "filename": "c.flac"}],
+        "overall_dr": 11, "album": null, "dr_rating": "Good"}"#;
+    std::fs::write(dir.path().join("dr_report.json"), v5).unwrap();
+
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.outliers.len(), 1);
+    assert_eq!((loaded.outliers[0].track, loaded.outliers[0].dr), (2, 6));
+    assert!(format::format_json(&loaded).contains("\"outliers\""));
+}
+
+#[test]
+fn test_peak_fallback_flags_one_block_tracks() {
+    let dir = tempfile::tempdir().unwrap();
+    let short = generate_sine_wav(dir.path(), "short.wav", 440.0, 0.5, 4.0, 8000);
+    let long = generate_sine_wav(dir.path(), "long.wav", 440.0, 0.5, 7.0, 8000);
+    let tiny = generate_sine_wav(dir.path(), "tiny.wav", 440.0, 0.5, 2.0, 8000);
+
+    let track = analyzer::analyze_file(&short).unwrap();
+    assert!(track.peak_fallback);
+    assert_eq!(track.num_blocks, 1);
+    assert!(format::format_table_single(&track).contains("Note: only one 3-second block"));
+    assert!(!analyzer::analyze_file(&long).unwrap().peak_fallback);
+    assert_eq!(analyzer::analyze_file(&long).unwrap().num_blocks, 2);
+    // No complete block at all is DR0, not a fallback
+    let tiny = analyzer::analyze_file(&tiny).unwrap();
+    assert!(!tiny.peak_fallback);
+    assert_eq!((tiny.dr, tiny.num_blocks), (0, 0));
+    assert!(format::format_json_single(&tiny).contains("\"num_blocks\": 0"));
+
+    let album = analyzer::analyze_directory(dir.path(), 1).unwrap();
+    let table = format::format_table(&album);
+    assert!(table.contains("Notes:\n  short: only one 3-second block"));
+    assert!(!table.contains("  long: only one"));
+}
+
+#[test]
+fn test_load_migrates_v11_peak_fallback() {
+    let dir = tempfile::tempdir().unwrap();
+    let v11 = r#"{"schema_version": 11, "tracks": [
+        {"dr": 5, "peak_db": -0.5, "rms_db": -9.0, "duration_secs": 4.5, "title": "A", "filename": "a.flac"},
+        {"dr": 9, "peak_db": -0.5, "rms_db": -9.0, "duration_secs": 60.0, "title": "B", "filename": "b.flac"}],
+        "overall_dr": 7, "album": null, "dr_rating": "Crushed"}"#;
+    std::fs::write(dir.path().join("dr_report.json"), v11).unwrap();
+
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.schema_version, dr::models::SCHEMA_VERSION);
+    assert!(loaded.tracks[0].peak_fallback);
+    assert!(!loaded.tracks[1].peak_fallback);
+}
+
+#[test]
+fn test_album_peak_is_loudest_track_peak() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_album(dir.path(), &[(440.0, 0.25, 6.0), (440.0, 0.5, 6.0)], 8000);
+
+    let album = analyzer::analyze_directory(dir.path(), 1).unwrap();
+    let loudest = album.tracks[1].peak_db;
+    assert!(loudest > album.tracks[0].peak_db);
+    assert_eq!(album.album_peak_db, Some(loudest));
+    assert!(format::format_table(&album).contains(&format!("Album peak:        {:.2} dB", loudest)));
+    assert!(format::format_json(&album).contains("\"album_peak_db\""));
+}
+
+#[test]
+fn test_load_migrates_v13_album_peak() {
+    let dir = tempfile::tempdir().unwrap();
+    let v13 = r#"{"schema_version": 13, "tracks": [
+        {"dr": 10, "peak_db": -3.5, "rms_db": -14.0, "duration_secs": 60.0, "title": "A", "filename": "a.flac"},
+        {"dr": 12, "peak_db": -0.25, "rms_db": -13.0, "duration_secs": 60.0, "title": "B", "filename": "b.flac"}],
+        "overall_dr": 11, "album": null, "dr_rating": "Good"}"#;
+    std::fs::write(dir.path().join("dr_report.json"), v13).unwrap();
+
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.schema_version, dr::models::SCHEMA_VERSION);
+    assert_eq!(loaded.album_peak_db, Some(-0.25));
+}
+
+#[test]
+fn test_load_migrates_v14_num_blocks() {
+    let dir = tempfile::tempdir().unwrap();
+    let v14 = r#"{"schema_version": 14, "tracks": [
+        {"dr": 10, "peak_db": -0.5, "rms_db": -14.0, "duration_secs": 61.5, "title": "A", "filename": "a.flac"},
+        {"dr": 9, "peak_db": -0.5, "rms_db": -13.0, "duration_secs": 13.0, "trim_edges_secs": 2.0, "title": "B", "filename": "b.flac"},
+        {"dr": 0, "peak_db": -0.5, "rms_db": -13.0, "duration_secs": 2.0, "title": "C", "filename": "c.flac"}],
+        "overall_dr": 6, "album": null, "dr_rating": "Crushed"}"#;
+    std::fs::write(dir.path().join("dr_report.json"), v14).unwrap();
+
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.schema_version, dr::models::SCHEMA_VERSION);
+    let blocks: Vec<u32> = loaded.tracks.iter().map(|t| t.num_blocks).collect();
+    assert_eq!(blocks, [20, 3, 0]);
+}
+
+#[test]
+fn test_load_migrates_v15_crest_factor() {
+    let dir = tempfile::tempdir().unwrap();
+    let v15 = r#"{"schema_version": 15, "tracks": [
+        {"dr": 10, "peak_db": -0.5, "rms_db": -14.0, "duration_secs": 60.0, "title": "A", "filename": "a.flac", "num_blocks": 20}],
+        "overall_dr": 10, "album": null, "dr_rating": "Compressed"}"#;
+    std::fs::write(dir.path().join("dr_report.json"), v15).unwrap();
+
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.schema_version, dr::models::SCHEMA_VERSION);
+    assert_eq!(loaded.tracks[0].crest_factor_db, 13.5);
+}
+
+#[test]
+fn test_load_migrates_v16_plr() {
+    let dir = tempfile::tempdir().unwrap();
+    let v16 = r#"{"schema_version": 16, "tracks": [
+        {"dr": 10, "peak_db": -0.5, "true_peak_db": -0.25, "rms_db": -14.0, "loudness_lufs": -9.5, "duration_secs": 60.0, "title": "A", "filename": "a.flac"},
+        {"dr": 0, "peak_db": -0.5, "true_peak_db": -0.25, "rms_db": -14.0, "loudness_lufs": null, "duration_secs": 60.0, "title": "B", "filename": "b.flac"}],
+        "overall_dr": 5, "album": null, "dr_rating": "Crushed"}"#;
+    std::fs::write(dir.path().join("dr_report.json"), v16).unwrap();
+
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.schema_version, dr::models::SCHEMA_VERSION);
+    assert_eq!(loaded.tracks[0].plr, Some(9.25));
+    assert_eq!(loaded.tracks[1].plr, None);
+}
+
+#[test]
+fn test_load_migrates_v17_confidence() {
+    let dir = tempfile::tempdir().unwrap();
+    let v17 = r#"{"schema_version": 17, "tracks": [
+        {"dr": 10, "peak_db": -0.5, "rms_db": -14.0, "duration_secs": 60.0, "num_blocks": 20, "title": "A", "filename": "a.flac"},
+        {"dr": 9, "peak_db": -0.5, "rms_db": -14.0, "duration_secs": 60.0, "num_blocks": 20, "decode_errors": 2, "title": "B", "filename": "b.flac"},
+        {"dr": 8, "peak_db": -0.5, "rms_db": -14.0, "duration_secs": 4.0, "num_blocks": 1, "peak_fallback": true, "title": "C", "filename": "c.flac"}],
+        "overall_dr": 9, "album": null, "dr_rating": "Compressed"}"#;
+    std::fs::write(dir.path().join("dr_report.json"), v17).unwrap();
+
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.schema_version, dr::models::SCHEMA_VERSION);
+    let confidence: Vec<_> = loaded.tracks.iter().map(|t| t.confidence).collect();
+    assert_eq!(
+        confidence,
+        [Confidence::High, Confidence::Medium, Confidence::Low]
+    );
+}
+
+#[test]
+fn test_confidence_reflects_track_length() {
+    let dir = tempfile::tempdir().unwrap();
+    let short = generate_sine_wav(dir.path(), "short.wav", 440.0, 0.5, 4.0, 8000);
+    let medium = generate_sine_wav(dir.path(), "medium.wav", 440.0, 0.5, 12.0, 8000);
+    let long = generate_sine_wav(dir.path(), "long.wav", 440.0, 0.5, 30.0, 8000);
+    let silent = dir.path().join("silent.wav");
+    signals::write_wav_16(&silent, &vec![0.0; 8000 * 12], 1, 8000).unwrap();
+
+    let confidence = |path: &PathBuf| analyzer::analyze_file(path).unwrap().confidence;
+    assert_eq!(confidence(&short), Confidence::Low);
+    assert_eq!(confidence(&medium), Confidence::Medium);
+    assert_eq!(confidence(&long), Confidence::High);
+    assert_eq!(confidence(&silent), Confidence::Low);
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(&short)
+        .arg("--json")
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["confidence"], "low");
+}
+
+#[test]
+fn test_plr_is_true_peak_over_loudness() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "01.wav", 997.0, 0.5, 6.0, 8000);
+
+    let track = analyzer::analyze_file(&path).unwrap();
+    let (Some(lufs), Some(plr)) = (track.loudness_lufs, track.plr) else {
+        panic!("no loudness for a sine");
+    };
+    assert!((plr - (track.true_peak_db - lufs)).abs() < 1e-9);
+    cargo_bin_cmd!("dr")
+        .arg(&path)
+        .arg("--plr")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("RMS (dB)  PLR (dB)"))
+        .stdout(predicates::str::contains(format!("{:.2}", plr)));
+}
+
+#[test]
+fn test_crest_factor_column() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_pink_noise(dir.path(), "01.wav", 0.9, 12.0, 8000, 7);
+
+    let album = analyzer::analyze_directory(dir.path(), 1).unwrap();
+    let track = &album.tracks[0];
+    assert!(
+        track.crest_factor_db > 3.0,
+        "crest {:.2}",
+        track.crest_factor_db
+    );
+    assert!((track.crest_factor_db - (track.peak_db - track.rms_db)).abs() < 1e-9);
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--crest-factor", "--no-cache"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("RMS (dB)  Crest (dB)"))
+        .stdout(predicates::str::contains(format!(
+            "{:.2}",
+            track.crest_factor_db
+        )));
+    assert!(!format::format_table(&album).contains("Crest"));
+    assert!(format::format_json(&album).contains("\"crest_factor_db\""));
+}
+
+#[test]
+fn test_load_migrates_v6_lossy_flag() {
+    let dir = tempfile::tempdir().unwrap();
+    let v6 = |ext: &str| {
+        format!(
+            r#"{{"schema_version": 6, "tracks": [
+            {{"dr": 8, "peak_db": -0.5, "rms_db": -9.0, "duration_secs": 60.0, "title": "A", "filename": "a.{0}"}},
+            {{"dr": 9, "peak_db": -0.5, "rms_db": -9.0, "duration_secs": 60.0, "title": "B", "filename": "b.{0}"}}],
+            "overall_dr": 9, "album": null, "dr_rating": "Compressed"}}"#,
+            ext
+        )
+    };
+
+    std::fs::write(dir.path().join("dr_report.json"), v6("mp3")).unwrap();
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert_eq!(loaded.schema_version, dr::models::SCHEMA_VERSION);
+    assert!(loaded.tracks.iter().all(|t| t.lossy));
+
+    std::fs::write(dir.path().join("dr_report.json"), v6("flac")).unwrap();
+    let loaded = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert!(loaded.tracks.iter().all(|t| !t.lossy));
+
+    // Ogg may be Vorbis or FLAC: only re-analysis can tell
+    std::fs::write(dir.path().join("dr_report.json"), v6("ogg")).unwrap();
+    assert!(cache::load_cached_report(dir.path(), BASENAME).is_none());
+}
+
+#[test]
+fn test_load_rejects_newer_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    let future = r#"{"schema_version": 9999, "tracks": [], "overall_dr": 9, "album": null}"#;
+    std::fs::write(dir.path().join("dr_report.json"), future).unwrap();
+
+    assert!(cache::load_cached_report(dir.path(), BASENAME).is_none());
+}
+
+// --- CLI validation tests ---
+
+#[test]
+fn test_bulk_tui_needs_album_subdirectories() {
+    // No --json/--txt needed: the TUI is the output. The tree is checked
+    // before the terminal is taken over.
+    let dir = tempfile::tempdir().unwrap();
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--bulk", "--tui"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No subdirectories found"));
+}
+
+#[test]
+fn test_bulk_requires_output_format() {
+    cargo_bin_cmd!("dr")
+        .args([".", "--bulk"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--bulk requires at least one output format",
+        ));
+}
+
+#[test]
+fn test_csv_combines_with_other_formats() {
+    let base = setup_bulk_dir();
+
+    cargo_bin_cmd!("dr")
+        .args([
+            base.path().to_str().unwrap(),
+            "--bulk",
+            "--json",
+            "--txt",
+            "--csv",
+        ])
+        .assert()
+        .success();
+    for ext in ["json", "txt", "csv"] {
+        assert!(
+            base.path()
+                .join(format!("Album A/dr_report.{}", ext))
+                .exists(),
+            "{}",
+            ext
+        );
+    }
+    let csv = std::fs::read_to_string(base.path().join("Album A/dr_report.csv")).unwrap();
+    assert!(csv.starts_with("DR,Peak (dBFS)"), "{}", csv);
+
+    // A single album directory writes the CSV alongside the cache
+    let album = base.path().join("Album B");
+    std::fs::remove_file(album.join("dr_report.csv")).unwrap();
+    cargo_bin_cmd!("dr")
+        .arg(&album)
+        .args(["--csv", "--regenerate"])
+        .assert()
+        .success();
+    assert!(album.join("dr_report.csv").exists());
+}
+
+#[test]
+fn test_csv_bom_and_header_language() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_album(dir.path(), &[(440.0, 0.5, 6.0)], 8000);
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--csv", "--csv-bom", "--csv-header", "fr"])
+        .assert()
+        .success();
+    let csv = std::fs::read_to_string(dir.path().join("dr_report.csv")).unwrap();
+    assert!(csv.starts_with("\u{feff}DR,Crête (dBFS),"), "{}", csv);
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args([
+            "--csv",
+            "--regenerate",
+            "--csv-header",
+            "Dyn,Pk,TP,Level,Time,MB,Name",
+        ])
+        .assert()
+        .success();
+    let csv = std::fs::read_to_string(dir.path().join("dr_report.csv")).unwrap();
+    assert!(csv.starts_with("Dyn,Pk,TP,Level,Time,MB,Name\n"), "{}", csv);
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--csv", "--csv-header", "Dyn,Pk"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "expected a language (en, de, fr, es) or 7 comma-separated labels",
+        ));
+}
+
+// --- Bulk mode integration tests ---
+
+/// Helper: create a temp directory with two "album" subdirectories, each containing a sine WAV.
+fn setup_bulk_dir() -> tempfile::TempDir {
+    let base = tempfile::tempdir().unwrap();
+
+    let album_a = base.path().join("Album A");
+    let album_b = base.path().join("Album B");
+    std::fs::create_dir(&album_a).unwrap();
+    std::fs::create_dir(&album_b).unwrap();
+
+    generate_sine_wav(&album_a, "01-track.wav", 440.0, 0.99, 12.0, 44100);
+    generate_sine_wav(&album_b, "01-track.wav", 880.0, 0.99, 12.0, 44100);
+
+    base
+}
+
+#[test]
+fn test_bulk_json_only() {
+    let base = setup_bulk_dir();
+
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .assert()
+        .success();
+
+    // JSON reports should exist in both album directories
+    assert!(base.path().join("Album A/dr_report.json").exists());
+    assert!(base.path().join("Album B/dr_report.json").exists());
+    // Text reports should NOT exist
+    assert!(!base.path().join("Album A/dr_report.txt").exists());
+    assert!(!base.path().join("Album B/dr_report.txt").exists());
+
+    // Verify JSON is valid
+    let json_a = std::fs::read_to_string(base.path().join("Album A/dr_report.json")).unwrap();
+    let parsed: AlbumResult = serde_json::from_str(&json_a).unwrap();
+    assert_eq!(parsed.tracks.len(), 1);
+}
+
+#[test]
+fn test_bulk_txt_only() {
+    let base = setup_bulk_dir();
+
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--txt"])
+        .assert()
+        .success();
+
+    // Text reports should exist
+    assert!(base.path().join("Album A/dr_report.txt").exists());
+    assert!(base.path().join("Album B/dr_report.txt").exists());
+    // JSON reports should NOT exist
+    assert!(!base.path().join("Album A/dr_report.json").exists());
+    assert!(!base.path().join("Album B/dr_report.json").exists());
+
+    // Verify text content has expected table structure
+    let txt = std::fs::read_to_string(base.path().join("Album A/dr_report.txt")).unwrap();
+    assert!(txt.contains("DR"));
+    assert!(txt.contains("Official DR value"));
+}
+
+#[test]
+fn test_bulk_json_and_txt() {
+    let base = setup_bulk_dir();
+
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--json", "--txt"])
+        .assert()
+        .success();
+
+    // Both report types should exist in both albums
+    for album in &["Album A", "Album B"] {
         assert!(base.path().join(album).join("dr_report.json").exists());
         assert!(base.path().join(album).join("dr_report.txt").exists());
     }
 }
 
-#[test]
-fn test_bulk_skips_existing_reports() {
-    let base = setup_bulk_dir();
+#[test]
+fn test_bulk_skips_existing_reports() {
+    let base = setup_bulk_dir();
+
+    // First run — generates reports
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .assert()
+        .success();
+
+    // Record modification time of Album A's report
+    let report_path = base.path().join("Album A/dr_report.json");
+    let mtime_before = std::fs::metadata(&report_path).unwrap().modified().unwrap();
+
+    // Brief sleep to ensure mtime would differ if file were rewritten
+    std::thread::sleep(std::time::Duration::from_millis(50));
+
+    // Second run — should skip
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Skipping"));
+
+    let mtime_after = std::fs::metadata(&report_path).unwrap().modified().unwrap();
+    assert_eq!(mtime_before, mtime_after, "Report should not have been rewritten");
+}
+
+#[test]
+fn test_bulk_regenerate_overwrites() {
+    let base = setup_bulk_dir();
+
+    // First run
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .assert()
+        .success();
+
+    let report_path = base.path().join("Album A/dr_report.json");
+    let mtime_before = std::fs::metadata(&report_path).unwrap().modified().unwrap();
+
+    std::thread::sleep(std::time::Duration::from_millis(50));
+
+    // Second run with --regenerate — should re-analyze
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--json", "--regenerate"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Analyzing"));
+
+    let mtime_after = std::fs::metadata(&report_path).unwrap().modified().unwrap();
+    assert_ne!(mtime_before, mtime_after, "Report should have been rewritten");
+}
+
+#[test]
+fn test_bulk_no_subdirectories() {
+    let base = tempfile::tempdir().unwrap();
+    // Empty dir — no subdirectories
+    std::fs::write(base.path().join("file.txt"), "not a dir").unwrap();
+
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No subdirectories found"));
+}
+
+#[test]
+fn test_bulk_summary_line() {
+    let base = setup_bulk_dir();
+
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .assert()
+        .success()
+        .stderr(
+            predicates::str::is_match(r"Progress: 1/2 albums \(50\.0%\), \S+ elapsed, ETA \S+")
+                .unwrap(),
+        )
+        .stderr(predicates::str::contains(
+            "Done: 2 analyzed, 0 skipped, 0 failed (out of 2 total)",
+        ));
+}
+
+#[test]
+fn test_bulk_empty_subdir_is_not_a_failure() {
+    let base = setup_bulk_dir();
+    std::fs::create_dir(base.path().join("Artwork Only")).unwrap();
+    std::fs::write(base.path().join("Artwork Only/cover.jpg"), b"not audio").unwrap();
+
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("No audio files, skipping"))
+        .stderr(predicates::str::contains(
+            "Done: 2 analyzed, 0 skipped, 0 failed, 1 empty (out of 3 total)",
+        ));
+}
+
+#[test]
+fn test_empty_directory_returns_typed_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let err = analyzer::analyze_directory(dir.path(), 1).unwrap_err();
+    assert!(dr::error::is_no_audio(&err));
+}
+
+// --- Single-directory --txt test ---
+
+#[test]
+fn test_single_dir_txt_flag() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "track.wav", 440.0, 0.99, 12.0, 44100);
+
+    cargo_bin_cmd!("dr")
+        .args([dir.path().to_str().unwrap(), "--txt"])
+        .assert()
+        .success();
+
+    // Both JSON (auto-saved) and TXT should exist
+    assert!(dir.path().join("dr_report.json").exists());
+    assert!(dir.path().join("dr_report.txt").exists());
+
+    let txt = std::fs::read_to_string(dir.path().join("dr_report.txt")).unwrap();
+    assert!(txt.contains("Official DR value"));
+}
+
+// --- Quiet mode ---
+
+#[test]
+fn test_quiet_suppresses_status_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "track.wav", 440.0, 0.99, 12.0, 44100);
+
+    // First run analyzes (benchmark line suppressed), second loads from cache
+    for _ in 0..2 {
+        cargo_bin_cmd!("dr")
+            .args([dir.path().to_str().unwrap(), "--quiet"])
+            .assert()
+            .success()
+            .stdout(predicates::str::contains("Official DR value"))
+            .stderr(predicates::str::is_empty());
+    }
+}
+
+#[test]
+fn test_quiet_bulk_has_no_progress() {
+    let base = setup_bulk_dir();
+
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--json", "--quiet"])
+        .assert()
+        .success()
+        .stderr(predicates::str::is_empty());
+
+    assert!(base.path().join("Album A/dr_report.json").exists());
+}
+
+#[test]
+fn test_verbose_prints_per_file_timing() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01-track.wav", 440.0, 0.99, 6.0, 44100);
+    generate_sine_wav(dir.path(), "02-track.wav", 880.0, 0.99, 6.0, 44100);
+
+    cargo_bin_cmd!("dr")
+        .args([dir.path().to_str().unwrap(), "--verbose", "--regenerate"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("01-track.wav:"))
+        .stderr(predicates::str::contains("02-track.wav:"))
+        .stderr(predicates::str::contains("MB/s"));
+}
+
+#[test]
+fn test_directory_progress_reports_each_track() {
+    use dr::models::AnalysisEvent;
+
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01-track.wav", 440.0, 0.5, 4.0, 8000);
+    generate_sine_wav(dir.path(), "02-track.wav", 880.0, 0.5, 4.0, 8000);
+
+    let events = std::sync::Mutex::new(Vec::new());
+    let options = analyzer::AnalysisOptions::default();
+    let album = analyzer::analyze_directory_with_progress(dir.path(), 2, &options, |event| {
+        let summary = match event {
+            AnalysisEvent::TrackStarted { index } => Some(("started", *index)),
+            AnalysisEvent::TrackCompleted { index, .. } => Some(("completed", *index)),
+            AnalysisEvent::Error { index, .. } => Some(("error", *index)),
+            _ => None,
+        };
+        events.lock().unwrap().extend(summary);
+    })
+    .unwrap();
+
+    assert_eq!(album.tracks.len(), 2);
+    let mut events = events.into_inner().unwrap();
+    events.sort();
+    assert_eq!(
+        events,
+        vec![
+            ("completed", 0),
+            ("completed", 1),
+            ("started", 0),
+            ("started", 1)
+        ]
+    );
+}
+
+#[test]
+fn test_failed_track_fails_album_on_both_paths() {
+    // Both paths fail the album; the async one still reports the partial
+    // album it could build, saying how many tracks it covers
+    use dr::models::AnalysisEvent;
+
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01-track.wav", 440.0, 0.5, 4.0, 8000);
+    std::fs::write(dir.path().join("02-broken.wav"), b"not audio at all").unwrap();
+    generate_sine_wav(dir.path(), "03-track.wav", 880.0, 0.5, 4.0, 8000);
+    let options = analyzer::AnalysisOptions::default();
+
+    let sync_err = analyzer::analyze_directory(dir.path(), 2).unwrap_err();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let async_err = analyzer::analyze_directory_async(dir.path(), tx, 2, &options).unwrap_err();
+    let events: Vec<AnalysisEvent> = rx.into_iter().collect();
+
+    assert_eq!(async_err.to_string(), sync_err.to_string());
+    assert!(
+        sync_err.to_string().contains("02-broken.wav"),
+        "{}",
+        sync_err
+    );
+    let partial = events
+        .iter()
+        .find_map(|e| match e {
+            AnalysisEvent::AlbumCompleted { result } => Some(result),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(partial.tracks.len(), 2);
+    assert_eq!(partial.failed_tracks, 1);
+    assert!(format::format_table(partial).contains("Official DR value: DR0 (2 of 3 tracks)"));
+    let mut completed: Vec<usize> = events
+        .iter()
+        .filter_map(|e| match e {
+            AnalysisEvent::TrackCompleted { index, .. } => Some(*index),
+            _ => None,
+        })
+        .collect();
+    completed.sort();
+    assert_eq!(completed, vec![0, 2]);
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, AnalysisEvent::Error { index: 1, .. })));
+}
+
+// --- Self-test ---
+
+#[test]
+fn test_selftest_passes() {
+    cargo_bin_cmd!("dr")
+        .arg("--selftest")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("PASS"))
+        .stdout(predicates::str::contains("FAIL").not())
+        .stdout(predicates::str::contains("realtime"));
+}
+
+// --- Stereo statistics ---
+
+/// Write a stereo WAV whose right channel is the left sine scaled by `right_gain`.
+fn generate_stereo_wav(dir: &std::path::Path, filename: &str, right_gain: f32) -> PathBuf {
+    let path = dir.join(filename);
+    let left = signals::sine(440.0, 0.5, 6.0, 44100);
+    let interleaved: Vec<f32> = left.iter().flat_map(|&l| [l, l * right_gain]).collect();
+    signals::write_wav_16(&path, &interleaved, 2, 44100).unwrap();
+    path
+}
+
+#[test]
+fn test_stereo_correlation_in_phase() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_stereo_wav(dir.path(), "in_phase.wav", 1.0);
+    let options = analyzer::AnalysisOptions {
+        stereo_stats: true,
+        ..Default::default()
+    };
+
+    let result = analyzer::analyze_file_with_options(&path, &options, |_| {}).unwrap();
+    let correlation = result.stereo_correlation.unwrap();
+    assert!(correlation > 0.99, "expected ~+1, got {}", correlation);
+}
+
+#[test]
+fn test_stereo_correlation_out_of_phase() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_stereo_wav(dir.path(), "inverted.wav", -1.0);
+    let options = analyzer::AnalysisOptions {
+        stereo_stats: true,
+        ..Default::default()
+    };
+
+    let result = analyzer::analyze_file_with_options(&path, &options, |_| {}).unwrap();
+    let correlation = result.stereo_correlation.unwrap();
+    assert!(correlation < -0.99, "expected ~-1, got {}", correlation);
+}
+
+#[test]
+fn test_stereo_correlation_absent_without_flag_or_for_mono() {
+    let dir = tempfile::tempdir().unwrap();
+    let stereo = generate_stereo_wav(dir.path(), "stereo.wav", 1.0);
+    let mono = generate_sine_wav(dir.path(), "mono.wav", 440.0, 0.5, 6.0, 44100);
+    let options = analyzer::AnalysisOptions {
+        stereo_stats: true,
+        ..Default::default()
+    };
+
+    assert!(analyzer::analyze_file(&stereo)
+        .unwrap()
+        .stereo_correlation
+        .is_none());
+    let result = analyzer::analyze_file_with_options(&mono, &options, |_| {}).unwrap();
+    assert!(result.stereo_correlation.is_none());
+}
+
+#[test]
+fn test_stereo_stats_cli_flags_phase_cancellation() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_stereo_wav(dir.path(), "inverted.wav", -1.0);
+
+    cargo_bin_cmd!("dr")
+        .args([path.to_str().unwrap(), "--stereo-stats"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Stereo correlation: -1.00"))
+        .stdout(predicates::str::contains("possible phase cancellation"));
+}
+
+// --- Config file ---
+
+#[test]
+fn test_config_file_sets_default_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "track.wav", 440.0, 0.5, 6.0, 44100);
+    std::fs::write(dir.path().join("dr.toml"), "formats = [\"json\"]\n").unwrap();
+
+    cargo_bin_cmd!("dr")
+        .current_dir(dir.path())
+        .arg(path.to_str().unwrap())
+        .env_remove("DR_FORMATS")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"dr\":"));
+}
+
+#[test]
+fn test_env_overrides_config_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "track.wav", 440.0, 0.5, 6.0, 44100);
+    std::fs::write(dir.path().join("dr.toml"), "formats = [\"json\"]\n").unwrap();
+
+    cargo_bin_cmd!("dr")
+        .current_dir(dir.path())
+        .arg(path.to_str().unwrap())
+        .env("DR_FORMATS", "txt")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Official DR value"));
+}
+
+#[test]
+fn test_no_cache_env_skips_report_and_flag_wins() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01.wav", 440.0, 0.5, 6.0, 8000);
+    let report = dir.path().join("dr_report.json");
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .env("DR_NO_CACHE", "1")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Official DR value"));
+    assert!(!report.exists());
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--cache")
+        .env("DR_NO_CACHE", "1")
+        .assert()
+        .success();
+    assert!(report.exists());
+
+    // An existing report is not read either
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--no-cache")
+        .env_remove("DR_NO_CACHE")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("loaded from cached report").not());
+}
+
+#[test]
+fn test_verbosity_controls_log_level() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01.wav", 440.0, 0.5, 6.0, 8000);
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("-vv")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "[debug dr::analyzer] 01.wav: pcm_s16le stream, 8000 Hz",
+        ))
+        .stderr(predicates::str::contains("[debug dr::cache] wrote report"));
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("-v")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("[debug dr::cache] using report"));
+    // Without -v, debug records stay hidden; with -q, so does info
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("loaded from cached report"))
+        .stderr(predicates::str::contains("[debug").not());
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("-q")
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn test_config_extensions_filter_scan() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "track.wav", 440.0, 0.5, 6.0, 44100);
+    let config = dir.path().join("custom.toml");
+    std::fs::write(&config, "extensions = [\"flac\"]\n").unwrap();
+
+    cargo_bin_cmd!("dr")
+        .args([dir.path().to_str().unwrap(), "--regenerate", "--config"])
+        .arg(&config)
+        .env_remove("DR_EXTENSIONS")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No audio files found"));
+}
+
+#[test]
+fn test_invalid_config_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("bad.toml");
+    std::fs::write(&config, "jobz = 4\n").unwrap();
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path().to_str().unwrap())
+        .arg("--config")
+        .arg(&config)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Invalid config file"));
+}
+
+// --- True peak ---
+
+#[test]
+fn test_true_peak_reported_alongside_sample_peak() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "tone.wav", 440.0, 0.5, 6.0, 44100);
+
+    let result = analyzer::analyze_file(&path).unwrap();
+    assert!(result.true_peak_db >= result.peak_db - 0.01);
+    assert!((result.true_peak_db - result.peak_db).abs() < 0.1);
+
+    cargo_bin_cmd!("dr")
+        .arg(path.to_str().unwrap())
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Peak (dBFS)"))
+        .stdout(predicates::str::contains("True Peak (dBTP)"));
+}
+
+#[test]
+fn test_load_rejects_v1_report_with_tracks() {
+    let dir = tempfile::tempdir().unwrap();
+    let v1 = r#"{"schema_version": 1, "tracks": [{"dr": 10, "peak_db": -0.5, "rms_db": -12.0,
+        "duration_secs": 180.0, "title": "A", "filename": "a.flac"}],
+        "overall_dr": 10, "album": null, "dr_rating": "Compressed"}"#;
+    std::fs::write(dir.path().join("dr_report.json"), v1).unwrap();
+
+    assert!(cache::load_cached_report(dir.path(), BASENAME).is_none());
+}
+
+// --- Loudness and normalization preview ---
+
+#[test]
+fn test_loudness_measured_per_track_and_album() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_album(dir.path(), &[(1000.0, 0.1, 6.0), (1000.0, 0.1, 6.0)], 48000);
+
+    let result = analyzer::analyze_directory(dir.path(), 1).unwrap();
+    for track in &result.tracks {
+        let lufs = track.loudness_lufs.unwrap();
+        assert!(
+            (lufs - -23.0).abs() < 0.3,
+            "expected ~-23 LUFS, got {:.2}",
+            lufs
+        );
+    }
+    assert!((result.album_lufs.unwrap() - -23.0).abs() < 0.3);
+}
+
+#[test]
+fn test_preview_normalized_safe_for_sine() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "tone.wav", 1000.0, 0.1, 6.0, 48000);
+
+    // -23 LUFS sine with a -20 dBFS peak, raised 9 dB, stays below 0 dBTP
+    cargo_bin_cmd!("dr")
+        .args([path.to_str().unwrap(), "--preview-normalized=-14"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Normalization to -14.0 LUFS"))
+        .stdout(predicates::str::contains("Safe to normalize"));
+}
+
+#[test]
+fn test_preview_normalized_flags_clipping() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_pink_noise(dir.path(), "noise.wav", 0.9, 12.0, 44100, 7);
+    cargo_bin_cmd!("dr")
+        .args([path.to_str().unwrap(), "--preview-normalized=-5", "--json"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"clips\": true"));
+}
+
+// --- Title source ---
+
+/// Write a sine WAV with a RIFF INFO `INAM` (title) tag placed before the data chunk.
+fn generate_tagged_wav(dir: &std::path::Path, filename: &str, title: &str) -> PathBuf {
+    generate_info_tagged_wav(dir, filename, &[(b"INAM", title)])
+}
+
+/// Write a sine WAV with the given RIFF INFO tags (e.g. `INAM` title, `IPRT`
+/// track number) placed before the data chunk.
+fn generate_info_tagged_wav(
+    dir: &std::path::Path,
+    filename: &str,
+    tags: &[(&[u8; 4], &str)],
+) -> PathBuf {
+    let path = generate_sine_wav(dir, filename, 440.0, 0.5, 3.5, 44100);
+    let mut bytes = std::fs::read(&path).unwrap();
+
+    let mut info = b"INFO".to_vec();
+    for (id, text) in tags {
+        let mut value = text.as_bytes().to_vec();
+        value.push(0);
+        if value.len() % 2 == 1 {
+            value.push(0);
+        }
+        info.extend_from_slice(*id);
+        info.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        info.extend_from_slice(&value);
+    }
+    let mut list = b"LIST".to_vec();
+    list.extend_from_slice(&(info.len() as u32).to_le_bytes());
+    list.extend_from_slice(&info);
+
+    // RIFF header (12) + fmt chunk (8 + 16)
+    bytes.splice(36..36, list.iter().copied());
+    let riff_size = (bytes.len() - 8) as u32;
+    bytes[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[test]
+fn test_reference_compares_album_dr() {
+    let dir = tempfile::tempdir().unwrap();
+    let album = dir.path().join("album");
+    std::fs::create_dir(&album).unwrap();
+    for name in ["01.wav", "02.wav"] {
+        generate_info_tagged_wav(&album, name, &[(b"IPRD", "Sines"), (b"IART", "Oscillator")]);
+    }
+    let refs = dir.path().join("refs.csv");
+    std::fs::write(&refs, "artist,album,dr\nOscillator,Sines,DR9\n").unwrap();
+
+    cargo_bin_cmd!("dr")
+        .arg(&album)
+        .arg("--reference")
+        .arg(&refs)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Reference:         measured DR0, database DR9 (differs)",
+        ));
+
+    // Also applied to the cached report, and carried in JSON
+    let output = cargo_bin_cmd!("dr")
+        .arg(&album)
+        .args(["--json", "--reference"])
+        .arg(&refs)
+        .output()
+        .unwrap();
+    let result: AlbumResult = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result.artist.as_deref(), Some("Oscillator"));
+    assert_eq!(result.reference_dr, Some(9));
+
+    std::fs::write(&refs, "[{\"album\": \"Other\", \"dr\": 9}]").unwrap();
+    let json_refs = dir.path().join("refs.json");
+    std::fs::rename(&refs, &json_refs).unwrap();
+    cargo_bin_cmd!("dr")
+        .arg(&album)
+        .arg("--reference")
+        .arg(&json_refs)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Reference:").not())
+        .stderr(predicates::str::contains(
+            "no reference entry for Oscillator - Sines",
+        ));
+}
+
+#[test]
+fn test_apply_existing_gain_scales_levels() {
+    let dir = tempfile::tempdir().unwrap();
+    let segments = [(440.0, 0.25, 9.0)];
+    let plain = generate_chained_ogg_flac(dir.path(), "plain.ogg", &segments, 8000);
+    let quiet = generate_tagged_ogg_flac(
+        dir.path(),
+        "quiet.ogg",
+        &segments,
+        8000,
+        &["REPLAYGAIN_TRACK_GAIN=-6.00 dB"],
+    );
+    let loud = generate_tagged_ogg_flac(
+        dir.path(),
+        "loud.ogg",
+        &segments,
+        8000,
+        &["REPLAYGAIN_TRACK_GAIN=+18.00 dB"],
+    );
+
+    let options = analyzer::AnalysisOptions {
+        apply_existing_gain: true,
+        ..Default::default()
+    };
+    let base = analyzer::analyze_file(&plain).unwrap();
+    let untouched = analyzer::analyze_file(&quiet).unwrap();
+    assert!(untouched.applied_gain_db.is_none(), "off by default");
+    assert_eq!(untouched.peak_db, base.peak_db);
+
+    let gained = analyzer::analyze_file_with_options(&quiet, &options, |_| {}).unwrap();
+    assert_eq!(gained.applied_gain_db, Some(-6.0));
+    assert!((gained.peak_db - (base.peak_db - 6.0)).abs() < 0.01);
+    assert!((gained.rms_db - (base.rms_db - 6.0)).abs() < 0.01);
+    assert_eq!(gained.dr, base.dr);
+    assert!(format::format_table_single(&gained)
+        .contains("Gain applied:      -6.00 dB (ReplayGain track gain)"));
+
+    // Positive gain pushes the peak above full scale: reported, not clamped
+    let hot = analyzer::analyze_file_with_options(&loud, &options, |_| {}).unwrap();
+    assert!(hot.peak_db > 5.0, "peak {}", hot.peak_db);
+    assert!(format::format_table_single(&hot).contains("peak above 0 dBFS, would clip"));
+
+    // Untagged files are measured as is
+    let as_is = analyzer::analyze_file_with_options(&plain, &options, |_| {}).unwrap();
+    assert!(as_is.applied_gain_db.is_none());
+    assert_eq!(as_is.peak_db, base.peak_db);
+}
+
+#[test]
+fn test_title_source_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_tagged_wav(dir.path(), "01-track.wav", "Tagged Title");
+    let analyze = |title_source| {
+        let options = analyzer::AnalysisOptions {
+            title_source,
+            ..Default::default()
+        };
+        analyzer::analyze_file_with_options(&path, &options, |_| {})
+            .unwrap()
+            .title
+    };
+
+    assert_eq!(analyze(analyzer::TitleSource::Metadata), "Tagged Title");
+    assert_eq!(analyze(analyzer::TitleSource::Filename), "01-track");
+    assert_eq!(
+        analyze(analyzer::TitleSource::Both),
+        "Tagged Title (01-track)"
+    );
+}
+
+#[test]
+fn test_title_source_cli_flag() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_tagged_wav(dir.path(), "01-track.wav", "Tagged Title");
+
+    cargo_bin_cmd!("dr")
+        .args([path.to_str().unwrap(), "--title-source", "filename"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("01-track"))
+        .stdout(predicates::str::contains("Tagged Title").not());
+}
+
+#[test]
+fn test_track_number_tags_order_the_album() {
+    let dir = tempfile::tempdir().unwrap();
+    // Filename order is the reverse of the tagged order
+    generate_info_tagged_wav(
+        dir.path(),
+        "a.wav",
+        &[(b"INAM", "Second"), (b"IPRT", "2/2")],
+    );
+    generate_info_tagged_wav(dir.path(), "b.wav", &[(b"INAM", "First"), (b"IPRT", "1/2")]);
+
+    let result = analyzer::analyze_directory(dir.path(), 1).unwrap();
+    let order: Vec<_> = result
+        .tracks
+        .iter()
+        .map(|t| (t.track_number, t.title.as_str()))
+        .collect();
+    assert_eq!(order, [(Some(1), "First"), (Some(2), "Second")]);
+
+    // Without a tag on every track, scan order stands
+    generate_tagged_wav(dir.path(), "c.wav", "Untagged");
+    let result = analyzer::analyze_directory(dir.path(), 1).unwrap();
+    let titles: Vec<_> = result.tracks.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(titles, ["Second", "First", "Untagged"]);
+    assert_eq!(result.tracks[2].track_number, None);
+}
+
+// --- STDIN follow mode ---
+
+#[test]
+fn test_follow_emits_periodic_snapshots() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "live.wav", 440.0, 0.5, 9.5, 44100);
+
+    let output = cargo_bin_cmd!("dr")
+        .args(["-", "--format", "wav", "--follow", "3"])
+        .write_stdin(std::fs::read(&path).unwrap())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let snapshots: Vec<&str> = stderr.lines().filter(|l| l.starts_with('[')).collect();
+    assert_eq!(
+        snapshots.len(),
+        3,
+        "expected snapshots at 3s, 6s, 9s: {}",
+        stderr
+    );
+    assert!(snapshots[0].starts_with("[0:03] DR"));
+    // The final result at EOF is unchanged
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Official DR value"));
+}
+
+#[test]
+fn test_follow_json_is_ndjson() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "live.wav", 440.0, 0.5, 6.5, 44100);
+
+    let output = cargo_bin_cmd!("dr")
+        .args(["-", "--format", "wav", "--follow", "3", "--json"])
+        .write_stdin(std::fs::read(&path).unwrap())
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    for line in stderr.lines() {
+        let partial: TrackResult = serde_json::from_str(line).unwrap();
+        assert_eq!(partial.filename, "STDIN");
+    }
+    assert_eq!(stderr.lines().count(), 2);
+}
+
+#[test]
+fn test_follow_requires_stdin() {
+    cargo_bin_cmd!("dr")
+        .args([".", "--follow", "3"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--follow only applies"));
+}
+
+// --- Duplicate detection ---
+
+#[test]
+fn test_dedupe_report_flags_identical_tracks() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01 clean.wav", 440.0, 0.5, 7.0, 8000);
+    generate_sine_wav(dir.path(), "02 other.wav", 440.0, 0.05, 7.0, 8000);
+    generate_sine_wav(dir.path(), "03 explicit.wav", 440.0, 0.5, 7.0, 8000);
+    // A square wave with the sine's RMS: the same levels, different content
+    let square: Vec<f32> = signals::sine(440.0, 0.5, 7.0, 8000)
+        .iter()
+        .map(|s| 0.5 * std::f32::consts::FRAC_1_SQRT_2 * s.signum())
+        .collect();
+    signals::write_wav_16(&dir.path().join("04 square.wav"), &square, 1, 8000).unwrap();
+
+    let result = analyzer::analyze_directory(dir.path(), 1).unwrap();
+    assert!(result.tracks.iter().all(|t| t.fingerprint.is_some()));
+    assert!((result.tracks[3].rms_db - result.tracks[0].rms_db).abs() < 0.1);
+    assert_eq!(
+        dr::fingerprint::likely_duplicates(&result.tracks),
+        vec![(0, 2)]
+    );
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--dedupe-report")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Likely duplicate tracks:"));
+    assert!(stdout.contains("01 clean") && stdout.contains("03 explicit"));
+    assert!(!stdout.contains("02 other  \u{2248}"));
+}
+
+// --- Singles mode ---
+
+#[test]
+fn test_singles_outputs_track_array() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "a.wav", 440.0, 0.5, 4.0, 8000);
+    generate_sine_wav(dir.path(), "b.wav", 440.0, 0.1, 4.0, 8000);
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--singles", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let tracks: Vec<TrackResult> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tracks.len(), 2);
+    assert_eq!(tracks[0].filename, "a.wav");
+    assert_eq!(tracks[1].filename, "b.wav");
+    // No album report is written for a bag of singles
+    assert!(!dir.path().join("dr_report.json").exists());
+}
+
+#[test]
+fn test_singles_skip_a_broken_file_and_fail_at_the_end() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "a.wav", 440.0, 0.5, 4.0, 8000);
+    std::fs::write(dir.path().join("b.wav"), b"not audio").unwrap();
+    generate_sine_wav(dir.path(), "c.wav", 440.0, 0.1, 4.0, 8000);
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--singles", "--json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let tracks: Vec<TrackResult> = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<&str> = tracks.iter().map(|t| t.filename.as_str()).collect();
+    assert_eq!(names, ["a.wav", "c.wav"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Skipping ") && stderr.contains("b.wav: "),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("1 of 3 files failed to analyze"),
+        "{}",
+        stderr
+    );
+}
+
+// --- STDIN file list ---
+
+#[test]
+fn test_stdin_list_emits_ndjson_and_skips_bad_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = generate_sine_wav(dir.path(), "a.wav", 440.0, 0.5, 4.0, 8000);
+    let b = generate_sine_wav(dir.path(), "b.wav", 440.0, 0.1, 4.0, 8000);
+    let junk = dir.path().join("notes.wav");
+    std::fs::write(&junk, b"not audio").unwrap();
+    let list = format!(
+        "{}\n{}\n\n{}\n{}\n",
+        a.display(),
+        dir.path().join("missing.flac").display(),
+        junk.display(),
+        b.display()
+    );
+
+    let output = cargo_bin_cmd!("dr")
+        .args(["--stdin-list", "--json"])
+        .write_stdin(list)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let tracks: Vec<TrackResult> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(tracks.len(), 2);
+    assert_eq!(tracks[0].filename, "a.wav");
+    assert_eq!(tracks[1].filename, "b.wav");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("missing.flac: not a file"));
+    assert!(stderr.contains("notes.wav"));
+}
+
+// --- Perceptual (K-weighted) DR ---
+
+#[test]
+fn test_k_weighted_dr_is_separate_from_official_dr() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "sine.wav", 1000.0, 0.5, 7.0, 8000);
+
+    let plain = analyzer::analyze_file(&path).unwrap();
+    assert!(plain.perceptual_dr.is_none());
+    assert!(!format::format_json_single(&plain).contains("perceptual_dr"));
+
+    let options = analyzer::AnalysisOptions {
+        k_weighted: true,
+        ..Default::default()
+    };
+    let weighted = analyzer::analyze_file_with_options(&path, &options, |_| {}).unwrap();
+    assert_eq!(weighted.dr, plain.dr);
+    // A steady sine stays a steady sine after filtering: its crest factor is unchanged
+    assert_eq!(weighted.perceptual_dr, Some(plain.dr));
+    let table = format::format_table_single(&weighted);
+    assert!(table.contains("Official DR value: DR"));
+    assert!(table.contains("Perceptual DR (K-weighted, experimental, not official):"));
+}
+
+// --- Progress event stream ---
+
+#[test]
+fn test_progress_json_streams_events_to_stderr() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_album(dir.path(), &[(440.0, 0.5, 4.0), (440.0, 0.2, 4.0)], 8000);
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--progress-json", "--json", "-j", "2"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let events: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let count = |name: &str| events.iter().filter(|e| e["event"] == name).count();
+    assert_eq!(count("TrackStarted"), 2);
+    assert_eq!(count("TrackCompleted"), 2);
+    assert_eq!(count("AlbumCompleted"), 1);
+    assert_eq!(events.last().unwrap()["event"], "AlbumCompleted");
+
+    // The report on stdout is unaffected, with tracks in file order
+    let album: AlbumResult = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<_> = album.tracks.iter().map(|t| t.filename.as_str()).collect();
+    assert_eq!(names, ["01.wav", "02.wav"]);
+}
+
+#[test]
+fn test_progress_json_throttles_track_progress() {
+    // A minute of 44.1 kHz audio decodes as thousands of packets
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01.wav", 440.0, 0.5, 60.0, 44100);
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--progress-json", "--no-cache"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let percents: Vec<f64> = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|e| e["event"] == "TrackProgress")
+        .map(|e| e["percent"].as_f64().unwrap())
+        .collect();
+    // Steps of at least 1%, plus the final 1.0
+    assert!(
+        (50..=101).contains(&percents.len()),
+        "{} progress events",
+        percents.len()
+    );
+    assert!(percents.windows(2).all(|w| w[0] <= w[1]), "{:?}", percents);
+    assert_eq!(*percents.last().unwrap(), 1.0);
+}
+
+// --- WAV format variants ---
+
+/// Write a WAV with an arbitrary fmt chunk body followed by `data`.
+fn write_wav_with_fmt(path: &std::path::Path, fmt: &[u8], data: &[u8]) {
+    let mut out = Vec::new();
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&((4 + 8 + fmt.len() + 8 + data.len()) as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+    out.extend_from_slice(fmt);
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    std::fs::write(path, out).unwrap();
+}
+
+/// The common 16-byte start of a fmt chunk.
+fn fmt_header(format_tag: u16, channels: u16, sample_rate: u32, bits: u16) -> Vec<u8> {
+    let block_align = channels * bits / 8;
+    let mut fmt = Vec::new();
+    fmt.extend_from_slice(&format_tag.to_le_bytes());
+    fmt.extend_from_slice(&channels.to_le_bytes());
+    fmt.extend_from_slice(&sample_rate.to_le_bytes());
+    fmt.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    fmt.extend_from_slice(&block_align.to_le_bytes());
+    fmt.extend_from_slice(&bits.to_le_bytes());
+    fmt
+}
+
+#[test]
+fn test_float_wav_matches_direct_analysis() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("float.wav");
+    // Float WAV can carry peaks above full scale; they must not be clipped
+    let samples = signals::pink_noise_with_dynamics(1.5, 9.0, 8000, 7);
+    let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    write_wav_with_fmt(&path, &fmt_header(3, 1, 8000, 32), &data);
+
+    let decoded = analyzer::analyze_file(&path).unwrap();
+    let expected = analyzer::analyze_samples(&samples, 1, 8000, &Default::default());
+    assert_eq!(decoded.dr, expected.dr);
+    assert!(
+        (5..=20).contains(&decoded.dr),
+        "implausible DR{}",
+        decoded.dr
+    );
+    assert!((decoded.peak_db - expected.peak_db).abs() < 1e-6);
+    assert!(
+        decoded.peak_db > 3.0,
+        "float peak above 0 dBFS, got {:.2}",
+        decoded.peak_db
+    );
+    assert!((decoded.duration_secs - 9.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_extensible_six_channel_wav() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("surround.wav");
+
+    // 5.1 with a different level per channel, so a wrong channel count or
+    // interleaving would change the measurement
+    let mono = signals::pink_noise_with_dynamics(0.8, 9.0, 8000, 11);
+    let gains = [1.0f32, 0.9, 0.7, 0.3, 0.5, 0.5];
+    let samples: Vec<f32> = mono
+        .iter()
+        .flat_map(|&s| gains.iter().map(move |g| s * g))
+        .collect();
+    let data: Vec<u8> = samples
+        .iter()
+        .flat_map(|&s| ((s * i16::MAX as f32) as i16).to_le_bytes())
+        .collect();
+
+    let mut fmt = fmt_header(0xFFFE, 6, 8000, 16);
+    fmt.extend_from_slice(&22u16.to_le_bytes()); // cbSize
+    fmt.extend_from_slice(&16u16.to_le_bytes()); // valid bits per sample
+    fmt.extend_from_slice(&0x3Fu32.to_le_bytes()); // FL FR FC LFE BL BR
+                                                   // KSDATAFORMAT_SUBTYPE_PCM
+    fmt.extend_from_slice(&[
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B,
+        0x71,
+    ]);
+    write_wav_with_fmt(&path, &fmt, &data);
+
+    let decoded = analyzer::analyze_file(&path).unwrap();
+    let quantized: Vec<f32> = samples
+        .iter()
+        .map(|&s| (s * i16::MAX as f32) as i16 as f32 / 32768.0)
+        .collect();
+    let expected = analyzer::analyze_samples(&quantized, 6, 8000, &Default::default());
+    assert!((decoded.duration_secs - 9.0).abs() < 1e-6);
+    assert_eq!(decoded.dr, expected.dr);
+    assert!((decoded.rms_db - expected.rms_db).abs() < 0.01);
+    let (got, want) = (
+        decoded.loudness_lufs.unwrap(),
+        expected.loudness_lufs.unwrap(),
+    );
+    assert!((got - want).abs() < 0.01, "{:.2} vs {:.2} LUFS", got, want);
+}
+
+// --- Loudness time series ---
+
+#[test]
+fn test_loudness_series_only_when_requested() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "sine.wav", 1000.0, 0.5, 4.0, 8000);
+
+    let plain = analyzer::analyze_file(&path).unwrap();
+    assert!(plain.momentary_lufs.is_empty() && plain.short_term_lufs.is_empty());
+    assert!(!format::format_json_single(&plain).contains("momentary_lufs"));
+
+    let options = analyzer::AnalysisOptions {
+        loudness_series: true,
+        ..Default::default()
+    };
+    let result = analyzer::analyze_file_with_options(&path, &options, |_| {}).unwrap();
+    // 40 hops of 100 ms
+    assert_eq!(result.momentary_lufs.len(), 37);
+    assert_eq!(result.short_term_lufs.len(), 11);
+    let integrated = result.loudness_lufs.unwrap();
+    assert!(result
+        .momentary_lufs
+        .iter()
+        .all(|m| (m - integrated).abs() < 0.5));
+}
+
+// --- Level envelope ---
+
+#[test]
+fn test_envelope_exports_block_rms_csv() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(dir.path(), "sine.wav", 1000.0, 0.5, 9.0, 8000);
+    let csv = dir.path().join("envelope.csv");
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(&path)
+        .arg("--json")
+        .arg("--envelope")
+        .arg(&csv)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let track: TrackResult = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(track.envelope_db.len(), 3);
+
+    let text = std::fs::read_to_string(&csv).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "Time (s),RMS (dB)");
+    assert_eq!(lines.len(), 4);
+    assert!(lines[3].starts_with("6.00,"));
+    // A sine's DR-RMS is its peak: 0.5 is about -6 dB
+    assert!(lines[1..].iter().all(|l| l.ends_with(",-6.02")));
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--envelope")
+        .arg(&csv)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--envelope needs a single file"));
+}
+// --- Bulk dry run ---
+
+#[test]
+fn test_bulk_dry_run_plans_without_analyzing() {
+    let base = setup_bulk_dir();
+    std::fs::create_dir(base.path().join("Artwork")).unwrap();
+    // Album A already has a report
+    cache::save_report(
+        &base.path().join("Album A"),
+        BASENAME,
+        &AlbumResult::default(),
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("dr")
+        .args([
+            base.path().to_str().unwrap(),
+            "--bulk",
+            "--json",
+            "--dry-run",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "skip     Album A (reports exist)",
+        ))
+        .stdout(predicates::str::contains("analyze  Album B (1 files)"))
+        .stdout(predicates::str::contains(
+            "empty    Artwork (no audio files)",
+        ))
+        .stderr(predicates::str::contains(
+            "Dry run: 1 to analyze, 1 to skip, 1 empty (out of 3 total)",
+        ));
+
+    assert!(!base.path().join("Album B/dr_report.json").exists());
+}
+
+#[test]
+fn test_collection_names_are_sanitized_and_unique() {
+    assert_eq!(cache::sanitize_basename("AC/DC: Live?"), "AC_DC_ Live_");
+    assert_eq!(cache::sanitize_basename(" ..Hidden. "), "Hidden");
+    assert_eq!(cache::sanitize_basename("..."), "album");
+
+    let mut names = cache::CollectionNames::default();
+    assert_eq!(names.claim("Greatest Hits"), "Greatest Hits");
+    assert_eq!(names.claim("greatest hits"), "greatest hits (2)");
+    assert_eq!(names.claim("Greatest Hits"), "Greatest Hits (3)");
+    assert_eq!(names.claim("Live"), "Live");
+}
+
+#[test]
+fn test_report_into_collects_album_named_reports() {
+    let base = setup_bulk_dir();
+    let into = tempfile::tempdir().unwrap();
+    let reports = into.path().join("reports");
+    // Same album name in two directories
+    let tagged = |dir: &str| {
+        let dir = base.path().join(dir);
+        std::fs::create_dir(&dir).unwrap();
+        generate_info_tagged_wav(&dir, "01.wav", &[(b"INAM", "One"), (b"IPRD", "Hits")]);
+    };
+    tagged("Hits 1");
+    tagged("Hits 2");
+
+    cargo_bin_cmd!("dr")
+        .args([
+            base.path().to_str().unwrap(),
+            "--bulk",
+            "--json",
+            "--txt",
+            "--report-into",
+        ])
+        .arg(&reports)
+        .assert()
+        .success();
+    for name in [
+        "Album A.json",
+        "Album B.txt",
+        "Hits.json",
+        "Hits (2).json",
+        "Hits (2).txt",
+    ] {
+        assert!(reports.join(name).exists(), "missing {}", name);
+    }
+    assert!(!base.path().join("Album A/dr_report.json").exists());
+
+    // A single album directory, whose report is never read back
+    let album = base.path().join("Album A");
+    cargo_bin_cmd!("dr")
+        .arg(&album)
+        .arg("--report-into")
+        .arg(&reports)
+        .assert()
+        .success();
+    cargo_bin_cmd!("dr")
+        .arg(&album)
+        .arg("--report-into")
+        .arg(&reports)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("loaded from cached report").not());
+    assert!(!album.join("dr_report.json").exists());
+}
+
+#[test]
+fn test_bulk_json_stdout_prints_all_albums() {
+    let base = setup_bulk_dir();
+    let run = || {
+        let output = cargo_bin_cmd!("dr")
+            .args([
+                base.path().to_str().unwrap(),
+                "--bulk",
+                "--json",
+                "--json-stdout",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice::<Vec<AlbumResult>>(&output.stdout).unwrap()
+    };
+
+    // Analyzed, then read back from the reports the first run wrote
+    for albums in [run(), run()] {
+        assert_eq!(albums.len(), 2);
+        assert!(albums.iter().all(|a| a.tracks.len() == 1));
+    }
+    assert!(base.path().join("Album A/dr_report.json").exists());
+
+    // Without --json-stdout, bulk prints nothing to stdout
+    cargo_bin_cmd!("dr")
+        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .assert()
+        .success()
+        .stdout("");
+    cargo_bin_cmd!("dr")
+        .args([
+            base.path().to_str().unwrap(),
+            "--bulk",
+            "--txt",
+            "--json-stdout",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("add --json"));
+}
+
+#[test]
+fn test_bulk_filter_processes_only_matching_albums() {
+    let base = setup_bulk_dir();
+    std::fs::create_dir(base.path().join("Artwork")).unwrap();
+
+    cargo_bin_cmd!("dr")
+        .args([
+            base.path().to_str().unwrap(),
+            "--bulk",
+            "--json",
+            "--bulk-filter",
+            "* B",
+        ])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Analyzing: Album B"))
+        .stderr(predicates::str::contains("Album A").not())
+        .stderr(predicates::str::contains(
+            "Done: 1 analyzed, 0 skipped, 0 failed (out of 1 total; 2 filtered out)",
+        ));
+    assert!(base.path().join("Album B/dr_report.json").exists());
+    assert!(!base.path().join("Album A/dr_report.json").exists());
+
+    cargo_bin_cmd!("dr")
+        .args([
+            base.path().to_str().unwrap(),
+            "--bulk",
+            "--json",
+            "--dry-run",
+            "--bulk-filter",
+            "Album ?",
+        ])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "Dry run: 1 to analyze, 1 to skip, 0 empty (out of 2 total; 1 filtered out)",
+        ));
+}
+
+// --- Report basename ---
+
+#[test]
+fn test_report_basename_applies_to_all_reports() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01.wav", 440.0, 0.5, 4.0, 8000);
+    let run = || {
+        cargo_bin_cmd!("dr")
+            .arg(dir.path())
+            .args(["--report-basename", "foo_dr", "--txt"])
+            .assert()
+            .success()
+    };
+
+    run();
+    assert!(dir.path().join("foo_dr.json").exists());
+    assert!(dir.path().join("foo_dr.txt").exists());
+    assert!(!dir.path().join("dr_report.json").exists());
+    assert!(cache::load_cached_report(dir.path(), "foo_dr").is_some());
+
+    // The renamed cache is found again on the next run
+    run().stderr(predicates::str::contains("(loaded from cached report)"));
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--report-basename", "../escape"])
+        .assert()
+        .failure();
+}
+
+// --- Time range ---
+
+#[test]
+fn test_time_range_analyzes_only_that_section() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sections.wav");
+    let mut samples = signals::sine(440.0, 0.9, 6.0, 8000);
+    samples.extend(signals::sine(440.0, 0.1, 6.0, 8000));
+    signals::write_wav_16(&path, &samples, 1, 8000).unwrap();
+
+    let options = analyzer::AnalysisOptions {
+        range: Some(analyzer::TimeRange {
+            start_secs: 7.0,
+            duration_secs: Some(3.0),
+        }),
+        ..Default::default()
+    };
+    let result = analyzer::analyze_file_with_options(&path, &options, |_| {}).unwrap();
+    assert!((result.duration_secs - 3.0).abs() < 1e-6);
+    assert!(
+        (result.peak_db - -20.0).abs() < 0.1,
+        "got {:.2} dBFS",
+        result.peak_db
+    );
+    assert_eq!(result.range_start_secs, Some(7.0));
+    // Times are in the file, not the range
+    assert_eq!(result.loudest_block_secs, Some(7.0));
+    assert!(result
+        .peak_at_secs
+        .is_some_and(|t| (7.0..10.0).contains(&t)));
+    assert!(format::format_table_single(&result).contains("Analyzed range:    0:07-0:10 (3.0s)"));
+
+    cargo_bin_cmd!("dr")
+        .arg(&path)
+        .args(["--start", "20"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("past the end"));
+
+    // A duration past the end reaches the end of the file
+    let output = cargo_bin_cmd!("dr")
+        .arg(&path)
+        .args(["--start", "7", "--duration", "1e300", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let track: TrackResult = serde_json::from_slice(&output.stdout).unwrap();
+    assert!((track.duration_secs - 5.0).abs() < 1e-6);
+    for (flag, value) in [("--start", "NaN"), ("--duration", "inf")] {
+        cargo_bin_cmd!("dr")
+            .arg(&path)
+            .args([flag, value])
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains("both finite"));
+    }
+}
+
+// --- Forced layout ---
+
+#[test]
+fn test_force_channels_and_sample_rate_rescue_bad_headers() {
+    let dir = tempfile::tempdir().unwrap();
+    let left = signals::pink_noise_with_dynamics(0.9, 12.0, 8000, 5);
+    let right = signals::sine(440.0, 0.3, 12.0, 8000);
+    let stereo: Vec<f32> = left
+        .iter()
+        .zip(&right)
+        .flat_map(|(&l, &r)| [l, r])
+        .collect();
+    let good = dir.path().join("good.wav");
+    signals::write_wav_16(&good, &stereo, 2, 8000).unwrap();
+    // The same samples behind headers claiming mono, or twice the rate
+    let mono = dir.path().join("mono.wav");
+    signals::write_wav_16(&mono, &stereo, 1, 8000).unwrap();
+    let fast = dir.path().join("fast.wav");
+    signals::write_wav_16(&fast, &stereo, 2, 16000).unwrap();
+
+    let expected = analyzer::analyze_file(&good).unwrap();
+    let rescue = |path: &std::path::Path, force_channels, force_sample_rate| {
+        let options = analyzer::AnalysisOptions {
+            force_channels,
+            force_sample_rate,
+            ..Default::default()
+        };
+        analyzer::analyze_file_with_options(path, &options, |_| {})
+    };
+    assert!((analyzer::analyze_file(&mono).unwrap().duration_secs - 24.0).abs() < 1e-6);
+    for rescued in [
+        rescue(&mono, Some(2), None).unwrap(),
+        rescue(&fast, None, Some(8000)).unwrap(),
+    ] {
+        assert_eq!(
+            (rescued.dr, rescued.channels, rescued.sample_rate),
+            (expected.dr, Some(2), Some(8000))
+        );
+        assert!((rescued.duration_secs - 12.0).abs() < 1e-6);
+        assert_eq!(rescued.rms_db, expected.rms_db);
+    }
+
+    let err = rescue(&good, Some(3), None).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("Can't force 3 channel(s) onto the 2 decoded channel(s)"),
+        "{:#}",
+        err
+    );
+    cargo_bin_cmd!("dr")
+        .arg(&mono)
+        .args(["--force-channels", "2"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "the DR is only right if the override matches the true layout",
+        ));
+}
+
+// --- Decode errors ---
+
+#[test]
+fn test_decode_errors_are_counted_and_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let clean = generate_chained_ogg_flac(dir.path(), "clean.ogg", &[(440.0, 0.5, 9.0)], 8000);
+    let damaged = generate_ogg_flac_with_corrupt_frames(
+        dir.path(),
+        "damaged.ogg",
+        &[(440.0, 0.5, 9.0)],
+        8000,
+        &[3, 5],
+    );
+
+    let clean_result = analyzer::analyze_file(&clean).unwrap();
+    assert_eq!(clean_result.decode_errors, 0);
+    assert!(!clean_result.lossy, "FLAC in Ogg is lossless");
+    let result = analyzer::analyze_file(&damaged).unwrap();
+    assert_eq!(result.decode_errors, 2);
+    assert!(format::format_table_single(&result)
+        .contains("Warning: 2 packets could not be decoded; DR is from incomplete audio"));
+
+    cargo_bin_cmd!("dr")
+        .arg(&damaged)
+        .arg("--json")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "damaged.ogg: 2 packet(s) could not be decoded",
+        ));
+}
+
+#[test]
+fn test_truncated_file_is_flagged_against_declared_duration() {
+    let dir = tempfile::tempdir().unwrap();
+    let whole = generate_sine_wav(dir.path(), "whole.wav", 440.0, 0.5, 12.0, 8000);
+    let truncated = generate_sine_wav(dir.path(), "truncated.wav", 440.0, 0.5, 12.0, 8000);
+    // Keep the header's data length but cut the file to about 6 seconds
+    let bytes = std::fs::read(&truncated).unwrap();
+    std::fs::write(&truncated, &bytes[..44 + 6 * 8000 * 2]).unwrap();
+
+    assert_eq!(
+        analyzer::analyze_file(&whole)
+            .unwrap()
+            .declared_duration_secs,
+        None
+    );
+    let result = analyzer::analyze_file(&truncated).unwrap();
+    assert!(
+        (result.duration_secs - 6.0).abs() < 0.1,
+        "{}",
+        result.duration_secs
+    );
+    assert_eq!(result.declared_duration_secs, Some(12.0));
+    assert!(format::format_table_single(&result).contains(
+        "Warning: decoded 6.0s of the 12.0s the container declares; the decode may have stopped early"
+    ));
+
+    cargo_bin_cmd!("dr")
+        .arg(&truncated)
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "\"declared_duration_secs\": 12.0",
+        ))
+        .stderr(predicates::str::contains(
+            "truncated.wav: decoded 6.0s of the 12.0s",
+        ));
+}
+
+#[test]
+fn test_stdin_and_file_analysis_agree() {
+    let dir = tempfile::tempdir().unwrap();
+    let wav = generate_sine_wav(dir.path(), "tone.wav", 440.0, 0.5, 9.0, 8000);
+    let damaged = generate_ogg_flac_with_corrupt_frames(
+        dir.path(),
+        "damaged.ogg",
+        &[(440.0, 0.5, 9.0)],
+        8000,
+        &[3],
+    );
+
+    for (path, format) in [(&wav, "wav"), (&damaged, "ogg")] {
+        let run = |stdin: bool| -> TrackResult {
+            let mut cmd = cargo_bin_cmd!("dr");
+            if stdin {
+                cmd.args(["-", "--format", format])
+                    .write_stdin(std::fs::read(path).unwrap());
+            } else {
+                cmd.arg(path);
+            }
+            let output = cmd.arg("--json").output().unwrap();
+            serde_json::from_slice(&output.stdout).unwrap()
+        };
+        let (from_file, from_stdin) = (run(false), run(true));
+        assert_eq!(from_stdin.filename, "STDIN");
+        assert_eq!(from_stdin.dr, from_file.dr, "{}", format);
+        assert_eq!(from_stdin.peak_db, from_file.peak_db, "{}", format);
+        assert_eq!(from_stdin.rms_db, from_file.rms_db, "{}", format);
+        assert_eq!(
+            from_stdin.duration_secs, from_file.duration_secs,
+            "{}",
+            format
+        );
+        assert_eq!(
+            from_stdin.decode_errors, from_file.decode_errors,
+            "{}",
+            format
+        );
+        assert_eq!(
+            from_stdin.bits_per_sample, from_file.bits_per_sample,
+            "{}",
+            format
+        );
+    }
+}
+
+#[test]
+fn test_stdin_format_detected_without_hint() {
+    let dir = tempfile::tempdir().unwrap();
+    let wav = generate_sine_wav(dir.path(), "tone.wav", 440.0, 0.5, 6.0, 8000);
+    let ogg = generate_chained_ogg_flac(dir.path(), "tone.ogg", &[(440.0, 0.5, 6.0)], 8000);
+
+    for (path, codec) in [(&wav, "pcm_s16le"), (&ogg, "flac")] {
+        let output = cargo_bin_cmd!("dr")
+            .args(["-", "--json", "--verbose"])
+            .write_stdin(std::fs::read(path).unwrap())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let track: TrackResult = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(track.codec.as_deref(), Some(codec));
+        assert_eq!(track.dr, analyzer::analyze_file(path).unwrap().dr);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!("Detected format: {}", codec)),
+            "{}",
+            stderr
+        );
+    }
+
+    cargo_bin_cmd!("dr")
+        .arg("-")
+        .write_stdin(vec![0x55u8; 4096])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("pass --format"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_special_files_are_rejected_without_opening() {
+    let dir = tempfile::tempdir().unwrap();
+    let fifo = dir.path().join("pipe.flac");
+    assert!(std::process::Command::new("mkfifo")
+        .arg(&fifo)
+        .status()
+        .unwrap()
+        .success());
+
+    // Opening the FIFO would block with no writer; the guard never opens it
+    for (path, kind) in [
+        (fifo.as_path(), "is a FIFO (named pipe)"),
+        (std::path::Path::new("/dev/null"), "is a character device"),
+    ] {
+        cargo_bin_cmd!("dr")
+            .arg(path)
+            .timeout(std::time::Duration::from_secs(30))
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains(kind))
+            .stderr(predicates::str::contains("dr - --format"));
+    }
+}
+
+#[test]
+fn test_stdin_title_and_album_override_labels() {
+    let dir = tempfile::tempdir().unwrap();
+    let wav = generate_info_tagged_wav(dir.path(), "tagged.wav", &[(b"INAM", "Tagged Title")]);
+    let run = |args: &[&str]| {
+        cargo_bin_cmd!("dr")
+            .args(["-", "--format", "wav"])
+            .args(args)
+            .write_stdin(std::fs::read(&wav).unwrap())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--json", "--title", "My Song", "--album", "My Album"]);
+    assert!(output.status.success());
+    let track: TrackResult = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(track.title, "My Song");
+    assert_eq!(track.album.as_deref(), Some("My Album"));
+    assert_eq!(track.filename, "STDIN");
+    assert!(track.missing_tags.is_empty());
+
+    let plain: TrackResult = serde_json::from_slice(&run(&["--json"]).stdout).unwrap();
+    assert_eq!(plain.title, "Tagged Title");
+    assert_eq!(plain.album, None);
+    assert_eq!(plain.missing_tags, ["album"]);
+
+    let table = String::from_utf8(run(&["--album", "My Album"]).stdout).unwrap();
+    assert!(table.contains("Album:             My Album"), "{}", table);
+
+    cargo_bin_cmd!("dr")
+        .arg(&wav)
+        .args(["--title", "My Song"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "only apply when reading from STDIN",
+        ));
+}
+
+// --- Edge trimming ---
+
+#[test]
+fn test_trim_edges_reports_non_standard_dr() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("live.wav");
+    let mut samples = signals::sine(440.0, 0.9, 1.0, 8000);
+    samples.extend(signals::sine(440.0, 0.1, 6.0, 8000));
+    samples.extend(signals::sine(440.0, 0.9, 1.0, 8000));
+    signals::write_wav_16(&path, &samples, 1, 8000).unwrap();
+
+    cargo_bin_cmd!("dr")
+        .arg(&path)
+        .args(["--trim-edges", "1.5"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Edges trimmed:     1.5s at each end (non-standard DR)",
+        ));
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(&path)
+        .args(["--trim-edges", "1.5", "--json"])
+        .output()
+        .unwrap();
+    let track: TrackResult = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(track.trim_edges_secs, Some(1.5));
+    assert!(
+        (track.peak_db - -20.0).abs() < 0.1,
+        "got {:.2} dBFS",
+        track.peak_db
+    );
+    assert!((track.duration_secs - 8.0).abs() < 1e-6);
+
+    cargo_bin_cmd!("dr")
+        .arg(&path)
+        .args(["--trim-edges", "0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--trim-edges must be positive"));
+}
+
+#[test]
+fn test_strict_dr_database_overrides_non_standard_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_pink_noise(dir.path(), "noise.wav", 0.9, 12.0, 8000, 7);
+    let json = |args: &[&str]| -> (TrackResult, String) {
+        let output = cargo_bin_cmd!("dr")
+            .arg(&path)
+            .arg("--json")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        (
+            serde_json::from_slice(&output.stdout).unwrap(),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        )
+    };
+
+    let (standard, _) = json(&[]);
+    let (strict, stderr) = json(&[
+        "--strict-dr-database",
+        "--legacy-dr",
+        "--trim-edges",
+        "1.5",
+        "--trim-silence",
+        "--preview-secs",
+        "6",
+    ]);
+    assert_eq!(
+        serde_json::to_value(&strict).unwrap(),
+        serde_json::to_value(&standard).unwrap()
+    );
+    assert!(
+        stderr.contains("--strict-dr-database overrides --legacy-dr, --trim-edges, --trim-silence, --preview-secs"),
+        "{}",
+        stderr
+    );
+
+    let (_, stderr) = json(&["--strict-dr-database"]);
+    assert!(!stderr.contains("overrides"), "{}", stderr);
+
+    cargo_bin_cmd!("dr")
+        .arg(&path)
+        .args(["--strict-dr-database", "--force-sample-rate", "16000"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "can't be combined with --strict-dr-database",
+        ));
+}
+
+#[test]
+fn test_strict_dr_database_never_reuses_cached_reports() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_album(dir.path(), &[(440.0, 0.5, 12.0), (880.0, 0.25, 12.0)], 8000);
+    // A trimmed report cached as the standard one, as older versions saved it
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--trim-edges", "4", "--json"])
+        .output()
+        .unwrap();
+    let trimmed: AlbumResult = serde_json::from_slice(&output.stdout).unwrap();
+    cache::save_report(dir.path(), BASENAME, &trimmed).unwrap();
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--strict-dr-database")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Edges trimmed").not())
+        .stderr(predicates::str::contains("loaded from cached report").not());
+    let cached = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert!(
+        cached.tracks.iter().all(|t| t.trim_edges_secs.is_none()),
+        "the standard report replaced it"
+    );
+}
+
+// --- Non-standard reports ---
+
+/// Run `dr` on an album with `args`, then plainly, asserting that the run
+/// left no cache behind and the plain run analyzes afresh. Bulk runs with
+/// `args` write their reports under `cache::NONSTANDARD_SUFFIX`, which a plain
+/// bulk run doesn't skip the album for.
+fn assert_not_cached_as_standard(args: &[&str]) {
+    let base = tempfile::tempdir().unwrap();
+    let album = base.path().join("album");
+    generate_album(&album, &[(440.0, 0.5, 12.0), (880.0, 0.25, 6.0)], 8000);
+    let standard = cache::report_path(&album, BASENAME, "json");
 
-    // First run — generates reports
     cargo_bin_cmd!("dr")
-        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .arg(&album)
+        .args(args)
         .assert()
         .success();
+    assert!(!standard.exists(), "{:?} saved the cache", args);
+    cargo_bin_cmd!("dr")
+        .arg(&album)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("loaded from cached report").not());
+    std::fs::remove_file(&standard).unwrap();
 
-    // Record modification time of Album A's report
-    let report_path = base.path().join("Album A/dr_report.json");
-    let mtime_before = std::fs::metadata(&report_path).unwrap().modified().unwrap();
+    cargo_bin_cmd!("dr")
+        .arg(base.path())
+        .args(["--bulk", "--json"])
+        .args(args)
+        .assert()
+        .success();
+    let nonstandard = format!("{}{}", BASENAME, cache::NONSTANDARD_SUFFIX);
+    assert!(
+        cache::report_path(&album, &nonstandard, "json").exists(),
+        "{:?}",
+        args
+    );
+    assert!(!standard.exists(), "{:?} saved the cache in bulk", args);
+    cargo_bin_cmd!("dr")
+        .arg(base.path())
+        .args(["--bulk", "--json"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Skipping").not());
+    assert!(standard.exists());
+}
 
-    // Brief sleep to ensure mtime would differ if file were rewritten
-    std::thread::sleep(std::time::Duration::from_millis(50));
+#[test]
+fn test_album_aggregation_reports_are_not_cached() {
+    assert_not_cached_as_standard(&["--album-agg", "median"]);
+    assert_not_cached_as_standard(&["--skip-silent-in-average"]);
+}
+
+#[test]
+fn test_trimmed_and_preview_reports_are_not_cached() {
+    assert_not_cached_as_standard(&["--trim-edges", "1"]);
+    assert_not_cached_as_standard(&["--trim-silence"]);
+    assert_not_cached_as_standard(&["--preview-secs", "6"]);
+}
+
+#[test]
+fn test_gained_reports_are_not_cached() {
+    assert_not_cached_as_standard(&["--apply-existing-gain"]);
+}
+
+#[test]
+fn test_legacy_dr_reports_are_not_cached() {
+    assert_not_cached_as_standard(&["--legacy-dr"]);
+}
+
+#[test]
+fn test_forced_layout_reports_are_not_cached() {
+    assert_not_cached_as_standard(&["--force-sample-rate", "16000"]);
+    assert_not_cached_as_standard(&["--force-channels", "2"]);
+}
+
+#[test]
+fn test_extended_reports_are_not_cached() {
+    assert_not_cached_as_standard(&["--per-channel"]);
+    assert_not_cached_as_standard(&["--noise-floor"]);
+}
+
+#[test]
+fn test_legacy_dr_is_noted_in_reports() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_album(dir.path(), &[(440.0, 0.5, 6.0), (880.0, 0.25, 6.0)], 8000);
+    cargo_bin_cmd!("dr").arg(dir.path()).assert().success();
 
-    // Second run — should skip
     cargo_bin_cmd!("dr")
-        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .arg(dir.path())
+        .arg("--legacy-dr")
         .assert()
         .success()
-        .stderr(predicates::str::contains("Skipping"));
+        .stdout(predicate::str::contains(
+            "Method:            channels combined",
+        ))
+        .stderr(predicate::str::contains("loaded from cached report").not());
+    cargo_bin_cmd!("dr")
+        .arg(dir.path().join("01.wav"))
+        .arg("--legacy-dr")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(legacy, non-standard DR)"));
+}
 
-    let mtime_after = std::fs::metadata(&report_path).unwrap().modified().unwrap();
-    assert_eq!(mtime_before, mtime_after, "Report should not have been rewritten");
+#[test]
+fn test_skip_silent_in_average_reports_excluded_tracks() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_pink_noise(dir.path(), "01.wav", 0.9, 12.0, 8000, 1);
+    generate_pink_noise(dir.path(), "02.wav", 0.9, 12.0, 8000, 2);
+    generate_sine_wav(dir.path(), "03.wav", 440.0, 0.0, 6.0, 8000);
+
+    // The silent track's RMS is -inf, null in JSON, so read the report loosely
+    let album = |args: &[&str]| -> serde_json::Value {
+        let output = cargo_bin_cmd!("dr")
+            .arg(dir.path())
+            .args(args)
+            .arg("--json")
+            .output()
+            .unwrap();
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+    let all = album(&["--no-cache"]);
+    let skipped = album(&["--skip-silent-in-average"]);
+    assert_eq!(
+        skipped["tracks"].as_array().unwrap().len(),
+        3,
+        "the silent track is still listed"
+    );
+    assert_eq!(
+        (
+            &all["silent_tracks_excluded"],
+            &skipped["silent_tracks_excluded"]
+        ),
+        (&0.into(), &1.into())
+    );
+    let dr = |album: &serde_json::Value, i: usize| album["tracks"][i]["dr"].as_u64().unwrap();
+    let audible = (dr(&all, 0) + dr(&all, 1)) as f64 / 2.0;
+    assert_eq!(skipped["overall_dr"].as_u64(), Some(audible.round() as u64));
+    assert!(skipped["overall_dr"].as_u64() > all["overall_dr"].as_u64());
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--skip-silent-in-average")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "(2 of 3 tracks, 1 silent excluded)",
+        ));
 }
 
 #[test]
-fn test_bulk_regenerate_overwrites() {
-    let base = setup_bulk_dir();
+fn test_preview_secs_marks_cut_tracks_and_is_not_reused() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_album(dir.path(), &[(440.0, 0.5, 12.0), (880.0, 0.25, 4.0)], 8000);
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--preview-secs", "6", "--json"])
+        .output()
+        .unwrap();
+    let album: AlbumResult = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(album.tracks[0].preview);
+    assert!((album.tracks[0].duration_secs - 6.0).abs() < 1e-6);
+    assert!(
+        !album.tracks[1].preview,
+        "a track shorter than the preview is measured in full"
+    );
 
-    // First run
     cargo_bin_cmd!("dr")
-        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .arg(dir.path())
+        .args(["--preview-secs", "6"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Preview:           1 of 2 tracks: only the start",
+        ));
+    // No preview is saved to stand in for a full measurement
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Preview:").not())
+        .stderr(predicate::str::contains("loaded from cached report").not());
+    // nor does one replace the full report cached since
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--preview-secs", "6"])
         .assert()
         .success();
+    let cached = cache::load_cached_report(dir.path(), BASENAME).unwrap();
+    assert!(cached.tracks.iter().all(|t| !t.preview));
+    assert!((cached.tracks[0].duration_secs - 12.0).abs() < 1e-6);
 
-    let report_path = base.path().join("Album A/dr_report.json");
-    let mtime_before = std::fs::metadata(&report_path).unwrap().modified().unwrap();
+    cargo_bin_cmd!("dr")
+        .arg(dir.path().join("01.wav"))
+        .args(["--preview-secs", "6"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Preview:           only the start of the track was measured",
+        ));
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--preview-secs", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--preview-secs must be positive"));
+}
 
-    std::thread::sleep(std::time::Duration::from_millis(50));
+// --- Table width ---
 
-    // Second run with --regenerate — should re-analyze
+#[test]
+fn test_width_flag_fits_table() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_sine_wav(
+        dir.path(),
+        "a-rather-long-file-name-for-a-track.wav",
+        440.0,
+        0.5,
+        3.5,
+        8000,
+    );
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(&path)
+        .args(["--width", "40"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().nth(1).unwrap(), "\u{2500}".repeat(40));
+    assert!(stdout.contains("  a-rather-long\u{2026}\n"));
+
+    // Piped output has no terminal width to fit: full layout
     cargo_bin_cmd!("dr")
-        .args([base.path().to_str().unwrap(), "--bulk", "--json", "--regenerate"])
+        .arg(&path)
+        .args(["--width", "auto"])
         .assert()
         .success()
-        .stderr(predicates::str::contains("Analyzing"));
+        .stdout(predicates::str::contains("True Peak (dBTP)"));
 
-    let mtime_after = std::fs::metadata(&report_path).unwrap().modified().unwrap();
-    assert_ne!(mtime_before, mtime_after, "Report should have been rewritten");
+    cargo_bin_cmd!("dr")
+        .arg(&path)
+        .args(["--width", "0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("expected a column count"));
 }
 
 #[test]
-fn test_bulk_no_subdirectories() {
-    let base = tempfile::tempdir().unwrap();
-    // Empty dir — no subdirectories
-    std::fs::write(base.path().join("file.txt"), "not a dir").unwrap();
+fn test_cuesheet_chapters_are_reported_as_tracks() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = generate_flac_with_cuesheet(
+        dir.path(),
+        "set.flac",
+        &[(440.0, 0.5, 4.0), (660.0, 0.1, 5.0)],
+    );
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(&path)
+        .arg("--json")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let album: AlbumResult = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(album.tracks.len(), 2);
+    let (first, second) = (&album.tracks[0], &album.tracks[1]);
+    assert_eq!(first.title, "set - Chapter 1");
+    assert_eq!(second.track_number, Some(2));
+    assert!(
+        (first.duration_secs - 4.0).abs() < 1e-3,
+        "got {}",
+        first.duration_secs
+    );
+    assert!(
+        (second.duration_secs - 5.0).abs() < 1e-3,
+        "got {}",
+        second.duration_secs
+    );
+    assert!(
+        (first.peak_db - -6.0).abs() < 0.1,
+        "got {:.2} dBFS",
+        first.peak_db
+    );
+    assert!(
+        (second.peak_db - -20.0).abs() < 0.1,
+        "got {:.2} dBFS",
+        second.peak_db
+    );
+
+    // A range analyzes the file as one track
+    let output = cargo_bin_cmd!("dr")
+        .arg(&path)
+        .args(["--start", "0", "--json"])
+        .output()
+        .unwrap();
+    let track: TrackResult = serde_json::from_slice(&output.stdout).unwrap();
+    assert!((track.duration_secs - 9.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_embedded_chapters_are_reported_as_tracks() {
+    let dir = tempfile::tempdir().unwrap();
+    let segments = [(440.0, 0.5, 4.0), (660.0, 0.1, 5.0)];
+    for path in [
+        generate_m4a_with_chapters(dir.path(), "book.m4a", &segments),
+        generate_mka_with_chapters(dir.path(), "set.mka", &segments),
+    ] {
+        let output = cargo_bin_cmd!("dr")
+            .arg(&path)
+            .arg("--json")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let album: AlbumResult = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(album.tracks.len(), 2, "{}", path.display());
+        let (first, second) = (&album.tracks[0], &album.tracks[1]);
+        assert_eq!(first.title, "Part 1");
+        assert_eq!(second.title, "Part 2");
+        assert_eq!(second.track_number, Some(2));
+        assert!(
+            (first.duration_secs - 4.0).abs() < 1e-3,
+            "got {}",
+            first.duration_secs
+        );
+        assert!(
+            (second.duration_secs - 5.0).abs() < 1e-3,
+            "got {}",
+            second.duration_secs
+        );
+        assert!(
+            (first.peak_db - -6.0).abs() < 0.1,
+            "got {:.2} dBFS",
+            first.peak_db
+        );
+        assert!(
+            (second.peak_db - -20.0).abs() < 0.1,
+            "got {:.2} dBFS",
+            second.peak_db
+        );
+    }
+}
+
+#[test]
+fn test_album_agg_median_ignores_outlier_track() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_album(dir.path(), &[(440.0, 0.9, 9.0), (660.0, 0.9, 9.0)], 8000);
+    generate_pink_noise(dir.path(), "03.wav", 0.9, 9.0, 8000, 7);
+
+    let album = |agg: &str| -> AlbumResult {
+        let output = cargo_bin_cmd!("dr")
+            .arg(dir.path())
+            .args(["--album-agg", agg, "--json", "--regenerate"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+    let (mean, median) = (album("mean"), album("median"));
+    let sine_dr = mean.tracks[0].dr;
+    assert!(
+        mean.tracks[2].dr > sine_dr + 6,
+        "outlier DR{}",
+        mean.tracks[2].dr
+    );
+    assert_eq!(median.overall_dr, sine_dr);
+    assert!(mean.overall_dr > median.overall_dr + 1);
+    assert_eq!(median.album_agg, dr::models::AlbumAggregate::Median);
 
     cargo_bin_cmd!("dr")
-        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .arg(dir.path())
+        .args(["--album-agg", "median", "--regenerate"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "Official DR value: DR{} (median of tracks)",
+            sine_dr
+        )));
+}
+
+#[test]
+fn test_stream_prints_rows_then_footer() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01-a.wav", 440.0, 0.5, 4.0, 8000);
+    generate_sine_wav(dir.path(), "02-b.wav", 440.0, 0.5, 4.0, 8000);
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--stream", "-j", "1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let streamed = String::from_utf8(output.stdout).unwrap();
+    // Same table as the all-at-once output (one job keeps the rows in order)
+    let cached = cargo_bin_cmd!("dr").arg(dir.path()).output().unwrap();
+    assert_eq!(streamed, String::from_utf8(cached.stdout).unwrap());
+    assert!(streamed.contains("01-a"));
+    assert!(streamed.contains("Official DR value:"));
+
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .args(["--stream", "--json"])
         .assert()
         .failure()
-        .stderr(predicates::str::contains("No subdirectories found"));
+        .stderr(predicates::str::contains("cannot be used with"));
 }
 
 #[test]
-fn test_bulk_summary_line() {
-    let base = setup_bulk_dir();
+fn test_source_sample_format_is_recorded() {
+    let dir = tempfile::tempdir().unwrap();
+    let samples = signals::sine(440.0, 0.5, 1.0, 8000);
+
+    let int16 = generate_sine_wav(dir.path(), "int16.wav", 440.0, 0.5, 1.0, 8000);
+    let int24 = dir.path().join("int24.wav");
+    let data: Vec<u8> = samples
+        .iter()
+        .flat_map(|s| ((s * 8_388_607.0) as i32).to_le_bytes()[..3].to_vec())
+        .collect();
+    write_wav_with_fmt(&int24, &fmt_header(1, 1, 8000, 24), &data);
+    let float = dir.path().join("float.wav");
+    let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    write_wav_with_fmt(&float, &fmt_header(3, 1, 8000, 32), &data);
+    let flac = generate_chained_ogg_flac(dir.path(), "int16.ogg", &[(440.0, 0.5, 1.0)], 8000);
+
+    let format_of = |path: &PathBuf| {
+        let track = analyzer::analyze_file(path).unwrap();
+        format::format_sample_format(&track)
+    };
+    assert_eq!(format_of(&int16).as_deref(), Some("16-bit integer"));
+    assert_eq!(format_of(&int24).as_deref(), Some("24-bit integer"));
+    assert_eq!(format_of(&float).as_deref(), Some("32-bit float"));
+    // FLAC decodes into 32-bit buffers; the stream's own depth wins
+    assert_eq!(format_of(&flac).as_deref(), Some("16-bit integer"));
 
     cargo_bin_cmd!("dr")
-        .args([base.path().to_str().unwrap(), "--bulk", "--json"])
+        .arg(&int24)
+        .arg("--verbose")
         .assert()
         .success()
-        .stderr(predicates::str::contains("Done: 2 analyzed, 0 skipped, 0 failed (out of 2 total)"));
+        .stderr(
+            predicates::str::contains("int24.wav: ")
+                .and(predicates::str::contains("24-bit integer")),
+        );
 }
 
-// --- Single-directory --txt test ---
+#[test]
+fn test_noise_floor_from_quietest_block() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fade.wav");
+    let mut samples = signals::sine(440.0, 0.5, 9.0, 8000);
+    samples.extend(signals::sine(440.0, 0.01, 3.0, 8000));
+    signals::write_wav_16(&path, &samples, 1, 8000).unwrap();
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(&path)
+        .args(["--noise-floor", "--json"])
+        .output()
+        .unwrap();
+    let track: TrackResult = serde_json::from_slice(&output.stdout).unwrap();
+    // DR-RMS of a sine equals its amplitude: 0.01 -> -40 dB
+    let floor = track.noise_floor_db.unwrap();
+    assert!((floor - -40.0).abs() < 0.1, "got {:.2} dB", floor);
+
+    cargo_bin_cmd!("dr")
+        .arg(&path)
+        .arg("--noise-floor")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Noise floor:       -40.0"))
+        .stdout(predicates::str::contains("dB below peak"));
+    let track = analyzer::analyze_file(&path).unwrap();
+    assert!(track.noise_floor_db.is_none());
+}
 
 #[test]
-fn test_single_dir_txt_flag() {
+fn test_precise_dr_rounds_to_dr() {
     let dir = tempfile::tempdir().unwrap();
-    generate_sine_wav(dir.path(), "track.wav", 440.0, 0.99, 12.0, 44100);
+    let path = generate_pink_noise(dir.path(), "noise.wav", 0.9, 12.0, 8000, 7);
+
+    let track = analyzer::analyze_file(&path).unwrap();
+    let precise = track.dr_precise.unwrap();
+    assert_eq!(precise.round() as u32, track.dr, "{}", precise);
 
     cargo_bin_cmd!("dr")
-        .args([dir.path().to_str().unwrap(), "--txt"])
+        .arg(&path)
+        .arg("--precise")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Precise"))
+        .stdout(predicates::str::contains(format!(
+            "DR{:<6} {:>7.1}",
+            track.dr, precise
+        )));
+    cargo_bin_cmd!("dr")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Precise").not());
+}
+
+#[test]
+fn test_per_channel_dr_labels_channels() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("uneven.wav");
+    // Left: a steady sine (DR0). Right: a quiet sine with a click in every block.
+    let left = signals::sine(440.0, 0.5, 12.0, 8000);
+    let mut right = signals::sine(440.0, 0.05, 12.0, 8000);
+    for block in 0..4 {
+        right[block * 24000 + 1000] = 0.9;
+    }
+    let interleaved: Vec<f32> = left
+        .iter()
+        .zip(&right)
+        .flat_map(|(&l, &r)| [l, r])
+        .collect();
+    signals::write_wav_16(&path, &interleaved, 2, 8000).unwrap();
+
+    let options = analyzer::AnalysisOptions {
+        per_channel: true,
+        ..Default::default()
+    };
+    let track = analyzer::analyze_file_with_options(&path, &options, |_| {}).unwrap();
+    let labels: Vec<&str> = track
+        .channel_dr
+        .iter()
+        .map(|c| c.channel.as_str())
+        .collect();
+    assert_eq!(labels, ["FL", "FR"]);
+    assert!(
+        track.channel_dr[1].dr > track.channel_dr[0].dr + 10,
+        "{:?}",
+        track.channel_dr
+    );
+    assert!(analyzer::analyze_file(&path).unwrap().channel_dr.is_empty());
+
+    let expected = format!(
+        "Channel DR:        FL: DR{}  FR: DR{}",
+        track.channel_dr[0].dr, track.channel_dr[1].dr
+    );
+    cargo_bin_cmd!("dr")
+        .arg(&path)
+        .arg("--per-channel")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(expected));
+}
+
+#[test]
+fn test_multi_disc_album_spans_disc_folders() {
+    let base = tempfile::tempdir().unwrap();
+    let album = base.path().join("Box Set");
+    let segments = [(440.0, 0.25, 3.0)];
+    for (disc, tracks) in [("CD10", 1), ("CD2", 2), ("CD1", 1)] {
+        let dir = album.join(disc);
+        std::fs::create_dir_all(&dir).unwrap();
+        for n in 1..=tracks {
+            let tag = format!("TRACKNUMBER={}", n);
+            generate_tagged_ogg_flac(&dir, &format!("{:02}.ogg", n), &segments, 8000, &[&tag]);
+        }
+    }
+    std::fs::create_dir(album.join("Scans")).unwrap();
+
+    let err = analyzer::analyze_directory(&album, 1).unwrap_err();
+    assert!(
+        dr::error::is_no_audio(&err),
+        "plain directory mode finds nothing"
+    );
+
+    let options = analyzer::AnalysisOptions {
+        multi_disc: true,
+        ..Default::default()
+    };
+    let result = analyzer::analyze_directory_with_timing(&album, 2, &options, |_, _| {}).unwrap();
+    let names: Vec<&str> = result.tracks.iter().map(|t| t.filename.as_str()).collect();
+    assert_eq!(
+        names,
+        ["CD1/01.ogg", "CD2/01.ogg", "CD2/02.ogg", "CD10/01.ogg"]
+    );
+    assert_eq!(result.tracks[0].title, "01");
+    // Untagged discs are numbered by their folder
+    let discs: Vec<Option<u32>> = result.tracks.iter().map(|t| t.disc).collect();
+    assert_eq!(discs, [Some(1), Some(2), Some(2), Some(10)]);
+
+    // Bulk treats the box set as one album rather than one per disc
+    cargo_bin_cmd!("dr")
+        .arg(base.path())
+        .args(["--bulk", "--json", "--multi-disc"])
         .assert()
         .success();
+    let nonstandard = format!("{}{}", BASENAME, cache::NONSTANDARD_SUFFIX);
+    let report = cache::load_cached_report(&album, &nonstandard).unwrap();
+    assert_eq!(report.tracks.len(), 4);
+    assert!(cache::load_cached_report(&album, BASENAME).is_none());
+}
 
-    // Both JSON (auto-saved) and TXT should exist
-    assert!(dir.path().join("dr_report.json").exists());
-    assert!(dir.path().join("dr_report.txt").exists());
+#[test]
+fn test_changed_extensions_reanalyze_the_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01.wav", 440.0, 0.5, 6.0, 8000);
+    let samples: Vec<f32> = (0..8000 * 6)
+        .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 8000.0).sin())
+        .collect();
+    generate_aiff(dir.path(), "02.aiff", None, 16, &samples, 8000);
 
-    let txt = std::fs::read_to_string(dir.path().join("dr_report.txt")).unwrap();
-    assert!(txt.contains("Official DR value"));
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .env_remove("DR_EXTENSIONS")
+        .assert()
+        .success();
+    assert_eq!(
+        cache::load_cached_report(dir.path(), BASENAME)
+            .unwrap()
+            .tracks
+            .len(),
+        2
+    );
+
+    // Only the WAV is scanned now, so the two-track cache can't be reused
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--json")
+        .env("DR_EXTENSIONS", "wav")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("loaded from cached report").not())
+        .get_output()
+        .stdout
+        .clone();
+    let result: AlbumResult = serde_json::from_slice(&output).unwrap();
+    assert_eq!(result.tracks.len(), 1);
+    assert_eq!(
+        cache::load_cached_report(dir.path(), BASENAME)
+            .unwrap()
+            .tracks
+            .len(),
+        2,
+        "the standard cache is left alone"
+    );
+}
+
+#[test]
+fn test_disc_number_tags_group_tracks() {
+    let dir = tempfile::tempdir().unwrap();
+    let segments = [(440.0, 0.25, 3.0)];
+    // A box set in one folder: each disc's track numbers start over
+    for (name, disc, track) in [
+        ("a.ogg", "2/2", 1),
+        ("b.ogg", "1/2", 2),
+        ("c.ogg", "1/2", 1),
+    ] {
+        let tags = [
+            format!("DISCNUMBER={}", disc),
+            format!("TRACKNUMBER={}", track),
+            format!("TITLE={}", name),
+        ];
+        let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+        generate_tagged_ogg_flac(dir.path(), name, &segments, 8000, &tags);
+    }
+
+    let result = analyzer::analyze_directory(dir.path(), 1).unwrap();
+    let order: Vec<(Option<u32>, &str)> = result
+        .tracks
+        .iter()
+        .map(|t| (t.disc, t.title.as_str()))
+        .collect();
+    assert_eq!(
+        order,
+        [(Some(1), "c.ogg"), (Some(1), "b.ogg"), (Some(2), "a.ogg")]
+    );
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--no-cache")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let disc_1 = stdout.find("\nDisc 1\n").expect(&stdout);
+    let disc_2 = stdout.find("\nDisc 2\n").expect(&stdout);
+    assert!(disc_1 < stdout.find("b.ogg").unwrap() && stdout.find("b.ogg").unwrap() < disc_2);
+}
+
+#[test]
+fn test_tags_from_a_later_metadata_revision() {
+    let dir = tempfile::tempdir().unwrap();
+    // The first stream's comment block is empty; the second stream's carries the tags
+    let segments = [(440.0, 0.25, 3.0), (440.0, 0.25, 3.0)];
+    generate_late_tagged_ogg_flac(
+        dir.path(),
+        "01.ogg",
+        &segments,
+        8000,
+        &["TITLE=Late", "ALBUM=Encore", "TRACKNUMBER=4"],
+    );
+
+    let album = analyzer::analyze_directory(dir.path(), 1).unwrap();
+    let track = &album.tracks[0];
+    assert_eq!(track.title, "Late");
+    assert_eq!(track.track_number, Some(4));
+    assert!(track.missing_tags.is_empty());
+    assert_eq!(album.album.as_deref(), Some("Encore"));
+}
+
+#[test]
+fn test_strict_metadata_lists_untagged_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let segments = [(440.0, 0.25, 3.0)];
+    generate_tagged_ogg_flac(
+        dir.path(),
+        "01.ogg",
+        &segments,
+        8000,
+        &["TITLE=Intro", "ALBUM=Demo"],
+    );
+    generate_tagged_ogg_flac(dir.path(), "02.ogg", &segments, 8000, &["TITLE=Outro"]);
+    generate_sine_wav(dir.path(), "03.wav", 440.0, 0.25, 3.0, 8000);
+
+    let tracks = analyzer::analyze_directory(dir.path(), 1).unwrap().tracks;
+    assert!(tracks[0].missing_tags.is_empty());
+    assert_eq!(tracks[1].missing_tags, ["album"]);
+    assert_eq!(tracks[2].missing_tags, ["title", "album"]);
+
+    // Without the flag the fallback names are used silently (and cached)
+    cargo_bin_cmd!("dr").arg(dir.path()).assert().success();
+    cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--strict-metadata")
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("Official DR value"))
+        .stderr(predicates::str::contains("Missing tags: 01.ogg").not())
+        .stderr(predicates::str::contains("Missing tags: 02.ogg (album)"))
+        .stderr(predicates::str::contains(
+            "Missing tags: 03.wav (title, album)",
+        ))
+        .stderr(predicates::str::contains(
+            "2 file(s) missing title or album tags",
+        ));
+}
+
+#[test]
+fn test_porcelain_output_fields() {
+    let dir = tempfile::tempdir().unwrap();
+    generate_sine_wav(dir.path(), "01-a.wav", 440.0, 0.5, 4.0, 8000);
+    generate_sine_wav(dir.path(), "02-b.wav", 440.0, 0.25, 4.0, 8000);
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path())
+        .arg("--porcelain")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<Vec<&str>> = stdout.lines().map(|l| l.split('\t').collect()).collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(&lines[0][..4], ["#album", "1", lines[1][1], "2"]);
+    assert_eq!(lines[1][0], "01-a.wav");
+    assert_eq!(lines[2].len(), 8);
+    assert_eq!(&lines[2][5..], ["4.000", "8000", "1"]);
+    assert!((lines[2][2].parse::<f64>().unwrap() - -12.04).abs() < 0.05);
+
+    let output = cargo_bin_cmd!("dr")
+        .arg(dir.path().join("01-a.wav"))
+        .arg("--porcelain")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("01-a.wav\t"));
+    assert_eq!(stdout.lines().count(), 1);
 }